@@ -0,0 +1,150 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::{
+    components::*, replay::ReplayPlayer, resources::*, systems::movement::read_paddle_input, PhysicsSet,
+    PhysicsStage,
+};
+
+/// What a player can do with their paddle, when it's played through leafwing-input-manager
+#[derive(Actionlike, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlayerAction {
+    /// Up, or right for the top and bottom paddles
+    MoveUp,
+    MoveDown,
+    Serve,
+    Pause,
+    /// Starts a dash, see `MatchSettings::paddle_dash`
+    Dash,
+}
+
+/// The bindings a paddle starts with: its keys from the `KeyBindings`, and for the first four paddles a gamepad
+/// each, the left's the first one connected. Its d-pad moves it, south serves, start pauses and the right
+/// trigger dashes, as do the dash keys for the left and right paddles.
+///
+/// The keyboard pauses with `KeyBindings::pause` as it always does, rather than through the map
+pub fn default_input_map(seat: Seat, half: Option<Half>, bindings: &KeyBindings) -> InputMap<PlayerAction> {
+    let (up, down) = match half {
+        Some(Half::Lower) => bindings.lower_paddle_keys(seat.side().unwrap_or(Side::Left)),
+        _ => bindings.paddle_keys(seat),
+    };
+    let mut input_map = InputMap::new([
+        (up, PlayerAction::MoveUp),
+        (down, PlayerAction::MoveDown),
+        (bindings.serve, PlayerAction::Serve),
+    ]);
+    match seat {
+        Seat::Left => input_map.insert(bindings.left_dash, PlayerAction::Dash),
+        Seat::Right => input_map.insert(bindings.right_dash, PlayerAction::Dash),
+        Seat::Top | Seat::Bottom => &mut input_map,
+    };
+
+    // a doubles side's lower paddle shares the keyboard, not its upper paddle's gamepad
+    if half != Some(Half::Lower) {
+        let pad_up = match seat {
+            Seat::Left | Seat::Right => GamepadButtonType::DPadUp,
+            Seat::Top | Seat::Bottom => GamepadButtonType::DPadRight,
+        };
+        let pad_down = match seat {
+            Seat::Left | Seat::Right => GamepadButtonType::DPadDown,
+            Seat::Top | Seat::Bottom => GamepadButtonType::DPadLeft,
+        };
+        input_map
+            .insert(pad_up, PlayerAction::MoveUp)
+            .insert(pad_down, PlayerAction::MoveDown)
+            .insert(GamepadButtonType::South, PlayerAction::Serve)
+            .insert(GamepadButtonType::Start, PlayerAction::Pause)
+            .insert(GamepadButtonType::RightTrigger2, PlayerAction::Dash)
+            .set_gamepad(Gamepad::new(Seat::ALL.iter().position(|s| *s == seat).unwrap_or_default()));
+    }
+    input_map
+}
+
+/// Plays the paddles through leafwing-input-manager, with the `input_manager` feature. Add it after `PongPlugin`,
+/// along with Bevy's `InputPlugin`, which `DefaultPlugins` has.
+///
+/// Each paddle is given an `InputMap<PlayerAction>` from `default_input_map` when it's spawned, which can
+/// be changed like any other, with chords and as many keys and buttons for an action as it likes. Their
+/// `ActionState`s are what the paddles follow in place of the keyboard, through `PaddleInputs`, so replays and
+/// online matches carry on as before
+pub struct PlayerActionsPlugin;
+
+impl Plugin for PlayerActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(InputManagerPlugin::<PlayerAction>::default())
+            .add_system(give_paddles_input_maps)
+            .add_system(pause_from_actions)
+            .add_system_to_stage(
+                PhysicsStage,
+                read_paddle_actions
+                    .label(PhysicsSet::Input)
+                    .after(read_paddle_input)
+                    .before(crate::network::play_remote_input),
+            );
+    }
+}
+
+// New paddles get the bindings for their seat, four-player and doubles ones included
+fn give_paddles_input_maps(
+    mut commands: Commands,
+    bindings: Res<KeyBindings>,
+    paddle_query: Query<(Entity, &Seat, Option<&Half>), (With<Paddle>, Without<InputMap<PlayerAction>>)>,
+) {
+    for (paddle, seat, half) in &paddle_query {
+        commands.entity(paddle).insert(InputManagerBundle {
+            action_state: ActionState::default(),
+            input_map: default_input_map(*seat, half.copied(), &bindings),
+        });
+    }
+}
+
+// Takes each paddle's input for the step from its actions, over what `read_paddle_input` read from the keys.
+// A replay being played back is left alone
+fn read_paddle_actions(
+    replay_player: Option<Res<ReplayPlayer>>,
+    mut paddle_inputs: ResMut<PaddleInputs>,
+    paddle_query: Query<(&ActionState<PlayerAction>, &Seat, Option<&Half>), With<Paddle>>,
+) {
+    if replay_player.is_some() {
+        return;
+    }
+
+    for (action_state, seat, half) in &paddle_query {
+        let mut input = PlayerInput::new(
+            action_state.pressed(PlayerAction::MoveUp),
+            action_state.pressed(PlayerAction::MoveDown),
+            action_state.pressed(PlayerAction::Serve),
+        );
+        if action_state.pressed(PlayerAction::Dash) {
+            input = input.dashing();
+        }
+        match (half, seat.side()) {
+            (Some(Half::Lower), Some(side)) => *paddle_inputs.lower_input_mut(side) = input,
+            _ => *paddle_inputs.input_mut(*seat) = input,
+        }
+    }
+}
+
+// Any player's pause button pauses, or unpauses, like the pause key
+fn pause_from_actions(
+    action_query: Query<&ActionState<PlayerAction>>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !action_query
+        .iter()
+        .any(|action_state| action_state.just_pressed(PlayerAction::Pause))
+    {
+        return;
+    }
+
+    // the pause key pressed on the same frame has already queued the change, and that one goes ahead
+    match state.current() {
+        GameState::Playing => {
+            let _ = state.push(GameState::Paused);
+        }
+        GameState::Paused => {
+            let _ = state.pop();
+        }
+        _ => {}
+    }
+}
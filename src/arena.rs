@@ -0,0 +1,790 @@
+use bevy::{math::Vec2Swizzles, prelude::*, render::camera::{ScalingMode, Viewport}, window::WindowResized};
+use rand::Rng;
+
+use crate::{components::*, config::*, resources::*, systems::serve::*, theme::*, ui::*};
+
+const LETTERBOX_COLOR: Color = Color::BLACK;
+
+const NUM_DOTTED_LINES: i32 = 10;
+
+// How many obstacles are scattered when the config doesn't place them
+const OBSTACLE_COUNT: usize = 3;
+// In front of the dotted line they stand on
+const OBSTACLE_Z: f32 = 0.1;
+
+/// Where the walls of the play field are, in `Transform` units
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct Arena {
+    pub left: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub top: f32,
+}
+
+impl Arena {
+    /// The arena centered on the origin with the configured size
+    pub fn fixed(config: &PongConfig) -> Arena {
+        let half_size = config.arena_size / 2.0;
+        Arena {
+            left: -half_size.x,
+            right: half_size.x,
+            bottom: -half_size.y,
+            top: half_size.y,
+        }
+    }
+
+    /// An arena filling a window of the given size, with the walls just inside its edges
+    pub fn from_window(width: f32, height: f32, config: &PongConfig) -> Arena {
+        let half_width = width / 2.0 - config.wall_thickness;
+        let half_height = height / 2.0 - config.wall_thickness;
+        Arena {
+            left: -half_width,
+            right: half_width,
+            bottom: -half_height,
+            top: half_height,
+        }
+    }
+
+    pub fn width(&self) -> f32 {
+        self.right - self.left
+    }
+
+    pub fn height(&self) -> f32 {
+        self.top - self.bottom
+    }
+
+    /// The size of everything that needs to be on screen, including the walls' thickness
+    pub fn view_size(&self, config: &PongConfig) -> Vec2 {
+        Vec2::new(self.width(), self.height()) + config.wall_thickness
+    }
+}
+
+/// How the arena is sized
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArenaScaling {
+    /// The arena fills the window, and grows and shrinks with it
+    #[default]
+    FitWindow,
+    /// The arena always has the configured size, whatever the window
+    Fixed,
+    /// The arena always has the configured size, and the camera scales it to fit the window,
+    /// with bars on the sides that don't match its aspect ratio
+    Letterbox,
+}
+
+// This bundle is a collection of the components that define a "wall" in our game
+#[derive(Bundle)]
+struct WallBundle {
+    // You can nest bundles inside of other bundles like this
+    // Allowing you to compose their functionality
+    sprite_bundle: SpriteBundle,
+    collider: Collider,
+    location: WallLocation,
+    themed: Themed,
+}
+
+/// Which side of the arena is this wall located on?
+#[derive(Component, Clone, Copy)]
+pub(crate) enum WallLocation {
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+impl WallLocation {
+    // Whose wall it is in a four-player match
+    fn seat(self) -> Seat {
+        match self {
+            WallLocation::Left => Seat::Left,
+            WallLocation::Right => Seat::Right,
+            WallLocation::Bottom => Seat::Bottom,
+            WallLocation::Top => Seat::Top,
+        }
+    }
+
+    fn position(&self, arena: &Arena) -> Vec2 {
+        match self {
+            WallLocation::Left => Vec2::new(arena.left, 0.),
+            WallLocation::Right => Vec2::new(arena.right, 0.),
+            WallLocation::Bottom => Vec2::new(0., arena.bottom),
+            WallLocation::Top => Vec2::new(0., arena.top),
+        }
+    }
+
+    fn size(&self, arena: &Arena, wall_thickness: f32) -> Vec2 {
+        let arena_height = arena.height();
+        let arena_width = arena.width();
+        // Make sure we haven't messed up our constants
+        assert!(arena_height > 0.0);
+        assert!(arena_width > 0.0);
+
+        match self {
+            WallLocation::Left | WallLocation::Right => {
+                Vec2::new(wall_thickness, arena_height + wall_thickness)
+            }
+            WallLocation::Bottom | WallLocation::Top => {
+                Vec2::new(arena_width + wall_thickness, wall_thickness)
+            }
+        }
+    }
+
+    fn transform(&self, arena: &Arena, config: &PongConfig) -> Transform {
+        Transform {
+            // We need to convert our Vec2 into a Vec3, by giving it a z-coordinate
+            // This is used to determine the order of our sprites
+            translation: self.position(arena).extend(0.0),
+            // The z-scale of 2D objects must always be 1.0,
+            // or their ordering will be affected in surprising ways.
+            // See https://github.com/bevyengine/bevy/issues/4149
+            scale: self.size(arena, config.wall_thickness).extend(1.0),
+            ..default()
+        }
+    }
+
+    fn sprite_bundle(&self, arena: &Arena, config: &PongConfig) -> SpriteBundle {
+        SpriteBundle {
+            transform: self.transform(arena, config),
+            sprite: Sprite {
+                color: config.wall_color,
+                ..default()
+            },
+            ..default()
+        }
+    }
+}
+
+impl WallBundle {
+    // This "builder method" allows us to reuse logic across our wall entities,
+    // making our code easier to read and less prone to bugs when we change the logic
+    fn new(location: WallLocation, arena: &Arena, config: &PongConfig) -> WallBundle {
+        WallBundle {
+            sprite_bundle: location.sprite_bundle(arena, config),
+            collider: Collider,
+            location,
+            themed: Themed(ThemeRole::Wall),
+        }
+    }
+}
+
+// The side walls behind the paddles are goals rather than colliders:
+// the ball passes through them and a point is scored for the other side
+#[derive(Bundle)]
+struct GoalBundle {
+    sprite_bundle: SpriteBundle,
+    goal: Goal,
+    location: WallLocation,
+}
+
+impl GoalBundle {
+    fn new(location: WallLocation, arena: &Arena, config: &PongConfig) -> GoalBundle {
+        GoalBundle {
+            sprite_bundle: location.sprite_bundle(arena, config),
+            goal: Goal,
+            location,
+        }
+    }
+}
+
+// One dash of the dotted line down the middle, numbered from the bottom
+#[derive(Component)]
+pub(crate) struct CenterLine(i32);
+
+impl CenterLine {
+    fn position(&self, arena: &Arena, config: &PongConfig) -> Vec2 {
+        let increment = arena.height() / (NUM_DOTTED_LINES as f32);
+        let bottom = arena.bottom + config.center_line_size.y + config.wall_thickness;
+        Vec2::new(0.0, self.0 as f32 * increment + bottom)
+    }
+}
+
+// Puts the obstacles in place at the start of a match in obstacle mode, and the moving one
+// when it's turned on, never in the demo.
+// They belong to the match, so a restart clears them and they're put back for the next one
+pub(crate) fn spawn_obstacles(
+    mut commands: Commands,
+    settings: Res<MatchSettings>,
+    state: Res<State<GameState>>,
+    config: Res<PongConfig>,
+    arena: Res<Arena>,
+    mut rng: ResMut<GameRng>,
+    obstacle_query: Query<(), With<Obstacle>>,
+) {
+    let wanted = settings.obstacles || settings.moving_obstacle;
+    if !wanted || *state.current() != GameState::Playing || !obstacle_query.is_empty() {
+        return;
+    }
+
+    let obstacle_bundle = |position: Vec2| {
+        (
+            SpriteBundle {
+                transform: Transform {
+                    translation: position.extend(OBSTACLE_Z),
+                    scale: config.obstacle_size.extend(1.0),
+                    ..default()
+                },
+                sprite: Sprite {
+                    color: config.wall_color,
+                    ..default()
+                },
+                ..default()
+            },
+            Collider,
+            Obstacle,
+            MatchEntity,
+            Themed(ThemeRole::Wall),
+        )
+    };
+
+    if settings.obstacles {
+        let positions = match &config.obstacles {
+            Some(positions) => positions.clone(),
+            None => scatter_obstacles(&arena, &config, &mut rng),
+        };
+        for position in positions {
+            commands.spawn(obstacle_bundle(position));
+        }
+    }
+    if settings.moving_obstacle {
+        commands.spawn((
+            obstacle_bundle(Vec2::new((arena.left + arena.right) / 2.0, 0.0)),
+            Velocity(Vec2::new(0.0, config.moving_obstacle_speed)),
+        ));
+    }
+}
+
+// Turns the moving obstacle around when it runs into the top or bottom wall,
+// the way the ball bounces off them
+pub(crate) fn patrol_center_line(
+    arena: Res<Arena>,
+    config: Res<PongConfig>,
+    mut obstacle_query: Query<(&mut Transform, &mut Velocity), (With<Obstacle>, Without<Ball>)>,
+) {
+    for (mut transform, mut velocity) in &mut obstacle_query {
+        let reach = config.wall_thickness / 2.0 + transform.scale.y / 2.0;
+        let (lower_bound, upper_bound) = (arena.bottom + reach, arena.top - reach);
+        let y = transform.translation.y;
+        if (y > upper_bound && velocity.y > 0.0) || (y < lower_bound && velocity.y < 0.0) {
+            velocity.y = -velocity.y;
+        }
+        // back inside by as far as it went past, as if it had bounced at the wall
+        if y > upper_bound {
+            transform.translation.y = 2.0 * upper_bound - y;
+        } else if y < lower_bound {
+            transform.translation.y = 2.0 * lower_bound - y;
+        }
+    }
+}
+
+// Random spots near the center line, kept apart from each other and from where the ball is served.
+// It only tries so many times, so a cramped arena gets fewer rather than hanging
+fn scatter_obstacles(arena: &Arena, config: &PongConfig, rng: &mut GameRng) -> Vec<Vec2> {
+    let margin = config.wall_thickness + config.obstacle_size.y;
+    let clearance = config.obstacle_size + config.ball_size;
+    let clear = |a: Vec2, b: Vec2| {
+        let gap = (a - b).abs();
+        gap.x > clearance.x || gap.y > clearance.y
+    };
+
+    let mut positions: Vec<Vec2> = vec![];
+    for _ in 0..OBSTACLE_COUNT * 10 {
+        if positions.len() == OBSTACLE_COUNT {
+            break;
+        }
+        let position = Vec2::new(
+            rng.rng.gen_range(-1.0..1.0) * arena.width() / 8.0,
+            rng.rng.gen_range(arena.bottom + margin..arena.top - margin),
+        );
+        if clear(position, config.ball_starting_position) && positions.iter().all(|&other| clear(position, other)) {
+            positions.push(position);
+        }
+    }
+    positions
+}
+
+// Spawns everything the simulation needs. What it looks and sounds like is left to `setup_render`
+pub(crate) fn setup(
+    mut commands: Commands,
+    windows: Option<Res<Windows>>,
+    arena_scaling: Res<ArenaScaling>,
+    config: Res<PongConfig>,
+    skip_menu: Option<Res<SkipMenu>>,
+    mut serve_timer: ResMut<ServeTimer>,
+){
+    // Arena
+    let arena = match (*arena_scaling, windows.as_ref().and_then(|windows| windows.get_primary())) {
+        (ArenaScaling::FitWindow, Some(window)) => {
+            Arena::from_window(window.width(), window.height(), &config)
+        }
+        _ => Arena::fixed(&config),
+    };
+    commands.insert_resource(arena);
+
+    // Background
+    commands.spawn((
+        SpriteBundle {
+            transform: Transform {
+                translation: Vec3::new(0.0, 0.0, -1.0),
+                scale: arena.view_size(&config).extend(1.0),
+                ..default()
+            },
+            sprite: Sprite {
+                color: config.background_color,
+                ..default()
+            },
+            ..default()
+        },
+        ArenaBackground,
+        Themed(ThemeRole::Background),
+    ));
+
+    // Paddle one
+    let paddle_one_x = paddle_line(Seat::Left, &arena, &config);
+
+    commands.spawn((
+        SpriteBundle {
+            transform: Transform {
+                translation: Vec3::new(paddle_one_x, 0.0, 0.0),
+                scale: config.paddle_size.extend(1.0),
+                ..default()
+            },
+            sprite: Sprite {
+                color: config.paddle_color,
+                ..default()
+            },
+            ..default()
+        },
+        Paddle,
+        PaddleSpeed(config.paddle_speed),
+        PaddleVelocity::default(),
+        LastPaddlePosition::default(),
+        PaddleAction::default(),
+        PaddleDash::default(),
+        Side::Left,
+        Seat::Left,
+        PaddleAxis::Vertical,
+        Collider,
+    ));
+
+    // Paddle two
+    let paddle_two_x = paddle_line(Seat::Right, &arena, &config);
+
+    let mut paddle_two = commands.spawn((
+        SpriteBundle {
+            transform: Transform {
+                translation: Vec3::new(paddle_two_x, 0.0, 0.0),
+                scale: config.paddle_size.extend(1.0),
+                ..default()
+            },
+            sprite: Sprite {
+                color: config.paddle_color,
+                ..default()
+            },
+            ..default()
+        },
+        Paddle,
+        PaddleSpeed(config.paddle_speed),
+        PaddleVelocity::default(),
+        LastPaddlePosition::default(),
+        PaddleAction::default(),
+        PaddleDash::default(),
+        Side::Right,
+        Seat::Right,
+        PaddleAxis::Vertical,
+        Collider,
+    ));
+    if skip_menu.is_some_and(|skip_menu| skip_menu.cpu_opponent) {
+        paddle_two.insert(CpuControlled);
+    }
+
+    // Ball
+    spawn_serve(&mut commands, &config, &mut serve_timer, None);
+
+    // Walls
+    commands.spawn(GoalBundle::new(WallLocation::Left, &arena, &config));
+    commands.spawn(GoalBundle::new(WallLocation::Right, &arena, &config));
+    commands.spawn(WallBundle::new(WallLocation::Bottom, &arena, &config));
+    commands.spawn(WallBundle::new(WallLocation::Top, &arena, &config));
+
+    // Dotted Line
+    for y_index in 0..NUM_DOTTED_LINES {
+        let line = CenterLine(y_index);
+        commands.spawn((
+            SpriteBundle {
+                transform: Transform {
+                    translation: line.position(&arena, &config).extend(0.0),
+                    scale: config.center_line_size.extend(0.0),
+                    ..default()
+                },
+                sprite: Sprite {
+                    color: config.wall_color,
+                    ..default()
+                },
+                ..default()
+            },
+            line,
+            Themed(ThemeRole::Wall),
+        ));
+    }
+}
+
+// The range of positions a paddle's center can move in along its axis without leaving the arena,
+// given the paddle's length along it. The top and bottom paddles are kept clear of the lines
+// the left and right ones move along, so the four never overlap in the corners
+pub(crate) fn paddle_bounds(
+    arena: &Arena,
+    config: &PongConfig,
+    axis: PaddleAxis,
+    half: Option<Half>,
+    paddle_length: f32,
+) -> (f32, f32) {
+    let margin = config.wall_thickness / 2.0 + paddle_length / 2.0 + config.paddle_padding;
+    let (lower_bound, upper_bound) = match axis {
+        PaddleAxis::Vertical => (arena.bottom + margin, arena.top - margin),
+        PaddleAxis::Horizontal => (arena.left + config.paddle_gap + margin, arena.right - config.paddle_gap - margin),
+    };
+
+    // doubles partners can't cross the middle, so they never end up on top of each other
+    let middle = (arena.bottom + arena.top) / 2.0;
+    match half {
+        Some(Half::Upper) => (lower_bound.max(middle + paddle_length / 2.0), upper_bound),
+        Some(Half::Lower) => (lower_bound, upper_bound.min(middle - paddle_length / 2.0)),
+        None => (lower_bound, upper_bound),
+    }
+}
+
+// How far in front of its wall a paddle sits, the x of the left and right paddles or the y of the others
+fn paddle_line(seat: Seat, arena: &Arena, config: &PongConfig) -> f32 {
+    match seat {
+        Seat::Left => arena.left + config.paddle_gap,
+        Seat::Right => arena.right - config.paddle_gap,
+        Seat::Top => arena.top - config.paddle_gap,
+        Seat::Bottom => arena.bottom + config.paddle_gap,
+    }
+}
+
+// Brings in the top and bottom paddles at the start of a four-player match, lying across the arena
+// in front of their walls. They belong to the match, so a restart clears them and they're put back
+pub(crate) fn spawn_four_player_paddles(
+    mut commands: Commands,
+    settings: Res<MatchSettings>,
+    state: Res<State<GameState>>,
+    config: Res<PongConfig>,
+    arena: Res<Arena>,
+    paddle_query: Query<&Seat, With<Paddle>>,
+) {
+    let spawned = paddle_query.iter().any(|seat| seat.axis() == PaddleAxis::Horizontal);
+    if !settings.four_players || *state.current() != GameState::Playing || spawned {
+        return;
+    }
+
+    for seat in [Seat::Top, Seat::Bottom] {
+        commands.spawn((
+            SpriteBundle {
+                transform: Transform {
+                    translation: Vec3::new(0.0, paddle_line(seat, &arena, &config), 0.0),
+                    scale: config.paddle_size.yx().extend(1.0),
+                    ..default()
+                },
+                sprite: Sprite {
+                    color: config.paddle_color,
+                    ..default()
+                },
+                ..default()
+            },
+            Paddle,
+            PaddleSpeed(config.paddle_speed),
+            PaddleVelocity::default(),
+            LastPaddlePosition::default(),
+            PaddleAction::default(),
+            PaddleDash::default(),
+            seat,
+            PaddleAxis::Horizontal,
+            Collider,
+            MatchEntity,
+        ));
+    }
+}
+
+// Where a paddle's center starts a match along its axis: the middle of the arena, or of its half in doubles
+pub(crate) fn paddle_start(arena: &Arena, axis: PaddleAxis, half: Option<Half>) -> f32 {
+    let middle = match axis {
+        PaddleAxis::Vertical => (arena.bottom + arena.top) / 2.0,
+        PaddleAxis::Horizontal => (arena.left + arena.right) / 2.0,
+    };
+    let quarter = arena.height() / 4.0;
+    match half {
+        Some(Half::Upper) => middle + quarter,
+        Some(Half::Lower) => middle - quarter,
+        None => middle,
+    }
+}
+
+// Gives each side a second paddle at the start of a doubles match, and keeps the two to their own halves,
+// the left and right paddles above the middle and their partners below it. The partners belong to the match,
+// and once a match isn't doubles they're gone and the left and right paddles have their whole side back
+pub(crate) fn arrange_doubles_paddles(
+    mut commands: Commands,
+    settings: Res<MatchSettings>,
+    state: Res<State<GameState>>,
+    config: Res<PongConfig>,
+    arena: Res<Arena>,
+    mut paddle_query: Query<(Entity, &Side, &mut Transform, Option<&Half>), With<Paddle>>,
+) {
+    if !settings.doubles {
+        for (paddle, _, _, half) in &paddle_query {
+            match half {
+                Some(Half::Upper) => {
+                    commands.entity(paddle).remove::<Half>();
+                }
+                Some(Half::Lower) => commands.entity(paddle).despawn_recursive(),
+                None => {}
+            }
+        }
+        return;
+    }
+    let spawned = paddle_query.iter().any(|(_, _, _, half)| half == Some(&Half::Lower));
+    if *state.current() != GameState::Playing || spawned {
+        return;
+    }
+
+    // each starts in the middle of its half
+    let lower_start = paddle_start(&arena, PaddleAxis::Vertical, Some(Half::Lower));
+    for (paddle, side, mut transform, _) in &mut paddle_query {
+        transform.translation.y = paddle_start(&arena, PaddleAxis::Vertical, Some(Half::Upper));
+        commands.entity(paddle).insert(Half::Upper);
+        commands.spawn((
+            SpriteBundle {
+                transform: Transform {
+                    translation: Vec3::new(transform.translation.x, lower_start, 0.0),
+                    scale: config.paddle_size.extend(1.0),
+                    ..default()
+                },
+                sprite: Sprite {
+                    color: config.paddle_color,
+                    ..default()
+                },
+                ..default()
+            },
+            Paddle,
+            PaddleSpeed(config.paddle_speed),
+            PaddleVelocity::default(),
+            LastPaddlePosition::default(),
+            PaddleAction::default(),
+            PaddleDash::default(),
+            *side,
+            Seat::from(*side),
+            PaddleAxis::Vertical,
+            Half::Lower,
+            Collider,
+            MatchEntity,
+        ));
+    }
+}
+
+// Decides which walls the ball bounces off. In a four-player match every player's wall is open
+// behind their paddle, until they're out of lives and their paddle goes and the wall closes up.
+// Otherwise it's only the left and right walls that are open, or just the left one in wall practice,
+// where the right paddle is put away
+pub(crate) fn close_walls_behind_players_out(
+    mut commands: Commands,
+    settings: Res<MatchSettings>,
+    lives: Res<Lives>,
+    wall_query: Query<(Entity, &WallLocation, Option<&Collider>)>,
+    mut paddle_query: Query<(Entity, &Seat, &mut Visibility, Option<&Collider>), With<Paddle>>,
+) {
+    let in_play = |seat: Seat| match seat {
+        Seat::Left => !settings.four_players || lives.lives(seat) > 0,
+        Seat::Right => !settings.wall_practice && (!settings.four_players || lives.lives(seat) > 0),
+        Seat::Top | Seat::Bottom => settings.four_players && lives.lives(seat) > 0,
+    };
+
+    for (wall, location, collider) in &wall_query {
+        let solid = !in_play(location.seat());
+        if solid && collider.is_none() {
+            commands.entity(wall).insert(Collider);
+        } else if !solid && collider.is_some() {
+            commands.entity(wall).remove::<Collider>();
+        }
+    }
+
+    for (paddle, seat, mut visibility, collider) in &mut paddle_query {
+        let playing = in_play(*seat);
+        if visibility.is_visible != playing {
+            visibility.is_visible = playing;
+        }
+        if playing && collider.is_none() {
+            commands.entity(paddle).insert(Collider);
+        } else if !playing && collider.is_some() {
+            commands.entity(paddle).remove::<Collider>();
+        }
+    }
+}
+
+// Keeps the arena matching the window's size as it is resized
+pub(crate) fn fit_arena_to_window(
+    arena_scaling: Res<ArenaScaling>,
+    config: Res<PongConfig>,
+    mut arena: ResMut<Arena>,
+    mut resize_events: EventReader<WindowResized>,
+) {
+    let Some(event) = resize_events.iter().last() else {
+        return;
+    };
+
+    if *arena_scaling == ArenaScaling::FitWindow {
+        let new_arena = Arena::from_window(event.width, event.height, &config);
+        if *arena != new_arena {
+            *arena = new_arena;
+        }
+    }
+}
+
+// Points the camera at just the arena when letterboxing, and keeps the scores over it
+pub(crate) fn letterbox_camera(
+    arena_scaling: Res<ArenaScaling>,
+    arena: Res<Arena>,
+    config: Res<PongConfig>,
+    theme: Res<Theme>,
+    windows: Res<Windows>,
+    mut resize_events: EventReader<WindowResized>,
+    mut clear_color: ResMut<ClearColor>,
+    mut camera_query: Query<(&mut Camera, &mut OrthographicProjection)>,
+    ui_scale: Res<UiScale>,
+    mut score_column_query: Query<(&mut Style, &Side), With<ScoreColumn>>,
+) {
+    let resized = resize_events.iter().last().is_some();
+    let changed = arena_scaling.is_changed() || arena.is_changed() || theme.is_changed() || ui_scale.is_changed();
+    if !resized && !changed {
+        return;
+    }
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+
+    let letterbox = *arena_scaling == ArenaScaling::Letterbox;
+    let window_size = Vec2::new(window.width(), window.height());
+    let view_size = arena.view_size(&config);
+
+    // the part of the window the arena is drawn in, in logical pixels from the top left
+    let (view_position, view_scaled_size) = if letterbox {
+        let scaled_size = view_size * (window_size / view_size).min_element();
+        ((window_size - scaled_size) / 2.0, scaled_size)
+    } else {
+        (Vec2::ZERO, window_size)
+    };
+
+    for (mut camera, mut projection) in &mut camera_query {
+        if letterbox {
+            let scale_factor = window.scale_factor() as f32;
+            camera.viewport = Some(Viewport {
+                physical_position: (view_position * scale_factor).as_uvec2(),
+                physical_size: (view_scaled_size * scale_factor).as_uvec2().max(UVec2::ONE),
+                ..default()
+            });
+            projection.scaling_mode = ScalingMode::FixedVertical(view_size.y);
+        } else {
+            camera.viewport = None;
+            projection.scaling_mode = ScalingMode::WindowSize;
+        }
+    }
+
+    clear_color.0 = if letterbox { LETTERBOX_COLOR } else { theme.background };
+
+    // the UI's pixels are scaled by the `UiScale`, unlike the window's
+    let ui_scale = ui_scale.scale as f32;
+    for (mut style, side) in &mut score_column_query {
+        let (inset, width) = if letterbox {
+            (
+                Val::Px(view_position.x / ui_scale),
+                Val::Px(view_scaled_size.x / 2.0 / ui_scale),
+            )
+        } else {
+            (Val::Px(0.0), Val::Percent(50.0))
+        };
+        style.position.top = Val::Px(view_position.y / ui_scale + SCOREBOARD_TEXT_PADDING);
+        style.size.width = width;
+        match side {
+            Side::Left => style.position.left = inset,
+            Side::Right => style.position.right = inset,
+        }
+    }
+}
+
+// Converts a position in the window, measured from its bottom left, into the world
+pub(crate) fn window_to_world(camera: &Camera, camera_transform: &GlobalTransform, position: Vec2) -> Option<Vec2> {
+    // `viewport_to_world` measures from the bottom left of the camera's viewport,
+    // which isn't the window's corner when letterboxing
+    let position = match (camera.logical_viewport_rect(), camera.logical_target_size()) {
+        (Some((min, max)), Some(target_size)) => position - Vec2::new(min.x, target_size.y - max.y),
+        _ => position,
+    };
+    camera
+        .viewport_to_world(camera_transform, position)
+        .map(|ray| ray.origin.truncate())
+}
+
+// Moves everything to match a resized arena. The ball and paddles keep their place relative
+// to the arena, rather than snapping back to their starting positions
+pub(crate) fn apply_arena_resize(
+    arena: Res<Arena>,
+    config: Res<PongConfig>,
+    mut previous_arena: Local<Option<Arena>>,
+    mut wall_query: Query<(&mut Transform, &WallLocation)>,
+    mut line_query: Query<(&mut Transform, &CenterLine), Without<WallLocation>>,
+    mut background_query: Query<
+        &mut Transform,
+        (With<ArenaBackground>, Without<WallLocation>, Without<CenterLine>),
+    >,
+    mut paddle_query: Query<
+        (&mut Transform, &Seat, Option<&Half>),
+        (With<Paddle>, Without<WallLocation>, Without<CenterLine>, Without<ArenaBackground>),
+    >,
+    // the balls, and the obstacles and power-ups in their way
+    mut ball_query: Query<
+        &mut Transform,
+        (
+            Or<(With<Ball>, With<Obstacle>, With<PowerUp>)>,
+            Without<Paddle>,
+            Without<WallLocation>,
+            Without<CenterLine>,
+            Without<ArenaBackground>,
+        ),
+    >,
+) {
+    if !arena.is_changed() {
+        return;
+    }
+
+    let Some(old_arena) = previous_arena.replace(*arena) else {
+        // everything was spawned to fit the first arena
+        return;
+    };
+
+    for (mut transform, location) in &mut wall_query {
+        *transform = location.transform(&arena, &config);
+    }
+
+    for (mut transform, line) in &mut line_query {
+        transform.translation = line.position(&arena, &config).extend(transform.translation.z);
+    }
+
+    for mut transform in &mut background_query {
+        transform.scale = arena.view_size(&config).extend(1.0);
+    }
+
+    let scale = Vec2::new(arena.width() / old_arena.width(), arena.height() / old_arena.height());
+    for (mut transform, seat, half) in &mut paddle_query {
+        let along = seat.axis().index();
+        let across = 1 - along;
+        transform.translation[across] = paddle_line(*seat, &arena, &config);
+        let (lower_bound, upper_bound) =
+            paddle_bounds(&arena, &config, seat.axis(), half.copied(), transform.scale[along]);
+        transform.translation[along] = (transform.translation[along] * scale[along]).clamp(lower_bound, upper_bound);
+    }
+
+    for mut transform in &mut ball_query {
+        transform.translation.x *= scale.x;
+        transform.translation.y *= scale.y;
+    }
+}
@@ -0,0 +1,152 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{components::*, events::*};
+#[cfg(feature = "music")]
+use crate::resources::GameState;
+
+// Loaded once in `load_sounds` rather than every time a sound plays
+#[derive(Resource)]
+pub(crate) struct SoundEffects {
+    wall: Handle<AudioSource>,
+    paddle: Handle<AudioSource>,
+    goal: Handle<AudioSource>,
+}
+
+/// Player controlled sound options
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    /// Silences every sound the game makes, toggled with M
+    pub muted: bool,
+    /// Plays music during matches, toggled with N while paused.
+    /// Only has an effect with the `music` feature enabled
+    pub music_enabled: bool,
+    /// From 0 to 1, applies to both music and sound effects
+    pub volume: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            muted: false,
+            music_enabled: true,
+            volume: 0.5,
+        }
+    }
+}
+
+// The looping music track. It is paused rather than stopped outside of matches,
+// so it carries on from where it was instead of starting over every match
+#[cfg(feature = "music")]
+#[derive(Resource)]
+pub(crate) struct Music {
+    source: Handle<AudioSource>,
+    sink: Option<Handle<bevy::audio::AudioSink>>,
+    // Where the fade in or out has got to, from 0 to 1
+    fade: f32,
+}
+
+pub(crate) fn load_sounds(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SoundEffects {
+        wall: asset_server.load("sounds/wall.ogg"),
+        paddle: asset_server.load("sounds/paddle.ogg"),
+        goal: asset_server.load("sounds/goal.ogg"),
+    });
+    #[cfg(feature = "music")]
+    commands.insert_resource(Music {
+        source: asset_server.load("sounds/music.ogg"),
+        sink: None,
+        fade: 0.0,
+    });
+}
+
+pub(crate) fn play_sound_effects(
+    audio: Res<Audio>,
+    audio_settings: Res<AudioSettings>,
+    sounds: Res<SoundEffects>,
+    paddle_query: Query<(), With<Paddle>>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut goal_events: EventReader<GoalEvent>,
+) {
+    if audio_settings.muted {
+        // don't let the events pile up and all play at once when unmuting
+        collision_events.clear();
+        goal_events.clear();
+        return;
+    }
+
+    let settings = PlaybackSettings::ONCE.with_volume(audio_settings.volume);
+
+    for event in collision_events.iter() {
+        if paddle_query.contains(event.collider) {
+            audio.play_with_settings(sounds.paddle.clone(), settings.clone());
+        } else {
+            audio.play_with_settings(sounds.wall.clone(), settings.clone());
+        }
+    }
+
+    for _ in goal_events.iter() {
+        audio.play_with_settings(sounds.goal.clone(), settings.clone());
+    }
+}
+
+pub(crate) fn toggle_mute(keyboard_input: Res<Input<KeyCode>>, mut audio_settings: ResMut<AudioSettings>) {
+    if keyboard_input.just_pressed(KeyCode::M) {
+        audio_settings.muted = !audio_settings.muted;
+    }
+}
+
+#[cfg(feature = "music")]
+pub(crate) fn toggle_music(keyboard_input: Res<Input<KeyCode>>, mut audio_settings: ResMut<AudioSettings>) {
+    if keyboard_input.just_pressed(KeyCode::N) {
+        audio_settings.music_enabled = !audio_settings.music_enabled;
+    }
+}
+
+// How long the music takes to fade in or out, in seconds
+#[cfg(feature = "music")]
+const MUSIC_FADE_TIME: f32 = 1.0;
+
+// Fades the music in during matches and out everywhere else
+#[cfg(feature = "music")]
+pub(crate) fn update_music(
+    time: Res<Time>,
+    audio: Res<Audio>,
+    audio_sinks: Res<Assets<bevy::audio::AudioSink>>,
+    audio_settings: Res<AudioSettings>,
+    state: Res<State<GameState>>,
+    mut music: ResMut<Music>,
+) {
+    let audible = *state.current() == GameState::Playing
+        && audio_settings.music_enabled
+        && !audio_settings.muted;
+
+    let fade_step = time.delta_seconds() / MUSIC_FADE_TIME;
+    music.fade = if audible {
+        (music.fade + fade_step).min(1.0)
+    } else {
+        (music.fade - fade_step).max(0.0)
+    };
+
+    let Some(sink_handle) = &music.sink else {
+        if audible {
+            let settings = PlaybackSettings::LOOP.with_volume(0.0);
+            let sink = audio.play_with_settings(music.source.clone(), settings);
+            music.sink = Some(audio_sinks.get_handle(sink));
+        }
+        return;
+    };
+
+    // the sink only shows up once the track has finished loading
+    let Some(sink) = audio_sinks.get(sink_handle) else {
+        return;
+    };
+
+    sink.set_volume(audio_settings.volume * music.fade);
+    if music.fade > 0.0 {
+        sink.play();
+    } else {
+        sink.pause();
+    }
+}
@@ -0,0 +1,256 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A paddle, which also carries the `Seat` it defends and the `PaddleAxis` it slides along.
+/// The left and right paddles carry their `Side` too
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Paddle;
+
+/// One half of the arena. Paddles and score texts carry the side they belong to.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Reflect, FromReflect)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    pub fn opponent(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+}
+
+/// The wall a paddle defends. The left and right paddles are always there, the top and bottom ones
+/// only join in for four-player matches
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, FromReflect)]
+pub enum Seat {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Seat {
+    pub const ALL: [Seat; 4] = [Seat::Left, Seat::Right, Seat::Top, Seat::Bottom];
+
+    pub fn axis(self) -> PaddleAxis {
+        match self {
+            Seat::Left | Seat::Right => PaddleAxis::Vertical,
+            Seat::Top | Seat::Bottom => PaddleAxis::Horizontal,
+        }
+    }
+
+    /// The half of the arena the seat plays from, only the left and right ones have one
+    pub fn side(self) -> Option<Side> {
+        match self {
+            Seat::Left => Some(Side::Left),
+            Seat::Right => Some(Side::Right),
+            Seat::Top | Seat::Bottom => None,
+        }
+    }
+}
+
+impl From<Side> for Seat {
+    fn from(side: Side) -> Seat {
+        match side {
+            Side::Left => Seat::Left,
+            Side::Right => Seat::Right,
+        }
+    }
+}
+
+/// The way a paddle slides: up and down in front of the left and right walls,
+/// or across in front of the top and bottom ones
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaddleAxis {
+    Vertical,
+    Horizontal,
+}
+
+impl PaddleAxis {
+    /// Which coordinate the paddle moves along, 0 for x and 1 for y
+    pub fn index(self) -> usize {
+        match self {
+            PaddleAxis::Vertical => 1,
+            PaddleAxis::Horizontal => 0,
+        }
+    }
+
+    /// The direction the paddle moves in for a positive `PaddleVelocity`, up or to the right
+    pub fn unit(self) -> Vec2 {
+        match self {
+            PaddleAxis::Vertical => Vec2::Y,
+            PaddleAxis::Horizontal => Vec2::X,
+        }
+    }
+}
+
+/// The half of its side a paddle is kept to in a doubles match, where each side has two.
+/// The left and right paddles take the upper half, and their partners the lower
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Half {
+    Upper,
+    Lower,
+}
+
+/// How fast a paddle moves, in `Transform` units per second. Spawned from `PongConfig::paddle_speed`,
+/// and changing it gives one paddle a handicap. Its size is its `Transform::scale`
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct PaddleSpeed(pub f32);
+
+/// How fast a paddle moved along its `PaddleAxis` over the last physics step, positive going up
+/// or to the right. With `MatchSettings::paddle_inertia` it's also what the paddle carries into the next step.
+/// A ball it hits picks up some of it, see `PongConfig::paddle_spin`
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct PaddleVelocity(pub f32);
+
+/// What a paddle's player wants it to do on the current physics step, whatever they're playing with.
+/// The `PaddleInputs` (the keyboard, a replay or the other player online), the mouse, a finger and the computer
+/// each set it for the paddles they control at the start of `PhysicsSet::Paddles`, and every paddle is then
+/// moved by it the same way. Pausing isn't part of it, as no steps are played while the match is paused
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct PaddleAction {
+    /// How fast to move along the paddle's `PaddleAxis`, from -1 to 1 as a fraction of its `PaddleSpeed`.
+    /// Positive is up, or right for the top and bottom
+    pub axis: f32,
+    /// Whether the serve key is held. Serves are on a timer, so nothing waits for it yet
+    pub serve: bool,
+    /// Whether the dash key is held. Pressing it while moving starts a `PaddleDash`
+    pub dash: bool,
+}
+
+/// A paddle's dash, with `MatchSettings::paddle_dash`: how much of the one under way is left and which way
+/// it's going, and how long until the paddle can dash again. It takes over from the `PaddleAction` for as
+/// long as it lasts
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct PaddleDash {
+    /// Seconds left of the dash under way, 0 when the paddle isn't dashing
+    pub remaining: f32,
+    /// 1 dashing up or right, -1 down or left
+    pub direction: f32,
+    /// Seconds until the paddle can dash again
+    pub cooldown: f32,
+    // Whether the dash key was held on the last step, so holding it down only dashes the once
+    pub(crate) held: bool,
+}
+
+// Where a paddle was along its `PaddleAxis` at the end of the last physics step, if it's had one
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub(crate) struct LastPaddlePosition(pub(crate) Option<f32>);
+
+// A paddle moved by the computer instead of the keyboard
+#[derive(Component)]
+pub struct CpuControlled;
+
+// A paddle that follows the mouse cursor instead of the keyboard
+#[derive(Component)]
+pub struct MouseControlled;
+
+/// A ball in play, or waiting to be served
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Ball;
+
+// Belongs to the match in progress, rather than the arena or the UI,
+// and is despawned when the match is restarted
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub(crate) struct MatchEntity;
+
+/// How far a ball, or a moving obstacle, moves each second, in `Transform` units
+#[derive(Component, Deref, DerefMut, Reflect, Default)]
+#[reflect(Component)]
+pub struct Velocity(pub Vec2);
+
+#[derive(Component)]
+pub(crate) struct Collider;
+
+/// A block in the middle of the arena that the ball bounces straight off, in obstacle mode.
+/// One with a `Velocity` patrols the center line, and gives the ball some of its speed
+#[derive(Component)]
+pub struct Obstacle;
+
+#[derive(Component)]
+pub(crate) struct Goal;
+
+// Fills the arena with the background color, for when the window around it is letterboxed
+#[derive(Component)]
+pub(crate) struct ArenaBackground;
+
+// How long a ball has been on the same side of the center line
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub(crate) struct BallWatchdog {
+    pub(crate) on_right: bool,
+    pub(crate) time_on_side: f32,
+}
+
+// A ball sitting still until the serve countdown finishes,
+// then it's served towards the given seat, or a random one
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub(crate) struct AwaitingServe(pub(crate) Option<Seat>);
+
+/// What a power-up does to a paddle when a ball runs into it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerUpKind {
+    /// The paddle of whoever last hit the ball grows by half
+    Grow,
+    /// Their opponent's paddle shrinks by a third
+    Shrink,
+    /// The ball goes half as fast again, until the next goal
+    Turbo,
+    /// The ball splits into three, up to `PongConfig::max_balls`
+    MultiBall,
+}
+
+impl PowerUpKind {
+    /// Every kind, which the spawner picks between
+    pub const ALL: [PowerUpKind; 4] =
+        [PowerUpKind::Grow, PowerUpKind::Shrink, PowerUpKind::Turbo, PowerUpKind::MultiBall];
+
+    /// How much a paddle's height is multiplied by while it lasts, for the kinds that resize one
+    pub fn scale(self) -> Option<f32> {
+        match self {
+            PowerUpKind::Grow => Some(1.5),
+            PowerUpKind::Shrink => Some(1.0 / 1.5),
+            PowerUpKind::Turbo | PowerUpKind::MultiBall => None,
+        }
+    }
+}
+
+/// A power-up waiting in the arena for a ball to collect it, until its lifetime runs out
+#[derive(Component, Debug, Clone)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+    pub lifetime: Timer,
+}
+
+impl PowerUp {
+    pub fn new(kind: PowerUpKind, lifetime: f32) -> PowerUp {
+        PowerUp {
+            kind,
+            lifetime: Timer::from_seconds(lifetime, TimerMode::Once),
+        }
+    }
+}
+
+/// A power-up's effect on a paddle, which goes back to its old height once the timer finishes.
+/// Collecting another one while it lasts replaces it
+#[derive(Component, Debug, Clone)]
+pub struct ActiveEffect {
+    pub kind: PowerUpKind,
+    pub timer: Timer,
+    // the height the paddle had before any effect, to go back to
+    pub(crate) original_height: f32,
+}
+
+/// A ball sped up by a turbo power-up until the next goal, holding how much faster it was made
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Turbo(pub f32);
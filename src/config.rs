@@ -0,0 +1,398 @@
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadState, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    window::PresentMode,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{resources::*, skins::FLAT_SKIN};
+
+// These constants are defined in `Transform` units.
+// Using the default 2D camera they correspond 1:1 with screen pixels.
+// Those that can be tweaked are only defaults, the game reads them from `PongConfig`.
+const PADDLE_SIZE: Vec2 = Vec2::new(20.0, 120.0);
+const GAP_BETWEEN_PADDLE_AND_SIDES: f32 = 60.0;
+const PADDLE_SPEED: f32 = 500.0;
+// How close can the paddle get to the wall
+const PADDLE_PADDING: f32 = 10.0;
+const PADDLE_SPIN: f32 = 0.25;
+// With paddle inertia, the paddles take a sixth of a second to get up to speed and a little less to stop
+const PADDLE_ACCELERATION: f32 = 3000.0;
+const PADDLE_DECELERATION: f32 = 4000.0;
+const DASH_DISTANCE: f32 = 150.0;
+const DASH_DURATION: f32 = 0.1;
+const DASH_COOLDOWN: f32 = 2.0;
+
+const BALL_STARTING_POSITION: Vec2 = Vec2::new(0.0, -50.0);
+// We set the z-value of the ball to 1 so it renders on top in the case of overlapping sprites.
+const BALL_Z: f32 = 1.0;
+const BALL_SIZE: Vec2 = Vec2::new(30.0, 30.0);
+const BALL_SPEED: f32 = 400.0;
+// The rally speed up stops short of this anyway, it's a backstop against the ball tunnelling
+const MAX_BALL_SPEED: f32 = 900.0;
+// The least of the ball's speed that goes across the arena rather than up and down it
+const MIN_HORIZONTAL_SPEED_FRACTION: f32 = 0.25;
+// The steepest angle (from horizontal) the ball can be served at,
+// in degrees so it reads naturally in the config file
+const MAX_SERVE_ANGLE: f32 = 60.0;
+const WALL_THICKNESS: f32 = 10.0;
+const CENTER_LINE_SIZE: Vec2 = Vec2::new(5.0, 20.0);
+// The default arena, used in `ArenaScaling::Fixed` mode
+// x coordinates
+const LEFT_WALL: f32 = -450.;
+const RIGHT_WALL: f32 = 450.;
+// y coordinates
+const BOTTOM_WALL: f32 = -300.;
+const TOP_WALL: f32 = 300.;
+
+const SCOREBOARD_FONT_SIZE: f32 = 40.0;
+const BACKGROUND_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+const PADDLE_COLOR: Color = Color::rgb(0.3, 0.3, 0.7);
+const BALL_COLOR: Color = Color::rgb(1.0, 0.5, 0.5);
+const WALL_COLOR: Color = Color::rgb(0.8, 0.8, 0.8);
+const TEXT_COLOR: Color = Color::rgb(0.5, 0.5, 1.0);
+const PADDLE_FLASH_COLOR: Color = Color::WHITE;
+const SCREEN_SHAKE: f32 = 12.0;
+
+const POWER_UP_INTERVAL: f32 = 8.0;
+const POWER_UP_LIFETIME: f32 = 12.0;
+const MAX_BALLS: usize = 5;
+const OBSTACLE_SIZE: Vec2 = Vec2::new(20.0, 60.0);
+const MOVING_OBSTACLE_SPEED: f32 = 150.0;
+const RUBBER_BAND_GAP: usize = 3;
+const RUBBER_BAND_RATE: f32 = 0.5;
+
+/// The sizes, speeds and colors of everything in the game.
+///
+/// Insert one before adding `PongPlugin` to change them, it is only read when things are spawned
+/// and moved so changing it mid-match gives mixed results.
+/// It can also be loaded from a RON file, see `PongPlugin::config_path` and `assets/pong.ron`.
+/// Fields left out of the file keep their defaults, and colors are written as hex strings.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize, TypeUuid, Reflect)]
+#[uuid = "6f3c2b0e-5a41-4d2e-9c7b-8e1f0a3d5b27"]
+#[serde(default)]
+#[reflect(Resource)]
+pub struct PongConfig {
+    /// The size both paddles start with, each can be resized through its `Transform::scale`
+    pub paddle_size: Vec2,
+    /// How far each paddle's center is from the wall behind it
+    pub paddle_gap: f32,
+    /// The speed both paddles start with, see `PaddleSpeed`
+    pub paddle_speed: f32,
+    /// How close a paddle can get to the top and bottom walls
+    pub paddle_padding: f32,
+    /// The fraction of a moving paddle's velocity added to the ball it hits, to slice shots up or down
+    pub paddle_spin: f32,
+    /// How quickly a paddle picks up speed, in `Transform` units per second squared,
+    /// when `MatchSettings::paddle_inertia` is on
+    pub paddle_acceleration: f32,
+    /// How quickly a paddle slows down once it's let go, or turned around
+    pub paddle_deceleration: f32,
+    /// How far a paddle dashes, when `MatchSettings::paddle_dash` is on. It stops short at the end of its travel
+    pub dash_distance: f32,
+    /// How many seconds a dash takes to cover its distance
+    pub dash_duration: f32,
+    /// How many seconds from the start of a dash until the paddle can dash again
+    pub dash_cooldown: f32,
+    pub ball_size: Vec2,
+    /// Where the ball is served from
+    pub ball_starting_position: Vec2,
+    pub ball_speed: f32,
+    /// Nothing can make the ball go faster than this
+    pub max_ball_speed: f32,
+    /// After a bounce at least this fraction of the ball's speed is horizontal,
+    /// so it can't crawl across the arena bouncing between the top and bottom walls
+    pub min_horizontal_speed_fraction: f32,
+    /// The steepest a serve can be, in degrees from horizontal.
+    /// Near vertical serves take ages to reach either paddle
+    pub max_serve_angle: f32,
+    pub wall_thickness: f32,
+    /// The size of each dash of the dotted line down the middle
+    pub center_line_size: Vec2,
+    /// The size of the arena when it doesn't fit the window, see `ArenaScaling`
+    pub arena_size: Vec2,
+    pub font_size: f32,
+    #[serde(with = "hex_color")]
+    pub background_color: Color,
+    #[serde(with = "hex_color")]
+    pub paddle_color: Color,
+    #[serde(with = "hex_color")]
+    pub ball_color: Color,
+    #[serde(with = "hex_color")]
+    pub wall_color: Color,
+    #[serde(with = "hex_color")]
+    pub text_color: Color,
+    /// The color a paddle flashes when it hits the ball, fading back to its own
+    #[serde(with = "hex_color")]
+    pub paddle_flash_color: Color,
+    /// How far the camera shakes for a goal at `max_ball_speed`, less for slower balls. 0 turns it off
+    pub screen_shake: f32,
+    /// How frames are put on screen, unless another is picked in the settings menu. `AutoVsync` never tears,
+    /// `AutoNoVsync` and `Immediate` have less latency. The game plays at the same speed whichever it is
+    #[reflect(ignore)]
+    pub present_mode: PresentMode,
+    /// Pauses the match when the window loses focus, like when alt-tabbing away. It doesn't unpause
+    /// on coming back, that still takes the pause key
+    pub auto_pause_on_focus_loss: bool,
+    /// How many seconds the arena goes without a power-up, when they're turned on in `MatchSettings`
+    pub power_up_interval: f32,
+    /// How many seconds a power-up waits to be collected before it disappears
+    pub power_up_lifetime: f32,
+    /// How many balls can be in play at once, a multi-ball power-up adds no more past it
+    pub max_balls: usize,
+    /// The size of each obstacle, when they're turned on in `MatchSettings`
+    pub obstacle_size: Vec2,
+    /// Where the obstacles go. When left out a few are scattered around the center line, differently each match
+    pub obstacles: Option<Vec<Vec2>>,
+    /// How fast the obstacle patrolling the center line moves, when it's turned on in `MatchSettings`
+    pub moving_obstacle_speed: f32,
+    /// How many points either side has to be ahead by before the computer starts playing easier or harder,
+    /// in rubber band mode. See `MatchSettings::rubber_band`
+    pub rubber_band_gap: usize,
+    /// How many notches of `Difficulty` the computer moves for each point of lead from `rubber_band_gap` on.
+    /// It never moves more than one notch from the difficulty picked in the menu
+    pub rubber_band_rate: f32,
+    /// The skin the paddles and ball are drawn with, from `assets/skins/<skin>/paddle.png` and `ball.png`.
+    /// "flat" draws them in the theme's plain colors and needs no images
+    pub skin: String,
+    /// Seeds the serves so matches can be repeated, random when left out.
+    /// A seed from the command line or the page's URL takes precedence
+    pub seed: Option<u64>,
+}
+
+impl Default for PongConfig {
+    fn default() -> Self {
+        PongConfig {
+            paddle_size: PADDLE_SIZE,
+            paddle_gap: GAP_BETWEEN_PADDLE_AND_SIDES,
+            paddle_speed: PADDLE_SPEED,
+            paddle_padding: PADDLE_PADDING,
+            paddle_spin: PADDLE_SPIN,
+            paddle_acceleration: PADDLE_ACCELERATION,
+            paddle_deceleration: PADDLE_DECELERATION,
+            dash_distance: DASH_DISTANCE,
+            dash_duration: DASH_DURATION,
+            dash_cooldown: DASH_COOLDOWN,
+            ball_size: BALL_SIZE,
+            ball_starting_position: BALL_STARTING_POSITION,
+            ball_speed: BALL_SPEED,
+            max_ball_speed: MAX_BALL_SPEED,
+            min_horizontal_speed_fraction: MIN_HORIZONTAL_SPEED_FRACTION,
+            max_serve_angle: MAX_SERVE_ANGLE,
+            wall_thickness: WALL_THICKNESS,
+            center_line_size: CENTER_LINE_SIZE,
+            arena_size: Vec2::new(RIGHT_WALL - LEFT_WALL, TOP_WALL - BOTTOM_WALL),
+            font_size: SCOREBOARD_FONT_SIZE,
+            background_color: BACKGROUND_COLOR,
+            paddle_color: PADDLE_COLOR,
+            ball_color: BALL_COLOR,
+            wall_color: WALL_COLOR,
+            text_color: TEXT_COLOR,
+            paddle_flash_color: PADDLE_FLASH_COLOR,
+            screen_shake: SCREEN_SHAKE,
+            present_mode: PresentMode::AutoVsync,
+            auto_pause_on_focus_loss: true,
+            power_up_interval: POWER_UP_INTERVAL,
+            power_up_lifetime: POWER_UP_LIFETIME,
+            max_balls: MAX_BALLS,
+            obstacle_size: OBSTACLE_SIZE,
+            obstacles: None,
+            moving_obstacle_speed: MOVING_OBSTACLE_SPEED,
+            rubber_band_gap: RUBBER_BAND_GAP,
+            rubber_band_rate: RUBBER_BAND_RATE,
+            skin: FLAT_SKIN.to_string(),
+            seed: None,
+        }
+    }
+}
+
+impl PongConfig {
+    /// Where a new ball is spawned
+    pub(crate) fn ball_spawn_point(&self) -> Vec3 {
+        self.ball_starting_position.extend(BALL_Z)
+    }
+
+    /// How many notches of `Difficulty` rubber banding moves the computer by when the player
+    /// is `player_lead` points ahead of it, up for a lead and down when they're behind
+    pub fn rubber_band_shift(&self, player_lead: i64) -> f32 {
+        let past_gap = player_lead.unsigned_abs() as f32 - self.rubber_band_gap as f32 + 1.0;
+        if past_gap <= 0.0 {
+            return 0.0;
+        }
+        (past_gap * self.rubber_band_rate).clamp(0.0, 1.0) * player_lead.signum() as f32
+    }
+
+    // Puts back the default for anything the game can't play with, like a negative speed or a dash that takes
+    // no time, and brings the rest into range. Each is warned about, so a typo in the file doesn't go unnoticed
+    pub(crate) fn validate(&mut self) {
+        let defaults = PongConfig::default();
+
+        for (name, value, default) in [
+            ("paddle_speed", &mut self.paddle_speed, defaults.paddle_speed),
+            ("paddle_acceleration", &mut self.paddle_acceleration, defaults.paddle_acceleration),
+            ("paddle_deceleration", &mut self.paddle_deceleration, defaults.paddle_deceleration),
+            ("dash_duration", &mut self.dash_duration, defaults.dash_duration),
+            ("ball_speed", &mut self.ball_speed, defaults.ball_speed),
+            ("max_ball_speed", &mut self.max_ball_speed, defaults.max_ball_speed),
+            ("font_size", &mut self.font_size, defaults.font_size),
+            ("power_up_interval", &mut self.power_up_interval, defaults.power_up_interval),
+            ("power_up_lifetime", &mut self.power_up_lifetime, defaults.power_up_lifetime),
+        ] {
+            if !(value.is_finite() && *value > 0.0) {
+                warn!("The config's {name} of {value} has to be more than 0, using {default} instead");
+                *value = default;
+            }
+        }
+
+        for (name, value, default) in [
+            ("paddle_gap", &mut self.paddle_gap, defaults.paddle_gap),
+            ("paddle_padding", &mut self.paddle_padding, defaults.paddle_padding),
+            ("dash_distance", &mut self.dash_distance, defaults.dash_distance),
+            ("dash_cooldown", &mut self.dash_cooldown, defaults.dash_cooldown),
+            ("wall_thickness", &mut self.wall_thickness, defaults.wall_thickness),
+            ("screen_shake", &mut self.screen_shake, defaults.screen_shake),
+            ("moving_obstacle_speed", &mut self.moving_obstacle_speed, defaults.moving_obstacle_speed),
+            // a negative rate would sharpen the computer for a player who's losing
+            ("rubber_band_rate", &mut self.rubber_band_rate, defaults.rubber_band_rate),
+        ] {
+            if !(value.is_finite() && *value >= 0.0) {
+                warn!("The config's {name} of {value} can't be negative, using {default} instead");
+                *value = default;
+            }
+        }
+
+        for (name, size, default) in [
+            ("paddle_size", &mut self.paddle_size, defaults.paddle_size),
+            ("ball_size", &mut self.ball_size, defaults.ball_size),
+            ("center_line_size", &mut self.center_line_size, defaults.center_line_size),
+            ("arena_size", &mut self.arena_size, defaults.arena_size),
+            ("obstacle_size", &mut self.obstacle_size, defaults.obstacle_size),
+        ] {
+            if !(size.is_finite() && size.min_element() > 0.0) {
+                warn!("The config's {name} of {size} has to be more than 0 each way, using {default} instead");
+                *size = default;
+            }
+        }
+
+        if self.max_ball_speed < self.ball_speed {
+            warn!(
+                "The config's max_ball_speed of {} is slower than its ball_speed, raising it to {}",
+                self.max_ball_speed, self.ball_speed
+            );
+            self.max_ball_speed = self.ball_speed;
+        }
+        let fraction = self.min_horizontal_speed_fraction;
+        if !(0.0..=1.0).contains(&fraction) {
+            let clamped = if fraction.is_nan() {
+                defaults.min_horizontal_speed_fraction
+            } else {
+                fraction.clamp(0.0, 1.0)
+            };
+            warn!("The config's min_horizontal_speed_fraction of {fraction} is outside 0 to 1, using {clamped}");
+            self.min_horizontal_speed_fraction = clamped;
+        }
+        let angle = self.max_serve_angle;
+        if !(0.0..=90.0).contains(&angle) {
+            let clamped = if angle.is_nan() { defaults.max_serve_angle } else { angle.clamp(0.0, 90.0) };
+            warn!("The config's max_serve_angle of {angle} is outside 0 to 90 degrees, using {clamped}");
+            self.max_serve_angle = clamped;
+        }
+        if self.max_balls == 0 {
+            warn!("The config's max_balls can't be 0, using 1 instead");
+            self.max_balls = 1;
+        }
+    }
+}
+
+// (De)serializes colors as "#rrggbb" or "#rrggbbaa" strings, in sRGB
+mod hex_color {
+    use bevy::prelude::Color;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        let [r, g, b, a] = color.as_rgba_f32().map(|channel| (channel * 255.0).round() as u8);
+        if a == u8::MAX {
+            serializer.serialize_str(&format!("#{r:02x}{g:02x}{b:02x}"))
+        } else {
+            serializer.serialize_str(&format!("#{r:02x}{g:02x}{b:02x}{a:02x}"))
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        Color::hex(hex.trim_start_matches('#'))
+            .map_err(|err| D::Error::custom(format!("invalid color {hex:?}: {err:?}")))
+    }
+}
+
+// Reads a `PongConfig` from RON. Going through the asset server means this also works on the web,
+// where the assets folder is fetched over HTTP
+#[derive(Default)]
+pub(crate) struct PongConfigLoader;
+
+impl AssetLoader for PongConfigLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let config = ron::de::from_bytes::<PongConfig>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(config));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
+
+// The config file from `PongPlugin::config_path`, while it's being loaded
+#[derive(Resource)]
+pub(crate) struct ConfigFile {
+    pub(crate) path: Option<String>,
+    pub(crate) handle: Option<Handle<PongConfig>>,
+}
+
+// Without an asset server, in a headless app, the game goes with the `PongConfig` it has
+pub(crate) fn load_config(asset_server: Option<Res<AssetServer>>, mut config_file: ResMut<ConfigFile>) {
+    if let (Some(path), Some(asset_server)) = (&config_file.path, asset_server) {
+        config_file.handle = Some(asset_server.load(path.as_str()));
+    }
+}
+
+// Holds off spawning anything until the config file has either loaded or failed to
+pub(crate) fn wait_for_config(
+    asset_server: Option<Res<AssetServer>>,
+    mut config: ResMut<PongConfig>,
+    configs: Option<ResMut<Assets<PongConfig>>>,
+    config_file: Res<ConfigFile>,
+    skip_menu: Option<Res<SkipMenu>>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if let (Some(handle), Some(asset_server), Some(mut configs)) = (&config_file.handle, asset_server, configs) {
+        match asset_server.get_load_state(handle) {
+            LoadState::Loaded => {
+                if let Some(loaded_config) = configs.remove(handle) {
+                    *config = loaded_config;
+                }
+            }
+            LoadState::Failed => {
+                warn!(
+                    "Couldn't load the config from {:?}, using the defaults",
+                    config_file.path.as_deref().unwrap_or_default()
+                );
+            }
+            _ => return,
+        }
+    }
+    config.validate();
+
+    if skip_menu.is_some() {
+        state.set(GameState::Playing).unwrap();
+    } else {
+        state.set(GameState::Menu).unwrap();
+    }
+}
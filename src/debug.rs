@@ -0,0 +1,231 @@
+use bevy::{
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+    utils::HashSet,
+};
+
+use crate::{components::*, config::*, theme::*, ui::UiFont};
+
+// How often the numbers in the FPS overlay change, in seconds, slow enough to read them
+const FPS_REFRESH_INTERVAL: f32 = 0.25;
+const FPS_TEXT_PADDING: f32 = 5.0;
+// The collider outlines, drawn in front of everything
+const OUTLINE_THICKNESS: f32 = 1.5;
+const OUTLINE_Z: f32 = 5.0;
+const OUTLINE_COLOR: Color = Color::rgb(0.0, 1.0, 0.0);
+const VELOCITY_COLOR: Color = Color::rgb(1.0, 0.0, 1.0);
+// How far ahead of the ball its velocity line reaches, in seconds of travel
+const VELOCITY_LINE_TIME: f32 = 0.2;
+
+// Counts the physics steps run since the last frame was drawn
+#[derive(Resource, Default)]
+pub(crate) struct PhysicsStepCounter(u32);
+
+// The FPS overlay in the top left corner, toggled with F3. It's only text on screen,
+// nothing of it goes into a replay
+#[derive(Component)]
+pub(crate) struct FpsText;
+
+// Whether the collider outlines are showing, toggled with F4
+#[derive(Resource, Default)]
+pub(crate) struct ShowColliders(bool);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Edge {
+    const ALL: [Edge; 4] = [Edge::Top, Edge::Bottom, Edge::Left, Edge::Right];
+
+    // Where the edge of a box goes, and its size, given the box's center and size
+    fn transform(self, center: Vec2, size: Vec2) -> Transform {
+        // long enough to cover the corners
+        let across = Vec2::new(size.x + OUTLINE_THICKNESS, OUTLINE_THICKNESS);
+        let down = Vec2::new(OUTLINE_THICKNESS, size.y + OUTLINE_THICKNESS);
+        let (offset, scale) = match self {
+            Edge::Top => (Vec2::new(0.0, size.y / 2.0), across),
+            Edge::Bottom => (Vec2::new(0.0, -size.y / 2.0), across),
+            Edge::Left => (Vec2::new(-size.x / 2.0, 0.0), down),
+            Edge::Right => (Vec2::new(size.x / 2.0, 0.0), down),
+        };
+        Transform::from_translation((center + offset).extend(OUTLINE_Z)).with_scale(scale.extend(1.0))
+    }
+}
+
+// One side of the box a collider or ball collides as, which for the ball isn't the circle that's drawn
+#[derive(Component)]
+pub(crate) struct OutlineEdge {
+    target: Entity,
+    edge: Edge,
+}
+
+// The way a ball, or a moving obstacle, is heading, and how fast
+#[derive(Component)]
+pub(crate) struct VelocityLine {
+    target: Entity,
+}
+
+pub(crate) fn count_physics_steps(mut counter: ResMut<PhysicsStepCounter>) {
+    counter.0 += 1;
+}
+
+pub(crate) fn toggle_fps_overlay(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    font: Res<UiFont>,
+    config: Res<PongConfig>,
+    theme: Res<Theme>,
+    text_query: Query<Entity, With<FpsText>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F3) {
+        return;
+    }
+
+    if let Ok(text) = text_query.get_single() {
+        commands.entity(text).despawn_recursive();
+        return;
+    }
+
+    let style = TextStyle {
+        font: font.0.clone(),
+        font_size: config.font_size / 2.0,
+        color: theme.text,
+    };
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new("", style.clone()),
+            TextSection::new("", style.clone()),
+            TextSection::new("", style),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(FPS_TEXT_PADDING),
+                left: Val::Px(FPS_TEXT_PADDING),
+                ..default()
+            },
+            ..default()
+        }),
+        FpsText,
+        Themed(ThemeRole::Text),
+    ));
+}
+
+// Shows the frame rate and frame time, averaged by `FrameTimeDiagnosticsPlugin`,
+// and how many physics steps the last frame ran
+pub(crate) fn update_fps_overlay(
+    time: Res<Time>,
+    diagnostics: Res<Diagnostics>,
+    mut counter: ResMut<PhysicsStepCounter>,
+    mut refresh: Local<Option<Timer>>,
+    mut text_query: Query<&mut Text, With<FpsText>>,
+) {
+    let steps = std::mem::take(&mut counter.0);
+    let refresh = refresh.get_or_insert_with(|| Timer::from_seconds(FPS_REFRESH_INTERVAL, TimerMode::Repeating));
+    if !refresh.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let average = |id| diagnostics.get(id).and_then(|diagnostic| diagnostic.average());
+    let fps = average(FrameTimeDiagnosticsPlugin::FPS).unwrap_or_default();
+    // measured in seconds
+    let frame_time = average(FrameTimeDiagnosticsPlugin::FRAME_TIME).unwrap_or_default() * 1000.0;
+    for mut text in &mut text_query {
+        text.sections[0].value = format!("FPS: {fps:.0}\n");
+        text.sections[1].value = format!("Frame time: {frame_time:.1} ms\n");
+        text.sections[2].value = format!("Physics steps: {steps}");
+    }
+}
+
+pub(crate) fn toggle_collider_outlines(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut show_colliders: ResMut<ShowColliders>,
+    outline_query: Query<Entity, Or<(With<OutlineEdge>, With<VelocityLine>)>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F4) {
+        return;
+    }
+
+    show_colliders.0 = !show_colliders.0;
+    if !show_colliders.0 {
+        for outline in &outline_query {
+            commands.entity(outline).despawn_recursive();
+        }
+    }
+}
+
+// Keeps the outlines on their colliders and balls as they move and change size,
+// adding them for anything new and taking them away from anything that's gone
+pub(crate) fn update_collider_outlines(
+    mut commands: Commands,
+    show_colliders: Res<ShowColliders>,
+    target_query: Query<
+        (Entity, &Transform, Option<&Velocity>),
+        (Or<(With<Collider>, With<Ball>)>, Without<OutlineEdge>, Without<VelocityLine>),
+    >,
+    mut edge_query: Query<(Entity, &OutlineEdge, &mut Transform), Without<VelocityLine>>,
+    mut line_query: Query<(Entity, &VelocityLine, &mut Transform), Without<OutlineEdge>>,
+) {
+    if !show_colliders.0 {
+        return;
+    }
+
+    let mut outlined = HashSet::default();
+    for (edge_entity, edge, mut transform) in &mut edge_query {
+        let Ok((_, target, _)) = target_query.get(edge.target) else {
+            commands.entity(edge_entity).despawn_recursive();
+            continue;
+        };
+        outlined.insert(edge.target);
+        *transform = edge.edge.transform(target.translation.truncate(), target.scale.truncate());
+    }
+
+    let mut lined = HashSet::default();
+    for (line_entity, line, mut transform) in &mut line_query {
+        let Ok((_, target, Some(velocity))) = target_query.get(line.target) else {
+            commands.entity(line_entity).despawn_recursive();
+            continue;
+        };
+        lined.insert(line.target);
+        *transform = velocity_line_transform(target.translation.truncate(), velocity.0);
+    }
+
+    for (target, transform, velocity) in &target_query {
+        let center = transform.translation.truncate();
+        if !outlined.contains(&target) {
+            for edge in Edge::ALL {
+                let edge_transform = edge.transform(center, transform.scale.truncate());
+                commands.spawn((outline_sprite(edge_transform, OUTLINE_COLOR), OutlineEdge { target, edge }));
+            }
+        }
+        if let (Some(velocity), false) = (velocity, lined.contains(&target)) {
+            commands.spawn((
+                outline_sprite(velocity_line_transform(center, velocity.0), VELOCITY_COLOR),
+                VelocityLine { target },
+            ));
+        }
+    }
+}
+
+// A line from the center along the velocity, as far as it will go in `VELOCITY_LINE_TIME`
+fn velocity_line_transform(center: Vec2, velocity: Vec2) -> Transform {
+    let line = velocity * VELOCITY_LINE_TIME;
+    Transform {
+        translation: (center + line / 2.0).extend(OUTLINE_Z),
+        rotation: Quat::from_rotation_z(line.y.atan2(line.x)),
+        scale: Vec3::new(line.length(), OUTLINE_THICKNESS, 1.0),
+    }
+}
+
+fn outline_sprite(transform: Transform, color: Color) -> SpriteBundle {
+    SpriteBundle {
+        transform,
+        sprite: Sprite { color, ..default() },
+        ..default()
+    }
+}
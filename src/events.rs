@@ -0,0 +1,87 @@
+use bevy::{prelude::*, sprite::collide_aabb::Collision};
+
+use crate::components::*;
+
+/// Sent when the watchdog removes a ball that has got stuck or left the arena without scoring.
+/// No point is awarded, and if it was the last ball a new one is served
+pub struct BallResetEvent {
+    /// The ball that was removed
+    pub ball: Entity,
+    pub reason: BallResetReason,
+}
+
+/// What was wrong with a ball the watchdog removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BallResetReason {
+    /// It got out of the arena other than through a goal
+    Escaped,
+    /// Its velocity or position stopped being a sensible number, or it stopped moving
+    InvalidMotion,
+    /// It stayed on one side of the center line for too long
+    Stuck,
+}
+
+/// Sent when a ball leaves the arena past a paddle
+pub struct GoalEvent {
+    /// The side that gets the point
+    pub scorer: Side,
+    /// The score after this goal, as (left, right)
+    pub new_score: (usize, usize),
+    /// The ball that went in, it is despawned once the goal has been handled
+    pub ball: Entity,
+    /// How fast the ball was going as it went in
+    pub speed: f32,
+}
+
+/// Sent when a ball leaves the arena past a paddle in a four-player match, instead of a `GoalEvent`
+pub struct LifeLost {
+    /// The player whose wall it went through
+    pub seat: Seat,
+    /// The lives they have left after this one, none puts them out
+    pub lives_left: usize,
+    /// The ball that went in, it is despawned once the life has been handled
+    pub ball: Entity,
+    /// How fast the ball was going as it went in
+    pub speed: f32,
+}
+
+/// Sent when a ball gets past the left paddle in wall practice, instead of a `GoalEvent`
+pub struct BallMissed {
+    /// The paddle hits in a row the miss put an end to
+    pub streak: usize,
+    /// The ball that got past, it is despawned once the miss has been handled
+    pub ball: Entity,
+    /// How fast the ball was going as it got past
+    pub speed: f32,
+}
+
+/// Sent whenever a ball runs into a collider
+pub struct CollisionEvent {
+    pub ball: Entity,
+    /// The wall or paddle that was hit
+    pub collider: Entity,
+    /// Which side of the collider the ball hit
+    pub side: Collision,
+    /// How fast the ball was going just before the bounce
+    pub speed: f32,
+}
+
+/// Sent when a ball is sent back off a paddle, along with its `CollisionEvent`.
+/// Glancing off a paddle without being sent back isn't a hit
+pub struct PaddleHitEvent {
+    pub ball: Entity,
+    pub paddle: Entity,
+    /// The seat of the paddle that hit it
+    pub seat: Seat,
+    /// How fast the ball is going as it leaves the paddle
+    pub speed: f32,
+}
+
+/// Sent when a ball runs into a power-up. Each effect reads these and picks out the kinds it handles
+pub struct PowerUpCollected {
+    pub kind: PowerUpKind,
+    /// Whoever last hit the ball that collected it
+    pub collector_side: Side,
+    /// The ball that ran into it
+    pub ball: Entity,
+}
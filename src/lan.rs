@@ -0,0 +1,212 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::ErrorKind,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+};
+
+use crate::network::*;
+
+/// The UDP port hosts announce their games on, and that the menu listens on for them
+pub const LAN_DISCOVERY_PORT: u16 = 7879;
+/// The port a game hosted from the menu waits for the other player on
+pub const LAN_GAME_PORT: u16 = 7878;
+// Seconds between a host's announcements, and how long a game can go unheard before it's taken off the list
+const BEACON_INTERVAL: f32 = 0.5;
+const LAN_GAME_TIMEOUT: f32 = 3.0;
+
+/// What a host waiting for the other player announces on the network, twice a second
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LanBeacon {
+    /// The host's player name
+    pub name: String,
+    /// Where on the host the game is waiting
+    pub port: u16,
+}
+
+impl LanBeacon {
+    // Anything else on the port is ignored, without these in front
+    const MAGIC: &'static [u8; 8] = b"PONGLAN1";
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = LanBeacon::MAGIC.to_vec();
+        bytes.extend(bincode::serialize(self).expect("beacons always serialize"));
+        bytes
+    }
+
+    /// `None` for anything that isn't a beacon
+    pub fn from_bytes(bytes: &[u8]) -> Option<LanBeacon> {
+        let beacon = bytes.strip_prefix(LanBeacon::MAGIC)?;
+        bincode::deserialize(beacon).ok()
+    }
+}
+
+/// A game found on the network, waiting for someone to join
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanGame {
+    pub name: String,
+    /// The host's address, with the port its game is on
+    pub address: SocketAddr,
+    // seconds since the host last announced it
+    unheard: f32,
+}
+
+impl LanGame {
+    /// The address to join it at, as a `NetworkRole::Client` wants it
+    pub fn join_address(&self) -> String {
+        format!("ws://{}", self.address)
+    }
+}
+
+/// The games being announced on the network, while a `LanBrowser` is listening for them.
+/// Most recently found last
+#[derive(Resource, Debug, Default)]
+pub struct LanGames(Vec<LanGame>);
+
+impl LanGames {
+    pub fn games(&self) -> &[LanGame] {
+        &self.0
+    }
+
+    // Adds the game a beacon announces, or if it's already listed notes that it's still there.
+    // Whether the list shown needs to change, for a new game or a host that's been renamed
+    fn heard(&mut self, beacon: LanBeacon, from: SocketAddr) -> bool {
+        let address = SocketAddr::new(from.ip(), beacon.port);
+        match self.0.iter_mut().find(|game| game.address == address) {
+            Some(game) => {
+                game.unheard = 0.0;
+                let renamed = game.name != beacon.name;
+                game.name = beacon.name;
+                renamed
+            }
+            None => {
+                self.0.push(LanGame {
+                    name: beacon.name,
+                    address,
+                    unheard: 0.0,
+                });
+                true
+            }
+        }
+    }
+}
+
+/// Announces this game on the network, while it's hosting and the other player hasn't joined yet.
+/// Insert one along with a `NetworkRole::Host`, it goes once the other player joins or the match is left
+#[derive(Resource)]
+pub struct LanAnnouncer {
+    socket: UdpSocket,
+    beacon: LanBeacon,
+    target: SocketAddr,
+    // seconds until the next announcement
+    until_next: f32,
+}
+
+impl LanAnnouncer {
+    /// Broadcasts to every machine on the network, on `LAN_DISCOVERY_PORT`
+    pub fn broadcast(beacon: LanBeacon) -> std::io::Result<LanAnnouncer> {
+        LanAnnouncer::to(beacon, (Ipv4Addr::BROADCAST, LAN_DISCOVERY_PORT).into())
+    }
+
+    /// Sends only to `target`, like a `LanBrowser` in the same process
+    pub fn to(beacon: LanBeacon, target: SocketAddr) -> std::io::Result<LanAnnouncer> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_broadcast(true)?;
+        Ok(LanAnnouncer {
+            socket,
+            beacon,
+            target,
+            until_next: 0.0,
+        })
+    }
+}
+
+/// Listens for the games hosts are announcing, and keeps `LanGames` up to date with them.
+/// The menu's join page has one while it's open
+#[derive(Resource)]
+pub struct LanBrowser {
+    socket: UdpSocket,
+}
+
+impl LanBrowser {
+    /// Listens on `port`, which is `LAN_DISCOVERY_PORT` unless it's a test. 0 picks a free one
+    pub fn bind(port: u16) -> std::io::Result<LanBrowser> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))?;
+        socket.set_nonblocking(true)?;
+        Ok(LanBrowser { socket })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.socket.local_addr().map_or(0, |address| address.port())
+    }
+}
+
+// Announces the game every `BEACON_INTERVAL` until the other player has joined, or the match has been left
+pub(crate) fn announce_lan_game(
+    mut commands: Commands,
+    time: Res<Time>,
+    role: Option<Res<NetworkRole>>,
+    status: Option<Res<NetworkStatus>>,
+    announcer: Option<ResMut<LanAnnouncer>>,
+) {
+    let Some(mut announcer) = announcer else {
+        return;
+    };
+    let waiting = matches!(role.as_deref(), Some(NetworkRole::Host { .. }))
+        && status.as_deref() == Some(&NetworkStatus::Connecting);
+    if !waiting {
+        commands.remove_resource::<LanAnnouncer>();
+        return;
+    }
+
+    announcer.until_next -= time.delta_seconds();
+    if announcer.until_next > 0.0 {
+        return;
+    }
+    announcer.until_next = BEACON_INTERVAL;
+    if let Err(err) = announcer.socket.send_to(&announcer.beacon.to_bytes(), announcer.target) {
+        warn!("Couldn't announce the game on the network: {err}");
+    }
+}
+
+// Lists every game heard from since the last frame, and takes off the ones whose hosts have gone quiet,
+// having started their match or quit. Without a `LanBrowser` the list is emptied
+pub(crate) fn browse_lan_games(
+    time: Res<Time>,
+    browser: Option<Res<LanBrowser>>,
+    mut lan_games: ResMut<LanGames>,
+) {
+    let Some(browser) = browser else {
+        if !lan_games.0.is_empty() {
+            lan_games.0.clear();
+        }
+        return;
+    };
+
+    let delta = time.delta_seconds();
+    let mut changed = false;
+    for game in &mut lan_games.bypass_change_detection().0 {
+        game.unheard += delta;
+    }
+    let mut buffer = [0; 512];
+    loop {
+        match browser.socket.recv_from(&mut buffer) {
+            Ok((length, from)) => {
+                if let Some(beacon) = LanBeacon::from_bytes(&buffer[..length]) {
+                    changed |= lan_games.bypass_change_detection().heard(beacon, from);
+                }
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+            Err(err) => {
+                warn!("Couldn't look for games on the network: {err}");
+                break;
+            }
+        }
+    }
+
+    let listed = lan_games.0.len();
+    lan_games.bypass_change_detection().0.retain(|game| game.unheard < LAN_GAME_TIMEOUT);
+    if changed || lan_games.0.len() != listed {
+        lan_games.set_changed();
+    }
+}
@@ -1,456 +1,695 @@
+// Bevy queries and systems naturally end up with long type signatures and many parameters
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
+
+#[cfg(feature = "input_manager")]
+pub mod actions;
+pub mod arena;
+pub mod audio;
+pub mod components;
+pub mod config;
+pub mod debug;
+pub mod events;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod lan;
+pub mod menu;
+pub mod network;
+#[cfg(all(feature = "netplay", not(target_arch = "wasm32")))]
+pub mod netplay;
+pub mod replay;
+pub mod resources;
+pub mod save;
+pub mod skins;
+pub mod stats;
+pub mod systems;
+pub mod theme;
+pub mod ui;
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+/// Everything needed to add the game to an app and drive it, `use bevy_pong::prelude::*`
+pub mod prelude {
+    pub use crate::{
+        arena::{Arena, ArenaScaling},
+        audio::AudioSettings,
+        components::{
+            ActiveEffect, Ball, CpuControlled, Half, MouseControlled, Obstacle, Paddle, PaddleAction, PaddleAxis,
+            PaddleDash, PaddleSpeed, PaddleVelocity, PowerUp, PowerUpKind, Seat, Side, Turbo, Velocity,
+        },
+        config::PongConfig,
+        events::{
+            BallMissed, BallResetEvent, BallResetReason, CollisionEvent, GoalEvent, LifeLost, PaddleHitEvent,
+            PowerUpCollected,
+        },
+        network::{
+            MatchSnapshot, MatchSync, NetMessage, NetworkLink, NetworkRole, NetworkStatus, Spectators,
+            MAX_SPECTATORS,
+        },
+        replay::{Replay, ReplayError, ReplayPlayer, ReplayRecorder, TrajectoryHash},
+        resources::{
+            Action, CpuHandicap, CpuSkill, Deuce, Difficulty, DifficultyShift, GameRng, GameState, KeyBindings,
+            LastTouched, Lives, MatchFormat, MatchMode, MatchScore, MatchSettings, MatchTimer, MovementKeys,
+            PaddleInputs, PhysicsStep, PlayerInput, PlayerNames, Rally, RallySettings, Scoreboard, ServeRule,
+            ServeTimer, SkipMenu, Streak, TimedFormat, DEFAULT_GRAVITY,
+        },
+        save::{MemoryStorage, SaveStorage, Settings, StorageBackend},
+        simulate_ticks,
+        stats::{MatchStats, PersistentStats},
+        systems::collision::{
+            constrain_velocity, paddle_bounce, predict_intercept, reflect, resolve_overlap, swept_collision,
+        },
+        systems::serve::serve_direction,
+        theme::{DisplaySettings, Theme, ThemePreset},
+        PhysicsSet, PhysicsStage, PongPlugin, PongRenderPlugin, TIME_STEP,
+    };
+    #[cfg(not(target_arch = "wasm32"))]
+    pub use crate::lan::{
+        LanAnnouncer, LanBeacon, LanBrowser, LanGame, LanGames, LAN_DISCOVERY_PORT, LAN_GAME_PORT,
+    };
+    #[cfg(all(feature = "netplay", not(target_arch = "wasm32")))]
+    pub use crate::netplay::{NetplayConfig, NetplayPlugin};
+    #[cfg(feature = "input_manager")]
+    pub use crate::actions::{default_input_map, PlayerAction, PlayerActionsPlugin};
+}
+
 use bevy::{
+	diagnostic::FrameTimeDiagnosticsPlugin,
+	ecs::schedule::ShouldRun,
 	prelude::*,
-	sprite::collide_aabb::{collide, Collision},
-	sprite::MaterialMesh2dBundle,
-	time::FixedTimestep,
+	time::TimeUpdateStrategy,
+	utils::Instant,
 };
 
+use serde::Deserialize;
+use std::time::Duration;
+
 use wasm_bindgen::prelude::*;
 
-use rand::Rng;
-
-// Defines the amount of time that should elapse between each physics step.
-const TIME_STEP: f32 = 1.0 / 60.0;
-
-// These constants are defined in `Transform` units.
-// Using the default 2D camera they correspond 1:1 with screen pixels.
-const PADDLE_SIZE: Vec3 = Vec3::new(20.0, 120.0, 0.0);
-const GAP_BETWEEN_PADDLE_AND_SIDES: f32 = 60.0;
-const PADDLE_SPEED: f32 = 500.0;
-// How close can the paddle get to the wall
-const PADDLE_PADDING: f32 = 10.0;
-
-// We set the z-value of the ball to 1 so it renders on top in the case of overlapping sprites.
-const BALL_STARTING_POSITION: Vec3 = Vec3::new(0.0, -50.0, 1.0);
-const BALL_SIZE: Vec3 = Vec3::new(30.0, 30.0, 0.0);
-const BALL_SPEED: f32 = 400.0;
-
-const WALL_THICKNESS: f32 = 10.0;
-// x coordinates
-const LEFT_WALL: f32 = -450.;
-const RIGHT_WALL: f32 = 450.;
-// y coordinates
-const BOTTOM_WALL: f32 = -300.;
-const TOP_WALL: f32 = 300.;
-
-const SCOREBOARD_FONT_SIZE: f32 = 40.0;
-const SCOREBOARD_TEXT_PADDING: f32 = 5.0;
-
-const BACKGROUND_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
-const PADDLE_COLOR: Color = Color::rgb(0.3, 0.3, 0.7);
-const BALL_COLOR: Color = Color::rgb(1.0, 0.5, 0.5);
-const WALL_COLOR: Color = Color::rgb(0.8, 0.8, 0.8);
-const TEXT_COLOR: Color = Color::rgb(0.5, 0.5, 1.0);
-const SCORE_COLOR: Color = Color::rgb(1.0, 0.5, 0.5);
-
-const SCREEN_WIDTH: f32 = RIGHT_WALL - LEFT_WALL;
-const SCREEN_HEIGHT: f32 = TOP_WALL - BOTTOM_WALL;
-const LINE_DIMS: Vec2 = Vec2::new(5.0, 20.0);
-const NUM_DOTTED_LINES: i32 = 10;
+use crate::{
+    arena::*,
+    audio::*,
+    components::{Ball, Obstacle, Paddle, Velocity},
+    config::*,
+    debug::*,
+    events::*,
+    menu::*,
+    network::*,
+    replay::*,
+    resources::*,
+    save::*,
+    skins::*,
+    stats::*,
+    systems::{collision::*, movement::*, power_ups::*, scoring::*, serve::*},
+    theme::*,
+    ui::*,
+};
+
+/// Defines the amount of time that should elapse between each physics step, unless a `PhysicsStep`
+/// says otherwise.
+pub const TIME_STEP: f32 = 1.0 / 60.0;
+
+/// The stage the physics runs in, before `CoreStage::Update`. It runs once for every `PhysicsStep`
+/// that has passed since the last frame, which can be none or several, and only during play
+#[derive(StageLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PhysicsStage;
+
+/// The parts of a physics step, which run in this order. Label your own systems in the
+/// `PhysicsStage` before or after these to fit them into the step
+#[derive(SystemLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PhysicsSet {
+    /// Reads the paddles' input, and adds or takes away the obstacles, power-ups and effects
+    /// the step starts with
+    Input,
+    /// Serves the ball and moves it, and anything else with a `Velocity`
+    Motion,
+    /// Moves the paddles, however they're controlled
+    Paddles,
+    /// Bounces the balls off whatever they ran into, and collects power-ups
+    Collision,
+    /// Scores goals, and works out when the game and match are over
+    Scoring,
+}
+
+// What the page can set up when it starts the game, every field optional. From JavaScript it's an object like
+// `{ canvas: "#pong", width: 900, height: 600, scoreLimit: 5, ai: true, mute: true, seed: 1234 }`,
+// or `{ join: "ws://example.com:7878" }` to join an online match, `{ watch: ... }` to watch one
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct StartOptions {
+	// A CSS selector for the canvas to draw in, Bevy adds a canvas of its own when it's left out
+	canvas: Option<String>,
+	width: Option<f32>,
+	height: Option<f32>,
+	// Points needed to win a match
+	score_limit: Option<usize>,
+	// Skips the menu and starts a match against the computer
+	ai: bool,
+	mute: bool,
+	// Seeds the serves, though a seed in the page's URL takes precedence
+	seed: Option<u64>,
+	// The WebSocket address of an online match to join, see `NetworkRole::Client`
+	join: Option<String>,
+	// The WebSocket address of an online match to watch, see `NetworkRole::Spectator`
+	watch: Option<String>,
+}
+
+/// Starts the game with the options the page passes, like `start({ canvas: "#pong", ai: true })`.
+/// Fails if the options aren't an object with the right fields
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn start(options: JsValue) -> Result<(), JsValue> {
+	let options = if options.is_undefined() || options.is_null() {
+		StartOptions::default()
+	} else {
+		serde_wasm_bindgen::from_value(options)?
+	};
+	run(options);
+	Ok(())
+}
 
+/// Starts the game with its defaults, `start` lets the page pick its own
 #[wasm_bindgen]
 pub fn main(){
-	App::new()
-		.add_plugins(DefaultPlugins.set(WindowPlugin {
-			window: WindowDescriptor {
-				title: "Pong!".to_string(),
-				..default()
-			},
+	run(StartOptions::default());
+}
+
+fn run(options: StartOptions) {
+	let mut window = WindowDescriptor {
+		title: "Pong!".to_string(),
+		canvas: options.canvas,
+		..default()
+	};
+	window.width = options.width.unwrap_or(window.width);
+	window.height = options.height.unwrap_or(window.height);
+
+	let mut app = App::new();
+	app.add_plugins(DefaultPlugins.set(WindowPlugin {
+			window,
 			..default()
 		}))
-		.insert_resource(Scoreboard { left_score:0, right_score: 0 })
-        .insert_resource(ClearColor(BACKGROUND_COLOR))
-		.add_startup_system(setup)
-		.add_event::<CollisionEvent>()
-        .add_system_set(
-            SystemSet::new()
-                .with_run_criteria(FixedTimestep::step(TIME_STEP as f64))
-                .with_system(check_for_collisions)
-                .with_system(move_paddle_left.before(check_for_collisions))
-                .with_system(move_paddle_right.before(check_for_collisions))
-                .with_system(apply_velocity.before(check_for_collisions)),
-        )
-        .add_system(bevy::window::close_on_esc)
+		.insert_resource(AudioSettings {
+			muted: options.mute,
+			..default()
+		});
+	if let Some(score_limit) = options.score_limit {
+		app.insert_resource(MatchSettings {
+			mode: MatchMode::Games(MatchFormat {
+				points_per_game: score_limit,
+				..default()
+			}),
+			..default()
+		});
+	}
+	if options.ai {
+		app.insert_resource(SkipMenu { cpu_opponent: true });
+	}
+	if let Some(address) = options.join {
+		app.insert_resource(SkipMenu { cpu_opponent: false })
+			.insert_resource(NetworkRole::Client { address });
+	}
+	if let Some(address) = options.watch {
+		app.insert_resource(SkipMenu { cpu_opponent: false })
+			.insert_resource(NetworkRole::Spectator { address });
+	}
+	if let Some(seed) = options.seed {
+		app.insert_resource(GameRng::seeded(seed));
+	}
+	#[cfg(target_arch = "wasm32")]
+	if let Some(seed) = seed_from_url() {
+		app.insert_resource(GameRng::seeded(seed));
+	}
+	app.add_plugin(PongPlugin {
+			config_path: Some("pong.ron".to_string()),
+			..default()
+		})
+		.add_plugin(PongRenderPlugin)
 		.run()
 }
 
-#[derive(Component)]
-struct Paddle;
-
-#[derive(Component)]
-pub struct Left;
-#[derive(Component)]
-pub struct Right;
-
-#[derive(Component)]
-struct Ball;
-
-#[derive(Component, Deref, DerefMut)]
-struct Velocity(Vec2);
-
-#[derive(Component)]
-struct Collider;
-
-#[derive(Default)]
-struct CollisionEvent;
-
-// This bundle is a collection of the components that define a "wall" in our game
-#[derive(Bundle)]
-struct WallBundle {
-    // You can nest bundles inside of other bundles like this
-    // Allowing you to compose their functionality
-    sprite_bundle: SpriteBundle,
-    collider: Collider,
+// A `seed` in the page's query string, like `?seed=1234`
+#[cfg(target_arch = "wasm32")]
+fn seed_from_url() -> Option<u64> {
+	let search = web_sys::window()?.location().search().ok()?;
+	search
+		.trim_start_matches('?')
+		.split('&')
+		.find_map(|param| param.strip_prefix("seed="))
+		.and_then(|seed| seed.parse().ok())
 }
 
-/// Which side of the arena is this wall located on?
-enum WallLocation {
-    Left,
-    Right,
-    Bottom,
-    Top,
+/// Adds the game's simulation to an app: the match flow, physics, scoring and the computer player.
+///
+/// On its own this runs headless, with `MinimalPlugins` and no window, which is how the tests drive it
+/// through `simulate_ticks`. Add `PongRenderPlugin` as well, after `DefaultPlugins`, for the playable game.
+#[derive(Default)]
+pub struct PongPlugin {
+    /// The keys the game responds to
+    pub bindings: KeyBindings,
+    /// How the arena fits into the window
+    pub arena_scaling: ArenaScaling,
+    /// A RON file in the assets folder to load the `PongConfig` from before anything is spawned.
+    /// If it is missing or can't be read the game carries on with the `PongConfig` it already has
+    pub config_path: Option<String>,
 }
 
-impl WallLocation {
-    fn position(&self) -> Vec2 {
-        match self {
-            WallLocation::Left => Vec2::new(LEFT_WALL, 0.),
-            WallLocation::Right => Vec2::new(RIGHT_WALL, 0.),
-            WallLocation::Bottom => Vec2::new(0., BOTTOM_WALL),
-            WallLocation::Top => Vec2::new(0., TOP_WALL),
+impl Plugin for PongPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Scoreboard { left_score: 0, right_score: 0 })
+            .insert_resource(self.bindings.clone())
+            // Reflected so the inspector can show and edit them
+            .register_type::<Paddle>()
+            .register_type::<Ball>()
+            .register_type::<Velocity>()
+            .register_type::<Scoreboard>()
+            .register_type::<PongConfig>()
+            .register_type::<MatchSettings>()
+            // Inserting a `MatchSettings` before adding the plugin overrides the default match rules
+            .init_resource::<MatchSettings>()
+            .init_resource::<MatchScore>()
+            .init_resource::<Lives>()
+            .init_resource::<Streak>()
+            .init_resource::<MatchTimer>()
+            .init_resource::<Difficulty>()
+            .init_resource::<DifficultyShift>()
+            .init_resource::<RallySettings>()
+            .init_resource::<Rally>()
+            .init_resource::<PlayerNames>()
+            // Inserting a `GameRng` before adding the plugin makes the serves repeatable
+            .init_resource::<GameRng>()
+            .init_resource::<ServeTimer>()
+            .init_resource::<PowerUpTimer>()
+            .init_resource::<PaddleInputs>()
+            // Inserting a `PhysicsStep` before adding the plugin changes how often the physics runs
+            .init_resource::<PhysicsStep>()
+            .init_resource::<ReplayRecorder>()
+            .init_resource::<TrajectoryHash>()
+            .init_resource::<PersistentStats>()
+            .init_resource::<MatchStats>()
+            .init_resource::<RemoteInput>()
+            .init_resource::<LatestSnapshot>()
+            // Inserting `Spectators` before adding the plugin changes how many can watch a hosted match
+            .init_resource::<Spectators>()
+            .insert_resource(self.arena_scaling)
+            .init_resource::<PongConfig>()
+            .insert_resource(ConfigFile {
+                path: self.config_path.clone(),
+                handle: None,
+            })
+            .add_state(GameState::Loading)
+            .add_startup_system(load_config)
+            .add_startup_system(open_network_link)
+            .add_system_set(SystemSet::on_update(GameState::Loading).with_system(wait_for_config))
+            .add_system_set(
+                SystemSet::on_exit(GameState::Loading)
+                    .with_system(setup)
+                    .with_system(seed_rng),
+            )
+            .add_event::<CollisionEvent>()
+            .add_event::<PaddleHitEvent>()
+            .add_event::<GoalEvent>()
+            .add_event::<LifeLost>()
+            .add_event::<BallMissed>()
+            .add_event::<BallResetEvent>()
+            .add_event::<PowerUpCollected>()
+            .add_stage_before(
+                CoreStage::Update,
+                PhysicsStage,
+                SystemStage::parallel().with_run_criteria(run_physics_steps.pipe(speed_up_replay)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing)
+                    .with_system(start_match_timer)
+                    // the `PhysicsStage` runs before this one, so these are set going before the first step
+                    .with_system(start_replay_recording)
+                    .with_system(start_replay_playback),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver)
+                    .with_system(stop_replay_recording)
+                    .with_system(finish_replay_playback)
+                    .with_system(record_match),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Menu)
+                    .with_system(despawn_with::<Obstacle>)
+                    .with_system(leave_online_match),
+            )
+            .add_system(apply_arena_resize.with_run_criteria(run_if_set_up))
+            .add_system(track_longest_rally)
+            // Online play, which does nothing without a `NetworkRole`
+            .add_system_to_stage(CoreStage::PreUpdate, receive_from_network)
+            .add_system(pause_when_disconnected)
+            .add_system(send_client_input)
+            .add_system(apply_snapshot)
+            .add_system(follow_snapshot.after(apply_snapshot))
+            .add_system(admit_spectators)
+            .add_system_to_stage(CoreStage::PostUpdate, send_snapshot);
+        for set in physics_sets() {
+            app.add_system_set_to_stage(PhysicsStage, set);
         }
-    }
 
-    fn size(&self) -> Vec2 {
-        let arena_height = TOP_WALL - BOTTOM_WALL;
-        let arena_width = RIGHT_WALL - LEFT_WALL;
-        // Make sure we haven't messed up our constants
-        assert!(arena_height > 0.0);
-        assert!(arena_width > 0.0);
-
-        match self {
-            WallLocation::Left | WallLocation::Right => {
-                Vec2::new(WALL_THICKNESS, arena_height + WALL_THICKNESS)
-            }
-            WallLocation::Bottom | WallLocation::Top => {
-                Vec2::new(arena_width + WALL_THICKNESS, WALL_THICKNESS)
-            }
+        // The config file is loaded as an asset, which takes the asset server from `DefaultPlugins`
+        if app.world.contains_resource::<AssetServer>() {
+            app.add_asset::<PongConfig>().init_asset_loader::<PongConfigLoader>();
         }
+
+        // Hosting and finding games on the local network, from the menu
+        #[cfg(not(target_arch = "wasm32"))]
+        app.init_resource::<lan::LanGames>()
+            .add_system(lan::announce_lan_game)
+            .add_system(lan::browse_lan_games);
+
+        // After everything the frame had to send
+        #[cfg(target_arch = "wasm32")]
+        app.add_system_to_stage(CoreStage::Last, web::send_queued_messages);
     }
 }
 
-impl WallBundle {
-    // This "builder method" allows us to reuse logic across our wall entities,
-    // making our code easier to read and less prone to bugs when we change the logic
-    fn new(location: WallLocation) -> WallBundle {
-        WallBundle {
-            sprite_bundle: SpriteBundle {
-                transform: Transform {
-                    // We need to convert our Vec2 into a Vec3, by giving it a z-coordinate
-                    // This is used to determine the order of our sprites
-                    translation: location.position().extend(0.0),
-                    // The z-scale of 2D objects must always be 1.0,
-                    // or their ordering will be affected in surprising ways.
-                    // See https://github.com/bevyengine/bevy/issues/4149
-                    scale: location.size().extend(1.0),
-                    ..default()
-                },
-                sprite: Sprite {
-                    color: WALL_COLOR,
-                    ..default()
-                },
-                ..default()
-            },
-            collider: Collider,
+/// Draws the game and plays its sounds, and takes everything else the player does through the window:
+/// the menus, mouse and touch paddles, and the keys for pausing, restarting and muting.
+///
+/// Needs `DefaultPlugins` and `PongPlugin` added first.
+#[derive(Default)]
+pub struct PongRenderPlugin;
+
+impl Plugin for PongRenderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AudioSettings>()
+            .init_resource::<TouchControls>()
+            .init_resource::<MenuFocus>()
+            .init_resource::<AttractMode>()
+            .init_resource::<CameraShake>()
+            .init_resource::<DisplaySettings>()
+            .init_resource::<Theme>()
+            .init_resource::<Skin>()
+            .init_resource::<PhysicsStepCounter>()
+            .init_resource::<ShowColliders>()
+            .add_startup_system(load_font)
+            .add_system(fall_back_to_embedded_font)
+            .add_system_set(
+                SystemSet::on_exit(GameState::Loading)
+                    .with_system(setup_render)
+                    .with_system(load_skin)
+                    .with_system(load_sounds),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Menu)
+                    .with_system(spawn_menu)
+                    .with_system(reset_idle_timer),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Menu)
+                    .with_system(rebind_key.before(navigate_menu))
+                    .with_system(navigate_menu)
+                    .with_system(update_menu.after(navigate_menu))
+                    .with_system(start_attract_mode),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Menu).with_system(despawn_with::<MenuText>));
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_system_set(
+            SystemSet::on_update(GameState::Menu).with_system(list_lan_games.before(navigate_menu)),
+        );
+        app
+            // Stepped alongside the physics, which they feed the paddles' positions to
+            .add_system_set_to_stage(
+                PhysicsStage,
+                SystemSet::new()
+                    .label(PhysicsSet::Paddles)
+                    .after(PhysicsSet::Motion)
+                    .with_system(steer_mouse_paddles.after(apply_paddle_inputs).before(move_paddles))
+                    .with_system(steer_touch_paddles.after(steer_mouse_paddles).before(move_paddles)),
+            )
+            .add_system_to_stage(PhysicsStage, count_physics_steps)
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(show_serve_countdown))
+            .add_system_set(
+                SystemSet::on_update(GameState::Demo)
+                    .with_system(show_serve_countdown)
+                    .with_system(stop_attract_mode),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Demo).with_system(end_attract_mode))
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver)
+                    .with_system(spawn_winner_text)
+                    .with_system(despawn_with::<CountdownText>),
+            )
+            .add_system_set(SystemSet::on_update(GameState::GameOver).with_system(toggle_match_stats))
+            .add_system_set(
+                SystemSet::on_exit(GameState::GameOver).with_system(despawn_with::<WinnerText>),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Paused)
+                    .with_system(spawn_pause_text)
+                    .with_system(despawn_with::<CountdownText>),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Paused).with_system(despawn_with::<PauseText>))
+            .add_system(toggle_pause)
+            .add_system(pause_on_focus_loss.after(toggle_pause))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(run_unless_capturing_keys)
+                    .with_system(control_replay_playback)
+                    .with_system(toggle_fullscreen)
+                    .with_system(toggle_mute)
+                    .with_system(toggle_fps_overlay)
+                    .with_system(toggle_collider_outlines),
+            )
+            .add_system(update_cursor_visibility)
+            .add_system(scale_ui_with_window)
+            .add_system(apply_fullscreen.after(toggle_fullscreen))
+            .add_system(apply_present_mode)
+            .add_system(assign_touches)
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(run_if_set_up)
+                    .with_system(fit_arena_to_window.before(apply_arena_resize))
+                    .with_system(update_theme)
+                    .with_system(apply_theme.after(update_theme))
+                    .with_system(apply_high_contrast_sizes)
+                    .with_system(
+                        letterbox_camera
+                            .after(fit_arena_to_window)
+                            .after(update_theme)
+                            .after(scale_ui_with_window),
+                    )
+                    .with_system(add_ball_meshes)
+                    .with_system(color_balls.after(add_ball_meshes))
+                    .with_system(apply_skin.after(add_ball_meshes))
+                    .with_system(show_ball_outlines.after(update_theme))
+                    .with_system(squash_on_paddle_hits.after(add_ball_meshes))
+                    .with_system(tween_scales.after(squash_on_paddle_hits))
+                    .with_system(play_sound_effects)
+                    .with_system(shake_camera)
+                    .with_system(restart_match),
+            )
+            .add_system(update_scoreboard)
+            .add_system(update_match_clock)
+            .add_system(update_rally_counter)
+            .add_system(update_lives)
+            .add_system(update_streak)
+            .add_system(update_cpu_level)
+            .add_system(update_network_text)
+            .add_system(flash_scores_in_sudden_death)
+            .add_system(flash_paddle_hits.before(tint_powered_up_paddles))
+            .add_system(tint_powered_up_paddles)
+            .add_system(add_dash_bars)
+            .add_system(show_dash_cooldowns.after(add_dash_bars))
+            .add_system(update_fps_overlay.after(toggle_fps_overlay))
+            .add_system(update_collider_outlines.after(toggle_collider_outlines))
+            // Esc leaves the game over screen for the menu instead, only stops the demo,
+            // keeps the old key when the menu's waiting for a new one, and does nothing while a name's typed in
+            .add_system(bevy::window::close_on_esc.with_run_criteria(run_if_esc_quits));
+
+        // Over the resources above, and the ones `PongPlugin` started with
+        load_saved(&mut app.world);
+        app.add_system_to_stage(CoreStage::Last, save_stats)
+            .add_system_to_stage(CoreStage::Last, save_settings);
+
+        // The FPS overlay's numbers
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugin(FrameTimeDiagnosticsPlugin);
         }
+
+        #[cfg(target_arch = "wasm32")]
+        app.add_startup_system(web::watch_tab_visibility)
+            .add_system(web::pause_when_tab_hidden.after(toggle_pause))
+            .add_system_to_stage(CoreStage::PostUpdate, web::share_with_page)
+            .add_system(web::report_goals)
+            .add_system(web::show_score_in_title)
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(web::report_game_over));
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_system(show_score_in_title);
+
+        #[cfg(all(feature = "inspector", not(target_arch = "wasm32")))]
+        app.add_plugin(bevy_inspector_egui::quick::WorldInspectorPlugin);
+
+        #[cfg(feature = "music")]
+        app.add_system(update_music.with_run_criteria(run_if_set_up))
+            .add_system_set(SystemSet::on_update(GameState::Paused).with_system(toggle_music));
     }
 }
 
-// This resource tracks the game's score
-#[derive(Resource)]
-struct Scoreboard {
-    left_score: usize,
-    right_score: usize,
+/// Runs a headless app for `ticks` updates, moving its clock on by exactly one `PhysicsStep`
+/// each time rather than following the wall clock. Once the match is underway that's one
+/// step of the simulation per update, however fast the machine runs them
+pub fn simulate_ticks(app: &mut App, ticks: u32) {
+    // a nanosecond over, so rounding never leaves an update a fraction short of a step
+    let step = Duration::from_secs_f32(app.world.resource::<PhysicsStep>().0) + Duration::from_nanos(1);
+    for _ in 0..ticks {
+        let last_update = app.world.resource::<Time>().last_update().unwrap_or_else(Instant::now);
+        app.insert_resource(TimeUpdateStrategy::ManualInstant(last_update + step));
+        app.update();
+    }
 }
 
-fn setup(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    asset_server: Res<AssetServer>,
-){
-    // Camera
-    commands.spawn(Camera2dBundle::default());
-
-    // Paddle one
-    let paddle_one_x = LEFT_WALL + GAP_BETWEEN_PADDLE_AND_SIDES;
-
-    commands.spawn((
-        SpriteBundle {
-            transform: Transform {
-                translation: Vec3::new(paddle_one_x, 0.0, 0.0),
-                scale: PADDLE_SIZE,
-                ..default()
-            },
-            sprite: Sprite {
-                color: PADDLE_COLOR,
-                ..default()
-            },
-            ..default()
-        },
-        Paddle,
-        Left,
-        Collider,
-    ));
-
-    // Paddle two
-    let paddle_two_x = RIGHT_WALL - GAP_BETWEEN_PADDLE_AND_SIDES;
-
-    commands.spawn((
-        SpriteBundle {
-            transform: Transform {
-                translation: Vec3::new(paddle_two_x, 0.0, 0.0),
-                scale: PADDLE_SIZE,
-                ..default()
-            },
-            sprite: Sprite {
-                color: PADDLE_COLOR,
-                ..default()
-            },
-            ..default()
-        },
-        Paddle,
-        Right,
-        Collider,
-    ));
-
-    // Ball
-    let mut rng = rand::thread_rng();
-    let initial_ball_direction: Vec2 = Vec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
-
-    commands.spawn((
-        MaterialMesh2dBundle {
-            mesh: meshes.add(shape::Circle::default().into()).into(),
-            material: materials.add(ColorMaterial::from(BALL_COLOR)),
-            transform: Transform::from_translation(BALL_STARTING_POSITION).with_scale(BALL_SIZE),
-            ..default()
-        },
-        Ball,
-        Velocity(initial_ball_direction.normalize() * BALL_SPEED),
-    ));
-
-    // Scoreboard
-    /*commands.spawn(
-        TextBundle::from_sections([
-            TextSection::new(
-                "Score: ",
-                TextStyle {
-                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                    font_size: SCOREBOARD_FONT_SIZE,
-                    color: TEXT_COLOR,
-                },
-            ),
-            TextSection::from_style(TextStyle {
-                font: asset_server.load("fonts/FiraMono-Medium.ttf"),
-                font_size: SCOREBOARD_FONT_SIZE,
-                color: SCORE_COLOR,
-            }),
-        ])
-        .with_style(Style {
-            position_type: PositionType::Absolute,
-            position: UiRect {
-                top: SCOREBOARD_TEXT_PADDING,
-                left: SCOREBOARD_TEXT_PADDING,
-                ..default()
-            },
-            ..default()
-        }),
-    );*/
-
-    // Walls
-    commands.spawn(WallBundle::new(WallLocation::Left));
-    commands.spawn(WallBundle::new(WallLocation::Right));
-    commands.spawn(WallBundle::new(WallLocation::Bottom));
-    commands.spawn(WallBundle::new(WallLocation::Top));
-
-    // Dotted Line
-    let increment: f32 = SCREEN_HEIGHT / (NUM_DOTTED_LINES as f32);
-    let bottom: f32 = -SCREEN_HEIGHT / 2.0 + LINE_DIMS.y + WALL_THICKNESS;
-
-    for y_index in 0..NUM_DOTTED_LINES {
-        commands.spawn((
-            SpriteBundle {
-                transform: Transform {
-                    translation: Vec2::new(0.0, y_index as f32 * increment + bottom).extend(0.0),
-                    scale: LINE_DIMS.extend(0.0),
-                    ..default()
-                },
-                sprite: Sprite {
-                    color: WALL_COLOR,
-                    ..default()
-                },
-                ..default()
-            },
-        ));
+// For systems that need what `setup` spawns, which waits for the config file
+fn run_if_set_up(arena: Option<Res<Arena>>) -> ShouldRun {
+    if arena.is_some() {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
     }
+}
 
-    // Scoreboard
-    commands.spawn((
-        TextBundle::from_sections([
-            TextSection::new(
-                "0",
-                TextStyle {
-                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
-                    font_size: SCOREBOARD_FONT_SIZE,
-                    color: TEXT_COLOR,
-                },
+// Everything that happens in a physics step, in its `PhysicsSet`s. They're added to the `PhysicsStage`,
+// and with the `netplay` feature to the rollback schedule in its place
+pub(crate) fn physics_sets() -> [SystemSet; 5] {
+    [
+        SystemSet::new()
+            .label(PhysicsSet::Input)
+            .with_system(read_paddle_input)
+            .with_system(play_remote_input.after(read_paddle_input))
+            .with_system(record_replay_input.after(play_remote_input))
+            .with_system(spawn_four_player_paddles)
+            .with_system(arrange_doubles_paddles)
+            .with_system(close_walls_behind_players_out.after(spawn_four_player_paddles))
+            .with_system(spawn_obstacles.before(spawn_power_ups))
+            .with_system(spawn_power_ups)
+            .with_system(despawn_stale_power_ups.after(spawn_power_ups))
+            .with_system(wear_off_effects),
+        SystemSet::new()
+            .label(PhysicsSet::Motion)
+            .after(PhysicsSet::Input)
+            .with_system(count_down_serve)
+            .with_system(apply_gravity.after(count_down_serve))
+            .with_system(apply_velocity.after(apply_gravity))
+            .with_system(patrol_center_line.after(apply_velocity)),
+        SystemSet::new()
+            .label(PhysicsSet::Paddles)
+            .after(PhysicsSet::Motion)
+            // every paddle's action is set before any of them move
+            .with_system(apply_paddle_inputs)
+            .with_system(steer_cpu_paddles)
+            .with_system(start_dashes.after(apply_paddle_inputs).after(steer_cpu_paddles))
+            .with_system(move_paddles.after(start_dashes))
+            .with_system(track_paddle_velocity.after(move_paddles)),
+        SystemSet::new()
+            .label(PhysicsSet::Collision)
+            .after(PhysicsSet::Paddles)
+            .with_system(check_for_collisions)
+            .with_system(track_last_touched.after(check_for_collisions))
+            .with_system(collect_power_ups.after(track_last_touched))
+            .with_system(apply_paddle_size_effects.after(collect_power_ups))
+            .with_system(apply_turbo.after(collect_power_ups))
+            .with_system(apply_multi_ball.after(apply_turbo)),
+        SystemSet::new()
+            .label(PhysicsSet::Scoring)
+            .after(PhysicsSet::Collision)
+            .with_system(check_for_goals)
+            .with_system(check_for_lost_lives)
+            .with_system(count_streak)
+            .with_system(check_for_misses.after(count_streak))
+            .with_system(
+                reset_ball_after_goal
+                    .after(check_for_goals)
+                    .after(check_for_lost_lives)
+                    .after(check_for_misses),
             )
-        ])
-        .with_style(Style{
-            position_type: PositionType::Absolute,
-            position: UiRect {
-                top: Val::Px(SCOREBOARD_TEXT_PADDING),
-                left: Val::Px(SCREEN_WIDTH / 2.0),
-                ..default()
-            },
-            ..default()
-        }),
-    ))
-    .insert(Left);
-
-    commands.spawn((
-        TextBundle::from_sections([
-            TextSection::new(
-                "0",
-                TextStyle {
-                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
-                    font_size: SCOREBOARD_FONT_SIZE,
-                    color: TEXT_COLOR,
-                },
+            .with_system(track_match_stats.after(watch_for_stuck_balls))
+            .with_system(watch_for_stuck_balls.after(reset_ball_after_goal))
+            .with_system(tick_match_timer)
+            .with_system(check_for_winner.after(reset_ball_after_goal).after(tick_match_timer))
+            .with_system(rubber_band_difficulty.after(check_for_winner))
+            .with_system(
+                check_for_last_player_standing
+                    .after(reset_ball_after_goal)
+                    .after(tick_match_timer),
             )
-        ])
-        .with_style(Style{
-            position_type: PositionType::Absolute,
-            position: UiRect{
-                top: Val::Px(SCOREBOARD_TEXT_PADDING),
-                right: Val::Px(SCREEN_WIDTH / 2.0),
-                
-                ..default()
-            },
-            ..default()
-        }),
-    ))
-    .insert(Right);
+            .with_system(
+                hash_ball_trajectory
+                    .after(watch_for_stuck_balls)
+                    .after(check_for_winner)
+                    .after(check_for_last_player_standing),
+            ),
+    ]
 }
 
-fn move_paddle_left(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut query: Query<&mut Transform, (With<Paddle>, With<Left>)>,
-) {
-    let mut paddle_transform = query.single_mut();
-    let mut direction = 0.0;
-
-    if keyboard_input.pressed(KeyCode::W) {
-        direction += 1.0;
-    }
-
-    if keyboard_input.pressed(KeyCode::S) {
-        direction -= 1.0;
-    }
-
-    // Calculate the new horizontal paddle position based on player input
-    let new_paddle_position = paddle_transform.translation.y + direction * PADDLE_SPEED * TIME_STEP;
-
-    // Update the paddle position,
-    // making sure it doesn't cause the paddle to leave the arena
-    let upper_bound = TOP_WALL - WALL_THICKNESS / 2.0 - PADDLE_SIZE.y / 2.0 - PADDLE_PADDING;
-    let lower_bound = BOTTOM_WALL + WALL_THICKNESS / 2.0 + PADDLE_SIZE.y / 2.0 + PADDLE_PADDING;
-    //let right_bound = RIGHT_WALL - WALL_THICKNESS / 2.0 - PADDLE_SIZE.x / 2.0 - PADDLE_PADDING;
+// A frame longer than this, in seconds, is a gap in play rather than a slow frame, like a browser tab
+// coming back from the background. Rather than catching up on all of it, which would teleport the ball,
+// only this many steps are
+const MAX_FRAME_GAP: f64 = 0.25;
+const MAX_CATCH_UP_STEPS: f64 = 2.0;
 
-    paddle_transform.translation.y = new_paddle_position.clamp(lower_bound, upper_bound);
+// The time the `PhysicsStage` has yet to catch up on
+#[derive(Default)]
+struct PhysicsClock {
+    accumulator: f64,
+    // whether this frame's time has already been added, as the stage checks again after every step
+    looping: bool,
 }
 
-fn move_paddle_right(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut query: Query<&mut Transform, (With<Paddle>, With<Right>)>,
-) {
-    let mut paddle_transform = query.single_mut();
-    let mut direction = 0.0;
-
-    if keyboard_input.pressed(KeyCode::Up) {
-        direction += 1.0;
+// Runs the `PhysicsStage` once for every whole `PhysicsStep` that has passed. Outside of play,
+// or the demo, the steps go by without running, so neither the physics nor paddle input move.
+// Nor do they online, unless this is the host and the client is there
+fn run_physics_steps(
+    mut clock: Local<PhysicsClock>,
+    time: Res<Time>,
+    step: Res<PhysicsStep>,
+    state: Res<State<GameState>>,
+    network_role: Option<Res<NetworkRole>>,
+    network_status: Option<Res<NetworkStatus>>,
+) -> ShouldRun {
+    let step = step.0 as f64;
+    if !clock.looping {
+        let delta = time.delta_seconds_f64();
+        clock.accumulator += delta;
+        if delta > MAX_FRAME_GAP {
+            clock.accumulator = clock.accumulator.min(step * MAX_CATCH_UP_STEPS);
+        }
     }
 
-    if keyboard_input.pressed(KeyCode::Down) {
-        direction -= 1.0;
+    if clock.accumulator < step {
+        clock.looping = false;
+        return ShouldRun::No;
+    }
+    clock.accumulator -= step;
+    clock.looping = true;
+    let simulated = simulates_match(network_role.as_deref(), network_status.as_deref());
+    if matches!(state.current(), GameState::Playing | GameState::Demo) && simulated {
+        ShouldRun::YesAndCheckAgain
+    } else {
+        ShouldRun::NoAndCheckAgain
     }
-
-    // Calculate the new horizontal paddle position based on player input
-    let new_paddle_position = paddle_transform.translation.y + direction * PADDLE_SPEED * TIME_STEP;
-
-    // Update the paddle position,
-    // making sure it doesn't cause the paddle to leave the arena
-    let upper_bound = TOP_WALL - WALL_THICKNESS / 2.0 - PADDLE_SIZE.y / 2.0 - PADDLE_PADDING;
-    let lower_bound = BOTTOM_WALL + WALL_THICKNESS / 2.0 + PADDLE_SIZE.y / 2.0 + PADDLE_PADDING;
-    //let right_bound = RIGHT_WALL - WALL_THICKNESS / 2.0 - PADDLE_SIZE.x / 2.0 - PADDLE_PADDING;
-
-    paddle_transform.translation.y = new_paddle_position.clamp(lower_bound, upper_bound);
 }
 
-fn apply_velocity(mut query: Query<(&mut Transform, &Velocity)>) {
-    for (mut transform, velocity) in &mut query {
-        transform.translation.x += velocity.x * TIME_STEP;
-        transform.translation.y += velocity.y * TIME_STEP;
+fn run_if_esc_quits(state: Res<State<GameState>>, focus: Res<MenuFocus>) -> ShouldRun {
+    if matches!(state.current(), GameState::GameOver | GameState::Demo) || focus.capturing_keys() {
+        ShouldRun::No
+    } else {
+        ShouldRun::Yes
     }
 }
 
+// Keeps the hotkeys quiet while a name is being typed in or a key rebound, so typing an M doesn't mute the game
+fn run_unless_capturing_keys(focus: Res<MenuFocus>) -> ShouldRun {
+    if focus.capturing_keys() {
+        ShouldRun::No
+    } else {
+        ShouldRun::Yes
+    }
+}
 
-
-fn check_for_collisions(
-    mut commands: Commands,
-    mut scoreboard: ResMut<Scoreboard>,
-    mut ball_query: Query<(&mut Velocity, &Transform), With<Ball>>,
-    collider_query: Query<(Entity, &Transform), With<Collider>>,
-    mut collision_events: EventWriter<CollisionEvent>,
-) {
-    let (mut ball_velocity, ball_transform) = ball_query.single_mut();
-    let ball_size = ball_transform.scale.truncate();
-
-    // check collision with walls
-    for (collider_entity, transform) in &collider_query {
-        let collision = collide(
-            ball_transform.translation,
-            ball_size,
-            transform.translation,
-            transform.scale.truncate(),
-        );
-        if let Some(collision) = collision {
-            // Sends a collision event so that other systems can react to the collision
-            collision_events.send_default();
-
-            // reflect the ball when it collides
-            let mut reflect_x = false;
-            let mut reflect_y = false;
-
-            // only reflect if the ball's velocity is going in the opposite direction of the
-            // collision
-            match collision {
-                Collision::Left => reflect_x = ball_velocity.x > 0.0,
-                Collision::Right => reflect_x = ball_velocity.x < 0.0,
-                Collision::Top => reflect_y = ball_velocity.y < 0.0,
-                Collision::Bottom => reflect_y = ball_velocity.y > 0.0,
-                Collision::Inside => { /* do nothing */ }
-            }
-
-            // reflect velocity on the x-axis if we hit something on the x-axis
-            if reflect_x {
-                ball_velocity.x = -ball_velocity.x;
-            }
-
-            // reflect velocity on the y-axis if we hit something on the y-axis
-            if reflect_y {
-                ball_velocity.y = -ball_velocity.y;
-            }
-        }
+fn despawn_with<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,274 @@
+//! The native build, with command line flags for setting up a match without the menu
+
+use bevy::{asset::FileAssetIo, prelude::*, window::WindowMode};
+
+use bevy_pong::prelude::{
+    AudioSettings, DisplaySettings, GameRng, MatchFormat, MatchMode, MatchSettings, NetworkRole, PongConfig,
+    PongPlugin, PongRenderPlugin, Replay, ReplayPlayer, ReplayRecorder, SkipMenu,
+};
+
+// Relative to the assets folder, like the web build loads it
+const CONFIG_FILE: &str = "pong.ron";
+
+const HELP: &str = "\
+Pong!
+
+USAGE:
+    bevy_pong [FLAGS]
+
+FLAGS:
+    --ai                  Skip the menu and play against the computer
+    --score-limit <N>     Points needed to win a match [default: 11]
+    --ball-speed <SPEED>  Serve speed of the ball in pixels per second [default: from assets/pong.ron, or 400]
+    --seed <SEED>         Seed for the serves, so a match can be repeated [default: from assets/pong.ron, or random]
+    --fullscreen          Start in borderless fullscreen
+    --mute                Start with all sound muted
+    --power-ups           Turn on power-ups that change the paddles or the ball
+    --obstacles           Put blocks around the center line for the ball to bounce off
+    --moving-obstacle     Add a block that patrols up and down the center line
+    --gravity <ACCEL>     Pull the ball down, in pixels per second squared [e.g. 600]
+    --paddle-inertia      Make the paddles speed up and slow down rather than start and stop dead
+    --dash                Let the paddles dash with Left Shift and Right Ctrl, every couple of seconds
+    --record <FILE>       Record the inputs of each match, saving the replay to FILE when it ends
+    --replay <FILE>       Play back a recorded match. F toggles double speed
+    --host <PORT>         Host an online match on the left paddle, waiting for the other player on PORT
+    --join <ADDRESS>      Join an online match on the right paddle [e.g. ws://192.168.1.20:7878]
+    --watch <ADDRESS>     Watch an online match without playing, like --join
+    --sync-test <STEPS>   Play two players under GGRS rollback, playing the last STEPS (1-7) again
+                          every step to check it. Needs the netplay feature
+    -h, --help            Print this help and exit
+";
+
+// Everything the flags can change, `None` meaning the game's usual default
+#[derive(Default)]
+struct Args {
+    ai: bool,
+    score_limit: Option<usize>,
+    ball_speed: Option<f32>,
+    seed: Option<u64>,
+    fullscreen: bool,
+    mute: bool,
+    power_ups: bool,
+    obstacles: bool,
+    moving_obstacle: bool,
+    gravity: Option<f32>,
+    paddle_inertia: bool,
+    dash: bool,
+    record: Option<String>,
+    replay: Option<String>,
+    host: Option<u16>,
+    join: Option<String>,
+    watch: Option<String>,
+    sync_test: Option<usize>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--ai" => parsed.ai = true,
+                "--score-limit" => parsed.score_limit = Some(value(&arg, args.next())?),
+                "--ball-speed" => parsed.ball_speed = Some(value(&arg, args.next())?),
+                "--seed" => parsed.seed = Some(value(&arg, args.next())?),
+                "--fullscreen" => parsed.fullscreen = true,
+                "--mute" => parsed.mute = true,
+                "--power-ups" => parsed.power_ups = true,
+                "--obstacles" => parsed.obstacles = true,
+                "--moving-obstacle" => parsed.moving_obstacle = true,
+                "--gravity" => parsed.gravity = Some(value(&arg, args.next())?),
+                "--paddle-inertia" => parsed.paddle_inertia = true,
+                "--dash" => parsed.dash = true,
+                "--record" => parsed.record = Some(value(&arg, args.next())?),
+                "--replay" => parsed.replay = Some(value(&arg, args.next())?),
+                "--host" => parsed.host = Some(value(&arg, args.next())?),
+                "--join" => parsed.join = Some(value(&arg, args.next())?),
+                "--watch" => parsed.watch = Some(value(&arg, args.next())?),
+                "--sync-test" => parsed.sync_test = Some(value(&arg, args.next())?),
+                _ => return Err(format!("unknown flag {arg:?}")),
+            }
+        }
+        // the client has none of the match to record or replay, it all happens on the host
+        if parsed.join.is_some() && (parsed.host.is_some() || parsed.record.is_some() || parsed.replay.is_some()) {
+            return Err("--join can't be used with --host, --record or --replay".to_string());
+        }
+        let playing = parsed.ai || parsed.host.is_some() || parsed.join.is_some();
+        if parsed.watch.is_some() && (playing || parsed.record.is_some() || parsed.replay.is_some()) {
+            return Err("--watch can't be used with --ai, --host, --join, --record or --replay".to_string());
+        }
+        // a replay brings its own rules and config, it wouldn't play back the same under any others
+        if parsed.replay.is_some() {
+            let rules = parsed.score_limit.is_some()
+                || parsed.ball_speed.is_some()
+                || parsed.power_ups
+                || parsed.obstacles
+                || parsed.moving_obstacle
+                || parsed.gravity.is_some()
+                || parsed.paddle_inertia
+                || parsed.dash;
+            if rules {
+                let flags = "--score-limit, --ball-speed, --power-ups, the obstacles, --gravity, --paddle-inertia \
+                             or --dash";
+                return Err(format!("--replay can't be used with {flags}"));
+            }
+        }
+        if parsed.host.is_some() && (parsed.ai || parsed.replay.is_some()) {
+            return Err("--host can't be used with --ai or --replay".to_string());
+        }
+        if let Some(steps) = parsed.sync_test {
+            if cfg!(not(feature = "netplay")) {
+                return Err("--sync-test needs the game built with the netplay feature".to_string());
+            }
+            if !(1..8).contains(&steps) {
+                return Err(format!("--sync-test plays 1 to 7 steps again, not {steps}"));
+            }
+            // the rollback only keeps track of a classic match, and neither the recording nor the network would keep up
+            let online = parsed.host.is_some() || parsed.join.is_some() || parsed.watch.is_some();
+            let replayed = parsed.record.is_some() || parsed.replay.is_some();
+            if parsed.ai || online || replayed || parsed.power_ups || parsed.obstacles || parsed.moving_obstacle {
+                let flags = "--ai, --host, --join, --watch, --record, --replay, --power-ups or the obstacles";
+                return Err(format!("--sync-test can't be used with {flags}"));
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+// Parses the value following a flag
+fn value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{flag} needs a value"))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value {value:?} for {flag}"))
+}
+
+// The config file is read up front rather than through the asset server,
+// so the flags can be applied on top of it
+fn load_config() -> PongConfig {
+    let path = FileAssetIo::get_base_path().join("assets").join(CONFIG_FILE);
+    let loaded = std::fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|ron| ron::from_str(&ron).map_err(|err| err.to_string()));
+
+    loaded.unwrap_or_else(|err| {
+        eprintln!("Couldn't load the config from {}, using the defaults: {err}", path.display());
+        PongConfig::default()
+    })
+}
+
+fn load_replay(path: &str) -> Result<Replay, String> {
+    let bytes = std::fs::read(path).map_err(|err| err.to_string())?;
+    Replay::from_bytes(&bytes).map_err(|err| err.to_string())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{HELP}");
+        return;
+    }
+    let args = match Args::parse(args.into_iter()) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("error: {err}\n\n{HELP}");
+            std::process::exit(2);
+        }
+    };
+
+    let replay = args.replay.as_deref().map(|path| {
+        load_replay(path).unwrap_or_else(|err| {
+            eprintln!("error: couldn't load the replay from {path}: {err}");
+            std::process::exit(1);
+        })
+    });
+
+    // a replay brings its own config, and the flags that would change it aren't allowed with one
+    let mut config = match &replay {
+        Some(replay) => replay.config.clone(),
+        None => load_config(),
+    };
+    if let Some(ball_speed) = args.ball_speed {
+        config.ball_speed = ball_speed;
+    }
+
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins.set(WindowPlugin {
+        window: WindowDescriptor {
+            title: "Pong!".to_string(),
+            mode: if args.fullscreen {
+                WindowMode::BorderlessFullscreen
+            } else {
+                WindowMode::Windowed
+            },
+            ..default()
+        },
+        ..default()
+    }))
+    .insert_resource(config);
+
+    let mut settings = MatchSettings {
+        power_ups: args.power_ups,
+        obstacles: args.obstacles,
+        moving_obstacle: args.moving_obstacle,
+        gravity: args.gravity,
+        paddle_inertia: args.paddle_inertia,
+        paddle_dash: args.dash,
+        ..default()
+    };
+    if let Some(score_limit) = args.score_limit {
+        settings.mode = MatchMode::Games(MatchFormat {
+            points_per_game: score_limit,
+            ..default()
+        });
+    }
+    app.insert_resource(settings);
+    if let Some(seed) = args.seed {
+        app.insert_resource(GameRng::seeded(seed));
+    }
+    if let Some(path) = args.record {
+        let mut recorder = ReplayRecorder::enabled();
+        recorder.save_path = Some(path);
+        app.insert_resource(recorder);
+    }
+    if args.ai {
+        app.insert_resource(SkipMenu { cpu_opponent: true });
+    }
+    if let Some(port) = args.host {
+        app.insert_resource(SkipMenu { cpu_opponent: false })
+            .insert_resource(NetworkRole::Host { port });
+    }
+    if let Some(address) = args.join {
+        app.insert_resource(SkipMenu { cpu_opponent: false })
+            .insert_resource(NetworkRole::Client { address });
+    }
+    if let Some(address) = args.watch {
+        app.insert_resource(SkipMenu { cpu_opponent: false })
+            .insert_resource(NetworkRole::Spectator { address });
+    }
+    if args.sync_test.is_some() {
+        app.insert_resource(SkipMenu { cpu_opponent: false });
+    }
+    if let Some(replay) = replay {
+        // which paddles the computer plays comes from the replay, as do the rules
+        app.insert_resource(SkipMenu { cpu_opponent: false })
+            .insert_resource(ReplayPlayer::new(replay));
+    }
+
+    app.add_plugin(PongPlugin::default())
+        .add_plugin(PongRenderPlugin);
+    #[cfg(feature = "input_manager")]
+    app.add_plugin(bevy_pong::prelude::PlayerActionsPlugin);
+    #[cfg(feature = "netplay")]
+    if let Some(check_distance) = args.sync_test {
+        app.add_plugin(bevy_pong::prelude::NetplayPlugin { check_distance });
+    }
+    // after the plugins, which load the sound and display settings saved last time
+    if args.mute {
+        app.world.resource_mut::<AudioSettings>().muted = true;
+    }
+    // otherwise the first frame puts the window back the way the settings have it
+    if args.fullscreen {
+        app.world.resource_mut::<DisplaySettings>().fullscreen = true;
+    }
+    app.run();
+}
@@ -0,0 +1,861 @@
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::app::AppExit;
+use bevy::{ecs::system::SystemParam, prelude::*, window::PresentMode};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{lan::*, network::*};
+use crate::{
+    audio::*,
+    components::*,
+    config::*,
+    resources::*,
+    stats::*,
+    systems::{movement::*, scoring::*},
+    theme::*,
+    ui::UiFont,
+};
+
+// Seconds the menu has to sit untouched before the computer starts playing itself behind it
+const ATTRACT_MODE_IDLE_TIME: f32 = 15.0;
+// How much the menu darkens the demo match playing behind it, from 0 to 1
+const ATTRACT_MODE_DIM: f32 = 0.6;
+// Longest name a player can type in, in characters
+const MAX_NAME_LENGTH: usize = 12;
+
+#[derive(Component)]
+pub(crate) struct MenuText;
+
+/// An entry in the main menu, or in one of its other pages
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MenuItem {
+    OnePlayer,
+    TwoPlayers,
+    FourPlayers,
+    Doubles,
+    Practice,
+    WallPractice,
+    #[cfg(not(target_arch = "wasm32"))]
+    HostLan,
+    #[cfg(not(target_arch = "wasm32"))]
+    JoinLan,
+    // A game found on the network, by its place in the list on the join page
+    #[cfg(not(target_arch = "wasm32"))]
+    LanGame(usize),
+    Settings,
+    // The page has no app to quit, so the web build leaves it out
+    #[cfg(not(target_arch = "wasm32"))]
+    Quit,
+    Difficulty,
+    RubberBand,
+    LeftMouse,
+    RightMouse,
+    Sound,
+    Theme,
+    HighContrast,
+    Fullscreen,
+    PresentMode,
+    PowerUps,
+    Obstacles,
+    MovingObstacle,
+    Gravity,
+    PaddleInertia,
+    PaddleDash,
+    LeftName,
+    RightName,
+    Controls,
+    // The key for an action, on the controls page
+    Binding(Action),
+    MovementKeys,
+    ResetBindings,
+    Back,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum MenuPage {
+    #[default]
+    Main,
+    Settings,
+    // The games found on the network to join, native only
+    #[cfg(not(target_arch = "wasm32"))]
+    Lan,
+    // The keys for each action, opened from the settings
+    Controls,
+}
+
+impl MenuPage {
+    fn items(self) -> &'static [MenuItem] {
+        match self {
+            // There's nothing to quit to in a browser tab, and a browser can't host or look for games
+            #[cfg(not(target_arch = "wasm32"))]
+            MenuPage::Main => &[
+                MenuItem::OnePlayer,
+                MenuItem::TwoPlayers,
+                MenuItem::FourPlayers,
+                MenuItem::Doubles,
+                MenuItem::Practice,
+                MenuItem::WallPractice,
+                MenuItem::HostLan,
+                MenuItem::JoinLan,
+                MenuItem::Settings,
+                MenuItem::Quit,
+            ],
+            #[cfg(target_arch = "wasm32")]
+            MenuPage::Main => &[
+                MenuItem::OnePlayer,
+                MenuItem::TwoPlayers,
+                MenuItem::FourPlayers,
+                MenuItem::Doubles,
+                MenuItem::Practice,
+                MenuItem::WallPractice,
+                MenuItem::Settings,
+            ],
+            MenuPage::Settings => &[
+                MenuItem::Difficulty,
+                MenuItem::RubberBand,
+                MenuItem::LeftMouse,
+                MenuItem::RightMouse,
+                MenuItem::Sound,
+                MenuItem::Theme,
+                MenuItem::HighContrast,
+                MenuItem::Fullscreen,
+                MenuItem::PresentMode,
+                MenuItem::PowerUps,
+                MenuItem::Obstacles,
+                MenuItem::MovingObstacle,
+                MenuItem::Gravity,
+                MenuItem::PaddleInertia,
+                MenuItem::PaddleDash,
+                MenuItem::LeftName,
+                MenuItem::RightName,
+                MenuItem::Controls,
+                MenuItem::Back,
+            ],
+            // after the games, which `MenuFocus::items` puts in front
+            #[cfg(not(target_arch = "wasm32"))]
+            MenuPage::Lan => &[MenuItem::Back],
+            // after the actions, likewise
+            MenuPage::Controls => &[MenuItem::MovementKeys, MenuItem::ResetBindings, MenuItem::Back],
+        }
+    }
+
+    // The page to go back to, and the item on it that opened this one
+    fn opened_by(self) -> (MenuPage, MenuItem) {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            MenuPage::Lan => (MenuPage::Main, MenuItem::JoinLan),
+            MenuPage::Controls => (MenuPage::Settings, MenuItem::Controls),
+            _ => (MenuPage::Main, MenuItem::Settings),
+        }
+    }
+}
+
+// The page the menu is showing and the item that's highlighted on it,
+// along with the name being typed in when that item is a player's name,
+// whether the next key pressed is for the highlighted action and the
+// action that last gave up its key to another, and the games on the
+// network there are to join
+#[derive(Resource, Default)]
+pub(crate) struct MenuFocus {
+    page: MenuPage,
+    index: usize,
+    editing: Option<String>,
+    rebinding: bool,
+    swapped: Option<(Action, Action)>,
+    #[cfg(not(target_arch = "wasm32"))]
+    lan_games: Vec<LanGame>,
+}
+
+impl MenuFocus {
+    fn items(&self) -> Vec<MenuItem> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.page == MenuPage::Lan {
+            let games = (0..self.lan_games.len()).map(MenuItem::LanGame);
+            return games.chain(self.page.items().iter().copied()).collect();
+        }
+        if self.page == MenuPage::Controls {
+            let bindings = Action::ALL.into_iter().map(MenuItem::Binding);
+            return bindings.chain(self.page.items().iter().copied()).collect();
+        }
+        self.page.items().to_vec()
+    }
+
+    fn item(&self) -> MenuItem {
+        self.items()[self.index]
+    }
+
+    // Whether keys are going into a player's name or a new binding, rather than being hotkeys
+    pub(crate) fn capturing_keys(&self) -> bool {
+        self.editing.is_some() || self.rebinding
+    }
+}
+
+// How long the menu has been left alone, and which paddles the computer
+// was already playing before the demo handed it the rest
+#[derive(Resource)]
+pub(crate) struct AttractMode {
+    idle: Timer,
+    cpu_paddles: Vec<Entity>,
+}
+
+impl Default for AttractMode {
+    fn default() -> Self {
+        AttractMode {
+            idle: Timer::from_seconds(ATTRACT_MODE_IDLE_TIME, TimerMode::Once),
+            cpu_paddles: Vec::new(),
+        }
+    }
+}
+
+pub(crate) fn spawn_menu(mut commands: Commands, mut focus: ResMut<MenuFocus>,
+) {
+    *focus = MenuFocus::default();
+
+    // The title and items are spawned by `update_menu`, which respawns them when the page changes
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        },
+        MenuText,
+    ));
+}
+
+// The buttons on every gamepad that's connected
+#[derive(SystemParam)]
+pub(crate) struct MenuGamepads<'w, 's> {
+    gamepads: Res<'w, Gamepads>,
+    gamepad_input: Res<'w, Input<GamepadButton>>,
+    #[system_param(ignore)]
+    _marker: std::marker::PhantomData<&'s ()>,
+}
+
+impl MenuGamepads<'_, '_> {
+    fn just_pressed(&self, button_type: GamepadButtonType) -> bool {
+        self.gamepads
+            .iter()
+            .any(|gamepad| self.gamepad_input.just_pressed(GamepadButton::new(gamepad, button_type)))
+    }
+}
+
+// Up/W and Down/S or the d-pad move the highlight, Enter, the south button or a tap picks it.
+// Picking a name types a new one in until Enter is pressed again, and picking an action's key
+// waits for the new key, which `rebind_key` reads
+pub(crate) fn navigate_menu(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    gamepads: MenuGamepads,
+    touches: Res<Touches>,
+    windows: Res<Windows>,
+    mut focus: ResMut<MenuFocus>,
+    mut state: ResMut<State<GameState>>,
+    mut difficulty: ResMut<Difficulty>,
+    mut audio: ResMut<AudioSettings>,
+    mut display: ResMut<DisplaySettings>,
+    mut settings: ResMut<MatchSettings>,
+    mut names: ResMut<PlayerNames>,
+    mut bindings: ResMut<KeyBindings>,
+    #[cfg(not(target_arch = "wasm32"))] mut app_exit: EventWriter<AppExit>,
+    paddle_query: Query<(Entity, &Side, Option<&MouseControlled>), With<Paddle>>,
+) {
+    if focus.rebinding {
+        return;
+    }
+
+    let name_side = if focus.item() == MenuItem::LeftName {
+        Side::Left
+    } else {
+        Side::Right
+    };
+    if let Some(name) = &mut focus.editing {
+        for character in characters.iter() {
+            // backspace, return and the like arrive as characters too, and anything else is fair game
+            if !character.char.is_control() && name.chars().count() < MAX_NAME_LENGTH {
+                name.push(character.char);
+            }
+        }
+        if keyboard_input.just_pressed(KeyCode::Back) {
+            name.pop();
+        }
+        if keyboard_input.just_pressed(KeyCode::Return) {
+            // a blank name keeps the old one
+            let trimmed = name.trim();
+            if !trimmed.is_empty() {
+                *names.name_mut(name_side) = trimmed.to_string();
+            }
+            focus.editing = None;
+        }
+        return;
+    }
+    // typing in W or S shouldn't be taken as the first letters of a name
+    characters.clear();
+
+    let item_count = focus.items().len();
+    if keyboard_input.any_just_pressed([KeyCode::Up, KeyCode::W])
+        || gamepads.just_pressed(GamepadButtonType::DPadUp)
+    {
+        focus.index = (focus.index + item_count - 1) % item_count;
+    }
+    if keyboard_input.any_just_pressed([KeyCode::Down, KeyCode::S])
+        || gamepads.just_pressed(GamepadButtonType::DPadDown)
+    {
+        focus.index = (focus.index + 1) % item_count;
+    }
+
+    let selected = keyboard_input.just_pressed(KeyCode::Return)
+        || gamepads.just_pressed(GamepadButtonType::South)
+        || touch_button_tapped(&touches, &windows);
+    if !selected {
+        return;
+    }
+
+    let set_cpu_opponent = |commands: &mut Commands, cpu_opponent: bool| {
+        for (paddle, side, _) in &paddle_query {
+            if *side != Side::Right {
+                continue;
+            }
+
+            if cpu_opponent {
+                // the computer takes over the paddle whatever was controlling it
+                commands.entity(paddle).insert(CpuControlled).remove::<MouseControlled>();
+            } else {
+                commands.entity(paddle).remove::<CpuControlled>();
+            }
+        }
+    };
+
+    match focus.item() {
+        MenuItem::OnePlayer
+        | MenuItem::TwoPlayers
+        | MenuItem::FourPlayers
+        | MenuItem::Doubles
+        | MenuItem::Practice
+        | MenuItem::WallPractice => {
+            // with another change of state already queued, the press is ignored rather than half acted on
+            if state.set(GameState::Playing).is_err() {
+                return;
+            }
+            let item = focus.item();
+            set_cpu_opponent(&mut commands, matches!(item, MenuItem::OnePlayer | MenuItem::Practice));
+            settings.practice = item == MenuItem::Practice;
+            settings.four_players = item == MenuItem::FourPlayers;
+            settings.doubles = item == MenuItem::Doubles;
+            settings.wall_practice = item == MenuItem::WallPractice;
+        }
+        // A two-player match with the host on the left, over the same connection as an online match
+        #[cfg(not(target_arch = "wasm32"))]
+        MenuItem::HostLan | MenuItem::LanGame(_) => {
+            if state.set(GameState::Playing).is_err() {
+                return;
+            }
+            let role = match focus.item() {
+                MenuItem::LanGame(index) => NetworkRole::Client {
+                    address: focus.lan_games[index].join_address(),
+                },
+                _ => {
+                    let beacon = LanBeacon {
+                        name: names.left.clone(),
+                        port: LAN_GAME_PORT,
+                    };
+                    match LanAnnouncer::broadcast(beacon) {
+                        Ok(announcer) => commands.insert_resource(announcer),
+                        Err(err) => warn!("Couldn't announce the game on the network: {err}"),
+                    }
+                    NetworkRole::Host { port: LAN_GAME_PORT }
+                }
+            };
+            commands.remove_resource::<LanBrowser>();
+            set_cpu_opponent(&mut commands, false);
+            settings.practice = false;
+            settings.four_players = false;
+            settings.doubles = false;
+            settings.wall_practice = false;
+            start_online_match(&mut commands, role);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        MenuItem::JoinLan => {
+            match LanBrowser::bind(LAN_DISCOVERY_PORT) {
+                Ok(browser) => commands.insert_resource(browser),
+                Err(err) => warn!("Couldn't look for games on the network: {err}"),
+            }
+            *focus = MenuFocus {
+                page: MenuPage::Lan,
+                ..default()
+            };
+        }
+        MenuItem::Settings => {
+            *focus = MenuFocus {
+                page: MenuPage::Settings,
+                ..default()
+            };
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        MenuItem::Quit => app_exit.send(AppExit),
+        MenuItem::Difficulty => *difficulty = difficulty.next(),
+        MenuItem::RubberBand => settings.rubber_band = !settings.rubber_band,
+        MenuItem::LeftMouse | MenuItem::RightMouse => {
+            let toggled = if focus.item() == MenuItem::LeftMouse {
+                Side::Left
+            } else {
+                Side::Right
+            };
+            for (paddle, side, mouse_controlled) in &paddle_query {
+                if *side != toggled {
+                    continue;
+                }
+
+                if mouse_controlled.is_some() {
+                    commands.entity(paddle).remove::<MouseControlled>();
+                } else {
+                    commands.entity(paddle).insert(MouseControlled);
+                }
+            }
+        }
+        MenuItem::Sound => audio.muted = !audio.muted,
+        MenuItem::Theme => display.theme = display.theme.next(),
+        MenuItem::HighContrast => display.high_contrast = !display.high_contrast,
+        MenuItem::Fullscreen => display.fullscreen = !display.fullscreen,
+        MenuItem::PresentMode => {
+            // the mode the window is in, which is the config's until one has been picked
+            let present_mode = windows.get_primary().map_or(PresentMode::AutoVsync, Window::present_mode);
+            display.present_mode = Some(next_present_mode(present_mode));
+        }
+        MenuItem::PowerUps => settings.power_ups = !settings.power_ups,
+        MenuItem::Obstacles => settings.obstacles = !settings.obstacles,
+        MenuItem::MovingObstacle => settings.moving_obstacle = !settings.moving_obstacle,
+        MenuItem::Gravity => {
+            settings.gravity = match settings.gravity {
+                Some(_) => None,
+                None => Some(DEFAULT_GRAVITY),
+            };
+        }
+        MenuItem::PaddleInertia => settings.paddle_inertia = !settings.paddle_inertia,
+        // off, on, then on for the computer too
+        MenuItem::PaddleDash => {
+            (settings.paddle_dash, settings.cpu_dash) = match (settings.paddle_dash, settings.cpu_dash) {
+                (false, _) => (true, false),
+                (true, false) => (true, true),
+                (true, true) => (false, false),
+            };
+        }
+        MenuItem::LeftName | MenuItem::RightName => {
+            focus.editing = Some(names.name(name_side).to_string());
+        }
+        MenuItem::Controls => {
+            *focus = MenuFocus {
+                page: MenuPage::Controls,
+                ..default()
+            };
+        }
+        MenuItem::Binding(_) => focus.rebinding = true,
+        MenuItem::MovementKeys => bindings.movement_keys = bindings.movement_keys.next(),
+        MenuItem::ResetBindings => {
+            *bindings = KeyBindings::default();
+            focus.swapped = None;
+        }
+        MenuItem::Back => {
+            #[cfg(not(target_arch = "wasm32"))]
+            commands.remove_resource::<LanBrowser>();
+            let (page, opened_by) = focus.page.opened_by();
+            *focus = MenuFocus {
+                page,
+                index: page
+                    .items()
+                    .iter()
+                    .position(|item| *item == opened_by)
+                    .unwrap_or_default(),
+                ..default()
+            };
+        }
+    }
+}
+
+// While an action's key is being changed, the next key pressed is bound to it, or Escape leaves it as it was.
+// The press is swallowed, so it doesn't also move the highlight or pick something
+pub(crate) fn rebind_key(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut focus: ResMut<MenuFocus>,
+    mut bindings: ResMut<KeyBindings>,
+) {
+    if !focus.rebinding {
+        return;
+    }
+    let Some(&key) = keyboard_input.get_just_pressed().next() else {
+        return;
+    };
+
+    if let (MenuItem::Binding(action), false) = (focus.item(), key == KeyCode::Escape) {
+        // a key that's already taken is swapped rather than shared, and the other action says so
+        focus.swapped = bindings.rebind(action, key).map(|swapped| (swapped, action));
+    }
+    focus.rebinding = false;
+    keyboard_input.clear();
+}
+
+fn action_label(action: Action) -> &'static str {
+    match action {
+        Action::LeftUp => "Left up",
+        Action::LeftDown => "Left down",
+        Action::RightUp => "Right up",
+        Action::RightDown => "Right down",
+        Action::LeftDash => "Left dash",
+        Action::RightDash => "Right dash",
+        Action::TopLeft => "Top left",
+        Action::TopRight => "Top right",
+        Action::BottomLeft => "Bottom left",
+        Action::BottomRight => "Bottom right",
+        Action::LeftLowerUp => "Left lower up",
+        Action::LeftLowerDown => "Left lower down",
+        Action::RightLowerUp => "Right lower up",
+        Action::RightLowerDown => "Right lower down",
+        Action::Serve => "Serve",
+        Action::Pause => "Pause",
+        Action::Restart => "Restart",
+    }
+}
+
+fn menu_label(
+    item: MenuItem,
+    focus: &MenuFocus,
+    difficulty: Difficulty,
+    audio: &AudioSettings,
+    display: &DisplaySettings,
+    config: &PongConfig,
+    settings: &MatchSettings,
+    names: &PlayerNames,
+    bindings: &KeyBindings,
+    mouse_query: &Query<&Side, (With<Paddle>, With<MouseControlled>)>,
+) -> String {
+    let on_off = |on: bool| if on { "On" } else { "Off" };
+    let mouse_controlled = |side: Side| mouse_query.iter().any(|s| *s == side);
+    match item {
+        MenuItem::OnePlayer => "1 Player".to_string(),
+        MenuItem::TwoPlayers => "2 Players".to_string(),
+        MenuItem::FourPlayers => "4 Players".to_string(),
+        MenuItem::Doubles => "Doubles".to_string(),
+        MenuItem::Practice => "Practice".to_string(),
+        MenuItem::WallPractice => "Wall Practice".to_string(),
+        #[cfg(not(target_arch = "wasm32"))]
+        MenuItem::HostLan => "Host LAN game".to_string(),
+        #[cfg(not(target_arch = "wasm32"))]
+        MenuItem::JoinLan => "Join LAN game".to_string(),
+        #[cfg(not(target_arch = "wasm32"))]
+        MenuItem::LanGame(index) => {
+            let game = &focus.lan_games[index];
+            format!("{} at {}", game.name, game.address.ip())
+        }
+        MenuItem::Settings => "Settings".to_string(),
+        #[cfg(not(target_arch = "wasm32"))]
+        MenuItem::Quit => "Quit".to_string(),
+        MenuItem::Difficulty => format!("Difficulty: {difficulty:?}"),
+        MenuItem::RubberBand => format!("Rubber band: {}", on_off(settings.rubber_band)),
+        MenuItem::LeftMouse => format!("Left mouse control: {}", on_off(mouse_controlled(Side::Left))),
+        MenuItem::RightMouse => {
+            format!("Right mouse control: {}", on_off(mouse_controlled(Side::Right)))
+        }
+        MenuItem::Sound => format!("Sound: {}", on_off(!audio.muted)),
+        MenuItem::Theme => format!("Theme: {:?}", display.theme),
+        MenuItem::HighContrast => format!("High contrast: {}", on_off(display.high_contrast)),
+        MenuItem::Fullscreen => format!("Fullscreen: {}", on_off(display.fullscreen)),
+        MenuItem::PresentMode => format!("Present mode: {:?}", display.present_mode(config)),
+        MenuItem::PowerUps => format!("Power-ups: {}", on_off(settings.power_ups)),
+        MenuItem::Obstacles => format!("Obstacles: {}", on_off(settings.obstacles)),
+        MenuItem::MovingObstacle => format!("Moving obstacle: {}", on_off(settings.moving_obstacle)),
+        MenuItem::Gravity => format!("Gravity: {}", on_off(settings.gravity.is_some())),
+        MenuItem::PaddleInertia => format!("Paddle inertia: {}", on_off(settings.paddle_inertia)),
+        MenuItem::PaddleDash => match (settings.paddle_dash, settings.cpu_dash) {
+            (false, _) => "Paddle dash: Off".to_string(),
+            (true, false) => "Paddle dash: On".to_string(),
+            (true, true) => "Paddle dash: On, computer too".to_string(),
+        },
+        MenuItem::LeftName | MenuItem::RightName => {
+            let side = if item == MenuItem::LeftName { Side::Left } else { Side::Right };
+            match &focus.editing {
+                Some(name) if item == focus.item() => format!("{side:?} name: {name}_"),
+                _ => format!("{side:?} name: {}", names.name(side)),
+            }
+        }
+        MenuItem::Controls => "Controls".to_string(),
+        MenuItem::Binding(action) => match focus.swapped {
+            _ if focus.rebinding && item == focus.item() => format!("{}: press a key", action_label(action)),
+            Some((swapped, taken_by)) if swapped == action => format!(
+                "{}: {:?} (swapped with {})",
+                action_label(action),
+                bindings.key(action),
+                action_label(taken_by)
+            ),
+            _ => format!("{}: {:?}", action_label(action), bindings.key(action)),
+        },
+        MenuItem::MovementKeys => {
+            let movement_keys = match bindings.movement_keys {
+                MovementKeys::Logical => "by name",
+                MovementKeys::Physical => "by position",
+                MovementKeys::Both => "by name or position",
+            };
+            format!("Movement keys: {movement_keys}")
+        }
+        MenuItem::ResetBindings => "Reset to defaults".to_string(),
+        MenuItem::Back => "Back".to_string(),
+    }
+}
+
+pub(crate) fn update_menu(
+    mut commands: Commands,
+    font: Res<UiFont>,
+    config: Res<PongConfig>,
+    theme: Res<Theme>,
+    focus: Res<MenuFocus>,
+    difficulty: Res<Difficulty>,
+    audio: Res<AudioSettings>,
+    display: Res<DisplaySettings>,
+    settings: Res<MatchSettings>,
+    names: Res<PlayerNames>,
+    bindings: Res<KeyBindings>,
+    stats: Res<PersistentStats>,
+    mut shown_items: Local<Option<Vec<MenuItem>>>,
+    root_query: Query<Entity, With<MenuText>>,
+    mouse_query: Query<&Side, (With<Paddle>, With<MouseControlled>)>,
+    mut item_query: Query<(&MenuItem, &mut Text)>,
+) {
+    let Ok(root) = root_query.get_single() else {
+        // the menu was left, so whatever page it comes back on has to be spawned
+        *shown_items = None;
+        return;
+    };
+
+    let focused = focus.item();
+    let item_style = |item: MenuItem| TextStyle {
+        font: font.0.clone(),
+        font_size: config.font_size,
+        color: if item == focused { theme.paddle } else { theme.text },
+    };
+
+    // a new page, or games found or gone on the join page
+    let items = focus.items();
+    if shown_items.as_ref() != Some(&items) {
+        let mut root = commands.entity(root);
+        root.despawn_descendants();
+        root.with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "Pong",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: config.font_size * 2.0,
+                        color: theme.text,
+                    },
+                ),
+                Themed(ThemeRole::Text),
+            ));
+            let heading = match focus.page {
+                #[cfg(not(target_arch = "wasm32"))]
+                MenuPage::Lan if focus.lan_games.is_empty() => Some("Looking for games on the network..."),
+                #[cfg(not(target_arch = "wasm32"))]
+                MenuPage::Lan => Some("Games on the network"),
+                // the keyboard's keys, with room beside them for a gamepad's buttons
+                MenuPage::Controls => Some("Keyboard - Enter to change a key, Escape to keep it"),
+                _ => None,
+            };
+            if let Some(heading) = heading {
+                parent.spawn((
+                    TextBundle::from_section(
+                        heading,
+                        TextStyle {
+                            font: font.0.clone(),
+                            font_size: config.font_size / 2.0,
+                            color: theme.text,
+                        },
+                    ),
+                    Themed(ThemeRole::Text),
+                ));
+            }
+            for &item in &items {
+                let label = menu_label(
+                    item,
+                    &focus,
+                    *difficulty,
+                    &audio,
+                    &display,
+                    &config,
+                    &settings,
+                    &names,
+                    &bindings,
+                    &mouse_query,
+                );
+                parent.spawn((
+                    TextBundle::from_section(label, item_style(item)),
+                    item,
+                ));
+            }
+            if focus.page == MenuPage::Main && !stats.is_empty() {
+                let records = format!(
+                    "Wins against the computer: {} - {}\nBest winning margin: {}\nLongest rally: {}",
+                    stats.player_wins, stats.cpu_wins, stats.best_winning_margin, stats.longest_rally
+                );
+                parent.spawn((
+                    TextBundle::from_section(
+                        records,
+                        TextStyle {
+                            font: font.0.clone(),
+                            font_size: config.font_size / 2.0,
+                            color: theme.text,
+                        },
+                    ),
+                    Themed(ThemeRole::Text),
+                ));
+            }
+        });
+        *shown_items = Some(items);
+        return;
+    }
+
+    for (&item, mut text) in &mut item_query {
+        let label = menu_label(
+            item,
+            &focus,
+            *difficulty,
+            &audio,
+            &display,
+            &config,
+            &settings,
+            &names,
+            &bindings,
+            &mouse_query,
+        );
+        let color = item_style(item).color;
+        // only touch the text when something changed, so it isn't laid out again every frame
+        if text.sections[0].value != label {
+            text.sections[0].value = label;
+        }
+        if text.sections[0].style.color != color {
+            text.sections[0].style.color = color;
+        }
+    }
+}
+
+// Keeps the join page's list up with the games found, with the highlight staying on the game it was on
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn list_lan_games(lan_games: Res<LanGames>, mut focus: ResMut<MenuFocus>) {
+    if !lan_games.is_changed() {
+        return;
+    }
+
+    let focused = focus.item();
+    let focused_game = match focused {
+        MenuItem::LanGame(index) => focus.lan_games.get(index).map(|game| game.address),
+        _ => None,
+    };
+    focus.lan_games = lan_games.games().to_vec();
+    let items = focus.items();
+    let index = match focused_game {
+        Some(address) => focus.lan_games.iter().position(|game| game.address == address),
+        None => items.iter().position(|item| *item == focused),
+    };
+    focus.index = index.unwrap_or(focus.index).min(items.len() - 1);
+}
+
+pub(crate) fn reset_idle_timer(mut attract_mode: ResMut<AttractMode>) {
+    attract_mode.idle.reset();
+}
+
+// Once the menu has gone untouched for long enough, the computer takes both paddles
+// and plays a match behind it, like an arcade cabinet waiting for someone to walk past.
+// It's always left against right, whatever was played last
+pub(crate) fn start_attract_mode(
+    mut commands: Commands,
+    time: Res<Time>,
+    theme: Res<Theme>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    touches: Res<Touches>,
+    mut attract_mode: ResMut<AttractMode>,
+    mut settings: ResMut<MatchSettings>,
+    mut state: ResMut<State<GameState>>,
+    paddle_query: Query<(Entity, Option<&CpuControlled>), With<Paddle>>,
+    mut menu_query: Query<&mut BackgroundColor, With<MenuText>>,
+) {
+    let input = keyboard_input.get_pressed().next().is_some()
+        || mouse_input.get_pressed().next().is_some()
+        || gamepad_input.get_pressed().next().is_some()
+        || touches.iter().next().is_some();
+    if input {
+        attract_mode.idle.reset();
+        return;
+    }
+    if !attract_mode.idle.tick(time.delta()).just_finished() {
+        return;
+    }
+    // pushed rather than set, so the menu stays up in front of the demo.
+    // Should something else be changing the state, the demo waits for the next time the menu sits idle
+    if state.push(GameState::Demo).is_err() {
+        return;
+    }
+
+    attract_mode.cpu_paddles.clear();
+    for (paddle, cpu_controlled) in &paddle_query {
+        if cpu_controlled.is_some() {
+            attract_mode.cpu_paddles.push(paddle);
+        } else {
+            commands.entity(paddle).insert(CpuControlled);
+        }
+    }
+    for mut background in &mut menu_query {
+        background.0 = *theme.background.clone().set_a(ATTRACT_MODE_DIM);
+    }
+    settings.four_players = false;
+    settings.doubles = false;
+    settings.wall_practice = false;
+}
+
+// Any key or button ends the demo. The press is swallowed so it doesn't also pick something in the menu
+pub(crate) fn stop_attract_mode(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut mouse_input: ResMut<Input<MouseButton>>,
+    mut gamepad_input: ResMut<Input<GamepadButton>>,
+    touches: Res<Touches>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let input = keyboard_input.get_just_pressed().next().is_some()
+        || mouse_input.get_just_pressed().next().is_some()
+        || gamepad_input.get_just_pressed().next().is_some()
+        || touches.iter_just_pressed().next().is_some();
+    if !input {
+        return;
+    }
+
+    // if the demo can't be stopped just yet, the press is left for the menu
+    if state.pop().is_err() {
+        return;
+    }
+    keyboard_input.clear();
+    mouse_input.clear();
+    gamepad_input.clear();
+}
+
+// Hands the paddles back and wipes the demo's score, so none of it carries into a real match
+pub(crate) fn end_attract_mode(
+    mut attract_mode: ResMut<AttractMode>,
+    mut match_reset: MatchReset,
+    paddle_query: Query<Entity, With<Paddle>>,
+    mut menu_query: Query<&mut BackgroundColor, With<MenuText>>,
+) {
+    for paddle in &paddle_query {
+        if !attract_mode.cpu_paddles.contains(&paddle) {
+            match_reset.commands.entity(paddle).remove::<CpuControlled>();
+        }
+    }
+    for mut background in &mut menu_query {
+        background.0 = Color::NONE;
+    }
+
+    match_reset.reset();
+    attract_mode.idle.reset();
+}
@@ -0,0 +1,226 @@
+use bevy::{
+    ecs::schedule::{ShouldRun, StageLabel},
+    prelude::*,
+};
+use bevy_ggrs::{
+    ggrs::{Config, PlayerHandle, PlayerType, SessionBuilder},
+    GGRSPlugin, PlayerInputs, Rollback, RollbackIdProvider, Session,
+};
+use std::net::SocketAddr;
+
+use crate::{
+    components::*, network::play_remote_input, physics_sets, replay::*, resources::*, run_physics_steps, stats::*,
+    systems::movement::*, PhysicsSet, PhysicsStage,
+};
+
+// The rollback schedule's stages: a physics step, then tagging whatever it spawned so that's rolled back too
+#[derive(StageLabel, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RollbackStage {
+    Physics,
+    Tracking,
+}
+
+/// What GGRS plays a match with. Each player's input is the one byte of their `PlayerInput`
+#[derive(Debug)]
+pub struct NetplayConfig;
+
+impl Config for NetplayConfig {
+    type Input = u8;
+    type State = u8;
+    type Address = SocketAddr;
+}
+
+/// Plays two-player matches under GGRS rollback netcode, with the `netplay` feature. Add it after `PongPlugin`.
+///
+/// During play the physics steps run in GGRS's rollback schedule instead of the `PhysicsStage`, one per
+/// GGRS frame, and GGRS can put the match back the way it was some steps ago and play it forward again.
+/// So far both players are on this machine, in a sync test: every step GGRS rolls back `check_distance`
+/// steps and plays them again, comparing checksums of the `Scoreboard` and `TrajectoryHash` to catch
+/// anything the rollback misses. A mismatch is logged as a warning.
+///
+/// Only the keyboard plays, and only the classic match is rolled back, with or without gravity, in games
+/// or timed mode. Power-ups, obstacles, doubles and four-player matches spawn more than it keeps track of.
+/// Sounds and effects of steps that are played again go off again
+pub struct NetplayPlugin {
+    /// How many steps are rolled back and played again each step, less than 8
+    pub check_distance: usize,
+}
+
+impl Default for NetplayPlugin {
+    fn default() -> Self {
+        NetplayPlugin { check_distance: 2 }
+    }
+}
+
+// How many steps the sync test rolls back, for `start_sync_test`
+#[derive(Resource)]
+struct CheckDistance(usize);
+
+impl Plugin for NetplayPlugin {
+    fn build(&self, app: &mut App) {
+        let step = app
+            .world
+            .get_resource::<PhysicsStep>()
+            .expect("NetplayPlugin needs to be added after PongPlugin")
+            .0;
+        let mut schedule = Schedule::default()
+            .with_stage(
+                RollbackStage::Physics,
+                SystemStage::parallel().with_run_criteria(run_until_match_won),
+            )
+            .with_stage_after(
+                RollbackStage::Physics,
+                RollbackStage::Tracking,
+                SystemStage::parallel().with_system(track_rollback_entities),
+            );
+        for set in physics_sets() {
+            schedule.add_system_set_to_stage(RollbackStage::Physics, set);
+        }
+        schedule.add_system_to_stage(
+            RollbackStage::Physics,
+            apply_netplay_inputs
+                .label(PhysicsSet::Input)
+                .after(play_remote_input)
+                .before(record_replay_input),
+        );
+
+        GGRSPlugin::<NetplayConfig>::new()
+            .with_update_frequency((1.0 / step).round() as usize)
+            .with_input_system(netplay_input)
+            .register_rollback_component::<Transform>()
+            .register_rollback_component::<GlobalTransform>()
+            .register_rollback_component::<Visibility>()
+            .register_rollback_component::<ComputedVisibility>()
+            .register_rollback_component::<Ball>()
+            .register_rollback_component::<Paddle>()
+            .register_rollback_component::<Velocity>()
+            .register_rollback_component::<PaddleVelocity>()
+            .register_rollback_component::<PaddleDash>()
+            .register_rollback_component::<LastPaddlePosition>()
+            .register_rollback_component::<AwaitingServe>()
+            .register_rollback_component::<BallWatchdog>()
+            .register_rollback_component::<MatchEntity>()
+            .register_rollback_resource::<Scoreboard>()
+            .register_rollback_resource::<MatchScore>()
+            .register_rollback_resource::<MatchTimer>()
+            .register_rollback_resource::<MatchStats>()
+            .register_rollback_resource::<Rally>()
+            .register_rollback_resource::<ServeTimer>()
+            .register_rollback_resource::<GameRng>()
+            .register_rollback_resource::<TrajectoryHash>()
+            .with_rollback_schedule(schedule)
+            .build(app);
+
+        app.insert_resource(CheckDistance(self.check_distance))
+            .stage(PhysicsStage, |stage: &mut SystemStage| {
+                stage.set_run_criteria(run_physics_steps.pipe(speed_up_replay).pipe(leave_play_to_ggrs))
+            })
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(start_sync_test))
+            .add_system_set(SystemSet::on_pause(GameState::Playing).with_system(end_sync_test))
+            .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(end_sync_test))
+            .add_system_to_stage(CoreStage::PostUpdate, despawn_orphans);
+    }
+}
+
+// The `PhysicsStage` goes on stepping the demo, but during play the GGRS schedule steps the match
+fn leave_play_to_ggrs(In(should_run): In<ShouldRun>, state: Res<State<GameState>>) -> ShouldRun {
+    match should_run {
+        ShouldRun::Yes | ShouldRun::YesAndCheckAgain if *state.current() == GameState::Playing => {
+            ShouldRun::NoAndCheckAgain
+        }
+        _ => should_run,
+    }
+}
+
+// The physics stops on the step the match is won, rather than when the state changes at the end of the frame.
+// The state isn't rolled back, so the steps played again after a win have to go the same way without it
+fn run_until_match_won(match_score: Res<MatchScore>) -> ShouldRun {
+    if match_score.winner.is_none() {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+// Starts a sync test for the match once it's underway, and again when it's unpaused.
+// Everything on the field so far is tagged for the rollback before GGRS saves its first step
+fn start_sync_test(
+    mut commands: Commands,
+    check_distance: Res<CheckDistance>,
+    session: Option<Res<Session<NetplayConfig>>>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut rollback_ids: ResMut<RollbackIdProvider>,
+    untracked_query: Query<Entity, (Or<(With<Ball>, With<Paddle>)>, Without<Rollback>)>,
+) {
+    if session.is_some() {
+        return;
+    }
+
+    // the steps played again would be recorded again
+    recorder.recording = false;
+    for entity in &untracked_query {
+        commands.entity(entity).insert(Rollback::new(rollback_ids.next_id()));
+    }
+
+    let mut builder = SessionBuilder::<NetplayConfig>::new()
+        .with_num_players(2)
+        .with_check_distance(check_distance.0);
+    for handle in 0..2 {
+        builder = builder
+            .add_player(PlayerType::Local, handle)
+            .expect("both players are new and local");
+    }
+    let session = builder
+        .start_synctest_session()
+        .expect("the check distance must be less than 8");
+    commands.insert_resource(Session::SyncTestSession(session));
+}
+
+// Without a session GGRS stops stepping, so pausing and the end of the match hold the physics
+fn end_sync_test(mut commands: Commands) {
+    commands.remove_resource::<Session<NetplayConfig>>();
+}
+
+// One player's input for the next step, from the keyboard. The left paddle is player 0, the right player 1.
+// Without a keyboard it's whatever `PaddleInputs` has, which is how the tests play
+fn netplay_input(
+    In(handle): In<PlayerHandle>,
+    keyboard_input: Option<Res<Input<KeyCode>>>,
+    scan_input: Option<Res<Input<ScanCode>>>,
+    bindings: Res<KeyBindings>,
+    paddle_inputs: Res<PaddleInputs>,
+) -> u8 {
+    let mut paddle_inputs = *paddle_inputs;
+    if let (Some(keyboard_input), Some(scan_input)) = (keyboard_input, scan_input) {
+        read_keyboard(&keyboard_input, &scan_input, &bindings, &mut paddle_inputs);
+    }
+    let side = if handle == 0 { Side::Left } else { Side::Right };
+    paddle_inputs.input(side).0
+}
+
+// Hands the paddles the inputs GGRS has for this step, in place of the keyboard
+fn apply_netplay_inputs(inputs: Res<PlayerInputs<NetplayConfig>>, mut paddle_inputs: ResMut<PaddleInputs>) {
+    paddle_inputs.left = PlayerInput(inputs[0].0);
+    paddle_inputs.right = PlayerInput(inputs[1].0);
+}
+
+// Balls served during the step are tagged here, once they've been spawned, so the next save has them
+fn track_rollback_entities(
+    mut commands: Commands,
+    mut rollback_ids: ResMut<RollbackIdProvider>,
+    untracked_query: Query<Entity, (Or<(With<Ball>, With<Paddle>)>, Without<Rollback>)>,
+) {
+    for entity in &untracked_query {
+        commands.entity(entity).insert(Rollback::new(rollback_ids.next_id()));
+    }
+}
+
+// A rollback despawns the balls that weren't there yet without their children, which would leave
+// their meshes behind. Balls it puts back are given new ones
+fn despawn_orphans(mut commands: Commands, child_query: Query<(Entity, &Parent)>, entity_query: Query<()>) {
+    for (child, parent) in &child_query {
+        if entity_query.get(parent.get()).is_err() {
+            commands.entity(child).despawn_recursive();
+        }
+    }
+}
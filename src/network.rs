@@ -0,0 +1,808 @@
+use bevy::prelude::*;
+use crossbeam_channel::{Receiver, Sender, TryRecvError};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::*,
+    config::*,
+    resources::*,
+    systems::{movement::*, serve::*},
+};
+
+// How quickly the client eases its balls and paddles onto where the host has them, per second.
+// A ball further off than `SNAP_DISTANCE`, like one just put back after a goal, jumps straight there
+const SNAPSHOT_SMOOTHING: f32 = 20.0;
+const SNAP_DISTANCE: f32 = 100.0;
+
+/// How many spectators a host lets watch at once, unless its `Spectators` says otherwise
+pub const MAX_SPECTATORS: usize = 4;
+
+/// Which end of an online match this game is. Insert one before adding the plugin to play over the network,
+/// along with a `SkipMenu` for two players. Without one every match is played on this machine.
+///
+/// The host runs the match and plays the left paddle, the client only sends its input for the right paddle
+/// and shows what the host sends back. Spectators are shown the same, and play no part.
+/// Only the host can pause or restart, and only the keyboard plays
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub enum NetworkRole {
+    /// Waits for a client on this port, over a WebSocket, and lets spectators watch. Native only
+    Host { port: u16 },
+    /// Joins the host at a WebSocket address, like "ws://192.168.1.20:7878"
+    Client { address: String },
+    /// Watches the host's match at a WebSocket address, like a client that doesn't play
+    Spectator { address: String },
+}
+
+impl NetworkRole {
+    // What's sent first on joining the host, to say whether it's to play or watch
+    fn join_message(&self) -> Option<NetMessage> {
+        match self {
+            NetworkRole::Host { .. } => None,
+            NetworkRole::Client { .. } => Some(NetMessage::Join { spectator: false }),
+            NetworkRole::Spectator { .. } => Some(NetMessage::Join { spectator: true }),
+        }
+    }
+
+    // Whether it's only shown the match the host sends, rather than running its own
+    pub(crate) fn follows_host(&self) -> bool {
+        matches!(self, NetworkRole::Client { .. } | NetworkRole::Spectator { .. })
+    }
+}
+
+/// What the host, client and spectators send each other
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NetMessage {
+    /// The first thing sent to the host, by the client or a spectator
+    Join { spectator: bool },
+    /// The client's input, sent every frame
+    Input(PlayerInput),
+    /// The match as the host has it, sent every frame
+    Snapshot(MatchSnapshot),
+    /// Everything a spectator needs to show the match, sent before any snapshots when it joins
+    Welcome(MatchSync),
+    /// Sent to a spectator that's turned away, the host having as many as it lets watch
+    Full,
+}
+
+impl NetMessage {
+    /// Packed with bincode, a few dozen bytes for a snapshot
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("messages always serialize")
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<NetMessage, bincode::Error> {
+        bincode::deserialize(bytes)
+    }
+}
+
+/// Everything the client needs to show the match the way the host has it
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct MatchSnapshot {
+    /// How far up the left and right paddles are
+    pub paddles: [f32; 2],
+    /// Where each ball is, and its velocity
+    pub balls: Vec<(Vec2, Vec2)>,
+    /// The left and right points in the current game
+    pub score: [u32; 2],
+    /// The left and right games won
+    pub games: [u32; 2],
+    pub winner: Option<Side>,
+    pub paused: bool,
+    /// The paddle hits in the rally going on
+    pub rally: u32,
+}
+
+/// The whole match, for a spectator joining partway through
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct MatchSync {
+    pub names: PlayerNames,
+    pub snapshot: MatchSnapshot,
+}
+
+/// The connection to the other player, as the messages that have come in from it and a way to send
+/// it more. Opened from the `NetworkRole` when the game starts, unless one has been inserted already
+#[derive(Resource)]
+pub struct NetworkLink {
+    incoming: Receiver<NetMessage>,
+    outgoing: Sender<NetMessage>,
+    // on the host, the spectators that have connected, for `admit_spectators` to let in or turn away
+    spectators: Option<Receiver<NetworkLink>>,
+}
+
+// The connection's side of a `NetworkLink`, which passes on what arrives and sends what the game queues up.
+// Dropping it is how the game finds out the connection is gone
+pub(crate) struct LinkEnds {
+    pub(crate) incoming: Sender<NetMessage>,
+    pub(crate) outgoing: Receiver<NetMessage>,
+}
+
+impl NetworkLink {
+    /// Two links joined to each other, so whatever is sent on one arrives on the other.
+    /// For playing a host and a client in the same process, like the tests do
+    pub fn pair() -> (NetworkLink, NetworkLink) {
+        let (to_client, from_host) = crossbeam_channel::unbounded();
+        let (to_host, from_client) = crossbeam_channel::unbounded();
+        let host = NetworkLink {
+            incoming: from_client,
+            outgoing: to_client,
+            spectators: None,
+        };
+        let client = NetworkLink {
+            incoming: from_host,
+            outgoing: to_host,
+            spectators: None,
+        };
+        (host, client)
+    }
+
+    pub(crate) fn with_ends() -> (NetworkLink, LinkEnds) {
+        let (incoming_sender, incoming) = crossbeam_channel::unbounded();
+        let (outgoing, outgoing_receiver) = crossbeam_channel::unbounded();
+        let link = NetworkLink {
+            incoming,
+            outgoing,
+            spectators: None,
+        };
+        let ends = LinkEnds {
+            incoming: incoming_sender,
+            outgoing: outgoing_receiver,
+        };
+        (link, ends)
+    }
+
+    /// Starts hosting or joining, in the background. Failing to connect shows up later,
+    /// as the link being `NetworkStatus::Disconnected`
+    pub fn open(role: &NetworkRole) -> Result<NetworkLink, String> {
+        #[cfg(not(target_arch = "wasm32"))]
+        match role {
+            NetworkRole::Host { port } => socket::host(*port).map_err(|err| err.to_string()),
+            NetworkRole::Client { address } | NetworkRole::Spectator { address } => {
+                Ok(socket::join(address.clone()))
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        match role {
+            NetworkRole::Host { .. } => Err("a browser can't host, only join".to_string()),
+            NetworkRole::Client { address } | NetworkRole::Spectator { address } => {
+                crate::web::join_online_match(address)
+            }
+        }
+    }
+
+    /// Queues a message for the other player. It's dropped if the connection is gone
+    pub fn send(&self, message: NetMessage) {
+        let _ = self.outgoing.send(message);
+    }
+}
+
+/// How the connection to the other player is going, only there with a `NetworkRole`
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkStatus {
+    /// Waiting for the first message from the other player
+    Connecting,
+    Connected,
+    /// The other player left, or never could be reached. It's for good, the game has to be started again
+    Disconnected,
+    /// A spectator the host turned away, having as many watching as it lets in
+    Full,
+}
+
+// Whether this game runs the physics. A host waits for the client before the match gets going,
+// and a client only ever shows the host's
+pub(crate) fn simulates_match(role: Option<&NetworkRole>, status: Option<&NetworkStatus>) -> bool {
+    match role {
+        None => true,
+        Some(NetworkRole::Host { .. }) => status == Some(&NetworkStatus::Connected),
+        Some(NetworkRole::Client { .. } | NetworkRole::Spectator { .. }) => false,
+    }
+}
+
+/// The spectators watching the host's match, who are sent the same snapshots as the client.
+/// Inserting `Spectators::new` with a different limit before adding the plugin changes how many are let in
+#[derive(Resource)]
+pub struct Spectators {
+    /// How many can watch at once, any more that join are turned away
+    pub limit: usize,
+    watching: Vec<NetworkLink>,
+    joining: Vec<NetworkLink>,
+}
+
+impl Default for Spectators {
+    fn default() -> Self {
+        Spectators::new(MAX_SPECTATORS)
+    }
+}
+
+impl Spectators {
+    pub fn new(limit: usize) -> Spectators {
+        Spectators {
+            limit,
+            watching: Vec::new(),
+            joining: Vec::new(),
+        }
+    }
+
+    /// Lets in a spectator on the other end of `link` on the next frame, if there's room.
+    /// The host's socket hands over each one that connects, tests can hand over a `NetworkLink::pair`
+    pub fn join(&mut self, link: NetworkLink) {
+        self.joining.push(link);
+    }
+
+    /// How many are watching
+    pub fn watching(&self) -> usize {
+        self.watching.len()
+    }
+}
+
+// The client's latest input, which the host plays the right paddle with until the next one arrives
+#[derive(Resource, Default)]
+pub(crate) struct RemoteInput(PlayerInput);
+
+// The host's latest snapshot on the client or a spectator, and how many seconds old it is.
+// A spectator is also sent the players' names when it joins
+#[derive(Resource, Default)]
+pub(crate) struct LatestSnapshot {
+    snapshot: Option<MatchSnapshot>,
+    age: f32,
+    names: Option<PlayerNames>,
+}
+
+// A spectator's own names, put back when it leaves the match whose players' names it's been showing.
+// They're what's saved in the meantime
+#[derive(Resource)]
+pub(crate) struct LocalNames(pub(crate) PlayerNames);
+
+pub(crate) fn open_network_link(
+    mut commands: Commands,
+    role: Option<Res<NetworkRole>>,
+    link: Option<Res<NetworkLink>>,
+) {
+    let Some(role) = role else {
+        return;
+    };
+
+    if let Some(link) = link {
+        if let Some(message) = role.join_message() {
+            link.send(message);
+        }
+        commands.insert_resource(NetworkStatus::Connecting);
+        return;
+    }
+    start_online_match(&mut commands, role.clone());
+}
+
+// Hosts, joins or watches a match from the menu, or from the flags the game was started with
+pub(crate) fn start_online_match(commands: &mut Commands, role: NetworkRole) {
+    commands.insert_resource(NetworkStatus::Connecting);
+    match NetworkLink::open(&role) {
+        Ok(link) => {
+            if let Some(message) = role.join_message() {
+                link.send(message);
+            }
+            commands.insert_resource(link);
+        }
+        Err(err) => {
+            error!("Couldn't start the online match: {err}");
+            commands.insert_resource(NetworkStatus::Disconnected);
+        }
+    }
+    commands.insert_resource(role);
+}
+
+// Going back to the menu leaves the online match, and closes the connection along with any spectators'.
+// The next match is played here, under this player's own names
+pub(crate) fn leave_online_match(
+    mut commands: Commands,
+    role: Option<Res<NetworkRole>>,
+    local_names: Option<Res<LocalNames>>,
+    mut spectators: ResMut<Spectators>,
+) {
+    if role.is_none() {
+        return;
+    }
+
+    commands.remove_resource::<NetworkRole>();
+    commands.remove_resource::<NetworkLink>();
+    commands.remove_resource::<NetworkStatus>();
+    commands.insert_resource(RemoteInput::default());
+    commands.insert_resource(LatestSnapshot::default());
+    spectators.watching.clear();
+    spectators.joining.clear();
+    if let Some(local_names) = local_names {
+        commands.insert_resource(local_names.0.clone());
+        commands.remove_resource::<LocalNames>();
+    }
+    // the browser's socket isn't closed by letting go of the link
+    #[cfg(target_arch = "wasm32")]
+    crate::web::close_online_match();
+}
+
+// Takes in everything that has arrived since the last frame, before the physics steps that use it.
+// The first message from the other player is when they've connected
+pub(crate) fn receive_from_network(
+    link: Option<Res<NetworkLink>>,
+    status: Option<ResMut<NetworkStatus>>,
+    mut remote_input: ResMut<RemoteInput>,
+    mut latest: ResMut<LatestSnapshot>,
+) {
+    let (Some(link), Some(mut status)) = (link, status) else {
+        return;
+    };
+
+    loop {
+        match link.incoming.try_recv() {
+            Ok(NetMessage::Join { .. }) => {}
+            Ok(NetMessage::Input(input)) => remote_input.0 = input,
+            Ok(NetMessage::Snapshot(snapshot)) => {
+                latest.snapshot = Some(snapshot);
+                latest.age = 0.0;
+            }
+            Ok(NetMessage::Welcome(sync)) => {
+                *latest = LatestSnapshot {
+                    snapshot: Some(sync.snapshot),
+                    age: 0.0,
+                    names: Some(sync.names),
+                };
+            }
+            Ok(NetMessage::Full) => {
+                warn!("The match already has as many spectators as it lets watch");
+                *status = NetworkStatus::Full;
+                return;
+            }
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                if !matches!(*status, NetworkStatus::Disconnected | NetworkStatus::Full) {
+                    warn!("Lost the connection to the other player");
+                    *status = NetworkStatus::Disconnected;
+                }
+                return;
+            }
+        }
+        if *status == NetworkStatus::Connecting {
+            *status = NetworkStatus::Connected;
+        }
+    }
+}
+
+// The host plays the right paddle with the client's input, in place of its own keyboard's.
+// It's read before the replay records the step's inputs, so the host can record online matches
+pub(crate) fn play_remote_input(
+    role: Option<Res<NetworkRole>>,
+    remote_input: Res<RemoteInput>,
+    mut paddle_inputs: ResMut<PaddleInputs>,
+) {
+    if let Some(NetworkRole::Host { .. }) = role.as_deref() {
+        paddle_inputs.right = remote_input.0;
+    }
+}
+
+// Holds the match while the other player is gone, with nothing to carry on with
+pub(crate) fn pause_when_disconnected(status: Option<Res<NetworkStatus>>, mut state: ResMut<State<GameState>>) {
+    let Some(status) = status else {
+        return;
+    };
+
+    if status.is_changed() && *status == NetworkStatus::Disconnected && *state.current() == GameState::Playing {
+        let _ = state.push(GameState::Paused);
+    }
+}
+
+// The match as it stands on the host
+fn match_snapshot(
+    state: &State<GameState>,
+    scoreboard: &Scoreboard,
+    match_score: &MatchScore,
+    rally: &Rally,
+    paddle_query: &Query<(&Side, &Transform), (With<Paddle>, Without<Half>)>,
+    ball_query: &Query<(&Transform, &Velocity), With<Ball>>,
+) -> MatchSnapshot {
+    let mut paddles = [0.0; 2];
+    for (side, transform) in paddle_query {
+        paddles[*side as usize] = transform.translation.y;
+    }
+    MatchSnapshot {
+        paddles,
+        balls: ball_query
+            .iter()
+            .map(|(transform, velocity)| (transform.translation.truncate(), velocity.0))
+            .collect(),
+        score: [scoreboard.left_score as u32, scoreboard.right_score as u32],
+        games: [match_score.left_games as u32, match_score.right_games as u32],
+        winner: match_score.winner,
+        paused: *state.current() == GameState::Paused,
+        rally: rally.hits as u32,
+    }
+}
+
+// Sends the client and the spectators the match as it stands at the end of the host's frame
+pub(crate) fn send_snapshot(
+    role: Option<Res<NetworkRole>>,
+    status: Option<Res<NetworkStatus>>,
+    link: Option<Res<NetworkLink>>,
+    spectators: Res<Spectators>,
+    state: Res<State<GameState>>,
+    scoreboard: Res<Scoreboard>,
+    match_score: Res<MatchScore>,
+    rally: Res<Rally>,
+    paddle_query: Query<(&Side, &Transform), (With<Paddle>, Without<Half>)>,
+    ball_query: Query<(&Transform, &Velocity), With<Ball>>,
+) {
+    let (Some(NetworkRole::Host { .. }), Some(NetworkStatus::Connected), Some(link)) =
+        (role.as_deref(), status.as_deref(), link)
+    else {
+        return;
+    };
+
+    let snapshot = match_snapshot(&state, &scoreboard, &match_score, &rally, &paddle_query, &ball_query);
+    for spectator in &spectators.watching {
+        spectator.send(NetMessage::Snapshot(snapshot.clone()));
+    }
+    link.send(NetMessage::Snapshot(snapshot));
+}
+
+// Lets in the spectators that have joined the host while there's room, sending each the whole match
+// before any snapshots, and turns away the rest. Those that have gone are let go
+pub(crate) fn admit_spectators(
+    role: Option<Res<NetworkRole>>,
+    link: Option<Res<NetworkLink>>,
+    mut spectators: ResMut<Spectators>,
+    names: Res<PlayerNames>,
+    state: Res<State<GameState>>,
+    scoreboard: Res<Scoreboard>,
+    match_score: Res<MatchScore>,
+    rally: Res<Rally>,
+    paddle_query: Query<(&Side, &Transform), (With<Paddle>, Without<Half>)>,
+    ball_query: Query<(&Transform, &Velocity), With<Ball>>,
+) {
+    let Some(NetworkRole::Host { .. }) = role.as_deref() else {
+        return;
+    };
+
+    if let Some(connected) = link.as_ref().and_then(|link| link.spectators.as_ref()) {
+        spectators.joining.extend(connected.try_iter());
+    }
+    // a spectator has nothing to say after joining, so all there is to hear is that it's gone
+    spectators.watching.retain(|spectator| loop {
+        match spectator.incoming.try_recv() {
+            Ok(_) => {}
+            Err(TryRecvError::Empty) => break true,
+            Err(TryRecvError::Disconnected) => break false,
+        }
+    });
+    if spectators.joining.is_empty() {
+        return;
+    }
+
+    let snapshot = match_snapshot(&state, &scoreboard, &match_score, &rally, &paddle_query, &ball_query);
+    for spectator in std::mem::take(&mut spectators.joining) {
+        if spectators.watching.len() >= spectators.limit {
+            // dropped once it's sent, which closes the connection
+            spectator.send(NetMessage::Full);
+            continue;
+        }
+        spectator.send(NetMessage::Welcome(MatchSync {
+            names: names.clone(),
+            snapshot: snapshot.clone(),
+        }));
+        spectators.watching.push(spectator);
+    }
+}
+
+// Sends the host what the client's player is pressing. Either player's keys play the right paddle,
+// so whoever joins can use whichever they're used to
+pub(crate) fn send_client_input(
+    role: Option<Res<NetworkRole>>,
+    link: Option<Res<NetworkLink>>,
+    keyboard_input: Option<Res<Input<KeyCode>>>,
+    scan_input: Option<Res<Input<ScanCode>>>,
+    bindings: Res<KeyBindings>,
+    mut paddle_inputs: ResMut<PaddleInputs>,
+) {
+    let (Some(NetworkRole::Client { .. }), Some(link)) = (role.as_deref(), link) else {
+        return;
+    };
+
+    // without a keyboard, in a headless app, the inputs are left for whoever is driving it to set
+    if let (Some(keyboard_input), Some(scan_input)) = (keyboard_input, scan_input) {
+        read_keyboard(&keyboard_input, &scan_input, &bindings, &mut paddle_inputs);
+    }
+    link.send(NetMessage::Input(PlayerInput(paddle_inputs.left.0 | paddle_inputs.right.0)));
+}
+
+// Puts up the host's score and rally, and a spectator's players' names, and follows the host in and out
+// of pauses and the game over screen. Balls are spawned or despawned until there are as many as the host has
+pub(crate) fn apply_snapshot(
+    mut commands: Commands,
+    config: Res<PongConfig>,
+    role: Option<Res<NetworkRole>>,
+    status: Option<Res<NetworkStatus>>,
+    latest: Res<LatestSnapshot>,
+    mut state: ResMut<State<GameState>>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut match_score: ResMut<MatchScore>,
+    mut rally: ResMut<Rally>,
+    mut names: ResMut<PlayerNames>,
+    local_names: Option<Res<LocalNames>>,
+    ball_query: Query<Entity, With<Ball>>,
+    waiting_ball_query: Query<Entity, (With<Ball>, With<AwaitingServe>)>,
+) {
+    let (Some(role), Some(snapshot)) = (role.as_deref(), &latest.snapshot) else {
+        return;
+    };
+    if !role.follows_host() || !latest.is_changed() {
+        return;
+    }
+
+    if let Some(players) = latest.names.as_ref().filter(|players| **players != *names) {
+        if local_names.is_none() {
+            commands.insert_resource(LocalNames(names.clone()));
+        }
+        *names = players.clone();
+    }
+    if rally.hits != snapshot.rally as usize {
+        rally.hits = snapshot.rally as usize;
+    }
+
+    let score = snapshot.score.map(|points| points as usize);
+    if [scoreboard.left_score, scoreboard.right_score] != score {
+        [scoreboard.left_score, scoreboard.right_score] = score;
+    }
+    let games = snapshot.games.map(|games| games as usize);
+    if [match_score.left_games, match_score.right_games] != games || match_score.winner != snapshot.winner {
+        [match_score.left_games, match_score.right_games] = games;
+        match_score.winner = snapshot.winner;
+    }
+
+    let connected = status.as_deref() == Some(&NetworkStatus::Connected);
+    match *state.current() {
+        GameState::Playing if snapshot.winner.is_some() => {
+            let _ = state.set(GameState::GameOver);
+        }
+        GameState::Playing if snapshot.paused => {
+            let _ = state.push(GameState::Paused);
+        }
+        GameState::Paused if snapshot.winner.is_some() => {
+            let _ = state.replace(GameState::GameOver);
+        }
+        GameState::Paused if !snapshot.paused && connected => {
+            let _ = state.pop();
+        }
+        // the host has started another match
+        GameState::GameOver if snapshot.winner.is_none() => {
+            let _ = state.set(GameState::Playing);
+        }
+        _ => {}
+    }
+
+    // the host does the serving
+    for ball in &waiting_ball_query {
+        commands.entity(ball).remove::<AwaitingServe>();
+    }
+    if ball_query.iter().count() != snapshot.balls.len() {
+        for ball in &ball_query {
+            commands.entity(ball).despawn_recursive();
+        }
+        for (position, velocity) in &snapshot.balls {
+            let position = position.extend(config.ball_spawn_point().z);
+            spawn_ball(&mut commands, &config, position, *velocity);
+        }
+    }
+}
+
+// Moves the client's paddles and balls along after the host's, so they don't jump from one snapshot
+// to the next. The balls carry on at the velocity they were last sent, with the difference from where
+// the host had them eased away
+pub(crate) fn follow_snapshot(
+    time: Res<Time>,
+    role: Option<Res<NetworkRole>>,
+    mut latest: ResMut<LatestSnapshot>,
+    mut paddle_query: Query<(&Side, &mut Transform), (With<Paddle>, Without<Half>, Without<Ball>)>,
+    mut ball_query: Query<(&mut Transform, &mut Velocity), With<Ball>>,
+) {
+    if !role.is_some_and(|role| role.follows_host()) {
+        return;
+    }
+    let delta = time.delta_seconds();
+    // not a change anyone needs to hear about
+    let latest = latest.bypass_change_detection();
+    let Some(snapshot) = &latest.snapshot else {
+        return;
+    };
+    let blend = 1.0 - (-SNAPSHOT_SMOOTHING * delta).exp();
+
+    for (side, mut transform) in &mut paddle_query {
+        let target = snapshot.paddles[*side as usize];
+        transform.translation.y += (target - transform.translation.y) * blend;
+    }
+    for ((mut transform, mut velocity), (position, sent_velocity)) in ball_query.iter_mut().zip(&snapshot.balls) {
+        let target = *position + *sent_velocity * latest.age;
+        let moved = transform.translation.truncate() + *sent_velocity * delta;
+        let position = if moved.distance(target) > SNAP_DISTANCE {
+            target
+        } else {
+            moved.lerp(target, blend)
+        };
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        velocity.0 = *sent_velocity;
+    }
+    latest.age += delta;
+}
+
+// The WebSocket itself on native builds, on a thread of its own for each connection
+#[cfg(not(target_arch = "wasm32"))]
+mod socket {
+    use bevy::prelude::*;
+    use crossbeam_channel::{Sender, TryRecvError};
+    use std::{
+        io::ErrorKind,
+        net::{TcpListener, TcpStream},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+    use tungstenite::{client::IntoClientRequest, Message, WebSocket};
+
+    use super::{LinkEnds, NetMessage, NetworkLink};
+
+    // How long the thread waits on the socket for a message before checking for ones to send
+    const POLL_INTERVAL: Duration = Duration::from_millis(2);
+    // How long whoever connects has to say whether they're playing or watching
+    const JOIN_TIMEOUT: Duration = Duration::from_secs(3);
+
+    // Listens on `port` on every interface, and takes the first client to connect and the spectators
+    // that come after. It stops listening if the game drops the link before the client joins, so the port
+    // can be hosted on again, or once the client has gone
+    pub(super) fn host(port: u16) -> std::io::Result<NetworkLink> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        info!("Waiting for the other player to join on port {port}");
+        let (mut link, ends) = NetworkLink::with_ends();
+        let (spectators, connected_spectators) = crossbeam_channel::unbounded();
+        link.spectators = Some(connected_spectators);
+        thread::spawn(move || {
+            let mut ends = Some(ends);
+            let client_gone = Arc::new(AtomicBool::new(false));
+            loop {
+                let stream = match listener.accept() {
+                    Ok((stream, _)) => stream,
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                        let game_gone = ends
+                            .as_ref()
+                            .is_some_and(|ends| ends.outgoing.try_recv() == Err(TryRecvError::Disconnected));
+                        if game_gone || client_gone.load(Ordering::Relaxed) {
+                            return;
+                        }
+                        thread::sleep(POLL_INTERVAL);
+                        continue;
+                    }
+                    Err(err) => {
+                        warn!("Stopped waiting for players to join: {err}");
+                        return;
+                    }
+                };
+                match accept(stream) {
+                    Ok((socket, NetMessage::Join { spectator: true })) => {
+                        if !add_spectator(socket, &spectators) {
+                            return;
+                        }
+                    }
+                    Ok((socket, first_message)) => match ends.take() {
+                        Some(ends) => {
+                            let client_gone = client_gone.clone();
+                            thread::spawn(move || {
+                                if ends.incoming.send(first_message).is_ok() {
+                                    pump(socket, ends);
+                                }
+                                client_gone.store(true, Ordering::Relaxed);
+                            });
+                        }
+                        None => warn!("Turned away another player, the match has started"),
+                    },
+                    Err(err) => warn!("Someone couldn't join: {err}"),
+                }
+            }
+        });
+        Ok(link)
+    }
+
+    // Takes a connection as a WebSocket, and waits for it to say what it's joining as
+    fn accept(stream: TcpStream) -> Result<(WebSocket<TcpStream>, NetMessage), String> {
+        stream.set_nonblocking(false).map_err(|err| err.to_string())?;
+        stream.set_read_timeout(Some(JOIN_TIMEOUT)).map_err(|err| err.to_string())?;
+        let mut socket = tungstenite::accept(stream).map_err(|err| err.to_string())?;
+        loop {
+            match socket.read().map_err(|err| err.to_string())? {
+                Message::Binary(bytes) => {
+                    let message = NetMessage::from_bytes(&bytes).map_err(|err| err.to_string())?;
+                    return Ok((socket, message));
+                }
+                Message::Close(_) => return Err("it left straight away".to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    // Hands the game a spectator to let in or turn away, on a thread of its own. False once the game is gone
+    fn add_spectator(socket: WebSocket<TcpStream>, spectators: &Sender<NetworkLink>) -> bool {
+        let (link, ends) = NetworkLink::with_ends();
+        if spectators.send(link).is_err() {
+            return false;
+        }
+        thread::spawn(move || pump(socket, ends));
+        true
+    }
+
+    pub(super) fn join(address: String) -> NetworkLink {
+        let (link, ends) = NetworkLink::with_ends();
+        thread::spawn(move || match connect(&address) {
+            Ok(socket) => pump(socket, ends),
+            Err(err) => warn!("Couldn't join {address}: {err}"),
+        });
+        link
+    }
+
+    fn connect(address: &str) -> Result<WebSocket<TcpStream>, String> {
+        let request = address.into_client_request().map_err(|err| err.to_string())?;
+        let host = request.uri().host().ok_or("the address has no host")?;
+        let port = request.uri().port_u16().unwrap_or(80);
+        let stream = TcpStream::connect((host, port)).map_err(|err| err.to_string())?;
+        let (socket, _) = tungstenite::client(request, stream).map_err(|err| err.to_string())?;
+        Ok(socket)
+    }
+
+    // Passes messages between the socket and the game until either of them goes away
+    fn pump(mut socket: WebSocket<TcpStream>, ends: LinkEnds) {
+        if let Err(err) = socket.get_mut().set_read_timeout(Some(POLL_INTERVAL)) {
+            warn!("Couldn't set up the connection: {err}");
+            return;
+        }
+
+        loop {
+            loop {
+                match ends.outgoing.try_recv() {
+                    Ok(message) => {
+                        if let Err(err) = socket.write(Message::Binary(message.to_bytes())) {
+                            warn!("Couldn't send to the other player: {err}");
+                            return;
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    // the game has closed
+                    Err(TryRecvError::Disconnected) => {
+                        let _ = socket.close(None);
+                        let _ = socket.flush();
+                        return;
+                    }
+                }
+            }
+            if let Err(err) = socket.flush() {
+                warn!("Couldn't send to the other player: {err}");
+                return;
+            }
+
+            match socket.read() {
+                Ok(Message::Binary(bytes)) => match NetMessage::from_bytes(&bytes) {
+                    Ok(message) => {
+                        if ends.incoming.send(message).is_err() {
+                            return;
+                        }
+                    }
+                    Err(err) => warn!("Ignoring a message the other player sent that makes no sense: {err}"),
+                },
+                Ok(Message::Close(_)) => return,
+                Ok(_) => {}
+                // nothing arrived in time, there's more to send
+                Err(tungstenite::Error::Io(err))
+                    if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(err) => {
+                    warn!("Lost the connection to the other player: {err}");
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
+use rand::Rng;
+
+use crate::{components::*, config::*, resources::*};
+
+/// A recorded match: everything needed to play it back exactly, and how it ended so that can be checked.
+/// Paddles following the mouse or a finger aren't recorded, so only keyboard and computer players replay.
+/// Nor are four-player, doubles or wall practice matches, only the left and right paddles' inputs are kept
+#[derive(Debug, Clone, Default)]
+pub struct Replay {
+    /// The `GameRng` is reseeded with this when the recording starts
+    pub seed: u64,
+    pub config: PongConfig,
+    /// The rules the match was played under
+    pub settings: MatchSettings,
+    pub difficulty: Difficulty,
+    /// Whether the left and right paddles were played by the computer
+    pub cpu_controlled: [bool; 2],
+    /// The points on the scoreboard when the match was won
+    pub final_score: [u32; 2],
+    /// The `TrajectoryHash` when the match was won
+    pub trajectory_hash: u64,
+    /// Two bytes per physics step, the left player's `PlayerInput` then the right's
+    pub inputs: Vec<u8>,
+}
+
+impl Replay {
+    const MAGIC: &'static [u8; 8] = b"PONGRPL2";
+
+    /// The magic bytes, then a header of the seed, difficulty, computer players, final score,
+    /// trajectory hash and the lengths of the config and match settings, followed by the config and
+    /// the settings as RON and then the inputs. Numbers are little endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let config = ron::to_string(&self.config).expect("the config always serializes");
+        let settings = ron::to_string(&self.settings).expect("the match settings always serialize");
+        let capacity = Replay::MAGIC.len() + 34 + config.len() + settings.len() + self.inputs.len();
+        let mut bytes = Vec::with_capacity(capacity);
+        bytes.extend_from_slice(Replay::MAGIC);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(self.difficulty as u8);
+        bytes.push(u8::from(self.cpu_controlled[0]) | u8::from(self.cpu_controlled[1]) << 1);
+        bytes.extend_from_slice(&self.final_score[0].to_le_bytes());
+        bytes.extend_from_slice(&self.final_score[1].to_le_bytes());
+        bytes.extend_from_slice(&self.trajectory_hash.to_le_bytes());
+        bytes.extend_from_slice(&(config.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(settings.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(config.as_bytes());
+        bytes.extend_from_slice(settings.as_bytes());
+        bytes.extend_from_slice(&self.inputs);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Replay, ReplayError> {
+        let Some(mut rest) = bytes.strip_prefix(Replay::MAGIC.as_slice()) else {
+            return Err(ReplayError::NotAReplay);
+        };
+        let mut take = |len: usize| -> Result<&[u8], ReplayError> {
+            if rest.len() < len {
+                return Err(ReplayError::Truncated);
+            }
+            let (taken, remaining) = rest.split_at(len);
+            rest = remaining;
+            Ok(taken)
+        };
+
+        let seed = u64::from_le_bytes(take(8)?.try_into().unwrap());
+        let difficulty = match take(1)?[0] {
+            0 => Difficulty::Easy,
+            1 => Difficulty::Medium,
+            2 => Difficulty::Hard,
+            _ => return Err(ReplayError::NotAReplay),
+        };
+        let cpu_bits = take(1)?[0];
+        let left_score = u32::from_le_bytes(take(4)?.try_into().unwrap());
+        let right_score = u32::from_le_bytes(take(4)?.try_into().unwrap());
+        let trajectory_hash = u64::from_le_bytes(take(8)?.try_into().unwrap());
+        let config_len = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+        let settings_len = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+        let config = std::str::from_utf8(take(config_len)?).map_err(|_| ReplayError::NotAReplay)?;
+        let config = ron::from_str(config).map_err(ReplayError::Config)?;
+        let settings = std::str::from_utf8(take(settings_len)?).map_err(|_| ReplayError::NotAReplay)?;
+        let settings = ron::from_str(settings).map_err(ReplayError::Settings)?;
+        let inputs = rest.to_vec();
+
+        Ok(Replay {
+            seed,
+            config,
+            settings,
+            difficulty,
+            cpu_controlled: [cpu_bits & 1 != 0, cpu_bits & 2 != 0],
+            final_score: [left_score, right_score],
+            trajectory_hash,
+            inputs,
+        })
+    }
+
+    /// The inputs for a physics step, counting from the first one recorded
+    pub fn inputs(&self, tick: usize) -> Option<PaddleInputs> {
+        let inputs = self.inputs.get(tick * 2..tick * 2 + 2)?;
+        Some(PaddleInputs {
+            left: PlayerInput(inputs[0]),
+            right: PlayerInput(inputs[1]),
+            ..default()
+        })
+    }
+
+    /// How many physics steps were recorded
+    pub fn ticks(&self) -> usize {
+        self.inputs.len() / 2
+    }
+}
+
+/// Why a replay couldn't be read
+#[derive(Debug)]
+pub enum ReplayError {
+    /// The bytes don't start like a replay, or hold a value no replay would
+    NotAReplay,
+    /// The bytes end partway through the header, config or match settings
+    Truncated,
+    Config(ron::error::SpannedError),
+    Settings(ron::error::SpannedError),
+}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ReplayError::NotAReplay => write!(f, "not a replay"),
+            ReplayError::Truncated => write!(f, "the replay is cut short"),
+            ReplayError::Config(err) => write!(f, "the replay's config is invalid: {err}"),
+            ReplayError::Settings(err) => write!(f, "the replay's match settings are invalid: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// A running hash of where every ball has been on every physics step of the match,
+/// so two runs of the same replay can be compared without storing the whole path
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Resource, Hash)]
+pub struct TrajectoryHash(pub u64);
+
+/// Plays a replay back in place of the keyboard. Insert one before adding the plugin,
+/// along with the replay's config, and every match feeds its inputs to the paddles.
+/// The match is played under the replay's `MatchSettings`, whatever was inserted before
+#[derive(Resource, Debug)]
+pub struct ReplayPlayer {
+    pub replay: Replay,
+    /// Physics steps run per step of real time, 1 or 2
+    pub speed: u32,
+    pub(crate) tick: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> ReplayPlayer {
+        ReplayPlayer {
+            replay,
+            speed: 1,
+            tick: 0,
+        }
+    }
+
+    /// How many physics steps have been played back
+    pub fn tick(&self) -> usize {
+        self.tick
+    }
+}
+
+/// Records every physics step's input from the first serve of a match until it's over.
+/// Insert one with `enabled` set before adding the plugin to turn recording on
+#[derive(Resource, Debug, Default)]
+pub struct ReplayRecorder {
+    pub enabled: bool,
+    /// Where to write the replay once the match is over, native only
+    pub save_path: Option<String>,
+    pub(crate) recording: bool,
+    replay: Replay,
+}
+
+impl ReplayRecorder {
+    pub fn enabled() -> ReplayRecorder {
+        ReplayRecorder {
+            enabled: true,
+            ..default()
+        }
+    }
+
+    /// The last recording, or the one still in progress
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// The last recording in the replay format, for handing over to the page on the web build
+    pub fn bytes(&self) -> Vec<u8> {
+        self.replay.to_bytes()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_to_file(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.replay.to_bytes())
+    }
+}
+
+// Runs a second physics step after each real one while a replay plays back at double speed
+pub(crate) fn speed_up_replay(
+    In(should_run): In<ShouldRun>,
+    replay_player: Option<Res<ReplayPlayer>>,
+    mut extra_steps: Local<u32>,
+) -> ShouldRun {
+    let speed = replay_player.map_or(1, |replay_player| replay_player.speed);
+    match should_run {
+        ShouldRun::Yes | ShouldRun::YesAndCheckAgain => {
+            *extra_steps += speed.saturating_sub(1);
+            ShouldRun::YesAndCheckAgain
+        }
+        _ if *extra_steps > 0 => {
+            *extra_steps -= 1;
+            ShouldRun::YesAndCheckAgain
+        }
+        _ => should_run,
+    }
+}
+
+// Every two-player match starts a new recording, with the RNG reseeded so the serves can be played back
+pub(crate) fn start_replay_recording(
+    config: Res<PongConfig>,
+    settings: Res<MatchSettings>,
+    difficulty: Res<Difficulty>,
+    replay_player: Option<Res<ReplayPlayer>>,
+    mut rng: ResMut<GameRng>,
+    mut recorder: ResMut<ReplayRecorder>,
+    paddle_query: Query<(&Side, Option<&CpuControlled>), With<Paddle>>,
+) {
+    // a replay only has the left and right inputs, too few for four players or doubles,
+    // and wall practice never ends for the recording to be saved
+    let unrecorded = settings.four_players || settings.doubles || settings.wall_practice;
+    if !recorder.enabled || replay_player.is_some() || unrecorded {
+        return;
+    }
+
+    let mut cpu_controlled = [false; 2];
+    for (side, cpu) in &paddle_query {
+        cpu_controlled[*side as usize] = cpu.is_some();
+    }
+
+    let seed = rng.rng.gen();
+    *rng = GameRng::seeded(seed);
+    recorder.replay = Replay {
+        seed,
+        config: config.clone(),
+        settings: settings.clone(),
+        difficulty: *difficulty,
+        cpu_controlled,
+        ..default()
+    };
+    recorder.recording = true;
+}
+
+pub(crate) fn record_replay_input(paddle_inputs: Res<PaddleInputs>, mut recorder: ResMut<ReplayRecorder>) {
+    if !recorder.recording {
+        return;
+    }
+
+    recorder.replay.inputs.extend([paddle_inputs.left.0, paddle_inputs.right.0]);
+}
+
+pub(crate) fn stop_replay_recording(
+    scoreboard: Res<Scoreboard>,
+    trajectory_hash: Res<TrajectoryHash>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    if !recorder.recording {
+        return;
+    }
+    recorder.recording = false;
+    recorder.replay.final_score = [scoreboard.left_score as u32, scoreboard.right_score as u32];
+    recorder.replay.trajectory_hash = trajectory_hash.0;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = &recorder.save_path {
+        match recorder.save_to_file(path) {
+            Ok(()) => info!("Saved a replay of {} steps to {path}", recorder.replay.ticks()),
+            Err(err) => warn!("Couldn't save the replay to {path}: {err}"),
+        }
+    }
+}
+
+// Every match of a playback starts the replay over, with the same seed, rules, difficulty and players.
+// The obstacles and power-ups that go by the rules aren't spawned until the first physics step
+pub(crate) fn start_replay_playback(
+    mut commands: Commands,
+    mut rng: ResMut<GameRng>,
+    mut settings: ResMut<MatchSettings>,
+    mut difficulty: ResMut<Difficulty>,
+    replay_player: Option<ResMut<ReplayPlayer>>,
+    paddle_query: Query<(Entity, &Side), With<Paddle>>,
+) {
+    let Some(mut replay_player) = replay_player else {
+        return;
+    };
+
+    replay_player.tick = 0;
+    *rng = GameRng::seeded(replay_player.replay.seed);
+    *settings = replay_player.replay.settings.clone();
+    *difficulty = replay_player.replay.difficulty;
+    for (paddle, side) in &paddle_query {
+        let mut paddle = commands.entity(paddle);
+        paddle.remove::<MouseControlled>();
+        if replay_player.replay.cpu_controlled[*side as usize] {
+            paddle.insert(CpuControlled);
+        } else {
+            paddle.remove::<CpuControlled>();
+        }
+    }
+}
+
+// A replay that played back exactly ends on the same step with the same score and ball path
+pub(crate) fn finish_replay_playback(
+    scoreboard: Res<Scoreboard>,
+    trajectory_hash: Res<TrajectoryHash>,
+    replay_player: Option<Res<ReplayPlayer>>,
+) {
+    let Some(replay_player) = replay_player else {
+        return;
+    };
+
+    let replay = &replay_player.replay;
+    let final_score = [scoreboard.left_score as u32, scoreboard.right_score as u32];
+    if replay_player.tick == replay.ticks()
+        && final_score == replay.final_score
+        && trajectory_hash.0 == replay.trajectory_hash
+    {
+        info!("The replay played back exactly, {} steps", replay.ticks());
+    } else {
+        error!(
+            "The replay diverged: ended after {} of {} steps at {:?} rather than {:?}, with trajectory hash {:x} rather than {:x}",
+            replay_player.tick,
+            replay.ticks(),
+            final_score,
+            replay.final_score,
+            trajectory_hash.0,
+            replay.trajectory_hash,
+        );
+    }
+}
+
+// F switches a playback between normal and double speed. Pausing and restarting work as in a match
+pub(crate) fn control_replay_playback(keyboard_input: Res<Input<KeyCode>>, replay_player: Option<ResMut<ReplayPlayer>>) {
+    let Some(mut replay_player) = replay_player else {
+        return;
+    };
+
+    if keyboard_input.just_pressed(KeyCode::F) {
+        replay_player.speed = if replay_player.speed == 1 { 2 } else { 1 };
+    }
+}
+
+// Folds every ball's position into the hash, in an order that doesn't depend on the query's
+pub(crate) fn hash_ball_trajectory(
+    mut trajectory_hash: ResMut<TrajectoryHash>,
+    ball_query: Query<&Transform, With<Ball>>,
+) {
+    let mut step_hash = 0u64;
+    for transform in &ball_query {
+        // FNV-1a over the exact bits of the position
+        let mut ball_hash = 0xcbf2_9ce4_8422_2325u64;
+        for coordinate in [transform.translation.x, transform.translation.y] {
+            for byte in coordinate.to_bits().to_le_bytes() {
+                ball_hash ^= u64::from(byte);
+                ball_hash = ball_hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        step_hash = step_hash.wrapping_add(ball_hash);
+    }
+    trajectory_hash.0 = trajectory_hash.0.rotate_left(5) ^ step_hash;
+}
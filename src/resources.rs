@@ -0,0 +1,956 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+use crate::{components::*, TIME_STEP};
+
+// How long the ball waits before each serve, counting down from 3
+const SERVE_DELAY: f32 = 2.1;
+// What each player starts a four-player match with
+const STARTING_LIVES: usize = 5;
+// The scan codes for where the default letter and punctuation movement keys are on a QWERTY keyboard. Windows
+// and Linux number the keys alike, macOS has its own numbers, and a browser already names the keys by where
+// they are, so there the `KeyCode`s are enough. The arrows are where they are on every layout
+#[cfg(not(any(target_os = "macos", target_arch = "wasm32")))]
+const DEFAULT_SCAN_CODES: &[(Action, u32)] = &[
+    (Action::LeftUp, 17),
+    (Action::LeftDown, 31),
+    (Action::TopLeft, 47),
+    (Action::TopRight, 48),
+    (Action::BottomLeft, 51),
+    (Action::BottomRight, 52),
+    (Action::LeftLowerUp, 20),
+    (Action::LeftLowerDown, 34),
+    (Action::RightLowerUp, 23),
+    (Action::RightLowerDown, 37),
+];
+#[cfg(target_os = "macos")]
+const DEFAULT_SCAN_CODES: &[(Action, u32)] = &[
+    (Action::LeftUp, 13),
+    (Action::LeftDown, 1),
+    (Action::TopLeft, 9),
+    (Action::TopRight, 11),
+    (Action::BottomLeft, 43),
+    (Action::BottomRight, 47),
+    (Action::LeftLowerUp, 17),
+    (Action::LeftLowerDown, 5),
+    (Action::RightLowerUp, 34),
+    (Action::RightLowerDown, 40),
+];
+#[cfg(target_arch = "wasm32")]
+const DEFAULT_SCAN_CODES: &[(Action, u32)] = &[];
+
+/// Something the players can do with a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    LeftUp,
+    LeftDown,
+    RightUp,
+    RightDown,
+    LeftDash,
+    RightDash,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    LeftLowerUp,
+    LeftLowerDown,
+    RightLowerUp,
+    RightLowerDown,
+    Serve,
+    Pause,
+    Restart,
+}
+
+impl Action {
+    pub const ALL: [Action; 17] = [
+        Action::LeftUp,
+        Action::LeftDown,
+        Action::RightUp,
+        Action::RightDown,
+        Action::LeftDash,
+        Action::RightDash,
+        Action::TopLeft,
+        Action::TopRight,
+        Action::BottomLeft,
+        Action::BottomRight,
+        Action::LeftLowerUp,
+        Action::LeftLowerDown,
+        Action::RightLowerUp,
+        Action::RightLowerDown,
+        Action::Serve,
+        Action::Pause,
+        Action::Restart,
+    ];
+
+    /// The actions that move the paddle at `seat` up and down, or right and left for the top and bottom paddles
+    pub fn paddle(seat: Seat) -> (Action, Action) {
+        match seat {
+            Seat::Left => (Action::LeftUp, Action::LeftDown),
+            Seat::Right => (Action::RightUp, Action::RightDown),
+            Seat::Top => (Action::TopRight, Action::TopLeft),
+            Seat::Bottom => (Action::BottomRight, Action::BottomLeft),
+        }
+    }
+
+    /// The actions that move the lower paddle of a doubles side up and down
+    pub fn lower_paddle(side: Side) -> (Action, Action) {
+        match side {
+            Side::Left => (Action::LeftLowerUp, Action::LeftLowerDown),
+            Side::Right => (Action::RightLowerUp, Action::RightLowerDown),
+        }
+    }
+}
+
+/// How the paddles' movement keys are recognised. A `KeyCode` follows the keyboard's layout, so W is wherever
+/// the layout puts it, while a `ScanCode` is a place on the keyboard, wherever W would be on a QWERTY one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MovementKeys {
+    /// By their `KeyCode`s
+    Logical,
+    /// By their scan codes, or their `KeyCode`s for the actions without one
+    Physical,
+    /// By either, so the default keys play the same on AZERTY and QWERTZ keyboards as on QWERTY ones
+    #[default]
+    Both,
+}
+
+impl MovementKeys {
+    pub fn next(self) -> MovementKeys {
+        match self {
+            MovementKeys::Logical => MovementKeys::Physical,
+            MovementKeys::Physical => MovementKeys::Both,
+            MovementKeys::Both => MovementKeys::Logical,
+        }
+    }
+}
+
+/// Which key triggers each [`Action`]
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub left_up: KeyCode,
+    pub left_down: KeyCode,
+    pub right_up: KeyCode,
+    pub right_down: KeyCode,
+    /// Dashes the left and right paddles, see `MatchSettings::paddle_dash`
+    pub left_dash: KeyCode,
+    pub right_dash: KeyCode,
+    /// The top and bottom paddles, which only play in four-player matches
+    pub top_left: KeyCode,
+    pub top_right: KeyCode,
+    pub bottom_left: KeyCode,
+    pub bottom_right: KeyCode,
+    /// The second paddle on each side, which only plays in doubles matches
+    pub left_lower_up: KeyCode,
+    pub left_lower_down: KeyCode,
+    pub right_lower_up: KeyCode,
+    pub right_lower_down: KeyCode,
+    pub serve: KeyCode,
+    pub pause: KeyCode,
+    pub restart: KeyCode,
+    /// Where the movement keys are on the keyboard, whatever its layout. Only the paddles' movement has them
+    pub scan_codes: Vec<(Action, ScanCode)>,
+    pub movement_keys: MovementKeys,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            left_up: KeyCode::W,
+            left_down: KeyCode::S,
+            right_up: KeyCode::Up,
+            right_down: KeyCode::Down,
+            left_dash: KeyCode::LShift,
+            right_dash: KeyCode::RControl,
+            top_left: KeyCode::V,
+            top_right: KeyCode::B,
+            bottom_left: KeyCode::Comma,
+            bottom_right: KeyCode::Period,
+            left_lower_up: KeyCode::T,
+            left_lower_down: KeyCode::G,
+            right_lower_up: KeyCode::I,
+            right_lower_down: KeyCode::K,
+            serve: KeyCode::Space,
+            pause: KeyCode::P,
+            restart: KeyCode::R,
+            scan_codes: DEFAULT_SCAN_CODES
+                .iter()
+                .map(|(action, scan_code)| (*action, ScanCode(*scan_code)))
+                .collect(),
+            movement_keys: MovementKeys::default(),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: Action) -> KeyCode {
+        match action {
+            Action::LeftUp => self.left_up,
+            Action::LeftDown => self.left_down,
+            Action::RightUp => self.right_up,
+            Action::RightDown => self.right_down,
+            Action::LeftDash => self.left_dash,
+            Action::RightDash => self.right_dash,
+            Action::TopLeft => self.top_left,
+            Action::TopRight => self.top_right,
+            Action::BottomLeft => self.bottom_left,
+            Action::BottomRight => self.bottom_right,
+            Action::LeftLowerUp => self.left_lower_up,
+            Action::LeftLowerDown => self.left_lower_down,
+            Action::RightLowerUp => self.right_lower_up,
+            Action::RightLowerDown => self.right_lower_down,
+            Action::Serve => self.serve,
+            Action::Pause => self.pause,
+            Action::Restart => self.restart,
+        }
+    }
+
+    pub fn key_mut(&mut self, action: Action) -> &mut KeyCode {
+        match action {
+            Action::LeftUp => &mut self.left_up,
+            Action::LeftDown => &mut self.left_down,
+            Action::RightUp => &mut self.right_up,
+            Action::RightDown => &mut self.right_down,
+            Action::LeftDash => &mut self.left_dash,
+            Action::RightDash => &mut self.right_dash,
+            Action::TopLeft => &mut self.top_left,
+            Action::TopRight => &mut self.top_right,
+            Action::BottomLeft => &mut self.bottom_left,
+            Action::BottomRight => &mut self.bottom_right,
+            Action::LeftLowerUp => &mut self.left_lower_up,
+            Action::LeftLowerDown => &mut self.left_lower_down,
+            Action::RightLowerUp => &mut self.right_lower_up,
+            Action::RightLowerDown => &mut self.right_lower_down,
+            Action::Serve => &mut self.serve,
+            Action::Pause => &mut self.pause,
+            Action::Restart => &mut self.restart,
+        }
+    }
+
+    /// Where `action`'s key is on the keyboard, if it's one of the movement actions that has a scan code
+    pub fn scan_code(&self, action: Action) -> Option<ScanCode> {
+        self.scan_codes
+            .iter()
+            .find(|(scan_action, _)| *scan_action == action)
+            .map(|(_, scan_code)| *scan_code)
+    }
+
+    /// Whether `action`'s key is held, recognised as `movement_keys` says for the actions with scan codes
+    pub fn pressed(&self, action: Action, keyboard_input: &Input<KeyCode>, scan_input: &Input<ScanCode>) -> bool {
+        let by_name = keyboard_input.pressed(self.key(action));
+        let Some(scan_code) = self.scan_code(action) else {
+            return by_name;
+        };
+        match self.movement_keys {
+            MovementKeys::Logical => by_name,
+            MovementKeys::Physical => scan_input.pressed(scan_code),
+            MovementKeys::Both => by_name || scan_input.pressed(scan_code),
+        }
+    }
+
+    /// Binds `key` to `action`. If another action was on `key` it's given `action`'s old key in exchange,
+    /// so no two actions ever share a key, and that action is returned. Either one's scan code goes, as
+    /// its key is the one named now
+    pub fn rebind(&mut self, action: Action, key: KeyCode) -> Option<Action> {
+        let old_key = std::mem::replace(self.key_mut(action), key);
+        if old_key == key {
+            return None;
+        }
+        self.scan_codes.retain(|(scan_action, _)| *scan_action != action);
+        let swapped = Action::ALL
+            .into_iter()
+            .find(|other| *other != action && self.key(*other) == key)?;
+        *self.key_mut(swapped) = old_key;
+        self.scan_codes.retain(|(scan_action, _)| *scan_action != swapped);
+        Some(swapped)
+    }
+
+    /// The keys that move the paddle at `seat` up and down, or right and left for the top and bottom paddles
+    pub fn paddle_keys(&self, seat: Seat) -> (KeyCode, KeyCode) {
+        let (up, down) = Action::paddle(seat);
+        (self.key(up), self.key(down))
+    }
+
+    /// The keys that move the lower paddle of a doubles side up and down
+    pub fn lower_paddle_keys(&self, side: Side) -> (KeyCode, KeyCode) {
+        let (up, down) = Action::lower_paddle(side);
+        (self.key(up), self.key(down))
+    }
+}
+
+/// The points each side has in the current game
+#[derive(Resource, Debug, Reflect, Default, Hash)]
+#[reflect(Resource, Hash)]
+pub struct Scoreboard {
+    pub left_score: usize,
+    pub right_score: usize,
+}
+
+impl Scoreboard {
+    pub fn score(&self, side: Side) -> usize {
+        match side {
+            Side::Left => self.left_score,
+            Side::Right => self.right_score,
+        }
+    }
+
+    pub(crate) fn score_mut(&mut self, side: Side) -> &mut usize {
+        match side {
+            Side::Left => &mut self.left_score,
+            Side::Right => &mut self.right_score,
+        }
+    }
+}
+
+/// The names shown above the scores and in the result, changed in the menu's settings
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerNames {
+    pub left: String,
+    pub right: String,
+}
+
+impl Default for PlayerNames {
+    fn default() -> Self {
+        PlayerNames {
+            left: "Player 1".to_string(),
+            right: "Player 2".to_string(),
+        }
+    }
+}
+
+impl PlayerNames {
+    pub fn name(&self, side: Side) -> &str {
+        match side {
+            Side::Left => &self.left,
+            Side::Right => &self.right,
+        }
+    }
+
+    pub(crate) fn name_mut(&mut self, side: Side) -> &mut String {
+        match side {
+            Side::Left => &mut self.left,
+            Side::Right => &mut self.right,
+        }
+    }
+}
+
+// Rules for a match, insert your own before building the app to change them
+#[derive(Resource, Debug, Clone, Reflect, Default, Serialize, Deserialize)]
+#[reflect(Resource)]
+pub struct MatchSettings {
+    /// Who the ball is served towards after a goal
+    pub serve_rule: ServeRule,
+    /// How the match is won
+    pub mode: MatchMode,
+    /// When a game is level one point short of winning it, it goes to sudden death.
+    /// Timed matches always go to sudden death if they're level when the clock runs out
+    pub golden_goal: bool,
+    /// Games have to be won by two clear points, like table tennis
+    pub win_by_two: bool,
+    /// Nobody wins, the match goes on until it's restarted. Chosen from the menu's Practice entry
+    pub practice: bool,
+    /// Power-ups turn up in the middle of the arena, to change the paddles or the ball for a while
+    pub power_ups: bool,
+    /// Top and bottom paddles join the left and right ones, and each player defends their own wall
+    /// with `Lives` rather than scoring points
+    pub four_players: bool,
+    /// Each side has two paddles, one kept to the upper half of the arena and one to the lower,
+    /// for two players a side. Chosen from the menu's Doubles entry
+    pub doubles: bool,
+    /// The right paddle is taken out and its wall is solid, for one player to practice against on their own.
+    /// Nobody scores, a ball getting past the left paddle ends their `Streak` instead.
+    /// Chosen from the menu's Wall Practice entry
+    pub wall_practice: bool,
+    /// Blocks stand around the center line for the ball to bounce off, see `PongConfig::obstacles`
+    pub obstacles: bool,
+    /// One more obstacle patrols up and down the center line, see `PongConfig::moving_obstacle_speed`
+    pub moving_obstacle: bool,
+    /// Pulls the balls down, in `Transform` units per second squared, so they fly in arcs.
+    /// Serves are lobbed upwards to make up for it
+    pub gravity: Option<f32>,
+    /// The computer plays easier while it's well ahead of the player, and harder while it's well behind,
+    /// by up to one notch of `Difficulty`. See `PongConfig::rubber_band_gap`
+    pub rubber_band: bool,
+    /// The paddles speed up and slow down rather than starting and stopping dead,
+    /// see `PongConfig::paddle_acceleration`
+    pub paddle_inertia: bool,
+    /// Tapping the dash key while moving sends a paddle a short way at once, then it has to wait to
+    /// dash again. See `PongConfig::dash_distance`
+    pub paddle_dash: bool,
+    /// The computer dashes too, on Hard, when `paddle_dash` is on
+    pub cpu_dash: bool,
+}
+
+/// The gravity the menu turns on, see `MatchSettings::gravity`
+pub const DEFAULT_GRAVITY: f32 = 600.0;
+
+/// The ways a match can be won
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+pub enum MatchMode {
+    /// A race to a number of points, over one or more games
+    Games(MatchFormat),
+    /// Against the clock, whoever is ahead when it runs out wins
+    Timed(TimedFormat),
+}
+
+impl Default for MatchMode {
+    fn default() -> Self {
+        MatchMode::Games(MatchFormat::default())
+    }
+}
+
+/// How long a match is. The first side to `points_per_game` wins a game,
+/// and the first to win `games_to_win` games wins the match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct MatchFormat {
+    pub games_to_win: usize,
+    pub points_per_game: usize,
+}
+
+impl Default for MatchFormat {
+    fn default() -> Self {
+        MatchFormat {
+            games_to_win: 1,
+            points_per_game: 11,
+        }
+    }
+}
+
+impl MatchFormat {
+    /// Who has won a game with this score, if anyone has yet
+    pub fn game_winner(&self, left_score: usize, right_score: usize, win_by_two: bool) -> Option<Side> {
+        let (leader, leading_score, trailing_score) = if left_score >= right_score {
+            (Side::Left, left_score, right_score)
+        } else {
+            (Side::Right, right_score, left_score)
+        };
+        let margin = if win_by_two { 2 } else { 1 };
+        (leading_score >= self.points_per_game && leading_score - trailing_score >= margin).then_some(leader)
+    }
+
+    /// Whether a game with this score is in deuce, only possible when it has to be won by two
+    pub fn deuce(&self, left_score: usize, right_score: usize, win_by_two: bool) -> Option<Deuce> {
+        let one_short = self.points_per_game.saturating_sub(1);
+        if !win_by_two || left_score < one_short || right_score < one_short {
+            return None;
+        }
+        match left_score.cmp(&right_score) {
+            Ordering::Equal => Some(Deuce::Level),
+            Ordering::Greater if left_score - right_score == 1 => Some(Deuce::Advantage(Side::Left)),
+            Ordering::Less if right_score - left_score == 1 => Some(Deuce::Advantage(Side::Right)),
+            _ => None,
+        }
+    }
+
+    /// Whether the next point would win `side` the match, having already won `games_won` games
+    pub fn match_point(
+        &self,
+        side: Side,
+        left_score: usize,
+        right_score: usize,
+        games_won: usize,
+        win_by_two: bool,
+    ) -> bool {
+        if games_won + 1 < self.games_to_win {
+            return false;
+        }
+        let (left_score, right_score) = match side {
+            Side::Left => (left_score + 1, right_score),
+            Side::Right => (left_score, right_score + 1),
+        };
+        self.game_winner(left_score, right_score, win_by_two) == Some(side)
+    }
+
+    /// A match of several games, won by whoever takes the majority of `games`
+    pub fn best_of(games: usize, points_per_game: usize) -> MatchFormat {
+        MatchFormat {
+            games_to_win: games / 2 + 1,
+            points_per_game,
+        }
+    }
+}
+
+/// A game that has to be won by two, once both sides have come within a point of winning it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deuce {
+    /// Level, the next point gives someone the advantage
+    Level,
+    /// One point ahead, the next point wins it or goes back to level
+    Advantage(Side),
+}
+
+/// How long a timed match lasts
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct TimedFormat {
+    /// In seconds
+    pub duration: f32,
+    /// Whether the clock keeps going during the countdown before each serve
+    pub clock_runs_during_serve: bool,
+}
+
+impl Default for TimedFormat {
+    fn default() -> Self {
+        TimedFormat {
+            duration: 180.0,
+            clock_runs_during_serve: false,
+        }
+    }
+}
+
+/// The time left in a timed match. It only runs during play, so pausing stops the clock
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct MatchTimer(pub Timer);
+
+/// The number of games each side has won so far this match, and who won it once it's over
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub struct MatchScore {
+    pub left_games: usize,
+    pub right_games: usize,
+    pub winner: Option<Side>,
+    /// The next goal wins the game (or in timed matches, the match)
+    pub sudden_death: bool,
+}
+
+impl MatchScore {
+    pub fn games(&self, side: Side) -> usize {
+        match side {
+            Side::Left => self.left_games,
+            Side::Right => self.right_games,
+        }
+    }
+
+    pub fn games_mut(&mut self, side: Side) -> &mut usize {
+        match side {
+            Side::Left => &mut self.left_games,
+            Side::Right => &mut self.right_games,
+        }
+    }
+}
+
+/// The lives each player has left in a four-player match, where a ball getting past a paddle costs its
+/// player one. A player with none left is out and the wall behind them closes up, until only one is left
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lives {
+    pub left: usize,
+    pub right: usize,
+    pub top: usize,
+    pub bottom: usize,
+    /// The last player left in, or the one with the most lives when a timed match runs out
+    pub winner: Option<Seat>,
+}
+
+impl Default for Lives {
+    fn default() -> Self {
+        Lives {
+            left: STARTING_LIVES,
+            right: STARTING_LIVES,
+            top: STARTING_LIVES,
+            bottom: STARTING_LIVES,
+            winner: None,
+        }
+    }
+}
+
+impl Lives {
+    pub fn lives(&self, seat: Seat) -> usize {
+        match seat {
+            Seat::Left => self.left,
+            Seat::Right => self.right,
+            Seat::Top => self.top,
+            Seat::Bottom => self.bottom,
+        }
+    }
+
+    pub(crate) fn lives_mut(&mut self, seat: Seat) -> &mut usize {
+        match seat {
+            Seat::Left => &mut self.left,
+            Seat::Right => &mut self.right,
+            Seat::Top => &mut self.top,
+            Seat::Bottom => &mut self.bottom,
+        }
+    }
+
+    /// The players with lives left
+    pub fn in_play(&self) -> impl Iterator<Item = Seat> + '_ {
+        Seat::ALL.into_iter().filter(|seat| self.lives(*seat) > 0)
+    }
+}
+
+/// The wall practice player's paddle hits in a row, since the ball last got past them,
+/// and the most in a row there have been this match
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Streak {
+    pub hits: usize,
+    pub best: usize,
+}
+
+/// Who the ball is served towards after a goal. The first serve of a match always goes to a random side
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect, FromReflect, Serialize, Deserialize)]
+pub enum ServeRule {
+    /// At the side that just conceded, like the arcade game
+    #[default]
+    TowardLoser,
+    /// At the side that just scored
+    TowardWinner,
+    Random,
+}
+
+impl ServeRule {
+    /// Which side to serve towards after `scorer` scored, `None` for either
+    pub fn serve_toward(self, scorer: Side) -> Option<Side> {
+        match self {
+            ServeRule::TowardLoser => Some(scorer.opponent()),
+            ServeRule::TowardWinner => Some(scorer),
+            ServeRule::Random => None,
+        }
+    }
+}
+
+/// Skips the menu and starts a match as soon as the game has loaded
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct SkipMenu {
+    /// Whether the computer plays the right paddle, otherwise it's two players
+    pub cpu_opponent: bool,
+}
+
+/// Where every random choice in the game comes from
+#[derive(Resource, Clone, Reflect)]
+#[reflect_value(Resource)]
+pub struct GameRng {
+    pub(crate) rng: StdRng,
+    seed: u64,
+    // whether the seed was picked rather than drawn from entropy, a picked one isn't replaced by the config's
+    pub(crate) chosen: bool,
+}
+
+impl GameRng {
+    /// The same seed always gives the same sequence of serves
+    pub fn seeded(seed: u64) -> GameRng {
+        GameRng {
+            rng: StdRng::seed_from_u64(seed),
+            seed,
+            chosen: true,
+        }
+    }
+
+    /// What the RNG was last seeded with, to get the same serves again
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        let seed = StdRng::from_entropy().gen();
+        GameRng {
+            rng: StdRng::seed_from_u64(seed),
+            seed,
+            chosen: false,
+        }
+    }
+}
+
+/// What one player is pressing on one physics step, packed into a byte so replays stay small
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct PlayerInput(pub u8);
+
+impl PlayerInput {
+    const UP: u8 = 1 << 0;
+    const DOWN: u8 = 1 << 1;
+    const SERVE: u8 = 1 << 2;
+    const DASH: u8 = 1 << 3;
+
+    pub fn new(up: bool, down: bool, serve: bool) -> PlayerInput {
+        let mut bits = 0;
+        if up {
+            bits |= PlayerInput::UP;
+        }
+        if down {
+            bits |= PlayerInput::DOWN;
+        }
+        if serve {
+            bits |= PlayerInput::SERVE;
+        }
+        PlayerInput(bits)
+    }
+
+    /// 1 for up, -1 for down, and 0 for neither or both. The top and bottom paddles take up as right
+    pub fn direction(self) -> f32 {
+        let mut direction = 0.0;
+        if self.0 & PlayerInput::UP != 0 {
+            direction += 1.0;
+        }
+        if self.0 & PlayerInput::DOWN != 0 {
+            direction -= 1.0;
+        }
+        direction
+    }
+
+    pub fn serve(self) -> bool {
+        self.0 & PlayerInput::SERVE != 0
+    }
+
+    /// The same input with the dash key held, see `MatchSettings::paddle_dash`
+    pub fn dashing(self) -> PlayerInput {
+        PlayerInput(self.0 | PlayerInput::DASH)
+    }
+
+    pub fn dash(self) -> bool {
+        self.0 & PlayerInput::DASH != 0
+    }
+}
+
+/// The input the keyboard paddles follow on the current physics step.
+/// It's read from the keyboard at the start of every step, and the left and right are what replays record
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct PaddleInputs {
+    pub left: PlayerInput,
+    pub right: PlayerInput,
+    pub top: PlayerInput,
+    pub bottom: PlayerInput,
+    /// The lower paddles of a doubles match
+    pub left_lower: PlayerInput,
+    pub right_lower: PlayerInput,
+}
+
+impl PaddleInputs {
+    pub fn input(&self, seat: impl Into<Seat>) -> PlayerInput {
+        match seat.into() {
+            Seat::Left => self.left,
+            Seat::Right => self.right,
+            Seat::Top => self.top,
+            Seat::Bottom => self.bottom,
+        }
+    }
+
+    pub(crate) fn input_mut(&mut self, seat: Seat) -> &mut PlayerInput {
+        match seat {
+            Seat::Left => &mut self.left,
+            Seat::Right => &mut self.right,
+            Seat::Top => &mut self.top,
+            Seat::Bottom => &mut self.bottom,
+        }
+    }
+
+    /// The input for a paddle at `seat`, or for the lower paddle of its side in a doubles match
+    pub fn paddle_input(&self, seat: Seat, half: Option<Half>) -> PlayerInput {
+        match (seat.side(), half) {
+            (Some(Side::Left), Some(Half::Lower)) => self.left_lower,
+            (Some(Side::Right), Some(Half::Lower)) => self.right_lower,
+            _ => self.input(seat),
+        }
+    }
+
+    pub(crate) fn lower_input_mut(&mut self, side: Side) -> &mut PlayerInput {
+        match side {
+            Side::Left => &mut self.left_lower,
+            Side::Right => &mut self.right_lower,
+        }
+    }
+}
+
+/// How the ball speeds up over the course of a rally
+#[derive(Resource)]
+pub struct RallySettings {
+    /// The ball's speed is multiplied by this on every paddle hit
+    pub speed_up_factor: f32,
+    /// After this many paddle hits in a rally the ball stops speeding up
+    pub max_speed_ups: usize,
+}
+
+impl Default for RallySettings {
+    fn default() -> Self {
+        RallySettings {
+            speed_up_factor: 1.05,
+            max_speed_ups: 15,
+        }
+    }
+}
+
+/// The number of paddle hits since the last serve
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct Rally {
+    pub hits: usize,
+}
+
+/// The side whose paddle the ball last bounced off, gone until the first hit after each serve
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LastTouched(pub Side);
+
+/// The top level flow of the game. Physics and paddle input only run while `Playing`,
+/// or during the attract mode's demo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameState {
+    /// Waiting for the config file, nothing has been spawned yet
+    Loading,
+    Menu,
+    Playing,
+    Paused,
+    GameOver,
+    /// The computer playing itself behind the menu, pushed on top of `Menu` after it sits idle
+    Demo,
+}
+
+/// How well the computer plays, can be changed from the menu between matches
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    /// The fraction of its `PaddleSpeed` the computer's paddle can move at
+    pub fn max_speed_fraction(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.5,
+            Difficulty::Medium => 0.75,
+            Difficulty::Hard => 0.95,
+        }
+    }
+
+    /// How many seconds ahead of the ball the computer aims, following its current velocity
+    pub fn lookahead(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.0,
+            Difficulty::Medium => 0.1,
+            Difficulty::Hard => 0.25,
+        }
+    }
+
+    /// How far the ball can be from the paddle's center before the computer reacts
+    pub fn dead_zone(self) -> f32 {
+        match self {
+            Difficulty::Easy => 45.0,
+            Difficulty::Medium => 20.0,
+            Difficulty::Hard => 5.0,
+        }
+    }
+
+    /// How far behind the ball the computer plays, and how far off it aims
+    pub fn handicap(self) -> CpuHandicap {
+        match self {
+            Difficulty::Easy => CpuHandicap {
+                reaction_delay: 0.25,
+                aim_error: 40.0,
+            },
+            Difficulty::Medium => CpuHandicap {
+                reaction_delay: 0.15,
+                aim_error: 20.0,
+            },
+            Difficulty::Hard => CpuHandicap {
+                reaction_delay: 0.05,
+                aim_error: 8.0,
+            },
+        }
+    }
+
+    /// Whether the computer works out where a ball coming its way will reach its paddle,
+    /// bounces off the walls and all, rather than following it with the `lookahead`. Only Hard does
+    pub fn predicts_bounces(self) -> bool {
+        self == Difficulty::Hard
+    }
+
+    /// Whether the computer dashes to reach a ball it would otherwise be late for,
+    /// when `MatchSettings::cpu_dash` lets it. Only Hard does
+    pub fn dashes(self) -> bool {
+        self == Difficulty::Hard
+    }
+
+    pub(crate) fn next(self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Medium,
+            Difficulty::Medium => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    /// How many notches up from Easy it is
+    pub fn level(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.0,
+            Difficulty::Medium => 1.0,
+            Difficulty::Hard => 2.0,
+        }
+    }
+
+    /// How the computer plays at `level` notches up from Easy, blending the difficulties either side
+    /// of it when it's in between. It predicts bounces when it's closer to Hard than to Medium
+    pub fn skill_at(level: f32) -> CpuSkill {
+        let difficulties = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+        let level = level.clamp(0.0, Difficulty::Hard.level());
+        let below = difficulties[level.floor() as usize];
+        let above = difficulties[level.ceil() as usize];
+        let blend = level.fract();
+        let mix = |setting: fn(Difficulty) -> f32| setting(below) + (setting(above) - setting(below)) * blend;
+        CpuSkill {
+            max_speed_fraction: mix(Difficulty::max_speed_fraction),
+            lookahead: mix(Difficulty::lookahead),
+            dead_zone: mix(Difficulty::dead_zone),
+            predicts_bounces: difficulties[level.round() as usize].predicts_bounces(),
+            dashes: difficulties[level.round() as usize].dashes(),
+            handicap: CpuHandicap {
+                reaction_delay: mix(|difficulty| difficulty.handicap().reaction_delay),
+                aim_error: mix(|difficulty| difficulty.handicap().aim_error),
+            },
+        }
+    }
+}
+
+/// Everything about how the computer plays, from its `Difficulty`,
+/// or from between two of them when rubber banding has moved it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CpuSkill {
+    pub max_speed_fraction: f32,
+    pub lookahead: f32,
+    pub dead_zone: f32,
+    pub predicts_bounces: bool,
+    pub dashes: bool,
+    pub handicap: CpuHandicap,
+}
+
+/// How many notches of `Difficulty` the computer is playing above the one picked in the menu,
+/// or below it when negative. It follows the score in rubber band mode, and is 0 otherwise
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Default)]
+pub struct DifficultyShift(pub f32);
+
+/// What keeps the computer from playing perfectly, on top of its `Difficulty`'s speed and dead zone.
+/// Each difficulty has its own, insert one to play against another
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Default)]
+pub struct CpuHandicap {
+    /// The computer goes by where the balls were this many seconds ago
+    pub reaction_delay: f32,
+    /// How far off the computer aims, as the standard deviation in `Transform` units of a random error
+    /// that's rolled again for every shot coming its way
+    pub aim_error: f32,
+}
+
+/// Counts down to the next serve. It only runs during play, so pausing holds it
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct ServeTimer(pub Timer);
+
+impl Default for ServeTimer {
+    fn default() -> Self {
+        ServeTimer(Timer::from_seconds(SERVE_DELAY, TimerMode::Once))
+    }
+}
+
+/// How long each physics step is, in seconds. Everything that moves or counts down during play goes
+/// by it, so the speeds stay in units per second whatever it's set to. Insert one before adding
+/// `PongPlugin` to step the physics more or less often than `TIME_STEP`
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct PhysicsStep(pub f32);
+
+impl Default for PhysicsStep {
+    fn default() -> Self {
+        PhysicsStep(TIME_STEP)
+    }
+}
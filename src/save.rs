@@ -0,0 +1,180 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{io::ErrorKind, path::PathBuf};
+
+use crate::{audio::*, network::LocalNames, resources::*, stats::*, theme::*};
+
+// What the records and settings are saved under, the file names natively and the local storage keys on the web
+const STATS_KEY: &str = "stats";
+const SETTINGS_KEY: &str = "settings";
+
+/// Somewhere to keep the records and settings from one run of the game to the next
+pub trait StorageBackend: Send + Sync + 'static {
+    /// What was last saved under `key`, `None` if nothing ever was
+    fn load(&self, key: &str) -> Result<Option<String>, String>;
+    fn save(&mut self, key: &str, value: &str) -> Result<(), String>;
+}
+
+/// Keeps everything in memory, so it's gone when the game closes. Used when there's nowhere else to keep it
+#[derive(Default)]
+pub struct MemoryStorage(HashMap<String, String>);
+
+impl StorageBackend for MemoryStorage {
+    fn load(&self, key: &str) -> Result<Option<String>, String> {
+        Ok(self.0.get(key).cloned())
+    }
+
+    fn save(&mut self, key: &str, value: &str) -> Result<(), String> {
+        self.0.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+}
+
+/// A RON file for each key, in a folder that's created on the first save
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileStorage {
+    pub dir: PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileStorage {
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.ron"))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl StorageBackend for FileStorage {
+    fn load(&self, key: &str) -> Result<Option<String>, String> {
+        match std::fs::read_to_string(self.path(key)) {
+            Ok(saved) => Ok(Some(saved)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    fn save(&mut self, key: &str, value: &str) -> Result<(), String> {
+        std::fs::create_dir_all(&self.dir).map_err(|err| err.to_string())?;
+        std::fs::write(self.path(key), value).map_err(|err| err.to_string())
+    }
+}
+
+/// Where the game keeps its records and settings. Insert one before adding `PongRenderPlugin`
+/// to keep them somewhere other than `SaveStorage::for_platform`
+#[derive(Resource)]
+pub struct SaveStorage(pub Box<dyn StorageBackend>);
+
+impl SaveStorage {
+    /// A file in the platform's data folder natively, and the browser's local storage on the web.
+    /// Either falls back to memory when it isn't there to be used
+    pub fn for_platform() -> SaveStorage {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(dir) = dirs::data_dir() {
+            return SaveStorage(Box::new(FileStorage {
+                dir: dir.join("bevy_pong"),
+            }));
+        }
+        #[cfg(target_arch = "wasm32")]
+        if let Some(local_storage) = crate::web::LocalStorage::open() {
+            return SaveStorage(Box::new(local_storage));
+        }
+
+        warn!("There's nowhere to save to, the records and settings will only last until the game is closed");
+        SaveStorage(Box::new(MemoryStorage::default()))
+    }
+
+    /// What's saved under `key`, if anything. Anything that can't be read is left for the defaults, with a warning
+    pub fn load<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let saved = match self.0.load(key) {
+            Ok(saved) => saved?,
+            Err(err) => {
+                warn!("Couldn't load the saved {key}, using the defaults: {err}");
+                return None;
+            }
+        };
+        ron::from_str(&saved)
+            .map_err(|err| warn!("The saved {key} are unreadable, using the defaults: {err}"))
+            .ok()
+    }
+
+    pub fn save<T: Serialize>(&mut self, key: &str, value: &T) {
+        let saved = ron::to_string(value).map_err(|err| err.to_string());
+        if let Err(err) = saved.and_then(|saved| self.0.save(key, &saved)) {
+            warn!("Couldn't save the {key}: {err}");
+        }
+    }
+}
+
+/// The settings picked in the menu, and the sound options, saved between runs
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub display: DisplaySettings,
+    pub audio: AudioSettings,
+    pub difficulty: Difficulty,
+    pub names: PlayerNames,
+    /// `None` in settings saved before the keys could be changed in the menu, which keeps the `PongPlugin`'s
+    pub bindings: Option<KeyBindings>,
+}
+
+// Puts back the records and settings from last time, over whatever the plugins started with
+pub(crate) fn load_saved(world: &mut World) {
+    let storage = world.get_resource_or_insert_with(SaveStorage::for_platform);
+    let stats = storage.load::<PersistentStats>(STATS_KEY);
+    let settings = storage.load::<Settings>(SETTINGS_KEY);
+
+    if let Some(stats) = stats {
+        world.insert_resource(stats);
+    }
+    if let Some(settings) = settings {
+        world.insert_resource(settings.display);
+        world.insert_resource(settings.audio);
+        world.insert_resource(settings.difficulty);
+        world.insert_resource(settings.names);
+        if let Some(bindings) = settings.bindings {
+            world.insert_resource(bindings);
+        }
+    }
+}
+
+pub(crate) fn save_stats(mut storage: ResMut<SaveStorage>, stats: Res<PersistentStats>) {
+    if stats.is_changed() && !stats.is_added() {
+        storage.save(STATS_KEY, &*stats);
+    }
+}
+
+// Saves the settings whenever one of them changes. They're compared with the last ones seen, as
+// the menu can touch them without changing anything. A spectator's own names are saved, not the players'
+pub(crate) fn save_settings(
+    mut storage: ResMut<SaveStorage>,
+    display: Res<DisplaySettings>,
+    audio: Res<AudioSettings>,
+    difficulty: Res<Difficulty>,
+    names: Res<PlayerNames>,
+    bindings: Res<KeyBindings>,
+    local_names: Option<Res<LocalNames>>,
+    mut last_seen: Local<Option<Settings>>,
+) {
+    let changed = display.is_changed()
+        || audio.is_changed()
+        || difficulty.is_changed()
+        || names.is_changed()
+        || bindings.is_changed();
+    if !changed && last_seen.is_some() {
+        return;
+    }
+
+    let settings = Settings {
+        display: *display,
+        audio: audio.clone(),
+        difficulty: *difficulty,
+        names: local_names.map_or_else(|| names.clone(), |local_names| local_names.0.clone()),
+        bindings: Some(bindings.clone()),
+    };
+    // the first look is at what was just loaded, or the defaults, which don't need saving
+    if last_seen.as_ref().is_some_and(|last_seen| *last_seen != settings) {
+        storage.save(SETTINGS_KEY, &settings);
+    }
+    *last_seen = Some(settings);
+}
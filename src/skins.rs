@@ -0,0 +1,114 @@
+use bevy::{asset::LoadState, prelude::*, sprite::Mesh2dHandle};
+
+use crate::{components::*, config::*, ui::BallVisual};
+
+/// The skin that draws the paddles and ball in plain colors, which needs no images
+pub const FLAT_SKIN: &str = "flat";
+
+// One of the skin's images, only drawn once it has loaded so nothing goes invisible while it loads.
+// One that fails to load is given up on, and that part is drawn flat instead
+#[derive(Default)]
+pub(crate) enum SkinImage {
+    #[default]
+    Flat,
+    Loading {
+        path: String,
+        handle: Handle<Image>,
+    },
+    Loaded(Handle<Image>),
+}
+
+impl SkinImage {
+    fn load(asset_server: &AssetServer, skin: &str, file: &str) -> SkinImage {
+        let path = format!("skins/{skin}/{file}");
+        SkinImage::Loading {
+            handle: asset_server.load(path.as_str()),
+            path,
+        }
+    }
+
+    pub(crate) fn loaded(&self) -> Option<&Handle<Image>> {
+        match self {
+            SkinImage::Loaded(handle) => Some(handle),
+            _ => None,
+        }
+    }
+
+    // Moves on once the image has loaded or failed to, returning whether it has just loaded
+    fn poll(&mut self, asset_server: &AssetServer) -> bool {
+        let SkinImage::Loading { path, handle } = self else {
+            return false;
+        };
+        match asset_server.get_load_state(&*handle) {
+            LoadState::Loaded => {
+                *self = SkinImage::Loaded(handle.clone());
+                true
+            }
+            LoadState::Failed | LoadState::Unloaded => {
+                warn!("Couldn't load the skin image {path:?}, drawing it flat instead");
+                *self = SkinImage::Flat;
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
+// The images of the `PongConfig::skin`. The paddles keep their size in their `Transform`, which is
+// what collisions use, and the images are stretched over it whatever their own size
+#[derive(Resource, Default)]
+pub(crate) struct Skin {
+    pub(crate) paddle: SkinImage,
+    pub(crate) ball: SkinImage,
+    // A skinned ball is drawn as a square with the image on it, rather than the flat ball's circle
+    pub(crate) ball_mesh: Mesh2dHandle,
+}
+
+pub(crate) fn load_skin(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    config: Res<PongConfig>,
+) {
+    if config.skin == FLAT_SKIN {
+        commands.insert_resource(Skin::default());
+        return;
+    }
+
+    commands.insert_resource(Skin {
+        paddle: SkinImage::load(&asset_server, &config.skin, "paddle.png"),
+        ball: SkinImage::load(&asset_server, &config.skin, "ball.png"),
+        ball_mesh: meshes.add(shape::Quad::new(Vec2::ONE).into()).into(),
+    });
+}
+
+// Puts the skin's images on the paddles and balls once they've loaded
+pub(crate) fn apply_skin(
+    asset_server: Res<AssetServer>,
+    mut skin: ResMut<Skin>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut paddle_query: Query<(&mut Sprite, &mut Handle<Image>), With<Paddle>>,
+    mut visual_query: Query<(&mut Mesh2dHandle, &Handle<ColorMaterial>), With<BallVisual>>,
+) {
+    let skin = &mut *skin;
+    skin.paddle.poll(&asset_server);
+    if let Some(texture) = skin.paddle.loaded() {
+        for (mut sprite, mut image) in &mut paddle_query {
+            if *image != *texture {
+                *image = texture.clone();
+                // stretched over the paddle's `Transform`, rather than drawn at the image's own size
+                sprite.custom_size = Some(Vec2::ONE);
+            }
+        }
+    }
+
+    // new balls are given the image as they're spawned, only the ones already there need it now
+    if skin.ball.poll(&asset_server) {
+        for (mut mesh, material) in &mut visual_query {
+            *mesh = skin.ball_mesh.clone();
+            if let Some(material) = materials.get_mut(material) {
+                material.texture = skin.ball.loaded().cloned();
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{arena::WallLocation, components::*, events::*, replay::*, resources::*};
+
+/// Records kept from match to match, and saved by `PongRenderPlugin` to its `SaveStorage`
+/// so they last from one run of the game to the next
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PersistentStats {
+    /// Matches against the computer that the player won
+    pub player_wins: u32,
+    /// Matches against the computer that the computer won
+    pub cpu_wins: u32,
+    /// The most points a match has been won by, in its last game
+    pub best_winning_margin: usize,
+    /// The most paddle hits in one rally
+    pub longest_rally: usize,
+}
+
+impl PersistentStats {
+    /// Whether any match has been played yet to keep a record of
+    pub fn is_empty(&self) -> bool {
+        *self == PersistentStats::default()
+    }
+}
+
+/// How the current match is going, for the game over screen and anything exporting the match.
+/// It starts over with the match, on a restart or rematch and when the demo ends
+#[derive(Resource, Debug, Clone, PartialEq, Default, Reflect)]
+#[reflect(Resource)]
+pub struct MatchStats {
+    /// Paddle hits by the left side
+    pub left_hits: usize,
+    /// Paddle hits by the right side
+    pub right_hits: usize,
+    /// Paddle hits by the top and bottom players, in four-player matches
+    pub top_hits: usize,
+    pub bottom_hits: usize,
+    /// The most paddle hits in one rally, counting the one going on
+    pub longest_rally: usize,
+    /// Paddle hits in the rally going on
+    pub current_rally: usize,
+    /// The rallies that have ended, and the paddle hits there were in them
+    pub rallies: usize,
+    pub rally_hits: usize,
+    /// Times a ball has bounced off the top or bottom wall
+    pub wall_bounces: usize,
+    /// The fastest any ball has gone
+    pub max_ball_speed: f32,
+    /// Seconds of play, not counting the time spent paused
+    pub duration: f32,
+}
+
+impl MatchStats {
+    pub fn hits(&self, seat: impl Into<Seat>) -> usize {
+        match seat.into() {
+            Seat::Left => self.left_hits,
+            Seat::Right => self.right_hits,
+            Seat::Top => self.top_hits,
+            Seat::Bottom => self.bottom_hits,
+        }
+    }
+
+    /// Paddle hits in each rally that has ended, 0 before the first has
+    pub fn average_rally(&self) -> f32 {
+        if self.rallies == 0 {
+            return 0.0;
+        }
+        self.rally_hits as f32 / self.rallies as f32
+    }
+}
+
+// Runs with the physics, which doesn't while the game is paused, so every step is play.
+// The rally follows `Rally`, going up with the same paddle hits and ending when it's reset for a new serve
+pub(crate) fn track_match_stats(
+    step: Res<PhysicsStep>,
+    rally: Res<Rally>,
+    mut stats: ResMut<MatchStats>,
+    mut paddle_hits: EventReader<PaddleHitEvent>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut goal_events: EventReader<GoalEvent>,
+    mut life_events: EventReader<LifeLost>,
+    mut miss_events: EventReader<BallMissed>,
+    mut reset_events: EventReader<BallResetEvent>,
+    wall_query: Query<(), With<WallLocation>>,
+    ball_query: Query<&Velocity, With<Ball>>,
+) {
+    stats.duration += step.0;
+
+    for hit in paddle_hits.iter() {
+        match hit.seat {
+            Seat::Left => stats.left_hits += 1,
+            Seat::Right => stats.right_hits += 1,
+            Seat::Top => stats.top_hits += 1,
+            Seat::Bottom => stats.bottom_hits += 1,
+        }
+        stats.current_rally += 1;
+        stats.longest_rally = stats.longest_rally.max(stats.current_rally);
+    }
+    let wall_bounces = collision_events
+        .iter()
+        .filter(|event| wall_query.contains(event.collider))
+        .count();
+    stats.wall_bounces += wall_bounces;
+
+    // with other balls still in play a goal doesn't end the rally
+    let ball_gone = goal_events.iter().count()
+        + life_events.iter().count()
+        + miss_events.iter().count()
+        + reset_events.iter().count()
+        > 0;
+    if ball_gone && rally.hits == 0 {
+        stats.rallies += 1;
+        stats.rally_hits += std::mem::take(&mut stats.current_rally);
+    }
+
+    for velocity in &ball_query {
+        stats.max_ball_speed = stats.max_ball_speed.max(velocity.length());
+    }
+}
+
+// Keeps up with the rally as it goes, so one still going when the match is won counts too.
+// Replays and the demo are someone else's records
+pub(crate) fn track_longest_rally(
+    rally: Res<Rally>,
+    state: Res<State<GameState>>,
+    replay_player: Option<Res<ReplayPlayer>>,
+    mut stats: ResMut<PersistentStats>,
+) {
+    if *state.current() == GameState::Playing && replay_player.is_none() && rally.hits > stats.longest_rally {
+        stats.longest_rally = rally.hits;
+    }
+}
+
+pub(crate) fn record_match(
+    scoreboard: Res<Scoreboard>,
+    match_score: Res<MatchScore>,
+    replay_player: Option<Res<ReplayPlayer>>,
+    mut stats: ResMut<PersistentStats>,
+    cpu_query: Query<&Side, (With<Paddle>, With<CpuControlled>)>,
+) {
+    let (Some(winner), None) = (match_score.winner, replay_player) else {
+        return;
+    };
+
+    // a match against the computer has it playing one side, and someone else the other
+    let cpu_sides: Vec<Side> = cpu_query.iter().copied().collect();
+    if let [cpu_side] = cpu_sides[..] {
+        if cpu_side == winner {
+            stats.cpu_wins += 1;
+        } else {
+            stats.player_wins += 1;
+        }
+    }
+
+    let margin = scoreboard.score(winner).saturating_sub(scoreboard.score(winner.opponent()));
+    stats.best_winning_margin = stats.best_winning_margin.max(margin);
+}
@@ -0,0 +1,354 @@
+use bevy::{math::Vec2Swizzles, prelude::*, sprite::collide_aabb::Collision};
+
+use crate::{components::*, config::*, events::*, resources::*};
+
+// The steepest angle (from horizontal) the ball can leave a paddle at, hitting its very tip
+const MAX_BOUNCE_ANGLE: f32 = 5.0 * std::f32::consts::PI / 12.0;
+// How much of a moving collider's velocity a ball picks up from bouncing off it
+const MOVING_COLLIDER_SPIN: f32 = 0.5;
+// How many wall bounces `predict_intercept` follows a ball through before giving up on it
+const MAX_PREDICTED_BOUNCES: usize = 16;
+
+pub(crate) fn check_for_collisions(
+    config: Res<PongConfig>,
+    rally_settings: Res<RallySettings>,
+    step: Res<PhysicsStep>,
+    mut rally: ResMut<Rally>,
+    // a ball waiting to be served sits still, even if the moving obstacle passes over it
+    mut ball_query: Query<(Entity, &mut Velocity, &mut Transform), (With<Ball>, Without<AwaitingServe>)>,
+    collider_query: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&Seat>,
+            Option<&PaddleAxis>,
+            Option<&Velocity>,
+            Option<&PaddleVelocity>,
+        ),
+        (With<Collider>, Without<Ball>),
+    >,
+    mut collision_events: EventWriter<CollisionEvent>,
+    mut paddle_hits: EventWriter<PaddleHitEvent>,
+) {
+    for (ball, mut ball_velocity, mut ball_transform) in &mut ball_query {
+        let ball_size = ball_transform.scale.truncate();
+
+        // `apply_velocity` has already moved the ball this step, so work out where it came from.
+        // Testing the whole path rather than just the end position stops a fast ball
+        // from skipping straight over a thin paddle
+        let end = ball_transform.translation.truncate();
+        let start = end - ball_velocity.0 * step.0;
+
+        // find the first thing the ball ran into on its way, and its seat and axis if it's a paddle
+        let mut first_hit: Option<(f32, Collision, Vec2, Entity, &Transform, Option<(Seat, PaddleAxis)>, Vec2)> =
+            None;
+        for (collider, transform, seat, axis, velocity, paddle_velocity) in &collider_query {
+            let target = transform.translation.truncate();
+            let target_size = transform.scale.truncate();
+            // A moving collider has moved this step too. Sweeping the ball's path as seen from the collider
+            // is close enough, as neither moves far in a step
+            let collider_velocity = match (velocity, paddle_velocity, axis) {
+                (Some(velocity), ..) => velocity.0,
+                (None, Some(paddle_velocity), Some(axis)) => axis.unit() * paddle_velocity.0,
+                _ => Vec2::ZERO,
+            };
+            let relative_start = start + collider_velocity * step.0;
+            let hit = swept_collision(relative_start, end, ball_size, target, target_size)
+                .map(|(time, collision)| (time, collision, Vec2::ZERO))
+                .or_else(|| {
+                    // the ball was already overlapping at the start of the step,
+                    // so push it back out before it gets stuck inside
+                    resolve_overlap(end, ball_size, ball_velocity.0 - collider_velocity, target, target_size)
+                        .map(|(correction, collision)| (1.0, collision, correction))
+                });
+
+            if let Some((time, collision, correction)) = hit {
+                if first_hit.as_ref().is_none_or(|(first_time, ..)| time < *first_time) {
+                    first_hit = Some((
+                        time,
+                        collision,
+                        correction,
+                        collider,
+                        transform,
+                        seat.copied().zip(axis.copied()),
+                        collider_velocity,
+                    ));
+                }
+            }
+        }
+
+        let Some((time, collision, correction, collider, transform, paddle, collider_velocity)) = first_hit else {
+            continue;
+        };
+        let is_paddle = paddle.is_some();
+
+        // reflect the ball when it collides
+        let mut reflect_x = false;
+        let mut reflect_y = false;
+
+        // only reflect if the ball's velocity is going in the opposite direction of the
+        // collision, relative to the collider if it's moving
+        let relative_velocity = ball_velocity.0 - collider_velocity;
+        match collision {
+            Collision::Left => reflect_x = relative_velocity.x > 0.0,
+            Collision::Right => reflect_x = relative_velocity.x < 0.0,
+            Collision::Top => reflect_y = relative_velocity.y < 0.0,
+            Collision::Bottom => reflect_y = relative_velocity.y > 0.0,
+            Collision::Inside => { /* do nothing */ }
+        }
+
+        // Sends a collision event so that other systems can react to the collision
+        collision_events.send(CollisionEvent {
+            ball,
+            collider,
+            side: collision,
+            speed: ball_velocity.length(),
+        });
+
+        let impact = start + (end - start) * time + correction;
+
+        // the face of a paddle, rather than one of its ends
+        let paddle_face = match paddle {
+            Some((_, PaddleAxis::Vertical)) => reflect_x,
+            Some((_, PaddleAxis::Horizontal)) => reflect_y,
+            None => false,
+        };
+
+        // paddles send the ball off at an angle depending on where it hit them,
+        // the top and bottom ones the same as the others turned on their side
+        if paddle_face && reflect_x {
+            ball_velocity.0 = paddle_bounce(
+                ball_velocity.0,
+                impact.y - transform.translation.y,
+                transform.scale.y,
+            );
+        } else if paddle_face {
+            ball_velocity.0 = paddle_bounce(
+                ball_velocity.0.yx(),
+                impact.x - transform.translation.x,
+                transform.scale.x,
+            )
+            .yx();
+        } else if reflect_x {
+            // reflect velocity on the x-axis if we hit something on the x-axis,
+            // off the end of a moving collider it's as if off a wall that's moving
+            ball_velocity.x = reflect(ball_velocity.x, collider_velocity.x);
+            // a moving obstacle drags the ball along a little, like a paddle's spin
+            ball_velocity.y += collider_velocity.y * MOVING_COLLIDER_SPIN;
+        } else if reflect_y {
+            // and on the y-axis if we hit something on the y-axis
+            ball_velocity.y = reflect(ball_velocity.y, collider_velocity.y);
+        }
+
+        // every paddle hit speeds the ball up a little, until the rally has gone on long enough
+        if is_paddle && (reflect_x || reflect_y) {
+            if rally.hits < rally_settings.max_speed_ups {
+                ball_velocity.0 *= rally_settings.speed_up_factor;
+            }
+            rally.hits += 1;
+        }
+        // a paddle moving as it hits the ball slices it along the way it's moving
+        if paddle_face {
+            ball_velocity.0 += collider_velocity * config.paddle_spin;
+        }
+        ball_velocity.0 = constrain_velocity(
+            ball_velocity.0,
+            config.max_ball_speed,
+            config.min_horizontal_speed_fraction,
+        );
+
+        if let (Some((seat, _)), true) = (paddle, reflect_x || reflect_y) {
+            paddle_hits.send(PaddleHitEvent {
+                ball,
+                paddle: collider,
+                seat,
+                speed: ball_velocity.length(),
+            });
+        }
+
+        // bounce at the moment of impact, spending the rest of the step travelling the new way
+        let position = impact + ball_velocity.0 * (1.0 - time) * step.0;
+        ball_transform.translation = position.extend(ball_transform.translation.z);
+    }
+}
+
+/// Sweeps a ball moving from `start` to `end` against a box collider.
+///
+/// Returns the fraction of the movement at which the ball first touches the collider, and which
+/// side of the collider it touched (using the same convention as `collide_aabb::collide`). A ball that is
+/// already overlapping the collider at `start`, or never reaches it, returns `None`.
+pub fn swept_collision(
+    start: Vec2,
+    end: Vec2,
+    ball_size: Vec2,
+    target: Vec2,
+    target_size: Vec2,
+) -> Option<(f32, Collision)> {
+    // Growing the collider by the ball's size lets us treat the ball as a single point
+    let half_extents = (ball_size + target_size) / 2.0;
+    let min = target - half_extents;
+    let max = target + half_extents;
+    let delta = end - start;
+
+    let mut entry = Vec2::splat(f32::NEG_INFINITY);
+    let mut exit = Vec2::splat(f32::INFINITY);
+    for axis in 0..2 {
+        if delta[axis] == 0.0 {
+            // not moving on this axis, so the ball has to already be within it
+            if start[axis] <= min[axis] || start[axis] >= max[axis] {
+                return None;
+            }
+        } else {
+            let t1 = (min[axis] - start[axis]) / delta[axis];
+            let t2 = (max[axis] - start[axis]) / delta[axis];
+            entry[axis] = t1.min(t2);
+            exit[axis] = t1.max(t2);
+        }
+    }
+
+    let entry_time = entry.max_element();
+    let exit_time = exit.min_element();
+    if entry_time > exit_time || !(0.0..=1.0).contains(&entry_time) {
+        return None;
+    }
+
+    let collision = if entry.x > entry.y {
+        if delta.x > 0.0 {
+            Collision::Left
+        } else {
+            Collision::Right
+        }
+    } else if delta.y > 0.0 {
+        Collision::Bottom
+    } else {
+        Collision::Top
+    };
+
+    Some((entry_time, collision))
+}
+
+/// Works out how to push a ball that is overlapping a box collider back out of it.
+///
+/// Returns the offset that moves the ball clear, and which side of the collider it leaves from.
+/// Normally that's the side it is the least deep into, but if its center has ended up inside
+/// the collider it is sent back out against its velocity instead.
+pub fn resolve_overlap(
+    ball: Vec2,
+    ball_size: Vec2,
+    velocity: Vec2,
+    target: Vec2,
+    target_size: Vec2,
+) -> Option<(Vec2, Collision)> {
+    let offset = ball - target;
+    let penetration = (ball_size + target_size) / 2.0 - offset.abs();
+    if penetration.x <= 0.0 || penetration.y <= 0.0 {
+        return None;
+    }
+
+    let inside = offset.abs().cmplt(target_size / 2.0).all();
+    let (push_x, direction) = if inside {
+        if velocity.x.abs() >= velocity.y.abs() {
+            (true, -velocity.x.signum())
+        } else {
+            (false, -velocity.y.signum())
+        }
+    } else if penetration.x < penetration.y {
+        (true, offset.x.signum())
+    } else {
+        (false, offset.y.signum())
+    };
+
+    if push_x {
+        // when coming back out against the velocity, the ball has to clear the far edge
+        let depth = if inside {
+            (ball_size.x + target_size.x) / 2.0 - offset.x * direction
+        } else {
+            penetration.x
+        };
+        let collision = if direction < 0.0 {
+            Collision::Left
+        } else {
+            Collision::Right
+        };
+        Some((Vec2::new(direction * depth, 0.0), collision))
+    } else {
+        let depth = if inside {
+            (ball_size.y + target_size.y) / 2.0 - offset.y * direction
+        } else {
+            penetration.y
+        };
+        let collision = if direction < 0.0 {
+            Collision::Bottom
+        } else {
+            Collision::Top
+        };
+        Some((Vec2::new(0.0, direction * depth), collision))
+    }
+}
+
+/// A ball's velocity along one axis after bouncing off something moving at `surface_velocity` along it,
+/// which is 0 for a wall. As seen from the surface the ball just turns around
+pub fn reflect(velocity: f32, surface_velocity: f32) -> f32 {
+    2.0 * surface_velocity - velocity
+}
+
+/// Follows a ball from `position` at `velocity` to where it crosses the line at `target_x`,
+/// bouncing it off the walls at `min_y` and `max_y` with the same `reflect` the physics uses.
+///
+/// The walls are where the ball's center turns around, so the walls' and the ball's thickness have
+/// to be allowed for already. Returns the height it crosses at, or `None` if it isn't heading for the line.
+pub fn predict_intercept(position: Vec2, velocity: Vec2, target_x: f32, min_y: f32, max_y: f32) -> Option<f32> {
+    let heading_for_line = velocity.x != 0.0 && (target_x - position.x).signum() == velocity.x.signum();
+    if !heading_for_line || !position.is_finite() || !velocity.is_finite() || min_y >= max_y {
+        return None;
+    }
+
+    let mut position = Vec2::new(position.x, position.y.clamp(min_y, max_y));
+    let mut velocity = velocity;
+    for _ in 0..=MAX_PREDICTED_BOUNCES {
+        let time_to_line = (target_x - position.x) / velocity.x;
+        let crossing = position.y + velocity.y * time_to_line;
+        if (min_y..=max_y).contains(&crossing) {
+            return Some(crossing);
+        }
+
+        // it meets a wall first, and carries on from there the other way
+        let wall = if velocity.y > 0.0 { max_y } else { min_y };
+        position += velocity * ((wall - position.y) / velocity.y);
+        position.y = wall;
+        velocity.y = reflect(velocity.y, 0.0);
+    }
+    None
+}
+
+/// The velocity of a ball bouncing off the face of a paddle.
+///
+/// `offset` is the ball's height relative to the paddle's center. A hit in the middle sends
+/// the ball back flat, and the further out towards either tip it hits the steeper it leaves,
+/// up to `MAX_BOUNCE_ANGLE`. The ball's speed is unchanged.
+pub fn paddle_bounce(velocity: Vec2, offset: f32, paddle_height: f32) -> Vec2 {
+    let relative_offset = (offset / (paddle_height / 2.0)).clamp(-1.0, 1.0);
+    let angle = relative_offset * MAX_BOUNCE_ANGLE;
+    // send the ball back the way it came
+    let x_sign = -velocity.x.signum();
+    Vec2::new(x_sign * angle.cos(), angle.sin()) * velocity.length()
+}
+
+/// Keeps a ball's velocity after a bounce within the limits the game allows.
+///
+/// Its speed is capped at `max_speed`, without changing its direction. Then if less than
+/// `min_horizontal_fraction` of the speed is horizontal, some of the vertical speed is turned
+/// into horizontal speed to make up the difference, keeping the overall speed and both signs.
+pub fn constrain_velocity(velocity: Vec2, max_speed: f32, min_horizontal_fraction: f32) -> Vec2 {
+    let velocity = velocity.clamp_length_max(max_speed);
+    let speed = velocity.length();
+    let min_horizontal_speed = speed * min_horizontal_fraction.clamp(0.0, 1.0);
+    if velocity.x.abs() >= min_horizontal_speed {
+        return velocity;
+    }
+
+    let x_sign = if velocity.x < 0.0 { -1.0 } else { 1.0 };
+    let y_sign = if velocity.y < 0.0 { -1.0 } else { 1.0 };
+    let vertical_speed = (speed * speed - min_horizontal_speed * min_horizontal_speed).max(0.0).sqrt();
+    Vec2::new(x_sign * min_horizontal_speed, y_sign * vertical_speed)
+}
@@ -0,0 +1,5 @@
+pub mod collision;
+pub mod movement;
+pub mod power_ups;
+pub mod scoring;
+pub mod serve;
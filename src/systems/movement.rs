@@ -0,0 +1,560 @@
+use bevy::{math::Vec2Swizzles, prelude::*, utils::HashMap};
+use rand::Rng;
+use std::collections::VecDeque;
+
+use crate::{
+    arena::*,
+    components::*,
+    config::*,
+    replay::*,
+    resources::*,
+    systems::collision::{constrain_velocity, predict_intercept},
+    theme::*,
+    ui::*,
+};
+
+// The serve/pause button for touchscreens sits along the bottom middle of the window,
+// taking up these fractions of its width and height
+pub(crate) const TOUCH_BUTTON_WIDTH: f32 = 0.3;
+pub(crate) const TOUCH_BUTTON_HEIGHT: f32 = 0.15;
+// Which finger is steering each paddle, so lifting one doesn't affect the other player
+#[derive(Resource, Default)]
+pub(crate) struct TouchControls {
+    left: Option<u64>,
+    right: Option<u64>,
+}
+
+impl TouchControls {
+    fn finger(&self, side: Side) -> Option<u64> {
+        match side {
+            Side::Left => self.left,
+            Side::Right => self.right,
+        }
+    }
+
+    fn finger_mut(&mut self, side: Side) -> &mut Option<u64> {
+        match side {
+            Side::Left => &mut self.left,
+            Side::Right => &mut self.right,
+        }
+    }
+}
+
+// Samples the keyboard once per physics step, which is all the paddles see of it,
+// or takes the step's inputs from the replay being played back.
+// Without a keyboard, in a headless app, the inputs are left for whoever is driving it to set
+pub(crate) fn read_paddle_input(
+    keyboard_input: Option<Res<Input<KeyCode>>>,
+    scan_input: Option<Res<Input<ScanCode>>>,
+    bindings: Res<KeyBindings>,
+    replay_player: Option<ResMut<ReplayPlayer>>,
+    mut paddle_inputs: ResMut<PaddleInputs>,
+) {
+    if let Some(mut replay_player) = replay_player {
+        // once the replay runs out the paddles stand still
+        *paddle_inputs = replay_player.replay.inputs(replay_player.tick).unwrap_or_default();
+        replay_player.tick += 1;
+        return;
+    }
+    if let (Some(keyboard_input), Some(scan_input)) = (keyboard_input, scan_input) {
+        read_keyboard(&keyboard_input, &scan_input, &bindings, &mut paddle_inputs);
+    }
+}
+
+// Every player's input from the keys held down, by name or by where they are as the bindings say
+pub(crate) fn read_keyboard(
+    keyboard_input: &Input<KeyCode>,
+    scan_input: &Input<ScanCode>,
+    bindings: &KeyBindings,
+    paddle_inputs: &mut PaddleInputs,
+) {
+    let pressed = |action| bindings.pressed(action, keyboard_input, scan_input);
+    // there's only the one serve key, so it counts for every player
+    let serve = keyboard_input.pressed(bindings.serve);
+    for seat in Seat::ALL {
+        let (up, down) = Action::paddle(seat);
+        *paddle_inputs.input_mut(seat) = PlayerInput::new(pressed(up), pressed(down), serve);
+    }
+    // only the left and right players have dash keys
+    if pressed(Action::LeftDash) {
+        paddle_inputs.left = paddle_inputs.left.dashing();
+    }
+    if pressed(Action::RightDash) {
+        paddle_inputs.right = paddle_inputs.right.dashing();
+    }
+    for side in [Side::Left, Side::Right] {
+        let (up, down) = Action::lower_paddle(side);
+        *paddle_inputs.lower_input_mut(side) = PlayerInput::new(pressed(up), pressed(down), serve);
+    }
+}
+
+// The action that closes `offset` along a paddle's axis, as quickly as it can move
+fn steer_towards(offset: f32, speed: &PaddleSpeed, step: &PhysicsStep) -> f32 {
+    (offset / (speed.0 * step.0)).clamp(-1.0, 1.0)
+}
+
+// Sets the action of every paddle played with `PaddleInputs` from the input for its seat,
+// or for the lower paddle of its side in a doubles match
+pub(crate) fn apply_paddle_inputs(
+    paddle_inputs: Res<PaddleInputs>,
+    mut query: Query<
+        (&mut PaddleAction, &Seat, Option<&Half>),
+        (With<Paddle>, Without<CpuControlled>, Without<MouseControlled>),
+    >,
+) {
+    for (mut action, seat, half) in &mut query {
+        let input = paddle_inputs.paddle_input(*seat, half.copied());
+        *action = PaddleAction {
+            axis: input.direction(),
+            serve: input.serve(),
+            dash: input.dash(),
+        };
+    }
+}
+
+// Counts down each paddle's wait to dash again, and starts a dash for those whose dash key has just
+// gone down while they're moving, once they've waited long enough
+pub(crate) fn start_dashes(
+    step: Res<PhysicsStep>,
+    config: Res<PongConfig>,
+    settings: Res<MatchSettings>,
+    mut query: Query<(&PaddleAction, &mut PaddleDash), With<Paddle>>,
+) {
+    for (action, mut dash) in &mut query {
+        dash.cooldown = (dash.cooldown - step.0).max(0.0);
+        let pressed = action.dash && !dash.held;
+        dash.held = action.dash;
+        if settings.paddle_dash && pressed && dash.cooldown == 0.0 && action.axis != 0.0 {
+            dash.remaining = config.dash_duration;
+            dash.direction = action.axis.signum();
+            dash.cooldown = config.dash_cooldown;
+        }
+    }
+}
+
+// Moves every paddle along its axis by its action, however that was set, without leaving the arena.
+// With paddle inertia the action is the speed the paddle works its way up or down to, from the velocity
+// it had, rather than the one it moves at. A dash overrides either, until it's covered its distance
+pub(crate) fn move_paddles(
+    step: Res<PhysicsStep>,
+    arena: Res<Arena>,
+    config: Res<PongConfig>,
+    settings: Res<MatchSettings>,
+    mut query: Query<
+        (
+            &mut Transform,
+            &mut PaddleVelocity,
+            &mut PaddleDash,
+            &PaddleAction,
+            &PaddleSpeed,
+            &PaddleAxis,
+            Option<&Half>,
+        ),
+        With<Paddle>,
+    >,
+) {
+    for (mut paddle_transform, mut velocity, mut dash, action, speed, axis, half) in &mut query {
+        let along = axis.index();
+        let target_velocity = action.axis * speed.0;
+        let paddle_velocity = if dash.remaining > 0.0 {
+            // the last of it goes in one, rather than leaving a rounding error's sliver for the next step
+            let dash_time = if dash.remaining < step.0 * 1.001 { dash.remaining } else { step.0 };
+            dash.remaining -= dash_time;
+            dash.direction * config.dash_distance / config.dash_duration * dash_time / step.0
+        } else if settings.paddle_inertia {
+            let speeding_up = target_velocity.abs() > velocity.0.abs() && target_velocity * velocity.0 >= 0.0;
+            let rate = if speeding_up {
+                config.paddle_acceleration
+            } else {
+                config.paddle_deceleration
+            };
+            velocity.0 + (target_velocity - velocity.0).clamp(-rate * step.0, rate * step.0)
+        } else {
+            target_velocity
+        };
+        let new_paddle_position = paddle_transform.translation[along] + paddle_velocity * step.0;
+        let (lower_bound, upper_bound) =
+            paddle_bounds(&arena, &config, *axis, half.copied(), paddle_transform.scale[along]);
+        paddle_transform.translation[along] = new_paddle_position.clamp(lower_bound, upper_bound);
+        // reaching the end of its travel stops it dead, it doesn't keep pushing to come off it slowly
+        let stopped = paddle_transform.translation[along] != new_paddle_position;
+        if stopped {
+            dash.remaining = 0.0;
+        }
+        if settings.paddle_inertia {
+            velocity.0 = if stopped { 0.0 } else { paddle_velocity };
+        }
+    }
+}
+
+// Steers mouse controlled paddles towards the cursor's height in the arena.
+// They move no faster than the keyboard allows, so the paddle can't teleport
+pub(crate) fn steer_mouse_paddles(
+    step: Res<PhysicsStep>,
+    windows: Res<Windows>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    mut paddle_query: Query<
+        (&Transform, &mut PaddleAction, &PaddleSpeed),
+        (With<Paddle>, With<MouseControlled>, Without<CpuControlled>),
+    >,
+) {
+    let Some(cursor_position) = windows.get_primary().and_then(|window| window.cursor_position()) else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = camera_query.get_single() else {
+        return;
+    };
+    let Some(cursor_position) = window_to_world(camera, camera_transform, cursor_position) else {
+        return;
+    };
+
+    for (paddle_transform, mut action, speed) in &mut paddle_query {
+        action.axis = steer_towards(cursor_position.y - paddle_transform.translation.y, speed, &step);
+    }
+}
+
+// The cursor would only get in the way while someone is steering a paddle with it
+pub(crate) fn update_cursor_visibility(
+    mut windows: ResMut<Windows>,
+    state: Res<State<GameState>>,
+    mouse_paddle_query: Query<(), (With<Paddle>, With<MouseControlled>)>,
+) {
+    let Some(window) = windows.get_primary_mut() else {
+        return;
+    };
+
+    let visible = *state.current() != GameState::Playing || mouse_paddle_query.is_empty();
+    if window.cursor_visible() != visible {
+        window.set_cursor_visibility(visible);
+    }
+}
+
+pub(crate) fn toggle_fullscreen(keyboard_input: Res<Input<KeyCode>>, mut display: ResMut<DisplaySettings>) {
+    if keyboard_input.just_pressed(KeyCode::F11) {
+        display.fullscreen = !display.fullscreen;
+    }
+}
+
+// Puts the window into the mode the `DisplaySettings` ask for, including the one they were saved with.
+// The arena and camera fit themselves to the window's new size when it's resized
+pub(crate) fn apply_fullscreen(display: Res<DisplaySettings>, mut windows: ResMut<Windows>) {
+    if !display.is_changed() {
+        return;
+    }
+    let Some(window) = windows.get_primary_mut() else {
+        return;
+    };
+
+    let mode = if display.fullscreen {
+        WindowMode::BorderlessFullscreen
+    } else {
+        WindowMode::Windowed
+    };
+    if window.mode() != mode {
+        window.set_mode(mode);
+    }
+}
+
+pub(crate) fn apply_present_mode(
+    display: Res<DisplaySettings>,
+    config: Res<PongConfig>,
+    mut windows: ResMut<Windows>,
+) {
+    if !display.is_changed() && !config.is_changed() {
+        return;
+    }
+
+    let present_mode = display.present_mode(&config);
+    if windows.get_primary().is_some_and(|window| window.present_mode() != present_mode) {
+        if let Some(window) = windows.get_primary_mut() {
+            window.set_present_mode(present_mode);
+        }
+    }
+}
+
+// Touch positions are in window coordinates with the origin at the top left
+fn in_touch_button(position: Vec2, window: &Window) -> bool {
+    let x = position.x / window.width();
+    let y = position.y / window.height();
+    (x - 0.5).abs() < TOUCH_BUTTON_WIDTH / 2.0 && y > 1.0 - TOUCH_BUTTON_HEIGHT
+}
+
+pub(crate) fn touch_button_tapped(touches: &Touches, windows: &Windows) -> bool {
+    let Some(window) = windows.get_primary() else {
+        return false;
+    };
+    touches
+        .iter_just_pressed()
+        .any(|touch| in_touch_button(touch.position(), window))
+}
+
+// Hands each new finger to the paddle on its half of the screen, if nobody is steering it yet,
+// and frees the paddle again when the finger is lifted
+pub(crate) fn assign_touches(
+    touches: Res<Touches>,
+    windows: Res<Windows>,
+    mut touch_controls: ResMut<TouchControls>,
+    mut touch_button_query: Query<&mut Visibility, With<TouchButton>>,
+) {
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+
+    for touch in touches.iter_just_released().chain(touches.iter_just_cancelled()) {
+        for side in [Side::Left, Side::Right] {
+            let finger = touch_controls.finger_mut(side);
+            if *finger == Some(touch.id()) {
+                *finger = None;
+            }
+        }
+    }
+
+    for touch in touches.iter_just_pressed() {
+        // this is a touchscreen, so the players need the on-screen button
+        for mut visibility in &mut touch_button_query {
+            visibility.is_visible = true;
+        }
+
+        if in_touch_button(touch.position(), window) {
+            continue;
+        }
+
+        let side = if touch.position().x < window.width() / 2.0 {
+            Side::Left
+        } else {
+            Side::Right
+        };
+        let finger = touch_controls.finger_mut(side);
+        if finger.is_none() {
+            *finger = Some(touch.id());
+        }
+    }
+}
+
+// Steers each paddle that has a finger on it towards the finger's height, no faster than the keyboard.
+// Both paddles of a doubles side follow the finger, each as far as its own half allows
+pub(crate) fn steer_touch_paddles(
+    step: Res<PhysicsStep>,
+    touches: Res<Touches>,
+    windows: Res<Windows>,
+    touch_controls: Res<TouchControls>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    mut paddle_query: Query<
+        (&Transform, &mut PaddleAction, &PaddleSpeed, &Side),
+        (With<Paddle>, Without<CpuControlled>),
+    >,
+) {
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = camera_query.get_single() else {
+        return;
+    };
+
+    for (paddle_transform, mut action, speed, side) in &mut paddle_query {
+        let Some(finger) = touch_controls.finger(*side) else {
+            continue;
+        };
+        let Some(touch) = touches.get_pressed(finger) else {
+            continue;
+        };
+
+        // the camera expects the origin at the bottom left
+        let window_position = Vec2::new(touch.position().x, window.height() - touch.position().y);
+        let Some(touch_position) = window_to_world(camera, camera_transform, window_position) else {
+            continue;
+        };
+
+        action.axis = steer_towards(touch_position.y - paddle_transform.translation.y, speed, &step);
+    }
+}
+
+// Where a ball was and where it was going, as the computer saw it on one step
+#[derive(Clone, Copy)]
+struct Sighting {
+    position: Vec2,
+    velocity: Vec2,
+    size: Vec2,
+}
+
+// The error a paddle is aiming with, rolled when `ball` last started heading its way
+#[derive(Clone, Copy)]
+struct Aim {
+    ball: Entity,
+    incoming: bool,
+    error: f32,
+}
+
+// What the computer remembers: the last few sightings of each ball in play, oldest first,
+// so it can go by where they were a moment ago, and what each of its paddles is aiming at.
+// Each new ball starts with no history, so nothing carries over from one serve or match to the next
+#[derive(Default)]
+pub(crate) struct CpuMemory {
+    sightings: HashMap<Entity, VecDeque<Sighting>>,
+    aims: HashMap<Entity, Aim>,
+}
+
+// A normally distributed number with a standard deviation of 1, by the Box-Muller transform
+fn standard_normal(rng: &mut impl Rng) -> f32 {
+    let u1: f32 = rng.gen_range(f32::EPSILON..1.0);
+    let u2: f32 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+}
+
+// Steers the computer's paddles towards where the ball is heading, along whichever way they slide.
+// Lower difficulties look less far ahead, move slower and ignore small offsets,
+// so fast or steep shots get past them. Hard follows a ball coming its way all the way to its paddle,
+// working it out again every step so it picks up every bounce off a paddle or obstacle as it happens.
+// Either way it goes by where the ball was a `CpuHandicap::reaction_delay` ago, and aims a little off.
+// Rubber banding can move it up to a notch towards the difficulty above or below.
+// On hard, with `MatchSettings::cpu_dash`, it dashes for a ball further off than a dash goes
+pub(crate) fn steer_cpu_paddles(
+    step: Res<PhysicsStep>,
+    settings: Res<MatchSettings>,
+    difficulty: Res<Difficulty>,
+    shift: Res<DifficultyShift>,
+    handicap: Option<Res<CpuHandicap>>,
+    arena: Res<Arena>,
+    config: Res<PongConfig>,
+    mut rng: ResMut<GameRng>,
+    mut memory: Local<CpuMemory>,
+    ball_query: Query<(Entity, &Transform, &Velocity), With<Ball>>,
+    mut paddle_query: Query<
+        (Entity, &Transform, &mut PaddleAction, &PaddleDash, &PaddleSpeed, &PaddleAxis),
+        (With<Paddle>, With<CpuControlled>, Without<Ball>),
+    >,
+) {
+    let skill = Difficulty::skill_at(difficulty.level() + shift.0);
+    let handicap = handicap.map_or(skill.handicap, |handicap| *handicap);
+    // one for every step of the delay, and this step's
+    let remembered = (handicap.reaction_delay / step.0).round() as usize + 1;
+    let CpuMemory { sightings, aims } = &mut *memory;
+    sightings.retain(|ball, _| ball_query.contains(*ball));
+    aims.retain(|paddle, _| paddle_query.contains(*paddle));
+    for (ball, transform, velocity) in &ball_query {
+        let ball_sightings = sightings.entry(ball).or_default();
+        ball_sightings.push_back(Sighting {
+            position: transform.translation.truncate(),
+            velocity: velocity.0,
+            size: transform.scale.truncate(),
+        });
+        while ball_sightings.len() > remembered {
+            ball_sightings.pop_front();
+        }
+    }
+
+    for (paddle, paddle_transform, mut action, dash, speed, axis) in &mut paddle_query {
+        *action = PaddleAction::default();
+        let along = axis.index();
+        let across = 1 - along;
+
+        // keep an eye on whichever ball was closest
+        let paddle_line = paddle_transform.translation[across];
+        let closest_ball = ball_query
+            .iter()
+            .filter_map(|(ball, ..)| Some((ball, *sightings.get(&ball)?.front()?)))
+            .min_by(|(_, a), (_, b)| {
+                let a_distance = (a.position[across] - paddle_line).abs();
+                let b_distance = (b.position[across] - paddle_line).abs();
+                a_distance.total_cmp(&b_distance)
+            });
+        let Some((ball, seen)) = closest_ball else {
+            continue;
+        };
+
+        // looked at as if the paddle slides up and down, with the walls the ball bounces between above and below
+        let (ball_position, velocity) = match axis {
+            PaddleAxis::Vertical => (seen.position, seen.velocity),
+            PaddleAxis::Horizontal => (seen.position.yx(), seen.velocity.yx()),
+        };
+        let (bottom, top) = match axis {
+            PaddleAxis::Vertical => (arena.bottom, arena.top),
+            PaddleAxis::Horizontal => (arena.left, arena.right),
+        };
+        let margin = config.wall_thickness / 2.0 + seen.size[along] / 2.0;
+        // the face of the paddle the ball is coming at
+        let toward_paddle = (paddle_line - ball_position.x).signum();
+        let face = paddle_line - toward_paddle * (paddle_transform.scale[across] + seen.size[across]) / 2.0;
+
+        // a new error for every shot coming the paddle's way
+        let incoming = velocity.x != 0.0 && toward_paddle == velocity.x.signum();
+        let aim = aims.entry(paddle).or_insert(Aim {
+            ball,
+            incoming: false,
+            error: 0.0,
+        });
+        if aim.ball != ball {
+            *aim = Aim {
+                ball,
+                incoming: false,
+                error: 0.0,
+            };
+        }
+        if incoming && !aim.incoming && handicap.aim_error > 0.0 {
+            aim.error = standard_normal(&mut rng.rng) * handicap.aim_error;
+        }
+        aim.incoming = incoming;
+
+        let predicted = if skill.predicts_bounces {
+            predict_intercept(ball_position, velocity, face, bottom + margin, top - margin)
+        } else {
+            None
+        };
+        let target = predicted.unwrap_or_else(|| seen.position[along] + seen.velocity[along] * skill.lookahead);
+        let offset = target + aim.error - paddle_transform.translation[along];
+        if offset.abs() <= skill.dead_zone {
+            continue;
+        }
+
+        let max_speed = skill.max_speed_fraction;
+        action.axis = steer_towards(offset, speed, &step).clamp(-max_speed, max_speed);
+        // let go of the key between dashes, so the next one's a fresh press
+        action.dash = skill.dashes
+            && settings.cpu_dash
+            && offset.abs() > config.dash_distance
+            && dash.cooldown == 0.0
+            && dash.remaining == 0.0;
+    }
+}
+
+// Works out how fast each paddle moved along its axis this step, from however it was moved.
+// Where it was is kept on the paddle, rather than in the system, so a rollback puts it back too.
+// With paddle inertia `move_paddles` keeps the velocity itself, as the paddle really is moving at it
+pub(crate) fn track_paddle_velocity(
+    step: Res<PhysicsStep>,
+    settings: Res<MatchSettings>,
+    mut paddle_query: Query<(&Transform, &PaddleAxis, &mut PaddleVelocity, &mut LastPaddlePosition), With<Paddle>>,
+) {
+    for (transform, axis, mut velocity, mut last_position) in &mut paddle_query {
+        let position = transform.translation[axis.index()];
+        let last_position = last_position.0.replace(position).unwrap_or(position);
+        if !settings.paddle_inertia {
+            velocity.0 = (position - last_position) / step.0;
+        }
+    }
+}
+
+// Pulls the balls in play down in gravity mode. The speed limits still hold,
+// so a ball can't end up falling straight down or rolling along the bottom wall
+pub(crate) fn apply_gravity(
+    step: Res<PhysicsStep>,
+    config: Res<PongConfig>,
+    settings: Res<MatchSettings>,
+    mut ball_query: Query<&mut Velocity, (With<Ball>, Without<AwaitingServe>)>,
+) {
+    let Some(gravity) = settings.gravity else {
+        return;
+    };
+
+    for mut velocity in &mut ball_query {
+        velocity.y -= gravity * step.0;
+        velocity.0 = constrain_velocity(velocity.0, config.max_ball_speed, config.min_horizontal_speed_fraction);
+    }
+}
+
+pub(crate) fn apply_velocity(step: Res<PhysicsStep>, mut query: Query<(&mut Transform, &Velocity)>) {
+    for (mut transform, velocity) in &mut query {
+        transform.translation.x += velocity.x * step.0;
+        transform.translation.y += velocity.y * step.0;
+    }
+}
@@ -0,0 +1,273 @@
+use bevy::{prelude::*, sprite::collide_aabb::collide};
+use rand::Rng;
+use std::time::Duration;
+
+use crate::{
+    arena::*,
+    components::*,
+    config::*,
+    events::*,
+    resources::*,
+    systems::{collision::*, serve::*},
+};
+
+// How long a paddle stays grown or shrunk
+const POWER_UP_DURATION: f32 = 10.0;
+const POWER_UP_SIZE: Vec2 = Vec2::new(30.0, 30.0);
+// Under the ball, which passes over it
+const POWER_UP_Z: f32 = 0.5;
+const GROW_COLOR: Color = Color::rgb(0.3, 0.8, 0.4);
+const SHRINK_COLOR: Color = Color::rgb(0.9, 0.3, 0.3);
+const TURBO_COLOR: Color = Color::rgb(1.0, 0.6, 0.0);
+// How much faster a turbo makes the ball, as long as that's within `PongConfig::max_ball_speed`
+const TURBO_SPEED_UP: f32 = 1.5;
+const MULTI_BALL_COLOR: Color = Color::rgb(0.5, 0.4, 0.9);
+// How far each of the new balls' directions is turned from the one that split, in degrees
+const MULTI_BALL_SPREAD: f32 = 25.0;
+
+// Counts down to the next power-up, in physics steps so a replay spawns them on the same step.
+// Its duration is `PongConfig::power_up_interval`
+#[derive(Resource, Default)]
+pub(crate) struct PowerUpTimer(Timer);
+
+impl PowerUpTimer {
+    pub(crate) fn reset(&mut self) {
+        self.0.reset();
+    }
+}
+
+// The color of a power-up, and of the paddle or ball it's having an effect on
+pub(crate) fn effect_color(kind: PowerUpKind) -> Color {
+    match kind {
+        PowerUpKind::Grow => GROW_COLOR,
+        PowerUpKind::Shrink => SHRINK_COLOR,
+        PowerUpKind::Turbo => TURBO_COLOR,
+        PowerUpKind::MultiBall => MULTI_BALL_COLOR,
+    }
+}
+
+// Keeps `LastTouched` up to date with each paddle hit
+pub(crate) fn track_last_touched(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    paddle_query: Query<&Side, With<Paddle>>,
+) {
+    for event in collision_events.iter() {
+        if let Ok(side) = paddle_query.get(event.collider) {
+            commands.insert_resource(LastTouched(*side));
+        }
+    }
+}
+
+// Puts a power-up somewhere in the middle third of the arena, once there's been none for a while.
+// Only in a match with them turned on, never in the demo
+pub(crate) fn spawn_power_ups(
+    mut commands: Commands,
+    settings: Res<MatchSettings>,
+    state: Res<State<GameState>>,
+    config: Res<PongConfig>,
+    arena: Res<Arena>,
+    step: Res<PhysicsStep>,
+    mut rng: ResMut<GameRng>,
+    mut timer: ResMut<PowerUpTimer>,
+    power_up_query: Query<(), With<PowerUp>>,
+) {
+    if !settings.power_ups || *state.current() != GameState::Playing || !power_up_query.is_empty() {
+        return;
+    }
+
+    timer.0.set_duration(Duration::from_secs_f32(config.power_up_interval));
+    timer.0.tick(Duration::from_secs_f32(step.0));
+    if !timer.0.finished() {
+        return;
+    }
+    timer.0.reset();
+
+    let kind = PowerUpKind::ALL[rng.rng.gen_range(0..PowerUpKind::ALL.len())];
+    let center_x = (arena.left + arena.right) / 2.0;
+    let x = center_x + rng.rng.gen_range(-1.0..1.0) * arena.width() / 6.0;
+    let margin = config.wall_thickness + POWER_UP_SIZE.y;
+    let y = rng.rng.gen_range(arena.bottom + margin..arena.top - margin);
+
+    commands.spawn((
+        SpriteBundle {
+            transform: Transform {
+                translation: Vec3::new(x, y, POWER_UP_Z),
+                scale: POWER_UP_SIZE.extend(1.0),
+                ..default()
+            },
+            sprite: Sprite {
+                color: effect_color(kind),
+                ..default()
+            },
+            ..default()
+        },
+        PowerUp::new(kind, config.power_up_lifetime),
+        MatchEntity,
+    ));
+}
+
+// Takes away power-ups nobody collected in time
+pub(crate) fn despawn_stale_power_ups(
+    mut commands: Commands,
+    step: Res<PhysicsStep>,
+    mut power_up_query: Query<(Entity, &mut PowerUp)>,
+) {
+    for (entity, mut power_up) in &mut power_up_query {
+        power_up.lifetime.tick(Duration::from_secs_f32(step.0));
+        if power_up.lifetime.finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+// A ball running into a power-up collects it for whoever last hit the ball.
+// Until someone has hit it since the serve there's nobody to give it to, so it stays put
+pub(crate) fn collect_power_ups(
+    mut commands: Commands,
+    last_touched: Option<Res<LastTouched>>,
+    ball_query: Query<(Entity, &Transform), With<Ball>>,
+    power_up_query: Query<(Entity, &Transform, &PowerUp), Without<Ball>>,
+    mut collected_events: EventWriter<PowerUpCollected>,
+) {
+    let Some(last_touched) = last_touched else {
+        return;
+    };
+
+    for (entity, transform, power_up) in &power_up_query {
+        let collector = ball_query.iter().find(|(_, ball)| {
+            collide(
+                ball.translation,
+                ball.scale.truncate(),
+                transform.translation,
+                transform.scale.truncate(),
+            )
+            .is_some()
+        });
+        if let Some((ball, _)) = collector {
+            commands.entity(entity).despawn_recursive();
+            collected_events.send(PowerUpCollected {
+                kind: power_up.kind,
+                collector_side: last_touched.0,
+                ball,
+            });
+        }
+    }
+}
+
+// Grow makes the collector's paddle taller, Shrink makes their opponent's shorter
+pub(crate) fn apply_paddle_size_effects(
+    mut commands: Commands,
+    config: Res<PongConfig>,
+    arena: Res<Arena>,
+    mut collected_events: EventReader<PowerUpCollected>,
+    mut paddle_query: Query<(Entity, &Side, &mut Transform, Option<&Half>, Option<&ActiveEffect>), With<Paddle>>,
+) {
+    for event in collected_events.iter() {
+        let Some(scale) = event.kind.scale() else {
+            continue;
+        };
+        let side = match event.kind {
+            PowerUpKind::Shrink => event.collector_side.opponent(),
+            _ => event.collector_side,
+        };
+        for (paddle, paddle_side, mut paddle_transform, half, effect) in &mut paddle_query {
+            if *paddle_side != side {
+                continue;
+            }
+
+            // a new effect replaces the old one rather than stacking on top of it
+            let original_height = effect.map_or(paddle_transform.scale.y, |effect| effect.original_height);
+            paddle_transform.scale.y = original_height * scale;
+            clamp_paddle(&arena, &config, half.copied(), &mut paddle_transform);
+            commands.entity(paddle).insert(ActiveEffect {
+                kind: event.kind,
+                timer: Timer::from_seconds(POWER_UP_DURATION, TimerMode::Once),
+                original_height,
+            });
+        }
+    }
+}
+
+// Turbo speeds up the ball that collected it, on top of however much the rally has sped it up.
+// It's only done the once, another turbo for a ball that already has one does nothing
+pub(crate) fn apply_turbo(
+    mut commands: Commands,
+    config: Res<PongConfig>,
+    mut collected_events: EventReader<PowerUpCollected>,
+    mut ball_query: Query<&mut Velocity, (With<Ball>, Without<Turbo>)>,
+) {
+    for event in collected_events.iter() {
+        if event.kind != PowerUpKind::Turbo {
+            continue;
+        }
+        let Ok(mut velocity) = ball_query.get_mut(event.ball) else {
+            continue;
+        };
+
+        let speed = velocity.length();
+        if speed == 0.0 {
+            continue;
+        }
+        let speed_up = TURBO_SPEED_UP.min(config.max_ball_speed / speed).max(1.0);
+        velocity.0 *= speed_up;
+        commands.entity(event.ball).insert(Turbo(speed_up));
+    }
+}
+
+// Multi-ball splits the ball that collected it into three: it carries on as it was,
+// and the other two head off either side of it, turbo and all
+pub(crate) fn apply_multi_ball(
+    mut commands: Commands,
+    config: Res<PongConfig>,
+    mut collected_events: EventReader<PowerUpCollected>,
+    ball_query: Query<(&Transform, &Velocity, Option<&Turbo>), With<Ball>>,
+) {
+    let mut ball_count = ball_query.iter().count();
+    for event in collected_events.iter() {
+        if event.kind != PowerUpKind::MultiBall {
+            continue;
+        }
+        let Ok((transform, velocity, turbo)) = ball_query.get(event.ball) else {
+            continue;
+        };
+
+        for angle in [MULTI_BALL_SPREAD, -MULTI_BALL_SPREAD] {
+            if ball_count >= config.max_balls {
+                break;
+            }
+            let rotated = Vec2::from_angle(angle.to_radians()).rotate(velocity.0);
+            let velocity = constrain_velocity(rotated, config.max_ball_speed, config.min_horizontal_speed_fraction);
+            let ball = spawn_ball(&mut commands, &config, transform.translation, velocity);
+            if let Some(turbo) = turbo {
+                commands.entity(ball).insert(*turbo);
+            }
+            ball_count += 1;
+        }
+    }
+}
+
+// Puts paddles back to their own height once their effect runs out
+pub(crate) fn wear_off_effects(
+    mut commands: Commands,
+    config: Res<PongConfig>,
+    arena: Res<Arena>,
+    step: Res<PhysicsStep>,
+    mut paddle_query: Query<(Entity, &mut Transform, Option<&Half>, &mut ActiveEffect)>,
+) {
+    for (paddle, mut transform, half, mut effect) in &mut paddle_query {
+        effect.timer.tick(Duration::from_secs_f32(step.0));
+        if effect.timer.finished() {
+            transform.scale.y = effect.original_height;
+            clamp_paddle(&arena, &config, half.copied(), &mut transform);
+            commands.entity(paddle).remove::<ActiveEffect>();
+        }
+    }
+}
+
+// A paddle that has just grown could be poking through a wall, so bring it back inside
+fn clamp_paddle(arena: &Arena, config: &PongConfig, half: Option<Half>, paddle_transform: &mut Transform) {
+    let (lower_bound, upper_bound) =
+        paddle_bounds(arena, config, PaddleAxis::Vertical, half, paddle_transform.scale.y);
+    paddle_transform.translation.y = paddle_transform.translation.y.clamp(lower_bound, upper_bound);
+}
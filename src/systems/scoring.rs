@@ -0,0 +1,531 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use std::{cmp::Ordering, time::Duration};
+
+use crate::{
+    arena::*,
+    components::*,
+    config::*,
+    events::*,
+    network::*,
+    resources::*,
+    stats::*,
+    systems::{movement::*, power_ups::*, serve::*},
+};
+
+// How far outside the arena a ball can get before the watchdog gives up on it
+const BALL_ESCAPE_MARGIN: f32 = 50.0;
+// How long a ball can stay on one side of the center line before the watchdog decides it's stuck
+const BALL_STUCK_TIME: f32 = 20.0;
+// Runs after the collision check, which moves a ball that bounced back in front of the paddle,
+// so a ball clipping a paddle near the goal line can't both bounce and score in the same step.
+// Every ball that leaves the arena scores on its own. Four-player matches lose lives instead,
+// and wall practice has misses
+pub(crate) fn check_for_goals(
+    arena: Res<Arena>,
+    settings: Res<MatchSettings>,
+    mut scoreboard: ResMut<Scoreboard>,
+    ball_query: Query<(Entity, &Transform, &Velocity), With<Ball>>,
+    mut goal_events: EventWriter<GoalEvent>,
+) {
+    if settings.four_players || settings.wall_practice {
+        return;
+    }
+
+    for (ball, ball_transform, velocity) in &ball_query {
+        let ball_x = ball_transform.translation.x;
+
+        let scorer = if ball_x < arena.left {
+            Side::Right
+        } else if ball_x > arena.right {
+            Side::Left
+        } else {
+            continue;
+        };
+
+        *scoreboard.score_mut(scorer) += 1;
+        goal_events.send(GoalEvent {
+            scorer,
+            new_score: (scoreboard.left_score, scoreboard.right_score),
+            ball,
+            speed: velocity.length(),
+        });
+    }
+}
+
+// The four-player `check_for_goals`: a ball out past any wall costs that wall's player a life.
+// The walls of players already out are solid, so a ball only gets through one of theirs by
+// going wrong, and that's left to the watchdog
+pub(crate) fn check_for_lost_lives(
+    arena: Res<Arena>,
+    settings: Res<MatchSettings>,
+    mut lives: ResMut<Lives>,
+    ball_query: Query<(Entity, &Transform, &Velocity), With<Ball>>,
+    mut life_events: EventWriter<LifeLost>,
+) {
+    if !settings.four_players {
+        return;
+    }
+
+    for (ball, ball_transform, velocity) in &ball_query {
+        let position = ball_transform.translation.truncate();
+        let seat = if position.x < arena.left {
+            Seat::Left
+        } else if position.x > arena.right {
+            Seat::Right
+        } else if position.y > arena.top {
+            Seat::Top
+        } else if position.y < arena.bottom {
+            Seat::Bottom
+        } else {
+            continue;
+        };
+        if lives.lives(seat) == 0 {
+            continue;
+        }
+
+        *lives.lives_mut(seat) -= 1;
+        life_events.send(LifeLost {
+            seat,
+            lives_left: lives.lives(seat),
+            ball,
+            speed: velocity.length(),
+        });
+    }
+}
+
+// Counts the wall practice player's paddle hits towards their streak
+pub(crate) fn count_streak(
+    settings: Res<MatchSettings>,
+    mut streak: ResMut<Streak>,
+    mut paddle_hits: EventReader<PaddleHitEvent>,
+) {
+    let hits = paddle_hits.iter().count();
+    if !settings.wall_practice || hits == 0 {
+        return;
+    }
+
+    streak.hits += hits;
+    streak.best = streak.best.max(streak.hits);
+}
+
+// The wall practice `check_for_goals`. The right wall is solid, so it's only the left one a ball can get
+// past, which ends the streak rather than scoring
+pub(crate) fn check_for_misses(
+    arena: Res<Arena>,
+    settings: Res<MatchSettings>,
+    mut streak: ResMut<Streak>,
+    ball_query: Query<(Entity, &Transform, &Velocity), With<Ball>>,
+    mut miss_events: EventWriter<BallMissed>,
+) {
+    if !settings.wall_practice {
+        return;
+    }
+
+    for (ball, ball_transform, velocity) in &ball_query {
+        if ball_transform.translation.x < arena.left {
+            miss_events.send(BallMissed {
+                streak: std::mem::take(&mut streak.hits),
+                ball,
+                speed: velocity.length(),
+            });
+        }
+    }
+}
+
+// Removes the balls that went in, and once the last one is gone serves a new one.
+// In a four-player match it's served at whoever just lost a life, if they're still in,
+// and in wall practice it's always served at the player
+pub(crate) fn reset_ball_after_goal(
+    mut commands: Commands,
+    mut rally: ResMut<Rally>,
+    config: Res<PongConfig>,
+    settings: Res<MatchSettings>,
+    scoreboard: Res<Scoreboard>,
+    match_score: Res<MatchScore>,
+    lives: Res<Lives>,
+    mut serve_timer: ResMut<ServeTimer>,
+    ball_query: Query<Entity, With<Ball>>,
+    mut turbo_query: Query<(Entity, &mut Velocity, &Turbo)>,
+    mut goal_events: EventReader<GoalEvent>,
+    mut life_events: EventReader<LifeLost>,
+    mut miss_events: EventReader<BallMissed>,
+) {
+    let mut balls_scored = 0;
+    let mut last_scorer = None;
+    for event in goal_events.iter() {
+        commands.entity(event.ball).despawn_recursive();
+        balls_scored += 1;
+        last_scorer = Some(event.scorer);
+    }
+    let mut last_life_lost = None;
+    for event in life_events.iter() {
+        commands.entity(event.ball).despawn_recursive();
+        balls_scored += 1;
+        last_life_lost = Some(event.seat);
+    }
+    let mut missed = false;
+    for event in miss_events.iter() {
+        commands.entity(event.ball).despawn_recursive();
+        balls_scored += 1;
+        missed = true;
+    }
+
+    // a goal ends every turbo, and slows any other balls still in play back down
+    if balls_scored > 0 {
+        for (ball, mut velocity, turbo) in &mut turbo_query {
+            velocity.0 /= turbo.0;
+            commands.entity(ball).remove::<Turbo>();
+        }
+    }
+
+    if balls_scored == 0 || balls_scored < ball_query.iter().count() {
+        return;
+    }
+
+    // Put a new ball in the middle and serve again
+    let toward = if let Some(scorer) = last_scorer {
+        let mut toward = settings.serve_rule.serve_toward(scorer);
+        if let MatchMode::Games(format) = settings.mode {
+            let (left_score, right_score) = (scoreboard.left_score, scoreboard.right_score);
+            let game_won = match_score.sudden_death
+                || format.game_winner(left_score, right_score, settings.win_by_two).is_some();
+            if game_won {
+                // that won the game, the next one starts by swapping the serve round
+                toward = toward.map(Side::opponent);
+            }
+        }
+        toward.map(Seat::from)
+    } else if missed {
+        Some(Seat::Left)
+    } else {
+        last_life_lost.filter(|seat| lives.lives(*seat) > 0)
+    };
+    spawn_serve(&mut commands, &config, &mut serve_timer, toward);
+    rally.hits = 0;
+}
+
+// Removes balls that have escaped the arena, stopped making sense or stopped making progress,
+// so a physics glitch can't leave the match stuck. Balls that just scored are left to the goal systems
+pub(crate) fn watch_for_stuck_balls(
+    mut commands: Commands,
+    arena: Res<Arena>,
+    config: Res<PongConfig>,
+    step: Res<PhysicsStep>,
+    mut rally: ResMut<Rally>,
+    mut serve_timer: ResMut<ServeTimer>,
+    mut ball_query: Query<
+        (Entity, &Transform, &Velocity, &mut BallWatchdog),
+        (With<Ball>, Without<AwaitingServe>),
+    >,
+    waiting_ball_query: Query<(), With<AwaitingServe>>,
+    mut goal_events: EventReader<GoalEvent>,
+    mut life_events: EventReader<LifeLost>,
+    mut miss_events: EventReader<BallMissed>,
+    mut reset_events: EventWriter<BallResetEvent>,
+) {
+    let scored: Vec<Entity> = goal_events
+        .iter()
+        .map(|event| event.ball)
+        .chain(life_events.iter().map(|event| event.ball))
+        .chain(miss_events.iter().map(|event| event.ball))
+        .collect();
+    let mut balls_left = waiting_ball_query.iter().count();
+    let mut balls_reset = 0;
+
+    for (ball, transform, velocity, mut watchdog) in &mut ball_query {
+        if scored.contains(&ball) {
+            continue;
+        }
+
+        let position = transform.translation.truncate();
+        let on_right = position.x >= 0.0;
+        if on_right == watchdog.on_right {
+            watchdog.time_on_side += step.0;
+        } else {
+            watchdog.on_right = on_right;
+            watchdog.time_on_side = 0.0;
+        }
+
+        let reason = if !position.is_finite() || !velocity.is_finite() || velocity.0 == Vec2::ZERO {
+            Some(BallResetReason::InvalidMotion)
+        } else if position.x < arena.left - BALL_ESCAPE_MARGIN
+            || position.x > arena.right + BALL_ESCAPE_MARGIN
+            || position.y < arena.bottom - BALL_ESCAPE_MARGIN
+            || position.y > arena.top + BALL_ESCAPE_MARGIN
+        {
+            Some(BallResetReason::Escaped)
+        } else if watchdog.time_on_side > BALL_STUCK_TIME {
+            Some(BallResetReason::Stuck)
+        } else {
+            None
+        };
+
+        let Some(reason) = reason else {
+            balls_left += 1;
+            continue;
+        };
+
+        warn!("Resetting a ball: {reason:?} at {position}, moving at {}", velocity.0);
+        commands.entity(ball).despawn_recursive();
+        reset_events.send(BallResetEvent { ball, reason });
+        balls_reset += 1;
+    }
+
+    // Balls that scored aren't counted, if they were the only others left then
+    // `reset_ball_after_goal` won't have served, as this ball was still around
+    if balls_reset > 0 && balls_left == 0 {
+        spawn_serve(&mut commands, &config, &mut serve_timer, None);
+        rally.hits = 0;
+    }
+}
+
+// In games mode, ends the game once a side has enough points, and the match once a side has won
+// enough games. The points are reset between games, but left up at the end of the match.
+// In timed mode, ends the match when the clock runs out, unless it's a tie which plays on until the next goal
+pub(crate) fn check_for_winner(
+    mut scoreboard: ResMut<Scoreboard>,
+    settings: Res<MatchSettings>,
+    match_timer: Res<MatchTimer>,
+    mut match_score: ResMut<MatchScore>,
+    mut state: ResMut<State<GameState>>,
+) {
+    // Several physics steps can run in one frame, so the match may already be over.
+    // Practice matches, wall practice and the demo never end, and four-player matches are won on lives
+    if match_score.winner.is_some()
+        || settings.practice
+        || settings.wall_practice
+        || settings.four_players
+        || *state.current() == GameState::Demo
+    {
+        return;
+    }
+
+    let leader = match scoreboard.left_score.cmp(&scoreboard.right_score) {
+        Ordering::Greater => Some(Side::Left),
+        Ordering::Less => Some(Side::Right),
+        Ordering::Equal => None,
+    };
+
+    match settings.mode {
+        MatchMode::Games(format) => {
+            let game_winner = if match_score.sudden_death {
+                leader
+            } else {
+                format.game_winner(scoreboard.left_score, scoreboard.right_score, settings.win_by_two)
+            };
+            let Some(game_winner) = game_winner else {
+                // level one short of winning, or with win by two any level score after that
+                let one_short = format.points_per_game.saturating_sub(1);
+                if settings.golden_goal && leader.is_none() && scoreboard.left_score >= one_short {
+                    match_score.sudden_death = true;
+                }
+                return;
+            };
+
+            match_score.sudden_death = false;
+            *match_score.games_mut(game_winner) += 1;
+            if match_score.games(game_winner) >= format.games_to_win {
+                match_score.winner = Some(game_winner);
+            } else {
+                scoreboard.left_score = 0;
+                scoreboard.right_score = 0;
+            }
+        }
+        MatchMode::Timed(_) => {
+            if !match_timer.0.finished() {
+                return;
+            }
+            // a tie at the buzzer goes to sudden death, which the next goal ends
+            match_score.sudden_death = leader.is_none();
+            match_score.winner = leader;
+        }
+    }
+
+    if match_score.winner.is_some() {
+        let _ = state.set(GameState::GameOver);
+    }
+}
+
+// Ends a four-player match once only one player has lives left. A timed one also ends when the clock
+// runs out, won by whoever has the most lives, or if that's a tie it goes on until somebody has
+pub(crate) fn check_for_last_player_standing(
+    settings: Res<MatchSettings>,
+    match_timer: Res<MatchTimer>,
+    mut lives: ResMut<Lives>,
+    mut match_score: ResMut<MatchScore>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !settings.four_players || lives.winner.is_some() || settings.practice || *state.current() == GameState::Demo {
+        return;
+    }
+
+    let in_play: Vec<Seat> = lives.in_play().collect();
+    let winner = match (&in_play[..], settings.mode) {
+        ([last], _) => Some(*last),
+        (_, MatchMode::Timed(_)) if match_timer.0.finished() => {
+            let most = in_play.iter().map(|seat| lives.lives(*seat)).max().unwrap_or_default();
+            let leaders: Vec<Seat> = in_play.into_iter().filter(|seat| lives.lives(*seat) == most).collect();
+            // a tie at the buzzer plays on until one of them has the most lives on their own
+            if !match_score.sudden_death && leaders.len() > 1 {
+                match_score.sudden_death = true;
+            }
+            match leaders[..] {
+                [leader] => Some(leader),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+
+    if let Some(winner) = winner {
+        match_score.sudden_death = false;
+        lives.winner = Some(winner);
+        let _ = state.set(GameState::GameOver);
+    }
+}
+
+// Moves the computer's difficulty with the score in rubber band mode, by the `PongConfig::rubber_band_shift`
+// curve for the player's lead over it. Worked out again after every goal, and when the score goes back to nothing
+// for the next game or match. Only a match between a player and the computer has a lead to go by
+pub(crate) fn rubber_band_difficulty(
+    settings: Res<MatchSettings>,
+    config: Res<PongConfig>,
+    scoreboard: Res<Scoreboard>,
+    mut shift: ResMut<DifficultyShift>,
+    paddle_query: Query<(&Side, Option<&CpuControlled>), With<Paddle>>,
+) {
+    if !scoreboard.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    let cpu_sides: Vec<Side> = paddle_query
+        .iter()
+        .filter_map(|(side, cpu_controlled)| cpu_controlled.map(|_| *side))
+        .collect();
+    let player_side = match cpu_sides[..] {
+        [cpu_side] if settings.rubber_band && !settings.four_players => Some(cpu_side.opponent()),
+        _ => None,
+    };
+    let new_shift = player_side.map_or(0.0, |side| {
+        let player_lead = scoreboard.score(side) as i64 - scoreboard.score(side.opponent()) as i64;
+        config.rubber_band_shift(player_lead)
+    });
+    if shift.0 != new_shift {
+        shift.0 = new_shift;
+    }
+}
+
+// Sets the clock going at the start of a timed match
+pub(crate) fn start_match_timer(settings: Res<MatchSettings>, mut match_timer: ResMut<MatchTimer>) {
+    match_timer.0 = match settings.mode {
+        MatchMode::Timed(timed) => Timer::from_seconds(timed.duration, TimerMode::Once),
+        MatchMode::Games(_) => Timer::default(),
+    };
+}
+
+pub(crate) fn tick_match_timer(
+    settings: Res<MatchSettings>,
+    step: Res<PhysicsStep>,
+    mut match_timer: ResMut<MatchTimer>,
+    waiting_ball_query: Query<(), With<AwaitingServe>>,
+) {
+    let MatchMode::Timed(timed) = settings.mode else {
+        return;
+    };
+    if !timed.clock_runs_during_serve && !waiting_ball_query.is_empty() {
+        return;
+    }
+
+    match_timer.0.tick(Duration::from_secs_f32(step.0));
+}
+
+// Everything that has to be put back to start a match over from nothing
+#[derive(SystemParam)]
+pub(crate) struct MatchReset<'w, 's> {
+    pub(crate) commands: Commands<'w, 's>,
+    scoreboard: ResMut<'w, Scoreboard>,
+    match_score: ResMut<'w, MatchScore>,
+    rally: ResMut<'w, Rally>,
+    match_stats: ResMut<'w, MatchStats>,
+    lives: ResMut<'w, Lives>,
+    streak: ResMut<'w, Streak>,
+    config: Res<'w, PongConfig>,
+    arena: Res<'w, Arena>,
+    serve_timer: ResMut<'w, ServeTimer>,
+    power_up_timer: ResMut<'w, PowerUpTimer>,
+    match_entity_query: Query<'w, 's, Entity, With<MatchEntity>>,
+    paddle_query: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static mut Transform,
+            &'static mut PaddleVelocity,
+            &'static mut PaddleDash,
+            &'static PaddleAxis,
+            Option<&'static Half>,
+            Option<&'static ActiveEffect>,
+        ),
+        With<Paddle>,
+    >,
+}
+
+impl MatchReset<'_, '_> {
+    pub(crate) fn reset(&mut self) {
+        self.scoreboard.left_score = 0;
+        self.scoreboard.right_score = 0;
+        *self.match_score = MatchScore::default();
+        self.rally.hits = 0;
+        *self.match_stats = MatchStats::default();
+        *self.lives = Lives::default();
+        *self.streak = Streak::default();
+
+        for entity in &self.match_entity_query {
+            self.commands.entity(entity).despawn_recursive();
+        }
+        spawn_serve(&mut self.commands, &self.config, &mut self.serve_timer, None);
+        self.power_up_timer.reset();
+
+        for (paddle, mut paddle_transform, mut velocity, mut dash, axis, half, effect) in &mut self.paddle_query {
+            // back where it started along its own axis, which for a doubles partner is the middle of its half
+            paddle_transform.translation[axis.index()] = paddle_start(&self.arena, *axis, half.copied());
+            // a paddle with inertia, or one partway through a dash, would otherwise carry on from before
+            velocity.0 = 0.0;
+            *dash = PaddleDash::default();
+            if let Some(effect) = effect {
+                paddle_transform.scale.y = effect.original_height;
+                self.commands.entity(paddle).remove::<ActiveEffect>();
+            }
+        }
+    }
+}
+
+// Starts the match over from nothing, with the restart key at any point in a match,
+// or with Enter once it's over. Esc on the game over screen resets it too, but goes back to the menu.
+// Online, only the host restarts, the client and spectators can only leave
+pub(crate) fn restart_match(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    touches: Res<Touches>,
+    windows: Res<Windows>,
+    network_role: Option<Res<NetworkRole>>,
+    mut state: ResMut<State<GameState>>,
+    mut match_reset: MatchReset,
+) {
+    let follows_host = network_role.is_some_and(|role| role.follows_host());
+    let restart = keyboard_input.just_pressed(bindings.restart) && !follows_host;
+    let rematch = (keyboard_input.just_pressed(KeyCode::Return) || touch_button_tapped(&touches, &windows))
+        && !follows_host;
+    match state.current() {
+        GameState::Playing if restart => state.overwrite_restart(),
+        // back to playing and not to the paused match underneath
+        GameState::Paused if restart => state.overwrite_replace(GameState::Playing).unwrap(),
+        GameState::GameOver if restart || rematch => state.overwrite_set(GameState::Playing).unwrap(),
+        GameState::GameOver if keyboard_input.just_pressed(KeyCode::Escape) => {
+            state.overwrite_set(GameState::Menu).unwrap()
+        }
+        _ => return,
+    }
+
+    match_reset.reset();
+}
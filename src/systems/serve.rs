@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+use rand::Rng;
+use std::time::Duration;
+
+use crate::{components::*, config::*, resources::*};
+
+// Just what the simulation needs, `PongRenderPlugin` gives each ball its mesh
+#[derive(Bundle)]
+struct BallBundle {
+    spatial: SpatialBundle,
+    ball: Ball,
+    velocity: Velocity,
+    watchdog: BallWatchdog,
+    match_entity: MatchEntity,
+}
+
+impl BallBundle {
+    fn new(config: &PongConfig, position: Vec3, velocity: Vec2) -> BallBundle {
+        BallBundle {
+            spatial: SpatialBundle::from_transform(
+                Transform::from_translation(position).with_scale(config.ball_size.extend(1.0)),
+            ),
+            ball: Ball,
+            velocity: Velocity(velocity),
+            watchdog: BallWatchdog {
+                on_right: position.x >= 0.0,
+                time_on_side: 0.0,
+            },
+            match_entity: MatchEntity,
+        }
+    }
+}
+
+// Puts a still ball on the spawn point and starts counting down to serving it
+pub(crate) fn spawn_serve(
+    commands: &mut Commands,
+    config: &PongConfig,
+    serve_timer: &mut ServeTimer,
+    toward: Option<Seat>,
+) -> Entity {
+    serve_timer.0.reset();
+    // a new rally, nobody has hit this ball yet
+    commands.remove_resource::<LastTouched>();
+    let ball = spawn_ball(commands, config, config.ball_spawn_point(), Vec2::ZERO);
+    commands.entity(ball).insert(AwaitingServe(toward));
+    ball
+}
+
+// A ball already in play, on its way from `position`
+pub(crate) fn spawn_ball(commands: &mut Commands, config: &PongConfig, position: Vec3, velocity: Vec2) -> Entity {
+    commands.spawn(BallBundle::new(config, position, velocity)).id()
+}
+
+// Counts down to the next serve, then sends the waiting ball off. A four-player match serves
+// at a random player still in when it isn't told who to serve at
+pub(crate) fn count_down_serve(
+    mut commands: Commands,
+    config: Res<PongConfig>,
+    settings: Res<MatchSettings>,
+    lives: Res<Lives>,
+    step: Res<PhysicsStep>,
+    mut serve_timer: ResMut<ServeTimer>,
+    mut rng: ResMut<GameRng>,
+    mut ball_query: Query<(Entity, &mut Velocity, &AwaitingServe)>,
+) {
+    if ball_query.is_empty() {
+        return;
+    }
+
+    // counted in physics steps, so a replay serves on the same step as the match it recorded
+    serve_timer.0.tick(Duration::from_secs_f32(step.0));
+    if !serve_timer.0.finished() {
+        return;
+    }
+
+    for (ball, mut velocity, awaiting_serve) in &mut ball_query {
+        let mut toward = awaiting_serve.0;
+        if settings.four_players && toward.is_none() {
+            let in_play: Vec<Seat> = lives.in_play().collect();
+            toward = (!in_play.is_empty()).then(|| in_play[rng.rng.gen_range(0..in_play.len())]);
+        }
+        velocity.0 = serve_velocity(&config, &mut rng, toward, settings.gravity.is_some());
+        commands.entity(ball).remove::<AwaitingServe>();
+    }
+}
+
+// A fresh velocity for the ball, used for the opening serve and after every goal
+fn serve_velocity(config: &PongConfig, rng: &mut GameRng, toward: Option<Seat>, upward: bool) -> Vec2 {
+    serve_direction(&mut rng.rng, toward, config.max_serve_angle.to_radians(), upward) * config.ball_speed
+}
+
+/// Picks a serve towards the given seat (or the left or right at random), at most `max_angle` radians away
+/// from straight at it.
+/// Building the vector from an angle means it is always unit length,
+/// unlike normalizing two random components which can both land on zero.
+/// An upward serve, for when gravity will pull it down, is lobbed at least a quarter of `max_angle` up
+pub fn serve_direction(rng: &mut impl Rng, toward: Option<Seat>, max_angle: f32, upward: bool) -> Vec2 {
+    let max_angle = max_angle.clamp(0.0, std::f32::consts::FRAC_PI_2);
+    let angle = if upward {
+        rng.gen_range(max_angle / 4.0..=max_angle)
+    } else {
+        rng.gen_range(-max_angle..=max_angle)
+    };
+    let toward = toward.unwrap_or_else(|| if rng.gen_bool(0.5) { Seat::Right } else { Seat::Left });
+    match toward {
+        Seat::Left => Vec2::new(-angle.cos(), angle.sin()),
+        Seat::Right => Vec2::new(angle.cos(), angle.sin()),
+        Seat::Top => Vec2::new(angle.sin(), angle.cos()),
+        Seat::Bottom => Vec2::new(angle.sin(), -angle.cos()),
+    }
+}
+
+// Seeds the serves from the config, unless a seed was already picked, and logs the seed
+// so a good match can be played again with the same one
+pub(crate) fn seed_rng(config: Res<PongConfig>, mut rng: ResMut<GameRng>) {
+    if let (Some(seed), false) = (config.seed, rng.chosen) {
+        *rng = GameRng::seeded(seed);
+    }
+    info!("Serves are seeded with {}", rng.seed());
+}
@@ -0,0 +1,259 @@
+use bevy::{prelude::*, window::PresentMode};
+use serde::{Deserialize, Serialize};
+
+use crate::{arena::CenterLine, components::*, config::*};
+
+// How much bigger high contrast makes the ball, and how much wider the dashes of the dotted line
+const HIGH_CONTRAST_BALL_SCALE: f32 = 1.5;
+const HIGH_CONTRAST_LINE_SCALE: f32 = 2.0;
+
+/// Player controlled display options
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    /// The set of colors the game is drawn in
+    pub theme: ThemePreset,
+    /// Draws everything in white on black, whatever the theme, with a bigger ball and a bolder
+    /// dotted line. The ball's size is part of the `PongConfig`, so it's best changed between matches
+    pub high_contrast: bool,
+    /// Borderless fullscreen rather than a window, toggled with F11
+    pub fullscreen: bool,
+    /// How frames are put on screen, trading tearing for latency. `None` leaves it to the `PongConfig`
+    pub present_mode: Option<PresentMode>,
+}
+
+/// The present modes the settings menu goes through. Browsers can't present immediately
+#[cfg(not(target_arch = "wasm32"))]
+pub const PRESENT_MODES: &[PresentMode] = &[
+    PresentMode::AutoVsync,
+    PresentMode::AutoNoVsync,
+    PresentMode::Fifo,
+    PresentMode::Immediate,
+];
+#[cfg(target_arch = "wasm32")]
+pub const PRESENT_MODES: &[PresentMode] = &[PresentMode::AutoVsync, PresentMode::AutoNoVsync, PresentMode::Fifo];
+
+impl DisplaySettings {
+    /// The present mode picked in the settings, or else the config's. One that isn't in `PRESENT_MODES`
+    /// could panic where it isn't supported, so `AutoNoVsync` is used instead
+    pub fn present_mode(&self, config: &PongConfig) -> PresentMode {
+        let present_mode = self.present_mode.unwrap_or(config.present_mode);
+        if PRESENT_MODES.contains(&present_mode) {
+            present_mode
+        } else {
+            PresentMode::AutoNoVsync
+        }
+    }
+}
+
+/// The present mode after `present_mode` in the settings menu, wrapping around
+pub fn next_present_mode(present_mode: PresentMode) -> PresentMode {
+    let index = PRESENT_MODES.iter().position(|mode| *mode == present_mode);
+    PRESENT_MODES[index.map_or(0, |index| (index + 1) % PRESENT_MODES.len())]
+}
+
+/// The set of colors the game is drawn in, picked from the settings menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ThemePreset {
+    /// The colors from the `PongConfig`
+    #[default]
+    Classic,
+    /// Bright on a near-black background
+    Dark,
+    /// Glowing colors on deep purple
+    Neon,
+    /// Blue and orange, for red-green color blindness that's weak on green
+    Deuteranopia,
+    /// Sky blue and yellow on a dark background, for red-green color blindness that's weak on red
+    Protanopia,
+    /// Vermillion and near-black, for blue-yellow color blindness
+    Tritanopia,
+}
+
+impl ThemePreset {
+    /// The preset after this one in the settings menu, wrapping around
+    pub fn next(self) -> ThemePreset {
+        match self {
+            ThemePreset::Classic => ThemePreset::Dark,
+            ThemePreset::Dark => ThemePreset::Neon,
+            ThemePreset::Neon => ThemePreset::Deuteranopia,
+            ThemePreset::Deuteranopia => ThemePreset::Protanopia,
+            ThemePreset::Protanopia => ThemePreset::Tritanopia,
+            ThemePreset::Tritanopia => ThemePreset::Classic,
+        }
+    }
+}
+
+/// The colors everything is currently drawn in, kept up to date with the `DisplaySettings`
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub background: Color,
+    pub paddle: Color,
+    pub ball: Color,
+    pub wall: Color,
+    pub text: Color,
+    /// A ring drawn around the ball, so its color isn't the only thing marking it out
+    pub ball_outline: Option<Color>,
+}
+
+impl Theme {
+    pub fn new(preset: ThemePreset, config: &PongConfig) -> Theme {
+        match preset {
+            ThemePreset::Classic => Theme {
+                background: config.background_color,
+                paddle: config.paddle_color,
+                ball: config.ball_color,
+                wall: config.wall_color,
+                text: config.text_color,
+                ball_outline: None,
+            },
+            ThemePreset::Dark => Theme {
+                background: Color::rgb(0.07, 0.07, 0.08),
+                paddle: Color::rgb(0.45, 0.65, 1.0),
+                ball: Color::rgb(1.0, 0.5, 0.5),
+                wall: Color::rgb(0.3, 0.3, 0.33),
+                text: Color::rgb(0.9, 0.9, 0.95),
+                ball_outline: None,
+            },
+            ThemePreset::Neon => Theme {
+                background: Color::rgb(0.05, 0.0, 0.1),
+                paddle: Color::rgb(0.0, 1.0, 0.9),
+                ball: Color::rgb(1.0, 0.1, 0.8),
+                wall: Color::rgb(0.5, 0.1, 1.0),
+                text: Color::rgb(0.3, 1.0, 0.4),
+                ball_outline: None,
+            },
+            // from the Okabe-Ito palette, which stays distinguishable with each kind of color blindness
+            ThemePreset::Deuteranopia => Theme {
+                background: Color::rgb(0.95, 0.95, 0.95),
+                paddle: Color::rgb(0.0, 0.45, 0.7),
+                ball: Color::rgb(0.9, 0.6, 0.0),
+                wall: Color::rgb(0.6, 0.6, 0.6),
+                text: Color::rgb(0.0, 0.45, 0.7),
+                ball_outline: Some(Color::BLACK),
+            },
+            ThemePreset::Protanopia => Theme {
+                background: Color::rgb(0.1, 0.1, 0.12),
+                paddle: Color::rgb(0.34, 0.71, 0.91),
+                ball: Color::rgb(0.94, 0.89, 0.26),
+                wall: Color::rgb(0.4, 0.4, 0.42),
+                text: Color::rgb(0.34, 0.71, 0.91),
+                ball_outline: Some(Color::WHITE),
+            },
+            ThemePreset::Tritanopia => Theme {
+                background: Color::rgb(0.95, 0.95, 0.95),
+                paddle: Color::rgb(0.15, 0.15, 0.15),
+                ball: Color::rgb(0.84, 0.37, 0.0),
+                wall: Color::rgb(0.6, 0.6, 0.6),
+                text: Color::rgb(0.15, 0.15, 0.15),
+                ball_outline: Some(Color::BLACK),
+            },
+        }
+    }
+
+    /// The high contrast colors, white on black. The paddles are yellow so the menu's highlight,
+    /// which is in the paddles' color, still stands out from the rest of the text
+    pub fn high_contrast() -> Theme {
+        Theme {
+            background: Color::BLACK,
+            paddle: Color::YELLOW,
+            ball: Color::WHITE,
+            wall: Color::WHITE,
+            text: Color::WHITE,
+            ball_outline: None,
+        }
+    }
+}
+
+// The classic colors of the default config, until the real config is loaded
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::new(ThemePreset::Classic, &PongConfig::default())
+    }
+}
+
+// Which of the theme's colors an entity is drawn in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ThemeRole {
+    Background,
+    Wall,
+    Text,
+}
+
+// Marks what `apply_theme` recolors, for what is spawned once and stays on screen.
+// The paddles, balls and menu items are colored every frame anyway, as they change for other reasons too
+#[derive(Component, Debug, Clone, Copy)]
+pub(crate) struct Themed(pub(crate) ThemeRole);
+
+// Works out the theme's colors when the display settings change. It first runs once the config
+// is loaded, which the classic colors come from
+pub(crate) fn update_theme(display: Res<DisplaySettings>, config: Res<PongConfig>, mut theme: ResMut<Theme>) {
+    if !display.is_changed() {
+        return;
+    }
+
+    let new_theme = if display.high_contrast {
+        Theme::high_contrast()
+    } else {
+        Theme::new(display.theme, &config)
+    };
+    if *theme != new_theme {
+        *theme = new_theme;
+    }
+}
+
+// Recolors everything themed when the theme changes, and anything newly spawned in case it
+// was given the config's colors rather than the theme's
+pub(crate) fn apply_theme(
+    theme: Res<Theme>,
+    mut themed_query: Query<(&Themed, ChangeTrackers<Themed>, Option<&mut Sprite>, Option<&mut Text>)>,
+) {
+    for (themed, tracker, sprite, text) in &mut themed_query {
+        if !theme.is_changed() && !tracker.is_added() {
+            continue;
+        }
+
+        let color = match themed.0 {
+            ThemeRole::Background => theme.background,
+            ThemeRole::Wall => theme.wall,
+            ThemeRole::Text => theme.text,
+        };
+        if let Some(mut sprite) = sprite {
+            sprite.color = color;
+        }
+        if let Some(mut text) = text {
+            for section in &mut text.sections {
+                section.style.color = color;
+            }
+        }
+    }
+}
+
+// Makes the ball bigger and the dotted line bolder while high contrast is on, and puts them back
+// once it's off. The config is changed too, so the balls served from then on are the same size
+pub(crate) fn apply_high_contrast_sizes(
+    display: Res<DisplaySettings>,
+    mut config: ResMut<PongConfig>,
+    mut enlarged: Local<bool>,
+    mut ball_query: Query<&mut Transform, With<Ball>>,
+    mut line_query: Query<&mut Transform, (With<CenterLine>, Without<Ball>)>,
+) {
+    if display.high_contrast == *enlarged {
+        return;
+    }
+    *enlarged = display.high_contrast;
+
+    let (ball_scale, line_scale) = if display.high_contrast {
+        (HIGH_CONTRAST_BALL_SCALE, HIGH_CONTRAST_LINE_SCALE)
+    } else {
+        (1.0 / HIGH_CONTRAST_BALL_SCALE, 1.0 / HIGH_CONTRAST_LINE_SCALE)
+    };
+    config.ball_size *= ball_scale;
+    config.center_line_size.x *= line_scale;
+    for mut transform in &mut ball_query {
+        transform.scale = config.ball_size.extend(1.0);
+    }
+    for mut transform in &mut line_query {
+        transform.scale.x = config.center_line_size.x;
+    }
+}
@@ -0,0 +1,1366 @@
+use bevy::{
+    asset::LoadState,
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    window::{WindowFocused, WindowResized},
+};
+use rand::Rng;
+use std::f32::consts::TAU;
+
+use crate::{
+    components::*,
+    config::*,
+    events::*,
+    network::*,
+    resources::*,
+    skins::*,
+    stats::*,
+    systems::{movement::*, power_ups::*},
+    theme::*,
+};
+
+#[cfg(not(any(feature = "embedded_font", target_arch = "wasm32")))]
+const FONT_PATH: &str = "fonts/FiraMono-Medium.ttf";
+// The same font, built into the binary so there's always one to fall back on
+const EMBEDDED_FONT: &[u8] = include_bytes!("../assets/fonts/FiraMono-Medium.ttf");
+const SERVE_COUNTDOWN_FROM: f32 = 3.0;
+pub(crate) const SCOREBOARD_TEXT_PADDING: f32 = 5.0;
+// The window size the text and its padding are sized for, they're scaled up or down with the window from it
+const UI_REFERENCE_SIZE: Vec2 = Vec2::new(1280.0, 720.0);
+// How many times a second the scores flash during sudden death
+const SUDDEN_DEATH_FLASH_RATE: f32 = 2.0;
+// How much of the frozen match shows through the game over screen, from 0 to 1
+const GAME_OVER_OVERLAY_OPACITY: f32 = 0.8;
+// How wide the stats panel's labels are, in characters of the monospaced font
+const STATS_LABEL_WIDTH: usize = 15;
+// How long the camera shakes for, in seconds
+const SHAKE_DURATION: f32 = 0.25;
+// A paddle hit at least this fraction of `PongConfig::max_ball_speed` shakes the camera too
+const HARD_HIT_FRACTION: f32 = 0.8;
+// How a paddle hit squashes the paddle, as its width and height, and the ball, across and along its path
+const SQUASH: Vec2 = Vec2::new(1.15, 0.85);
+// How long the paddle and ball take to ease back into shape, in seconds
+const SQUASH_DURATION: f32 = 0.15;
+// How much wider than the ball its outline is, which makes the ring's thickness
+const BALL_OUTLINE_SCALE: f32 = 1.3;
+// How long a paddle takes to fade back from `PongConfig::paddle_flash_color` after a hit, in seconds
+const FLASH_DURATION: f32 = 0.2;
+// The color a ball reaches at `PongConfig::max_ball_speed`
+const HOT_BALL_COLOR: Color = Color::rgb(1.0, 1.0, 0.7);
+// How thick a paddle's dash cooldown bar is, and how far it sits out from the paddle
+const DASH_BAR_WIDTH: f32 = 4.0;
+const DASH_BAR_GAP: f32 = 4.0;
+/// The font all the text is set in
+#[derive(Resource)]
+pub struct UiFont(pub Handle<Font>);
+
+// Marks the score text entities, so queries for them don't also match the paddles
+// which carry the same `Side`
+#[derive(Component)]
+pub(crate) struct ScoreText;
+
+// The half of the window each score is centered in, also with the `Side`
+#[derive(Component)]
+pub(crate) struct ScoreColumn;
+
+// Each player's lives in a four-player match, by their wall, shown instead of the scores
+#[derive(Component)]
+pub(crate) struct LivesText(Seat);
+
+// The streak in wall practice, where it's shown instead of the scores
+#[derive(Component)]
+pub(crate) struct StreakText;
+
+// How well the computer is playing in rubber band mode, so it's no secret when it eases off
+#[derive(Component)]
+pub(crate) struct CpuLevelText;
+
+// How the connection is going in an online match, until the other player is there.
+// A spectator is reminded it's only watching
+#[derive(Component)]
+pub(crate) struct NetworkText;
+
+// The mesh every ball shares, given to each new ball's `BallVisual` once it's spawned.
+// Each ball has a material of its own, as they change color with their own speed,
+// but they share the one for the outline the color blind themes ring them with
+#[derive(Resource)]
+pub(crate) struct BallAssets {
+    mesh: Mesh2dHandle,
+    outline_material: Handle<ColorMaterial>,
+}
+
+// The ball's mesh, as a child of the ball so it can be squashed without changing the ball's own size
+#[derive(Component)]
+pub(crate) struct BallVisual;
+
+// A slightly bigger disc behind a ball's `BallVisual`, showing round its edge as a ring.
+// Only visible with a theme that has a `Theme::ball_outline`
+#[derive(Component)]
+pub(crate) struct BallOutline;
+
+// Eases a paddle or ball back from being squashed. Only what's drawn changes, a paddle's
+// `Sprite::custom_size` and the scale of a ball's `BallVisual`, so the sizes used for collisions
+// and by the paddle size power-ups are left alone
+#[derive(Component)]
+pub(crate) struct ScaleTween {
+    from: Vec2,
+    to: Vec2,
+    timer: Timer,
+}
+
+impl ScaleTween {
+    fn squash(from: Vec2) -> ScaleTween {
+        ScaleTween {
+            from,
+            to: Vec2::ONE,
+            timer: Timer::from_seconds(SQUASH_DURATION, TimerMode::Once),
+        }
+    }
+
+    // Eases out, so most of the way back happens straight after the hit
+    fn scale(&self) -> Vec2 {
+        let t = 1.0 - (1.0 - self.timer.percent()).powi(2);
+        self.from.lerp(self.to, t)
+    }
+}
+
+// The bar beside a paddle that grows back to the paddle's length as it waits to dash again
+#[derive(Component)]
+pub(crate) struct DashBar(Entity);
+
+// A paddle that has just hit the ball, fading back from its flash
+#[derive(Component)]
+pub(crate) struct Flash {
+    timer: Timer,
+}
+
+#[derive(Component)]
+pub(crate) struct WinnerText;
+
+// The two halves of the game over screen, only one of them showing at a time:
+// the result with the rematch prompt, and the match's stats
+#[derive(Component)]
+pub(crate) struct ResultPanel;
+
+#[derive(Component)]
+pub(crate) struct StatsPanel;
+
+#[derive(Component)]
+pub(crate) struct PauseText;
+
+#[derive(Component)]
+pub(crate) struct CountdownText;
+
+#[derive(Component)]
+pub(crate) struct MatchClockText;
+
+#[derive(Component)]
+pub(crate) struct RallyText;
+
+// The camera's shake, fading out over `SHAKE_DURATION`. While it lasts the camera is offset from
+// where it was when the shake started, and it's put back exactly there once it ends
+#[derive(Resource, Default)]
+pub(crate) struct CameraShake {
+    magnitude: f32,
+    timer: Timer,
+    origin: Option<Vec3>,
+}
+
+// The serve/pause button for touchscreens, which have no keyboard
+#[derive(Component)]
+pub(crate) struct TouchButton;
+
+// Loads the font from the assets folder
+#[cfg(not(any(feature = "embedded_font", target_arch = "wasm32")))]
+pub(crate) fn load_font(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(UiFont(asset_server.load(FONT_PATH)));
+}
+
+// Takes the font from the binary, with the `embedded_font` feature. Always done on the web,
+// where depending on how the page is hosted the assets folder can 404 and leave the scores invisible
+#[cfg(any(feature = "embedded_font", target_arch = "wasm32"))]
+pub(crate) fn load_font(mut commands: Commands, mut fonts: ResMut<Assets<Font>>) {
+    commands.insert_resource(UiFont(fonts.add(embedded_font())));
+}
+
+/// Switches to the font built into the game if the one in the assets folder can't be loaded,
+/// rather than leaving the text invisible. Text already spawned with the missing font is moved over too
+pub fn fall_back_to_embedded_font(
+    asset_server: Res<AssetServer>,
+    mut fonts: ResMut<Assets<Font>>,
+    mut font: ResMut<UiFont>,
+    mut text_query: Query<&mut Text>,
+) {
+    if asset_server.get_load_state(&font.0) != LoadState::Failed {
+        return;
+    }
+    error!(
+        "Couldn't load the font from {:?}, using the one built into the game",
+        asset_server.get_handle_path(&font.0).map(|path| path.path().to_owned()).unwrap_or_default()
+    );
+
+    let missing = std::mem::replace(&mut font.0, fonts.add(embedded_font()));
+    for mut text in &mut text_query {
+        for section in &mut text.sections {
+            if section.style.font == missing {
+                section.style.font = font.0.clone();
+            }
+        }
+    }
+}
+
+fn embedded_font() -> Font {
+    Font::try_from_bytes(EMBEDDED_FONT.to_vec()).expect("the embedded font is a valid TTF")
+}
+
+pub(crate) fn setup_render(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    font: Res<UiFont>,
+    config: Res<PongConfig>,
+    theme: Res<Theme>,
+){
+    // Camera
+    commands.spawn(Camera2dBundle::default());
+    commands.insert_resource(ClearColor(theme.background));
+
+    // Ball
+    commands.insert_resource(BallAssets {
+        mesh: meshes.add(shape::Circle::default().into()).into(),
+        outline_material: materials.add(ColorMaterial::from(theme.ball_outline.unwrap_or(Color::NONE))),
+    });
+
+    // Scoreboard, each side's score centered over its half of the window.
+    // `letterbox_camera` keeps the halves over the arena's when letterboxing
+    for side in [Side::Left, Side::Right] {
+        let mut position = UiRect {
+            top: Val::Px(SCOREBOARD_TEXT_PADDING),
+            ..default()
+        };
+        match side {
+            Side::Left => position.left = Val::Px(0.0),
+            Side::Right => position.right = Val::Px(0.0),
+        }
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position,
+                        size: Size::new(Val::Percent(50.0), Val::Auto),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                },
+                ScoreColumn,
+                side,
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    TextBundle::from_sections([
+                        // player name
+                        TextSection::new(
+                            "",
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: config.font_size / 2.0,
+                                color: theme.text,
+                            },
+                        ),
+                        TextSection::new(
+                            "0",
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: config.font_size,
+                                color: theme.text,
+                            },
+                        ),
+                        // games won
+                        TextSection::new(
+                            "",
+                            TextStyle {
+                                font: font.0.clone(),
+                                font_size: config.font_size / 2.0,
+                                color: theme.text,
+                            },
+                        ),
+                    ])
+                    .with_text_alignment(TextAlignment::TOP_CENTER),
+                    ScoreText,
+                    side,
+                    Themed(ThemeRole::Text),
+                ));
+            });
+    }
+
+    // Lives, one by each wall and only shown in four-player matches. The top and bottom ones
+    // are off to the side, clear of the match clock and the touch button
+    for seat in Seat::ALL {
+        let position = match seat {
+            Seat::Left => UiRect {
+                left: Val::Px(SCOREBOARD_TEXT_PADDING),
+                top: Val::Percent(45.0),
+                ..default()
+            },
+            Seat::Right => UiRect {
+                right: Val::Px(SCOREBOARD_TEXT_PADDING),
+                top: Val::Percent(45.0),
+                ..default()
+            },
+            Seat::Top => UiRect {
+                left: Val::Percent(20.0),
+                top: Val::Px(SCOREBOARD_TEXT_PADDING),
+                ..default()
+            },
+            Seat::Bottom => UiRect {
+                left: Val::Percent(20.0),
+                bottom: Val::Px(SCOREBOARD_TEXT_PADDING),
+                ..default()
+            },
+        };
+        let mut lives_text = TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.0.clone(),
+                font_size: config.font_size / 2.0,
+                color: theme.text,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position,
+            ..default()
+        });
+        lives_text.visibility.is_visible = false;
+        commands.spawn((lives_text, LivesText(seat), Themed(ThemeRole::Text)));
+    }
+
+    // Streak, over the player's half and only shown in wall practice
+    let mut streak_text = TextBundle::from_section(
+        "",
+        TextStyle {
+            font: font.0.clone(),
+            font_size: config.font_size / 2.0,
+            color: theme.text,
+        },
+    )
+    .with_style(Style {
+        position_type: PositionType::Absolute,
+        position: UiRect {
+            left: Val::Percent(20.0),
+            top: Val::Px(SCOREBOARD_TEXT_PADDING),
+            ..default()
+        },
+        ..default()
+    });
+    streak_text.visibility.is_visible = false;
+    commands.spawn((streak_text, StreakText, Themed(ThemeRole::Text)));
+
+    // The computer's level, over its half and only shown in rubber band mode
+    let mut cpu_level_text = TextBundle::from_section(
+        "",
+        TextStyle {
+            font: font.0.clone(),
+            font_size: config.font_size / 2.0,
+            color: theme.text,
+        },
+    )
+    .with_style(Style {
+        position_type: PositionType::Absolute,
+        position: UiRect {
+            right: Val::Percent(20.0),
+            top: Val::Px(SCOREBOARD_TEXT_PADDING),
+            ..default()
+        },
+        ..default()
+    });
+    cpu_level_text.visibility.is_visible = false;
+    commands.spawn((cpu_level_text, CpuLevelText, Themed(ThemeRole::Text)));
+
+    // Connection status, centered along the bottom and only shown online
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(SCOREBOARD_TEXT_PADDING),
+                    ..default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: config.font_size / 2.0,
+                        color: theme.text,
+                    },
+                ),
+                NetworkText,
+                Themed(ThemeRole::Text),
+            ));
+        });
+
+    // Match clock, centered along the top and only filled in for timed matches,
+    // with the rally counter under it
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(SCOREBOARD_TEXT_PADDING),
+                    ..default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: config.font_size / 2.0,
+                        color: theme.text,
+                    },
+                ),
+                MatchClockText,
+                Themed(ThemeRole::Text),
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: config.font_size / 3.0,
+                        color: theme.text,
+                    },
+                ),
+                RallyText,
+                Themed(ThemeRole::Text),
+            ));
+        });
+
+    // Touch button, only shown once the screen has been touched
+    let mut touch_button = TextBundle::from_section(
+        "Tap here to serve / pause",
+        TextStyle {
+            font: font.0.clone(),
+            font_size: config.font_size / 2.0,
+            color: theme.text,
+        },
+    )
+    .with_style(Style {
+        position_type: PositionType::Absolute,
+        position: UiRect {
+            bottom: Val::Percent(TOUCH_BUTTON_HEIGHT * 50.0),
+            left: Val::Percent((1.0 - TOUCH_BUTTON_WIDTH) * 50.0),
+            ..default()
+        },
+        ..default()
+    });
+    touch_button.visibility.is_visible = false;
+    commands.spawn((touch_button, TouchButton, Themed(ThemeRole::Text)));
+}
+
+// Scales the text, and everything else in the UI that's sized in pixels, with the window.
+// Whichever way the window is smaller for its size decides, so nothing outgrows a tall or wide window
+pub(crate) fn scale_ui_with_window(
+    windows: Res<Windows>,
+    mut resize_events: EventReader<WindowResized>,
+    mut ui_scale: ResMut<UiScale>,
+) {
+    let resized = resize_events.iter().last().is_some();
+    if !resized && !ui_scale.is_added() {
+        return;
+    }
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+
+    let scale = (Vec2::new(window.width(), window.height()) / UI_REFERENCE_SIZE).min_element() as f64;
+    if ui_scale.scale != scale {
+        ui_scale.scale = scale;
+    }
+}
+
+// Shows how long is left until the waiting ball is served, counting down from 3
+pub(crate) fn show_serve_countdown(
+    mut commands: Commands,
+    font: Res<UiFont>,
+    config: Res<PongConfig>,
+    theme: Res<Theme>,
+    serve_timer: Res<ServeTimer>,
+    ball_query: Query<(), With<AwaitingServe>>,
+    mut text_query: Query<(Entity, &mut Text), With<CountdownText>>,
+) {
+    if ball_query.is_empty() {
+        for (text, _) in &text_query {
+            commands.entity(text).despawn_recursive();
+        }
+        return;
+    }
+
+    let remaining = serve_timer.0.percent_left() * SERVE_COUNTDOWN_FROM;
+    let countdown = format!("{}", remaining.ceil() as u32);
+    if let Ok((_, mut text)) = text_query.get_single_mut() {
+        if text.sections[0].value != countdown {
+            text.sections[0].value = countdown;
+        }
+    } else {
+        commands.spawn((announcement(countdown, &font, &config, &theme), CountdownText));
+    }
+}
+
+// Gives every new ball the shared mesh and a material of its own, and its outline
+pub(crate) fn add_ball_meshes(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    skin: Res<Skin>,
+    ball_assets: Res<BallAssets>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    ball_query: Query<Entity, (With<Ball>, Without<Children>)>,
+) {
+    let texture = skin.ball.loaded();
+    let mesh = if texture.is_some() { skin.ball_mesh.clone() } else { ball_assets.mesh.clone() };
+    for ball in &ball_query {
+        commands.entity(ball).with_children(|parent| {
+            parent
+                .spawn((
+                    MaterialMesh2dBundle {
+                        mesh: mesh.clone(),
+                        material: materials.add(ColorMaterial {
+                            color: theme.ball,
+                            texture: texture.cloned(),
+                        }),
+                        ..default()
+                    },
+                    BallVisual,
+                ))
+                // a child of the visual, so it's squashed along with it
+                .with_children(|parent| {
+                    parent.spawn((
+                        MaterialMesh2dBundle {
+                            mesh: ball_assets.mesh.clone(),
+                            material: ball_assets.outline_material.clone(),
+                            transform: Transform::from_xyz(0.0, 0.0, -0.1)
+                                .with_scale(Vec2::splat(BALL_OUTLINE_SCALE).extend(1.0)),
+                            visibility: Visibility {
+                                is_visible: theme.ball_outline.is_some(),
+                            },
+                            ..default()
+                        },
+                        BallOutline,
+                    ));
+                });
+        });
+    }
+}
+
+// Shows or hides the balls' outlines, and recolors them, when the theme changes
+pub(crate) fn show_ball_outlines(
+    theme: Res<Theme>,
+    ball_assets: Res<BallAssets>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut outline_query: Query<&mut Visibility, With<BallOutline>>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+
+    if let Some(color) = theme.ball_outline {
+        if let Some(material) = materials.get_mut(&ball_assets.outline_material) {
+            material.color = color;
+        }
+    }
+    for mut visibility in &mut outline_query {
+        visibility.is_visible = theme.ball_outline.is_some();
+    }
+}
+
+// Heats each ball up as the rally speeds it up, from its own color at the serve speed to
+// `HOT_BALL_COLOR` at the fastest it can go. A ball with a turbo starts from the power-up's color,
+// so the players can see it coming
+pub(crate) fn color_balls(
+    config: Res<PongConfig>,
+    theme: Res<Theme>,
+    skin: Res<Skin>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    ball_query: Query<(&Velocity, &Children, Option<&Turbo>), With<Ball>>,
+    visual_query: Query<&Handle<ColorMaterial>, With<BallVisual>>,
+) {
+    let speed_range = (config.max_ball_speed - config.ball_speed).max(f32::EPSILON);
+    for (velocity, children, turbo) in &ball_query {
+        let base = if turbo.is_some() {
+            effect_color(PowerUpKind::Turbo)
+        } else if skin.ball.loaded().is_some() {
+            // the image's own colors
+            Color::WHITE
+        } else {
+            theme.ball
+        };
+        let heat = ((velocity.length() - config.ball_speed) / speed_range).clamp(0.0, 1.0);
+        let color = mix_colors(base, HOT_BALL_COLOR, heat);
+        for handle in visual_query.iter_many(children) {
+            // only when it's changed, as borrowing it mutably re-uploads it
+            if materials.get(handle).is_some_and(|material| material.color != color) {
+                materials.get_mut(handle).unwrap().color = color;
+            }
+        }
+    }
+}
+
+// Squashes the paddle and the ball on each paddle hit, the ball along the way it's now heading
+pub(crate) fn squash_on_paddle_hits(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    paddle_query: Query<&PaddleAxis, With<Paddle>>,
+    ball_query: Query<(&Velocity, &Children), With<Ball>>,
+    mut visual_query: Query<(Entity, &mut Transform), With<BallVisual>>,
+) {
+    for event in collision_events.iter() {
+        let Ok(axis) = paddle_query.get(event.collider) else {
+            continue;
+        };
+        // the top and bottom paddles lie across, so their thickness is their height
+        let squash = match axis {
+            PaddleAxis::Vertical => SQUASH,
+            PaddleAxis::Horizontal => Vec2::new(SQUASH.y, SQUASH.x),
+        };
+        commands.entity(event.collider).insert(ScaleTween::squash(squash));
+
+        let Ok((velocity, children)) = ball_query.get(event.ball) else {
+            continue;
+        };
+        let mut visuals = visual_query.iter_many_mut(children);
+        while let Some((visual, mut transform)) = visuals.fetch_next() {
+            transform.rotation = Quat::from_rotation_z(velocity.y.atan2(velocity.x));
+            commands.entity(visual).insert(ScaleTween::squash(Vec2::new(SQUASH.y, SQUASH.x)));
+        }
+    }
+}
+
+// Runs the squashes, putting everything back to its own shape at the end
+pub(crate) fn tween_scales(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut tween_query: Query<(Entity, &mut ScaleTween, Option<&mut Sprite>)>,
+    mut visual_query: Query<&mut Transform, With<BallVisual>>,
+) {
+    for (entity, mut tween, sprite) in &mut tween_query {
+        tween.timer.tick(time.delta());
+        let finished = tween.timer.finished();
+        if let Some(mut sprite) = sprite {
+            // back to the unit size at the end, which a skinned paddle needs to stay at
+            sprite.custom_size = Some(tween.scale());
+        } else if let Ok(mut transform) = visual_query.get_mut(entity) {
+            if finished {
+                *transform = Transform::IDENTITY;
+            } else {
+                transform.scale = tween.scale().extend(1.0);
+            }
+        }
+        if finished {
+            commands.entity(entity).remove::<ScaleTween>();
+        }
+    }
+}
+
+// Shakes the camera for goals and hard paddle hits, harder the faster the ball was going.
+// A new shake while one is going takes over if it's stronger, rather than adding to it
+pub(crate) fn shake_camera(
+    time: Res<Time>,
+    config: Res<PongConfig>,
+    mut shake: ResMut<CameraShake>,
+    mut goal_events: EventReader<GoalEvent>,
+    mut collision_events: EventReader<CollisionEvent>,
+    paddle_query: Query<(), With<Paddle>>,
+    mut camera_query: Query<&mut Transform, With<Camera2d>>,
+) {
+    let goal_speeds = goal_events.iter().map(|event| event.speed);
+    let hard_hit_speeds = collision_events
+        .iter()
+        .filter(|event| paddle_query.contains(event.collider))
+        .map(|event| event.speed)
+        .filter(|speed| *speed >= config.max_ball_speed * HARD_HIT_FRACTION);
+    let magnitude = goal_speeds
+        .chain(hard_hit_speeds)
+        .map(|speed| config.screen_shake * speed / config.max_ball_speed)
+        .fold(0.0, f32::max);
+
+    let Ok(mut camera_transform) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    let remaining = if shake.origin.is_some() { shake.magnitude * shake.timer.percent_left() } else { 0.0 };
+    if magnitude > 0.0 && magnitude > remaining {
+        if shake.origin.is_none() {
+            shake.origin = Some(camera_transform.translation);
+        }
+        shake.magnitude = magnitude;
+        shake.timer = Timer::from_seconds(SHAKE_DURATION, TimerMode::Once);
+    }
+
+    let Some(origin) = shake.origin else {
+        return;
+    };
+    shake.timer.tick(time.delta());
+    if shake.timer.finished() {
+        camera_transform.translation = origin;
+        shake.origin = None;
+        return;
+    }
+
+    let mut rng = rand::thread_rng();
+    let offset = Vec2::from_angle(rng.gen_range(0.0..TAU)) * shake.magnitude * shake.timer.percent_left();
+    camera_transform.translation = origin + offset.extend(0.0);
+}
+
+// A line of centered text over the middle of the arena, for menus and announcements
+fn announcement(
+    value: impl Into<String>,
+    font: &UiFont,
+    config: &PongConfig,
+    theme: &Theme,
+) -> TextBundle {
+    TextBundle::from_section(
+        value,
+        TextStyle {
+            font: font.0.clone(),
+            font_size: config.font_size,
+            color: theme.text,
+        },
+    )
+    .with_text_alignment(TextAlignment::CENTER)
+    .with_style(Style {
+        position_type: PositionType::Absolute,
+        position: UiRect {
+            top: Val::Percent(33.0),
+            left: Val::Percent(25.0),
+            ..default()
+        },
+        ..default()
+    })
+}
+
+// Pausing pushes `Paused` on top of `Playing`, so the match picks up exactly where it left off.
+// While paused the `PhysicsStage` keeps draining its accumulator without running any steps,
+// which means there is no burst of catch-up steps on resume. Online, only the host pauses,
+// and the client and spectators follow it
+pub(crate) fn toggle_pause(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    touches: Res<Touches>,
+    windows: Res<Windows>,
+    network_role: Option<Res<NetworkRole>>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if network_role.is_some_and(|role| role.follows_host()) {
+        return;
+    }
+    if !keyboard_input.just_pressed(bindings.pause) && !touch_button_tapped(&touches, &windows) {
+        return;
+    }
+
+    // a transition already queued this frame, like one from the page or the network, goes ahead instead
+    match state.current() {
+        GameState::Playing => {
+            let _ = state.push(GameState::Paused);
+        }
+        GameState::Paused => {
+            let _ = state.pop();
+        }
+        _ => {}
+    }
+}
+
+// Pauses when the window loses focus, so nobody is scored on while looking elsewhere. Coming back
+// doesn't unpause, so they aren't caught out before they're ready either
+pub(crate) fn pause_on_focus_loss(
+    config: Res<PongConfig>,
+    mut focus_events: EventReader<WindowFocused>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let lost_focus = focus_events.iter().filter(|event| !event.focused).count() > 0;
+    if lost_focus && config.auto_pause_on_focus_loss && *state.current() == GameState::Playing {
+        let _ = state.push(GameState::Paused);
+    }
+}
+
+pub(crate) fn spawn_pause_text(
+    mut commands: Commands,
+    font: Res<UiFont>,
+    config: Res<PongConfig>,
+    theme: Res<Theme>,
+    bindings: Res<KeyBindings>,
+) {
+    let mut text = "Paused\n".to_string();
+    // there's only music to toggle with the music feature
+    #[cfg(feature = "music")]
+    text.push_str("Press N to toggle music\n");
+    text.push_str(&format!("Press {:?} to restart", bindings.restart));
+    commands.spawn((
+        announcement(text, &font, &config, &theme),
+        PauseText,
+    ));
+}
+
+// Flashes the scores while the next goal decides it, so nobody misses the stakes
+pub(crate) fn flash_scores_in_sudden_death(
+    time: Res<Time>,
+    theme: Res<Theme>,
+    match_score: Res<MatchScore>,
+    mut query: Query<&mut Text, With<ScoreText>>,
+) {
+    let flashing = match_score.sudden_death && match_score.winner.is_none();
+    if !flashing && !match_score.is_changed() {
+        return;
+    }
+
+    let mut color = theme.text;
+    if flashing {
+        let phase = time.elapsed_seconds() * SUDDEN_DEATH_FLASH_RATE * std::f32::consts::TAU;
+        color.set_a(0.6 + 0.4 * phase.cos());
+    }
+    for mut text in &mut query {
+        for section in &mut text.sections {
+            section.style.color = color;
+        }
+    }
+}
+
+// Tints a paddle the color of the power-up it's under the effect of, for as long as it lasts,
+// over which any flash from a hit fades back
+pub(crate) fn tint_powered_up_paddles(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<PongConfig>,
+    theme: Res<Theme>,
+    skin: Res<Skin>,
+    mut query: Query<(Entity, &mut Sprite, Option<&ActiveEffect>, Option<&mut Flash>), With<Paddle>>,
+) {
+    // a skinned paddle is left in the image's own colors
+    let paddle_color = if skin.paddle.loaded().is_some() { Color::WHITE } else { theme.paddle };
+    for (paddle, mut sprite, effect, flash) in &mut query {
+        let mut color = effect.map_or(paddle_color, |effect| effect_color(effect.kind));
+        if let Some(mut flash) = flash {
+            flash.timer.tick(time.delta());
+            if flash.timer.finished() {
+                commands.entity(paddle).remove::<Flash>();
+            } else {
+                color = mix_colors(config.paddle_flash_color, color, flash.timer.percent());
+            }
+        }
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+// Flashes each paddle that hits the ball, starting over if it was still fading from the last hit
+pub(crate) fn flash_paddle_hits(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    paddle_query: Query<(), With<Paddle>>,
+) {
+    for event in collision_events.iter() {
+        if paddle_query.contains(event.collider) {
+            commands.entity(event.collider).insert(Flash {
+                timer: Timer::from_seconds(FLASH_DURATION, TimerMode::Once),
+            });
+        }
+    }
+}
+
+// Gives every new paddle its dash cooldown bar
+pub(crate) fn add_dash_bars(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    paddle_query: Query<Entity, Added<Paddle>>,
+) {
+    for paddle in &paddle_query {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: theme.paddle,
+                    ..default()
+                },
+                visibility: Visibility { is_visible: false },
+                ..default()
+            },
+            DashBar(paddle),
+        ));
+    }
+}
+
+// Keeps each dash bar beside its paddle, on the side away from the middle, as long as the part of the cooldown
+// that's passed. It's only shown while the paddle's waiting, with paddle dash on, and goes with its paddle
+pub(crate) fn show_dash_cooldowns(
+    mut commands: Commands,
+    config: Res<PongConfig>,
+    settings: Res<MatchSettings>,
+    theme: Res<Theme>,
+    paddle_query: Query<(&Transform, &PaddleAxis, &PaddleDash), With<Paddle>>,
+    mut bar_query: Query<(Entity, &DashBar, &mut Transform, &mut Sprite, &mut Visibility), Without<Paddle>>,
+) {
+    for (bar, DashBar(paddle), mut transform, mut sprite, mut visibility) in &mut bar_query {
+        let Ok((paddle_transform, axis, dash)) = paddle_query.get(*paddle) else {
+            commands.entity(bar).despawn();
+            continue;
+        };
+
+        let shown = settings.paddle_dash && dash.cooldown > 0.0;
+        if visibility.is_visible != shown {
+            visibility.is_visible = shown;
+        }
+        if !shown {
+            continue;
+        }
+        let along = axis.index();
+        let across = 1 - along;
+        let ready = 1.0 - dash.cooldown / config.dash_cooldown.max(f32::EPSILON);
+        let outward = paddle_transform.translation[across].signum();
+        transform.translation = paddle_transform.translation;
+        transform.translation[across] +=
+            outward * (paddle_transform.scale[across] / 2.0 + DASH_BAR_GAP + DASH_BAR_WIDTH / 2.0);
+        transform.scale[along] = paddle_transform.scale[along] * ready.clamp(0.0, 1.0);
+        transform.scale[across] = DASH_BAR_WIDTH;
+        if sprite.color != theme.paddle {
+            sprite.color = theme.paddle;
+        }
+    }
+}
+
+// The color `t` of the way from one color to the other
+fn mix_colors(from: Color, to: Color, t: f32) -> Color {
+    let [r, g, b, a] = Vec4::from(from.as_rgba_f32()).lerp(Vec4::from(to.as_rgba_f32()), t).to_array();
+    Color::rgba(r, g, b, a)
+}
+
+// Shows the time left in a timed match, rounded up to the second,
+// or when it matters more, that it's sudden death or deuce
+pub(crate) fn update_match_clock(
+    settings: Res<MatchSettings>,
+    match_timer: Res<MatchTimer>,
+    match_score: Res<MatchScore>,
+    scoreboard: Res<Scoreboard>,
+    mut query: Query<&mut Text, With<MatchClockText>>,
+) {
+    if !settings.is_changed()
+        && !match_timer.is_changed()
+        && !match_score.is_changed()
+        && !scoreboard.is_changed()
+    {
+        return;
+    }
+
+    let clock = match settings.mode {
+        _ if match_score.winner.is_some() => String::new(),
+        _ if match_score.sudden_death => "Sudden death!".to_string(),
+        MatchMode::Games(format) => {
+            match format.deuce(scoreboard.left_score, scoreboard.right_score, settings.win_by_two) {
+                Some(Deuce::Level) => "Deuce".to_string(),
+                Some(Deuce::Advantage(side)) => format!("Advantage {side:?}"),
+                None => String::new(),
+            }
+        }
+        MatchMode::Timed(_) => {
+            let seconds = match_timer.0.remaining_secs().ceil() as u32;
+            format!("{}:{:02}", seconds / 60, seconds % 60)
+        }
+    };
+    for mut text in &mut query {
+        if text.sections[0].value != clock {
+            text.sections[0].value = clock.clone();
+        }
+    }
+}
+
+// Shows the paddle hits in the rally going on and the most in one this match, from the same `Rally`
+// the speed-ups count. Both only change with it, so there's nothing to redo until it does.
+// It's left empty until the match's first hit
+pub(crate) fn update_rally_counter(
+    rally: Res<Rally>,
+    match_stats: Res<MatchStats>,
+    mut query: Query<&mut Text, With<RallyText>>,
+) {
+    if !rally.is_changed() {
+        return;
+    }
+
+    let longest = match_stats.longest_rally.max(rally.hits);
+    let counter = if longest == 0 {
+        String::new()
+    } else {
+        format!("Rally {}  Best {longest}", rally.hits)
+    };
+    for mut text in &mut query {
+        text.sections[0].value = counter.clone();
+    }
+}
+
+// The window's title, with the score while there's a match on, and what's riding on the next point.
+// A four-player match has everyone's lives instead, left, right, top then bottom, and wall practice the streak
+pub(crate) fn score_title(
+    state: GameState,
+    scoreboard: &Scoreboard,
+    match_score: &MatchScore,
+    settings: &MatchSettings,
+    lives: &Lives,
+    streak: &Streak,
+) -> String {
+    if matches!(state, GameState::Loading | GameState::Menu) {
+        return "Pong!".to_string();
+    }
+    if settings.four_players {
+        let lives = Seat::ALL.map(|seat| lives.lives(seat).to_string());
+        return format!("Pong! {}", lives.join(" \u{2013} "));
+    }
+    if settings.wall_practice {
+        return format!("Pong! Streak {} (best {})", streak.hits, streak.best);
+    }
+
+    let (left_score, right_score) = (scoreboard.left_score, scoreboard.right_score);
+    let stakes = match settings.mode {
+        _ if match_score.winner.is_some() || settings.practice => None,
+        MatchMode::Games(format) => {
+            // in sudden death the next point wins the game whatever the score
+            let match_point = [Side::Left, Side::Right].into_iter().any(|side| {
+                let games_won = match_score.games(side);
+                if match_score.sudden_death {
+                    games_won + 1 >= format.games_to_win
+                } else {
+                    format.match_point(side, left_score, right_score, games_won, settings.win_by_two)
+                }
+            });
+            if match_point {
+                Some("match point")
+            } else if format.deuce(left_score, right_score, settings.win_by_two) == Some(Deuce::Level) {
+                Some("deuce")
+            } else {
+                None
+            }
+        }
+        MatchMode::Timed(_) if match_score.sudden_death => Some("match point"),
+        MatchMode::Timed(_) => None,
+    };
+    match stakes {
+        Some(stakes) => format!("Pong! {left_score} \u{2013} {right_score} ({stakes})"),
+        None => format!("Pong! {left_score} \u{2013} {right_score}"),
+    }
+}
+
+// Keeps the score in the title, for when the window is in the background. The page's title on the web
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn show_score_in_title(
+    state: Res<State<GameState>>,
+    scoreboard: Res<Scoreboard>,
+    match_score: Res<MatchScore>,
+    settings: Res<MatchSettings>,
+    lives: Res<Lives>,
+    streak: Res<Streak>,
+    mut windows: ResMut<Windows>,
+) {
+    let changed = scoreboard.is_changed()
+        || match_score.is_changed()
+        || settings.is_changed()
+        || lives.is_changed()
+        || streak.is_changed();
+    if !state.is_changed() && !changed {
+        return;
+    }
+
+    let title = score_title(*state.current(), &scoreboard, &match_score, &settings, &lives, &streak);
+    // only borrowed mutably for a new title, so nothing else sees the windows change for nothing
+    if windows.get_primary().is_some_and(|window| window.title() != title) {
+        if let Some(window) = windows.get_primary_mut() {
+            window.set_title(title);
+        }
+    }
+}
+
+// Covers the frozen match with the result, the physics steps don't run outside of `Playing`
+pub(crate) fn spawn_winner_text(
+    mut commands: Commands,
+    scoreboard: Res<Scoreboard>,
+    match_score: Res<MatchScore>,
+    settings: Res<MatchSettings>,
+    lives: Res<Lives>,
+    names: Res<PlayerNames>,
+    match_stats: Res<MatchStats>,
+    font: Res<UiFont>,
+    config: Res<PongConfig>,
+    theme: Res<Theme>,
+) {
+    let result = match (match_score.winner, lives.winner) {
+        (Some(winner), _) => {
+            let loser = winner.opponent();
+            // a match of several games is decided by the games won, not the points in the last one
+            let several_games = matches!(settings.mode, MatchMode::Games(format) if format.games_to_win > 1);
+            let (winner_score, loser_score) = if several_games {
+                (match_score.games(winner), match_score.games(loser))
+            } else {
+                (scoreboard.score(winner), scoreboard.score(loser))
+            };
+            format!("{} wins {winner_score}\u{2013}{loser_score}", names.name(winner))
+        }
+        (None, Some(winner)) => format!("{} wins with {} left", seat_name(&names, winner), lives_left(&lives, winner)),
+        (None, None) => return,
+    };
+
+    let text_style = TextStyle {
+        font: font.0.clone(),
+        font_size: config.font_size,
+        color: theme.text,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: (*theme.background.clone().set_a(GAME_OVER_OVERLAY_OPACITY)).into(),
+                ..default()
+            },
+            WinnerText,
+        ))
+        .with_children(|parent| {
+            let panel = |display| NodeBundle {
+                style: Style {
+                    display,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            };
+            let small_style = TextStyle {
+                font_size: config.font_size / 2.0,
+                ..text_style.clone()
+            };
+
+            parent.spawn((panel(Display::Flex), ResultPanel)).with_children(|parent| {
+                parent.spawn(TextBundle::from_section(result, text_style.clone()));
+                parent.spawn(TextBundle::from_section(
+                    "Press Enter for rematch, Esc for menu, Tab for stats",
+                    small_style.clone(),
+                ));
+            });
+            parent.spawn((panel(Display::None), StatsPanel)).with_children(|parent| {
+                let rows = match_stats_rows(&match_stats, &names);
+                parent.spawn(TextBundle::from_sections(
+                    rows.into_iter().map(|row| TextSection::new(row, small_style.clone())),
+                ));
+                parent.spawn(TextBundle::from_section(
+                    "\nPress Tab to go back",
+                    small_style.clone(),
+                ));
+            });
+        });
+}
+
+// The stats in rows, lined up in columns by padding them out in the monospaced font. The numbers
+// each side has of its own go under their names, and the ones they share are centered under both
+fn match_stats_rows(stats: &MatchStats, names: &PlayerNames) -> Vec<String> {
+    let (left, right) = (names.name(Side::Left), names.name(Side::Right));
+    let column = left.chars().count().max(right.chars().count()).max(6) + 2;
+    let both = column * 2;
+    let shared = |label: &str, value: String| format!("{label:STATS_LABEL_WIDTH$}{value:^both$}\n");
+    let seconds = stats.duration as u32;
+
+    vec![
+        format!("{:STATS_LABEL_WIDTH$}{left:>column$}{right:>column$}\n", ""),
+        format!(
+            "{:STATS_LABEL_WIDTH$}{:>column$}{:>column$}\n",
+            "Paddle hits", stats.left_hits, stats.right_hits
+        ),
+        shared("Longest rally", stats.longest_rally.to_string()),
+        shared("Average rally", format!("{:.1}", stats.average_rally())),
+        shared("Wall bounces", stats.wall_bounces.to_string()),
+        shared("Top speed", format!("{:.0}", stats.max_ball_speed)),
+        shared("Match time", format!("{}:{:02}", seconds / 60, seconds % 60)),
+    ]
+}
+
+// Tab, or the gamepad's north button, switches the game over screen between the result and the stats.
+// The east button also goes back from the stats to the rematch prompt
+pub(crate) fn toggle_match_stats(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    mut result_query: Query<&mut Style, (With<ResultPanel>, Without<StatsPanel>)>,
+    mut stats_query: Query<&mut Style, (With<StatsPanel>, Without<ResultPanel>)>,
+) {
+    let gamepad_pressed = |button_type| {
+        gamepads
+            .iter()
+            .any(|gamepad| gamepad_input.just_pressed(GamepadButton::new(gamepad, button_type)))
+    };
+    let (Ok(mut result), Ok(mut stats)) = (result_query.get_single_mut(), stats_query.get_single_mut()) else {
+        return;
+    };
+
+    let showing_stats = stats.display == Display::Flex;
+    let toggle = keyboard_input.just_pressed(KeyCode::Tab) || gamepad_pressed(GamepadButtonType::North);
+    let back = showing_stats && gamepad_pressed(GamepadButtonType::East);
+    if !toggle && !back {
+        return;
+    }
+
+    (result.display, stats.display) = if showing_stats {
+        (Display::Flex, Display::None)
+    } else {
+        (Display::None, Display::Flex)
+    };
+}
+
+// Shows the points in the current game under the player's name,
+// with the games won underneath in matches of more than one
+pub(crate) fn update_scoreboard(
+    scoreboard: Res<Scoreboard>,
+    match_score: Res<MatchScore>,
+    settings: Res<MatchSettings>,
+    names: Res<PlayerNames>,
+    mut query: Query<(&mut Text, &Side), With<ScoreText>>,
+) {
+    if !scoreboard.is_changed() && !match_score.is_changed() && !settings.is_changed() && !names.is_changed() {
+        return;
+    }
+
+    let several_games = matches!(settings.mode, MatchMode::Games(format) if format.games_to_win > 1);
+    for (mut text, side) in &mut query {
+        text.sections[0].value = format!("{}\n", names.name(*side));
+        text.sections[1].value = scoreboard.score(*side).to_string();
+        text.sections[2].value = if several_games {
+            format!("\nGames: {}", match_score.games(*side))
+        } else {
+            String::new()
+        };
+    }
+}
+
+// What a player is called, the top and bottom players go by their seats
+fn seat_name(names: &PlayerNames, seat: Seat) -> &str {
+    match seat {
+        Seat::Left => names.name(Side::Left),
+        Seat::Right => names.name(Side::Right),
+        Seat::Top => "Top",
+        Seat::Bottom => "Bottom",
+    }
+}
+
+fn lives_left(lives: &Lives, seat: Seat) -> String {
+    match lives.lives(seat) {
+        1 => "1 life".to_string(),
+        count => format!("{count} lives"),
+    }
+}
+
+// Shows each player's lives by their wall in four-player matches, where they take the scores' place.
+// Wall practice has no scores either, it has `update_streak`
+pub(crate) fn update_lives(
+    lives: Res<Lives>,
+    settings: Res<MatchSettings>,
+    names: Res<PlayerNames>,
+    mut lives_query: Query<(&mut Text, &mut Visibility, &LivesText), Without<ScoreColumn>>,
+    mut column_query: Query<&mut Visibility, With<ScoreColumn>>,
+) {
+    if !lives.is_changed() && !settings.is_changed() && !names.is_changed() {
+        return;
+    }
+
+    for mut visibility in &mut column_query {
+        visibility.is_visible = !settings.four_players && !settings.wall_practice;
+    }
+    for (mut text, mut visibility, LivesText(seat)) in &mut lives_query {
+        visibility.is_visible = settings.four_players;
+        let name = seat_name(&names, *seat);
+        text.sections[0].value = match lives.lives(*seat) {
+            0 => format!("{name}\nOut"),
+            _ => format!("{name}\n{}", lives_left(&lives, *seat)),
+        };
+    }
+}
+
+// Shows the streak and the best one in wall practice, where there's no score to keep
+pub(crate) fn update_streak(
+    streak: Res<Streak>,
+    settings: Res<MatchSettings>,
+    mut query: Query<(&mut Text, &mut Visibility), With<StreakText>>,
+) {
+    if !streak.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    for (mut text, mut visibility) in &mut query {
+        visibility.is_visible = settings.wall_practice;
+        text.sections[0].value = format!("Streak {}\nBest {}", streak.hits, streak.best);
+    }
+}
+
+// Says what an online match is waiting for, and when the other player has gone
+pub(crate) fn update_network_text(
+    role: Option<Res<NetworkRole>>,
+    status: Option<Res<NetworkStatus>>,
+    mut query: Query<&mut Text, With<NetworkText>>,
+) {
+    // nothing once the online match has been left
+    let message = match (status.as_deref(), role.as_deref()) {
+        (Some(NetworkStatus::Connecting), Some(NetworkRole::Host { port })) => {
+            format!("Waiting for the other player to join on port {port}")
+        }
+        (Some(NetworkStatus::Connecting), Some(NetworkRole::Client { address })) => format!("Joining {address}"),
+        (Some(NetworkStatus::Connecting), Some(NetworkRole::Spectator { address })) => {
+            format!("Joining {address} to watch")
+        }
+        (Some(NetworkStatus::Connected), Some(NetworkRole::Spectator { .. })) => "Watching".to_string(),
+        (Some(NetworkStatus::Disconnected), Some(NetworkRole::Spectator { .. })) => {
+            "Lost the connection to the host".to_string()
+        }
+        (Some(NetworkStatus::Disconnected), Some(_)) => "Lost the connection to the other player".to_string(),
+        (Some(NetworkStatus::Full), Some(_)) => "The match has as many watching as it lets in".to_string(),
+        _ => String::new(),
+    };
+    for mut text in &mut query {
+        // the text is spawned after the connection is opened, so it's checked every frame
+        if text.sections[0].value != message {
+            text.sections[0].value = message.clone();
+        }
+    }
+}
+
+// Shows the difficulty the computer was set to and how far rubber banding has moved it from there
+pub(crate) fn update_cpu_level(
+    difficulty: Res<Difficulty>,
+    shift: Res<DifficultyShift>,
+    settings: Res<MatchSettings>,
+    mut query: Query<(&mut Text, &mut Visibility), With<CpuLevelText>>,
+) {
+    if !difficulty.is_changed() && !shift.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    for (mut text, mut visibility) in &mut query {
+        visibility.is_visible = settings.rubber_band;
+        text.sections[0].value = format!("Computer {difficulty:?}\n{:+.1}", shift.0);
+    }
+}
@@ -0,0 +1,314 @@
+use bevy::prelude::*;
+use crossbeam_channel::Receiver;
+use js_sys::{ArrayBuffer, Function, Uint8Array};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::atomic::{AtomicBool, Ordering},
+    thread::LocalKey,
+};
+use wasm_bindgen::{prelude::*, JsCast};
+use web_sys::{BinaryType, MessageEvent, WebSocket};
+
+use crate::{components::*, events::*, network::*, resources::*, save::*};
+
+// Set by the page when the tab goes into the background, and taken by `pause_when_tab_hidden`
+static TAB_HIDDEN: AtomicBool = AtomicBool::new(false);
+
+// Listens for the page's `visibilitychange`. The browser stops drawing frames for a background tab,
+// so nothing in the game gets to notice until the tab is back
+pub(crate) fn watch_tab_visibility() {
+    let Some(document) = web_sys::window().and_then(|window| window.document()) else {
+        return;
+    };
+
+    let on_visibility_change = Closure::<dyn FnMut()>::new(|| {
+        let hidden = web_sys::window()
+            .and_then(|window| window.document())
+            .is_some_and(|document| document.hidden());
+        if hidden {
+            TAB_HIDDEN.store(true, Ordering::Relaxed);
+        }
+    });
+    if document
+        .add_event_listener_with_callback("visibilitychange", on_visibility_change.as_ref().unchecked_ref())
+        .is_err()
+    {
+        warn!("Couldn't watch the tab's visibility, the game won't pause when it's in the background");
+    }
+    // kept for as long as the page is open
+    on_visibility_change.forget();
+}
+
+// Pauses once the tab is back from the background, however briefly it was away, like losing focus does.
+// It isn't unpaused until the pause key is pressed
+pub(crate) fn pause_when_tab_hidden(mut state: ResMut<State<GameState>>) {
+    if TAB_HIDDEN.swap(false, Ordering::Relaxed) && *state.current() == GameState::Playing {
+        let _ = state.push(GameState::Paused);
+    }
+}
+
+// What the page can read of the match, as of the last frame
+#[derive(Clone, Copy)]
+struct Snapshot {
+    left_score: usize,
+    right_score: usize,
+    left_games: usize,
+    right_games: usize,
+    winner: Option<Side>,
+    state: GameState,
+}
+
+thread_local! {
+    static SNAPSHOT: Cell<Snapshot> = const { Cell::new(Snapshot {
+        left_score: 0,
+        right_score: 0,
+        left_games: 0,
+        right_games: 0,
+        winner: None,
+        state: GameState::Loading,
+    }) };
+}
+
+pub(crate) fn share_with_page(
+    scoreboard: Res<Scoreboard>,
+    match_score: Res<MatchScore>,
+    state: Res<State<GameState>>,
+) {
+    SNAPSHOT.with(|snapshot| {
+        snapshot.set(Snapshot {
+            left_score: scoreboard.left_score,
+            right_score: scoreboard.right_score,
+            left_games: match_score.left_games,
+            right_games: match_score.right_games,
+            winner: match_score.winner,
+            state: *state.current(),
+        })
+    });
+}
+
+fn snapshot() -> Snapshot {
+    SNAPSHOT.with(Cell::get)
+}
+
+/// The left side's points in the current game.
+///
+/// Like everything the page can read, it's a snapshot taken once a frame, only there to be read.
+/// Poll it from something like `setInterval` to follow the match
+#[wasm_bindgen]
+pub fn get_left_score() -> usize {
+    snapshot().left_score
+}
+
+/// The right side's points in the current game
+#[wasm_bindgen]
+pub fn get_right_score() -> usize {
+    snapshot().right_score
+}
+
+/// The games the left side has won this match
+#[wasm_bindgen]
+pub fn get_left_games() -> usize {
+    snapshot().left_games
+}
+
+/// The games the right side has won this match
+#[wasm_bindgen]
+pub fn get_right_games() -> usize {
+    snapshot().right_games
+}
+
+/// What the game is doing: "Loading", "Menu", "Playing", "Paused", "GameOver" or "Demo"
+#[wasm_bindgen]
+pub fn get_game_state() -> String {
+    format!("{:?}", snapshot().state)
+}
+
+/// "Left" or "Right" once the match is over, and `undefined` until then
+#[wasm_bindgen]
+pub fn get_winner() -> Option<String> {
+    snapshot().winner.map(|side| format!("{side:?}"))
+}
+
+thread_local! {
+    // The page's callbacks, see `on_goal` and `on_game_over`
+    static GOAL_CALLBACK: RefCell<Option<Function>> = const { RefCell::new(None) };
+    static GAME_OVER_CALLBACK: RefCell<Option<Function>> = const { RefCell::new(None) };
+}
+
+/// Calls `callback(side, leftScore, rightScore)` after every goal, with the side that scored,
+/// "Left" or "Right", and the score after it. Replaces the callback from any earlier call.
+///
+/// ```js
+/// on_goal((side, left, right) => {
+///     document.querySelector("#score").textContent = `${left} - ${right}`;
+/// });
+/// ```
+#[wasm_bindgen]
+pub fn on_goal(callback: Function) {
+    GOAL_CALLBACK.with(|goal_callback| *goal_callback.borrow_mut() = Some(callback));
+}
+
+/// Calls `callback(winner, leftScore, rightScore)` when a match is won, with the winning side,
+/// "Left" or "Right", and the score of the last game. Replaces the callback from any earlier call.
+///
+/// ```js
+/// on_game_over((winner, left, right) => {
+///     fetch("/leaderboard", { method: "POST", body: JSON.stringify({ winner, left, right }) });
+/// });
+/// ```
+#[wasm_bindgen]
+pub fn on_game_over(callback: Function) {
+    GAME_OVER_CALLBACK.with(|game_over_callback| *game_over_callback.borrow_mut() = Some(callback));
+}
+
+// Anything the callback throws is logged rather than let loose on the game
+fn call_back(callback: &'static LocalKey<RefCell<Option<Function>>>, side: Option<Side>, score: (usize, usize)) {
+    // cloned out, so the callback can replace itself
+    let Some(callback) = callback.with(|callback| callback.borrow().clone()) else {
+        return;
+    };
+    let side = side.map_or(JsValue::UNDEFINED, |side| JsValue::from(format!("{side:?}")));
+    if let Err(err) = callback.call3(&JsValue::NULL, &side, &score.0.into(), &score.1.into()) {
+        warn!("The page's callback failed: {err:?}");
+    }
+}
+
+pub(crate) fn report_goals(mut goal_events: EventReader<GoalEvent>) {
+    for event in goal_events.iter() {
+        call_back(&GOAL_CALLBACK, Some(event.scorer), event.new_score);
+    }
+}
+
+pub(crate) fn report_game_over(scoreboard: Res<Scoreboard>, match_score: Res<MatchScore>) {
+    call_back(
+        &GAME_OVER_CALLBACK,
+        match_score.winner,
+        (scoreboard.left_score, scoreboard.right_score),
+    );
+}
+
+// The page's title, rather than the window's, which the browser doesn't show
+pub(crate) fn show_score_in_title(
+    state: Res<State<GameState>>,
+    scoreboard: Res<Scoreboard>,
+    match_score: Res<MatchScore>,
+    settings: Res<MatchSettings>,
+    lives: Res<Lives>,
+    streak: Res<Streak>,
+) {
+    let changed = scoreboard.is_changed()
+        || match_score.is_changed()
+        || settings.is_changed()
+        || lives.is_changed()
+        || streak.is_changed();
+    if !state.is_changed() && !changed {
+        return;
+    }
+
+    let title = crate::ui::score_title(*state.current(), &scoreboard, &match_score, &settings, &lives, &streak);
+    if let Some(document) = web_sys::window().and_then(|window| window.document()) {
+        if document.title() != title {
+            document.set_title(&title);
+        }
+    }
+}
+
+// Private browsing can leave the page without local storage, or with one that won't take anything
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
+
+// Saves to the browser's local storage, each key prefixed with the game's name
+pub(crate) struct LocalStorage;
+
+impl LocalStorage {
+    pub(crate) fn open() -> Option<LocalStorage> {
+        local_storage().map(|_| LocalStorage)
+    }
+}
+
+impl StorageBackend for LocalStorage {
+    fn load(&self, key: &str) -> Result<Option<String>, String> {
+        let storage = local_storage().ok_or("the local storage has gone")?;
+        storage
+            .get_item(&format!("bevy_pong_{key}"))
+            .map_err(|err| format!("{err:?}"))
+    }
+
+    fn save(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let storage = local_storage().ok_or("the local storage has gone")?;
+        storage
+            .set_item(&format!("bevy_pong_{key}"), value)
+            .map_err(|err| format!("{err:?}"))
+    }
+}
+
+thread_local! {
+    // The online match's socket, and the messages the game has queued up for it, see `send_queued_messages`
+    static SOCKET: RefCell<Option<(WebSocket, Receiver<NetMessage>)>> = const { RefCell::new(None) };
+}
+
+// Joins an online match through the browser's own WebSocket. Browsers can't listen for connections,
+// so a page can only ever be the client
+pub(crate) fn join_online_match(address: &str) -> Result<NetworkLink, String> {
+    let socket = WebSocket::new(address).map_err(|err| format!("{err:?}"))?;
+    socket.set_binary_type(BinaryType::Arraybuffer);
+    let (link, ends) = NetworkLink::with_ends();
+
+    // let go of when the socket closes, which the game sees as the connection going
+    let incoming = Rc::new(RefCell::new(Some(ends.incoming)));
+    let on_message = {
+        let incoming = incoming.clone();
+        Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+            let Ok(buffer) = event.data().dyn_into::<ArrayBuffer>() else {
+                return;
+            };
+            match NetMessage::from_bytes(&Uint8Array::new(&buffer).to_vec()) {
+                Ok(message) => {
+                    if let Some(incoming) = &*incoming.borrow() {
+                        let _ = incoming.send(message);
+                    }
+                }
+                Err(err) => warn!("Ignoring a message the other player sent that makes no sense: {err}"),
+            }
+        })
+    };
+    let on_close = Closure::<dyn FnMut()>::new(move || {
+        incoming.borrow_mut().take();
+    });
+    socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+    // kept for as long as the page is open
+    on_message.forget();
+    on_close.forget();
+
+    SOCKET.with(|cell| *cell.borrow_mut() = Some((socket, ends.outgoing)));
+    Ok(link)
+}
+
+// Closes the online match's socket once the game has left the match, with anything still queued for it
+pub(crate) fn close_online_match() {
+    SOCKET.with(|cell| {
+        if let Some((socket, _)) = cell.borrow_mut().take() {
+            let _ = socket.close();
+        }
+    });
+}
+
+// Sends what the game has queued up, once the socket has opened
+pub(crate) fn send_queued_messages() {
+    SOCKET.with(|cell| {
+        let Some((socket, outgoing)) = &*cell.borrow() else {
+            return;
+        };
+        if socket.ready_state() != WebSocket::OPEN {
+            return;
+        }
+        for message in outgoing.try_iter() {
+            if let Err(err) = socket.send_with_u8_array(&message.to_bytes()) {
+                warn!("Couldn't send to the other player: {err:?}");
+            }
+        }
+    });
+}
@@ -0,0 +1,206 @@
+// Shared by every test file, which each use a different part of it
+#![allow(dead_code)]
+
+use bevy::{
+    diagnostic::DiagnosticsPlugin,
+    prelude::*,
+    window::{WindowFocused, WindowId, WindowMode},
+};
+use bevy_pong::prelude::*;
+
+pub const SEED: u64 = 1234;
+
+/// A headless two player match, seeded with `SEED`. Insert resources to change the rules before
+/// calling `start`, and drive the paddles by writing `PaddleInputs`
+pub fn headless_app() -> App {
+    headless_app_with_step(TIME_STEP)
+}
+
+/// A `headless_app` whose physics steps are `step` seconds long
+pub fn headless_app_with_step(step: f32) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(SkipMenu {
+            cpu_opponent: false,
+        })
+        .insert_resource(GameRng::seeded(SEED))
+        .insert_resource(PhysicsStep(step))
+        .add_plugin(PongPlugin::default());
+    app
+}
+
+/// The game with `PongRenderPlugin` on top, sat at the menu with the settings saved in `storage`.
+/// Nothing is drawn or played: the window is a stand-in in `Windows`, focused and in `window_mode`,
+/// and the assets are never loaded. Keys are pressed by writing `Input<KeyCode>`, see `tap`
+pub fn rendered_app(storage: MemoryStorage, window_mode: WindowMode) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin::default())
+        .add_plugin(DiagnosticsPlugin)
+        .add_plugin(WindowPlugin {
+            add_primary_window: false,
+            ..Default::default()
+        })
+        .init_resource::<Input<KeyCode>>()
+        .init_resource::<Input<MouseButton>>()
+        .init_resource::<Input<GamepadButton>>()
+        .init_resource::<Gamepads>()
+        .init_resource::<Touches>()
+        .init_resource::<UiScale>()
+        .add_asset::<Mesh>()
+        .add_asset::<ColorMaterial>()
+        .add_asset::<Font>()
+        .add_asset::<Image>()
+        .add_asset::<AudioSource>()
+        .init_resource::<Audio>()
+        .insert_resource(SaveStorage(Box::new(storage)))
+        .insert_resource(GameRng::seeded(SEED))
+        .add_plugin(PongPlugin::default())
+        .add_plugin(PongRenderPlugin);
+
+    let descriptor = WindowDescriptor {
+        mode: window_mode,
+        ..Default::default()
+    };
+    let window = Window::new(WindowId::primary(), &descriptor, 900, 600, 1.0, None, None);
+    app.world.resource_mut::<Windows>().add(window);
+    app.world.send_event(WindowFocused {
+        id: WindowId::primary(),
+        focused: true,
+    });
+    app
+}
+
+/// Presses a key for one update
+pub fn tap(app: &mut App, key: KeyCode) {
+    app.world.resource_mut::<Input<KeyCode>>().press(key);
+    simulate_ticks(app, 1);
+    let mut keyboard = app.world.resource_mut::<Input<KeyCode>>();
+    keyboard.release(key);
+    keyboard.clear();
+}
+
+/// The stand-in window of a `rendered_app`
+pub fn window(app: &mut App) -> &mut Window {
+    app.world
+        .resource_mut::<Windows>()
+        .into_inner()
+        .get_primary_mut()
+        .expect("the app has a window")
+}
+
+/// Runs the app until the arena is set up and the match is underway
+pub fn start(app: &mut App) {
+    for _ in 0..10 {
+        if app.world.contains_resource::<Arena>() && state(app) == GameState::Playing {
+            return;
+        }
+        simulate_ticks(app, 1);
+    }
+    panic!("the match never started");
+}
+
+pub fn state(app: &App) -> GameState {
+    *app.world.resource::<State<GameState>>().current()
+}
+
+pub fn config(app: &App) -> PongConfig {
+    app.world.resource::<PongConfig>().clone()
+}
+
+pub fn arena(app: &App) -> Arena {
+    *app.world.resource::<Arena>()
+}
+
+/// The position and velocity of every ball
+pub fn balls(app: &mut App) -> Vec<(Vec2, Vec2)> {
+    let mut query = app
+        .world
+        .query_filtered::<(&Transform, &Velocity), With<Ball>>();
+    query
+        .iter(&app.world)
+        .map(|(transform, velocity)| (transform.translation.truncate(), velocity.0))
+        .collect()
+}
+
+/// The one ball in play
+pub fn ball(app: &mut App) -> (Vec2, Vec2) {
+    let balls = balls(app);
+    assert_eq!(balls.len(), 1, "expected exactly one ball");
+    balls[0]
+}
+
+/// Takes every ball out of play
+pub fn remove_balls(app: &mut App) {
+    let mut query = app.world.query_filtered::<Entity, With<Ball>>();
+    let balls: Vec<Entity> = query.iter(&app.world).collect();
+    for ball in balls {
+        app.world.despawn(ball);
+    }
+}
+
+/// Adds a ball already in play, in addition to any there are.
+/// The watchdog leaves it alone, so it can be kept still
+pub fn add_ball(app: &mut App, position: Vec2, velocity: Vec2) -> Entity {
+    let size = config(app).ball_size;
+    app.world
+        .spawn((
+            SpatialBundle::from_transform(
+                Transform::from_translation(position.extend(1.0)).with_scale(size.extend(1.0)),
+            ),
+            Ball,
+            Velocity(velocity),
+        ))
+        .id()
+}
+
+/// Replaces the ball waiting to be served with one already in play
+pub fn place_ball(app: &mut App, position: Vec2, velocity: Vec2) -> Entity {
+    remove_balls(app);
+    add_ball(app, position, velocity)
+}
+
+/// Runs the app until the waiting ball is served, and returns its velocity
+pub fn serve(app: &mut App) -> Vec2 {
+    for _ in 0..1000 {
+        let (_, velocity) = ball(app);
+        if velocity != Vec2::ZERO {
+            return velocity;
+        }
+        simulate_ticks(app, 1);
+    }
+    panic!("the ball was never served");
+}
+
+pub fn paddle(app: &mut App, side: Side) -> Entity {
+    let mut query = app.world.query_filtered::<(Entity, &Side), With<Paddle>>();
+    query
+        .iter(&app.world)
+        .find(|(_, paddle_side)| **paddle_side == side)
+        .map(|(paddle, _)| paddle)
+        .expect("both paddles are spawned")
+}
+
+/// Gives a paddle its own speed and height
+pub fn set_paddle(app: &mut App, side: Side, speed: f32, height: f32) {
+    let paddle = paddle(app, side);
+    let mut paddle = app.world.entity_mut(paddle);
+    paddle.insert(PaddleSpeed(speed));
+    paddle.get_mut::<Transform>().unwrap().scale.y = height;
+}
+
+pub fn paddle_y(app: &mut App, side: Side) -> f32 {
+    let mut query = app
+        .world
+        .query_filtered::<(&Transform, &Side), With<Paddle>>();
+    query
+        .iter(&app.world)
+        .find(|(_, paddle_side)| **paddle_side == side)
+        .map(|(transform, _)| transform.translation.y)
+        .expect("both paddles are spawned")
+}
+
+pub fn scores(app: &App) -> (usize, usize) {
+    let scoreboard = app.world.resource::<Scoreboard>();
+    (scoreboard.left_score, scoreboard.right_score)
+}
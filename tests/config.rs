@@ -0,0 +1,92 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_pong::prelude::*;
+use common::*;
+use std::time::{Duration, Instant};
+
+#[test]
+fn config_values_the_game_cant_play_with_are_put_right() {
+    let mut app = headless_app();
+    app.insert_resource(PongConfig {
+        paddle_speed: -500.0,
+        dash_duration: 0.0,
+        ball_speed: 600.0,
+        max_ball_speed: 300.0,
+        max_serve_angle: 120.0,
+        ball_size: Vec2::new(30.0, -30.0),
+        max_balls: 0,
+        ..Default::default()
+    });
+    start(&mut app);
+
+    let defaults = PongConfig::default();
+    let config = config(&app);
+    assert_eq!(config.paddle_speed, defaults.paddle_speed);
+    assert_eq!(config.dash_duration, defaults.dash_duration);
+    assert_eq!(config.ball_size, defaults.ball_size);
+    // out of range values are brought into it instead
+    assert_eq!(config.max_ball_speed, 600.0);
+    assert_eq!(config.max_serve_angle, 90.0);
+    assert_eq!(config.max_balls, 1);
+
+    // and the match goes ahead with them
+    let velocity = serve(&mut app);
+    assert!((velocity.length() - 600.0).abs() < 1e-3);
+}
+
+#[test]
+fn config_the_game_can_play_with_is_left_alone() {
+    let playable = PongConfig {
+        paddle_speed: 800.0,
+        dash_duration: 0.25,
+        screen_shake: 0.0,
+        max_serve_angle: 0.0,
+        ..Default::default()
+    };
+    let mut app = headless_app();
+    app.insert_resource(playable.clone());
+    start(&mut app);
+
+    assert_eq!(config(&app), playable);
+}
+
+#[test]
+fn config_file_is_read_before_the_match_starts() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin {
+            asset_folder: "tests/fixtures".to_string(),
+            watch_for_changes: false,
+        })
+        .insert_resource(SkipMenu {
+            cpu_opponent: false,
+        })
+        .insert_resource(GameRng::seeded(SEED))
+        .add_plugin(PongPlugin {
+            config_path: Some("fast_serve.ron".to_string()),
+            ..Default::default()
+        });
+
+    // the file is read off the main thread, so give it a moment
+    let loading_since = Instant::now();
+    while state(&app) == GameState::Loading {
+        assert!(
+            loading_since.elapsed() < Duration::from_secs(10),
+            "the config never loaded"
+        );
+        simulate_ticks(&mut app, 1);
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    start(&mut app);
+
+    let config = config(&app);
+    assert_eq!(config.ball_speed, 650.0);
+    assert_eq!(config.max_serve_angle, 10.0);
+    assert_eq!(config.ball_color, Color::rgb_u8(255, 128, 0));
+    assert_eq!(config.paddle_speed, PongConfig::default().paddle_speed);
+
+    let velocity = serve(&mut app);
+    assert!((velocity.length() - 650.0).abs() < 1e-3);
+    assert!(velocity.y.atan2(velocity.x.abs()).abs() <= 10f32.to_radians() + 1e-4);
+}
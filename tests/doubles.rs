@@ -0,0 +1,125 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_pong::prelude::*;
+use common::*;
+
+// A doubles match underway, with the partners in
+fn doubles_app() -> App {
+    let mut app = headless_app();
+    app.insert_resource(MatchSettings {
+        doubles: true,
+        ..default()
+    });
+    start(&mut app);
+    simulate_ticks(&mut app, 1);
+    app
+}
+
+fn half_paddle(app: &mut App, side: Side, half: Half) -> Vec2 {
+    let mut query = app
+        .world
+        .query_filtered::<(&Side, &Half, &Transform), With<Paddle>>();
+    query
+        .iter(&app.world)
+        .find(|(paddle_side, paddle_half, _)| **paddle_side == side && **paddle_half == half)
+        .map(|(_, _, transform)| transform.translation.truncate())
+        .expect("both sides have two paddles")
+}
+
+#[test]
+fn each_side_has_a_paddle_in_each_half() {
+    let mut app = doubles_app();
+    let mut query = app.world.query_filtered::<&Side, With<Paddle>>();
+    assert_eq!(query.iter(&app.world).count(), 4);
+
+    for side in [Side::Left, Side::Right] {
+        let upper = half_paddle(&mut app, side, Half::Upper);
+        let lower = half_paddle(&mut app, side, Half::Lower);
+        assert!(
+            upper.y > 0.0 && lower.y < 0.0,
+            "{side:?} paddles at {upper} and {lower}"
+        );
+        assert_eq!(upper.x, lower.x);
+    }
+}
+
+#[test]
+fn lower_paddles_follow_their_own_keys() {
+    let mut app = doubles_app();
+    let upper = half_paddle(&mut app, Side::Left, Half::Upper);
+    let lower = half_paddle(&mut app, Side::Left, Half::Lower);
+
+    place_ball(&mut app, Vec2::ZERO, Vec2::new(0.0, 1.0));
+    app.world.resource_mut::<PaddleInputs>().left_lower = PlayerInput::new(false, true, false);
+    simulate_ticks(&mut app, 10);
+
+    assert_eq!(half_paddle(&mut app, Side::Left, Half::Upper), upper);
+    assert!(half_paddle(&mut app, Side::Left, Half::Lower).y < lower.y);
+}
+
+#[test]
+fn partners_cant_cross_the_middle() {
+    let mut app = doubles_app();
+    let config = config(&app);
+    place_ball(&mut app, Vec2::ZERO, Vec2::new(0.0, 1.0));
+    app.world.resource_mut::<PaddleInputs>().left = PlayerInput::new(false, true, false);
+    app.world.resource_mut::<PaddleInputs>().left_lower = PlayerInput::new(true, false, false);
+    simulate_ticks(&mut app, 120);
+
+    let half_length = config.paddle_size.y / 2.0;
+    let upper = half_paddle(&mut app, Side::Left, Half::Upper);
+    let lower = half_paddle(&mut app, Side::Left, Half::Lower);
+    // the arena's middle is at 0
+    assert!(
+        (upper.y - half_length).abs() < 0.001,
+        "the upper paddle is at {upper}"
+    );
+    assert!(
+        (lower.y + half_length).abs() < 0.001,
+        "the lower paddle is at {lower}"
+    );
+}
+
+#[test]
+fn ball_bounces_off_the_lower_paddle_as_a_hit_for_its_side() {
+    let mut app = doubles_app();
+    let lower = half_paddle(&mut app, Side::Left, Half::Lower);
+    place_ball(
+        &mut app,
+        Vec2::new(lower.x + 100.0, lower.y),
+        Vec2::new(-400.0, 0.0),
+    );
+    simulate_ticks(&mut app, 30);
+
+    let (position, velocity) = ball(&mut app);
+    assert!(
+        velocity.x > 0.0,
+        "the ball is at {position} going {velocity}"
+    );
+    assert_eq!(app.world.resource::<MatchStats>().hits(Side::Left), 1);
+    assert_eq!(scores(&app), (0, 0));
+}
+
+#[test]
+fn singles_gives_the_paddles_their_whole_side_back() {
+    let mut app = doubles_app();
+    app.world.resource_mut::<MatchSettings>().doubles = false;
+    simulate_ticks(&mut app, 2);
+
+    let mut query = app.world.query_filtered::<&Side, With<Paddle>>();
+    assert_eq!(query.iter(&app.world).count(), 2);
+    let mut query = app.world.query_filtered::<(), With<Half>>();
+    assert_eq!(query.iter(&app.world).count(), 0);
+
+    // free to go below the middle again
+    let start = paddle_y(&mut app, Side::Left);
+    place_ball(&mut app, Vec2::ZERO, Vec2::new(0.0, 1.0));
+    app.world.resource_mut::<PaddleInputs>().left = PlayerInput::new(false, true, false);
+    simulate_ticks(&mut app, 120);
+    let end = paddle_y(&mut app, Side::Left);
+    assert!(
+        end < start && end < -config(&app).paddle_size.y,
+        "the paddle went from {start} to {end}"
+    );
+}
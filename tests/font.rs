@@ -0,0 +1,45 @@
+use std::{thread, time::Duration};
+
+use bevy::{prelude::*, text::FontLoader};
+use bevy_pong::ui::{fall_back_to_embedded_font, UiFont};
+
+#[test]
+fn missing_font_falls_back_to_the_embedded_one() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(AssetPlugin::default())
+        .add_asset::<Font>()
+        .init_asset_loader::<FontLoader>()
+        .add_system(fall_back_to_embedded_font);
+
+    let missing: Handle<Font> = app
+        .world
+        .resource::<AssetServer>()
+        .load("fonts/not-there.ttf");
+    app.insert_resource(UiFont(missing.clone()));
+    let score = app
+        .world
+        .spawn(TextBundle::from_section(
+            "0",
+            TextStyle {
+                font: missing.clone(),
+                ..default()
+            },
+        ))
+        .id();
+
+    // the asset server finds out the file is missing on another thread
+    for _ in 0..200 {
+        app.update();
+        if app.world.resource::<UiFont>().0 != missing {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let font = app.world.resource::<UiFont>().0.clone();
+    assert_ne!(font, missing, "the missing font was never replaced");
+    assert!(app.world.resource::<Assets<Font>>().contains(&font));
+    let text = app.world.get::<Text>(score).unwrap();
+    assert_eq!(text.sections[0].style.font, font);
+}
@@ -0,0 +1,207 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_pong::prelude::*;
+use common::*;
+
+// A four-player match underway, with the top and bottom paddles in
+fn four_player_app() -> App {
+    let mut app = headless_app();
+    app.insert_resource(MatchSettings {
+        four_players: true,
+        ..default()
+    });
+    start(&mut app);
+    // they're spawned on the first step, and in the arena from the next
+    simulate_ticks(&mut app, 1);
+    app
+}
+
+fn seat_paddle(app: &mut App, seat: Seat) -> (Entity, Vec2) {
+    let mut query = app
+        .world
+        .query_filtered::<(Entity, &Seat, &Transform), With<Paddle>>();
+    query
+        .iter(&app.world)
+        .find(|(_, paddle_seat, _)| **paddle_seat == seat)
+        .map(|(paddle, _, transform)| (paddle, transform.translation.truncate()))
+        .expect("all four paddles are spawned")
+}
+
+fn lives(app: &App) -> Lives {
+    *app.world.resource::<Lives>()
+}
+
+#[test]
+fn top_and_bottom_paddles_slide_across_with_their_own_keys() {
+    let mut app = four_player_app();
+    let arena = arena(&app);
+    let (_, top) = seat_paddle(&mut app, Seat::Top);
+    let (_, bottom) = seat_paddle(&mut app, Seat::Bottom);
+    assert!(top.y > 0.0 && top.y < arena.top);
+    assert!(bottom.y < 0.0 && bottom.y > arena.bottom);
+
+    // up is right for the top and bottom paddles
+    place_ball(&mut app, Vec2::ZERO, Vec2::new(0.0, 1.0));
+    app.world.resource_mut::<PaddleInputs>().top = PlayerInput::new(true, false, false);
+    app.world.resource_mut::<PaddleInputs>().bottom = PlayerInput::new(false, true, false);
+    simulate_ticks(&mut app, 10);
+
+    let (_, new_top) = seat_paddle(&mut app, Seat::Top);
+    let (_, new_bottom) = seat_paddle(&mut app, Seat::Bottom);
+    assert!(
+        new_top.x > top.x,
+        "the top paddle went from {top} to {new_top}"
+    );
+    assert!(
+        new_bottom.x < bottom.x,
+        "the bottom paddle went from {bottom} to {new_bottom}"
+    );
+    assert_eq!((new_top.y, new_bottom.y), (top.y, bottom.y));
+}
+
+#[test]
+fn ball_past_the_top_wall_costs_the_top_player_a_life() {
+    let mut app = four_player_app();
+    let arena = arena(&app);
+    place_ball(
+        &mut app,
+        Vec2::new(0.0, arena.top + 5.0),
+        Vec2::new(0.0, 300.0),
+    );
+    simulate_ticks(&mut app, 1);
+
+    let lives = lives(&app);
+    assert_eq!(lives.top, 4);
+    assert_eq!((lives.left, lives.right, lives.bottom), (5, 5, 5));
+    assert_eq!(scores(&app), (0, 0));
+
+    // served at whoever lost it
+    let velocity = serve(&mut app);
+    assert!(velocity.y > 0.0, "served at {velocity}");
+}
+
+#[test]
+fn ball_bounces_off_the_top_paddle_back_down() {
+    let mut app = four_player_app();
+    let (_, top) = seat_paddle(&mut app, Seat::Top);
+    place_ball(
+        &mut app,
+        Vec2::new(top.x, top.y - 100.0),
+        Vec2::new(0.0, 400.0),
+    );
+    simulate_ticks(&mut app, 30);
+
+    let (position, velocity) = ball(&mut app);
+    assert!(
+        velocity.y < 0.0,
+        "the ball is at {position} going {velocity}"
+    );
+    assert_eq!(lives(&app).top, 5);
+    assert_eq!(app.world.resource::<MatchStats>().hits(Seat::Top), 1);
+}
+
+#[test]
+fn wall_closes_behind_a_player_out_of_lives() {
+    let mut app = four_player_app();
+    let arena = arena(&app);
+    app.world.resource_mut::<Lives>().bottom = 1;
+    place_ball(
+        &mut app,
+        Vec2::new(200.0, arena.bottom - 5.0),
+        Vec2::new(0.0, -300.0),
+    );
+    simulate_ticks(&mut app, 2);
+    assert_eq!(lives(&app).bottom, 0);
+    let (paddle, _) = seat_paddle(&mut app, Seat::Bottom);
+    assert!(!app.world.get::<Visibility>(paddle).unwrap().is_visible);
+
+    // the ball comes back off the wall instead, and nobody loses a life for it
+    place_ball(
+        &mut app,
+        Vec2::new(200.0, arena.bottom + 40.0),
+        Vec2::new(0.0, -300.0),
+    );
+    simulate_ticks(&mut app, 30);
+    let (_, velocity) = ball(&mut app);
+    assert!(velocity.y > 0.0, "the ball is going {velocity}");
+    assert_eq!(
+        lives(&app),
+        Lives {
+            bottom: 0,
+            ..default()
+        }
+    );
+    assert_eq!(state(&app), GameState::Playing);
+}
+
+#[test]
+fn last_player_with_lives_wins() {
+    let mut app = four_player_app();
+    let arena = arena(&app);
+    *app.world.resource_mut::<Lives>() = Lives {
+        left: 0,
+        right: 0,
+        top: 1,
+        bottom: 2,
+        winner: None,
+    };
+    place_ball(
+        &mut app,
+        Vec2::new(0.0, arena.top + 5.0),
+        Vec2::new(0.0, 300.0),
+    );
+    simulate_ticks(&mut app, 2);
+
+    assert_eq!(state(&app), GameState::GameOver);
+    assert_eq!(lives(&app).winner, Some(Seat::Bottom));
+    assert_eq!(app.world.resource::<MatchScore>().winner, None);
+}
+
+#[test]
+fn timed_match_goes_to_whoever_has_the_most_lives() {
+    let mut app = headless_app();
+    app.insert_resource(MatchSettings {
+        four_players: true,
+        mode: MatchMode::Timed(TimedFormat {
+            duration: 1.0,
+            clock_runs_during_serve: true,
+        }),
+        ..default()
+    });
+    start(&mut app);
+    app.world.resource_mut::<Lives>().left = 3;
+    app.world.resource_mut::<Lives>().right = 4;
+    app.world.resource_mut::<Lives>().top = 4;
+    app.world.resource_mut::<Lives>().bottom = 2;
+
+    // level at the buzzer, so it plays on
+    simulate_ticks(&mut app, 70);
+    assert_eq!(state(&app), GameState::Playing);
+    assert!(app.world.resource::<MatchScore>().sudden_death);
+
+    app.world.resource_mut::<Lives>().right = 3;
+    simulate_ticks(&mut app, 1);
+    assert_eq!(state(&app), GameState::GameOver);
+    assert_eq!(lives(&app).winner, Some(Seat::Top));
+}
+
+#[test]
+fn two_player_matches_keep_the_top_and_bottom_walls() {
+    let mut app = headless_app();
+    start(&mut app);
+    simulate_ticks(&mut app, 1);
+    let mut query = app.world.query::<&Seat>();
+    assert_eq!(query.iter(&app.world).count(), 2);
+
+    let arena = arena(&app);
+    place_ball(
+        &mut app,
+        Vec2::new(0.0, arena.top - 40.0),
+        Vec2::new(0.0, 300.0),
+    );
+    simulate_ticks(&mut app, 30);
+    let (_, velocity) = ball(&mut app);
+    assert!(velocity.y < 0.0, "the ball is going {velocity}");
+    assert_eq!(lives(&app), Lives::default());
+}
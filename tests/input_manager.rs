@@ -0,0 +1,144 @@
+#![cfg(feature = "input_manager")]
+
+mod common;
+
+use bevy::{
+    input::{
+        gamepad::{GamepadEventRaw, GamepadEventType, GamepadInfo},
+        InputPlugin,
+    },
+    prelude::*,
+};
+use bevy_pong::prelude::*;
+use common::*;
+use leafwing_input_manager::prelude::*;
+
+/// A `headless_app` whose paddles are played through their actions, from the keyboard and gamepads
+fn actions_app() -> App {
+    let mut app = headless_app();
+    app.add_plugin(InputPlugin).add_plugin(PlayerActionsPlugin);
+    start(&mut app);
+    // the paddles are given their input maps once they're spawned
+    simulate_ticks(&mut app, 1);
+    app
+}
+
+fn connect_gamepad(app: &mut App, id: usize) -> Gamepad {
+    let gamepad = Gamepad::new(id);
+    let info = GamepadInfo {
+        name: "Test pad".to_string(),
+    };
+    app.world.send_event(GamepadEventRaw::new(
+        gamepad,
+        GamepadEventType::Connected(info),
+    ));
+    simulate_ticks(app, 1);
+    gamepad
+}
+
+fn press_button(app: &mut App, gamepad: Gamepad, button: GamepadButtonType) {
+    app.world.send_event(GamepadEventRaw::new(
+        gamepad,
+        GamepadEventType::ButtonChanged(button, 1.0),
+    ));
+}
+
+/// How far the left paddle moves in `ticks` steps with `keys` held
+fn left_paddle_travel(app: &mut App, keys: &[KeyCode], ticks: u32) -> f32 {
+    let start_y = paddle_y(app, Side::Left);
+    let mut keyboard = app.world.resource_mut::<Input<KeyCode>>();
+    keys.iter().for_each(|key| keyboard.press(*key));
+    simulate_ticks(app, ticks);
+    paddle_y(app, Side::Left) - start_y
+}
+
+#[test]
+fn every_paddle_gets_an_input_map_for_its_seat() {
+    let mut app = actions_app();
+    let mut query = app
+        .world
+        .query_filtered::<&Seat, (With<Paddle>, With<InputMap<PlayerAction>>)>();
+    let mut seats: Vec<Seat> = query.iter(&app.world).copied().collect();
+    seats.sort_by_key(|seat| Seat::ALL.iter().position(|s| s == seat));
+    assert_eq!(seats, vec![Seat::Left, Seat::Right]);
+}
+
+#[test]
+fn key_and_gamepad_both_move_the_left_paddle() {
+    let mut app = actions_app();
+    let speed = config(&app).paddle_speed;
+    let travel = left_paddle_travel(&mut app, &[KeyCode::W], 10);
+    assert!((travel - speed * TIME_STEP * 10.0).abs() < 1e-3);
+    app.world
+        .resource_mut::<Input<KeyCode>>()
+        .release(KeyCode::W);
+
+    let gamepad = connect_gamepad(&mut app, 0);
+    let start_y = paddle_y(&mut app, Side::Left);
+    press_button(&mut app, gamepad, GamepadButtonType::DPadUp);
+    simulate_ticks(&mut app, 10);
+    let travel = paddle_y(&mut app, Side::Left) - start_y;
+    // the step the button's pressed in is read before the map sees it
+    assert!(travel >= speed * TIME_STEP * 9.0 - 1e-3);
+    // the first gamepad is the left paddle's, not the right's
+    assert_eq!(paddle_y(&mut app, Side::Right), 0.0);
+}
+
+#[test]
+fn chord_bound_to_an_action_moves_the_paddle() {
+    let mut app = actions_app();
+    let left = paddle(&mut app, Side::Left);
+    app.world
+        .get_mut::<InputMap<PlayerAction>>(left)
+        .unwrap()
+        .insert_chord([KeyCode::LControl, KeyCode::Q], PlayerAction::MoveUp);
+
+    // half a chord does nothing
+    assert_eq!(left_paddle_travel(&mut app, &[KeyCode::Q], 5), 0.0);
+    assert!(left_paddle_travel(&mut app, &[KeyCode::LControl], 5) > 0.0);
+}
+
+#[test]
+fn dash_action_dashes_the_paddle() {
+    let mut app = actions_app();
+    app.insert_resource(MatchSettings {
+        paddle_dash: true,
+        ..default()
+    });
+    let config = config(&app);
+    let dash_steps = (config.dash_duration / TIME_STEP).round() as u32;
+    let travel = left_paddle_travel(&mut app, &[KeyCode::S, KeyCode::LShift], dash_steps);
+    assert!((travel + config.dash_distance).abs() < 1e-3);
+}
+
+#[test]
+fn pause_button_pauses_and_unpauses() {
+    let mut app = actions_app();
+    let gamepad = connect_gamepad(&mut app, 1);
+    press_button(&mut app, gamepad, GamepadButtonType::Start);
+    simulate_ticks(&mut app, 1);
+    assert_eq!(state(&app), GameState::Paused);
+
+    app.world.send_event(GamepadEventRaw::new(
+        gamepad,
+        GamepadEventType::ButtonChanged(GamepadButtonType::Start, 0.0),
+    ));
+    simulate_ticks(&mut app, 1);
+    press_button(&mut app, gamepad, GamepadButtonType::Start);
+    simulate_ticks(&mut app, 1);
+    assert_eq!(state(&app), GameState::Playing);
+}
+
+#[test]
+fn pause_button_gives_way_to_a_change_already_queued() {
+    let mut app = actions_app();
+    let gamepad = connect_gamepad(&mut app, 1);
+    // as the pause key would, pressed on the same frame
+    app.world
+        .resource_mut::<State<GameState>>()
+        .push(GameState::Paused)
+        .unwrap();
+    press_button(&mut app, gamepad, GamepadButtonType::Start);
+    simulate_ticks(&mut app, 1);
+    assert_eq!(state(&app), GameState::Paused);
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_pong::prelude::*;
+use common::*;
+use std::{net::Ipv4Addr, time::Duration};
+
+// A host waiting for the other player on `game_port`, announcing itself to the browser on `port` only.
+// Hosts on the same machine are told apart by their game's port
+fn announcing_host(name: &str, game_port: u16, port: u16) -> (App, NetworkLink) {
+    let (host_link, client_link) = NetworkLink::pair();
+    let mut app = headless_app();
+    let beacon = LanBeacon {
+        name: name.to_string(),
+        port: game_port,
+    };
+    app.insert_resource(NetworkRole::Host { port: game_port })
+        .insert_resource(host_link)
+        .insert_resource(LanAnnouncer::to(beacon, (Ipv4Addr::LOCALHOST, port).into()).unwrap());
+    start(&mut app);
+    (app, client_link)
+}
+
+fn browser_app() -> (App, u16) {
+    let mut app = headless_app();
+    let browser = LanBrowser::bind(0).unwrap();
+    let port = browser.port();
+    app.insert_resource(browser);
+    app.update();
+    (app, port)
+}
+
+// Steps every app a tick at a time, giving the beacons a moment to arrive
+fn run(apps: &mut [&mut App], ticks: u32) {
+    for _ in 0..ticks {
+        for app in apps.iter_mut() {
+            simulate_ticks(app, 1);
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+fn listed(app: &App) -> Vec<String> {
+    let mut names: Vec<String> = app
+        .world
+        .resource::<LanGames>()
+        .games()
+        .iter()
+        .map(|game| game.name.clone())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn beacons_survive_the_trip_and_junk_is_ignored() {
+    let beacon = LanBeacon {
+        name: "Left".to_string(),
+        port: 7878,
+    };
+    assert_eq!(LanBeacon::from_bytes(&beacon.to_bytes()), Some(beacon));
+    assert_eq!(LanBeacon::from_bytes(b"GET / HTTP/1.1"), None);
+    assert_eq!(LanBeacon::from_bytes(b"PONGLAN1"), None);
+}
+
+#[test]
+fn every_host_announcing_is_listed() {
+    let (mut browser, port) = browser_app();
+    let (mut alice, _alice_link) = announcing_host("Alice", 7878, port);
+    let (mut bob, _bob_link) = announcing_host("Bob", 7880, port);
+    run(&mut [&mut alice, &mut bob, &mut browser], 20);
+
+    assert_eq!(listed(&browser), ["Alice", "Bob"]);
+    let games = browser.world.resource::<LanGames>().games();
+    let alice = games.iter().find(|game| game.name == "Alice").unwrap();
+    assert_eq!(alice.address.port(), 7878);
+    assert_eq!(
+        alice.join_address(),
+        format!("ws://{}:7878", alice.address.ip())
+    );
+}
+
+#[test]
+fn a_host_that_quits_is_taken_off_the_list() {
+    let (mut browser, port) = browser_app();
+    let (mut alice, _alice_link) = announcing_host("Alice", 7878, port);
+    let (mut bob, _bob_link) = announcing_host("Bob", 7880, port);
+    run(&mut [&mut alice, &mut bob, &mut browser], 20);
+    assert_eq!(listed(&browser).len(), 2);
+
+    // Bob quits, and a few seconds later that game is gone while Alice's is still there
+    drop(bob);
+    run(&mut [&mut alice, &mut browser], 4 * 60);
+    assert_eq!(listed(&browser), ["Alice"]);
+
+    // and once the browser's closed nothing is listed
+    browser.world.remove_resource::<LanBrowser>();
+    browser.update();
+    assert!(listed(&browser).is_empty());
+}
+
+#[test]
+fn the_host_stops_announcing_once_the_other_player_joins() {
+    let (mut host, client_link) = announcing_host("Alice", 7878, 0);
+    simulate_ticks(&mut host, 2);
+    assert!(host.world.contains_resource::<LanAnnouncer>());
+
+    let mut client = headless_app();
+    client
+        .insert_resource(NetworkRole::Client {
+            address: "ws://localhost:7878".to_string(),
+        })
+        .insert_resource(client_link);
+    start(&mut client);
+    for _ in 0..3 {
+        simulate_ticks(&mut client, 1);
+        simulate_ticks(&mut host, 1);
+    }
+    assert_eq!(
+        *host.world.resource::<NetworkStatus>(),
+        NetworkStatus::Connected
+    );
+    assert!(!host.world.contains_resource::<LanAnnouncer>());
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use bevy::{
+    prelude::*,
+    window::{ReceivedCharacter, WindowCommand, WindowId, WindowMode},
+};
+use bevy_pong::prelude::*;
+use common::*;
+
+// From the main menu to typing in the left player's name
+fn edit_left_name(app: &mut App) {
+    simulate_ticks(app, 1);
+    assert_eq!(state(app), GameState::Menu);
+    // the settings are second from the bottom of the main menu, and the names fourth from the bottom of theirs
+    tap(app, KeyCode::Up);
+    tap(app, KeyCode::Up);
+    tap(app, KeyCode::Return);
+    for _ in 0..4 {
+        tap(app, KeyCode::Up);
+    }
+    tap(app, KeyCode::Return);
+}
+
+// Presses a key that types a character, as a keyboard would
+fn type_key(app: &mut App, key: KeyCode, character: char) {
+    app.world.send_event(ReceivedCharacter {
+        id: WindowId::primary(),
+        char: character,
+    });
+    tap(app, key);
+}
+
+fn asked_to_quit(app: &mut App) -> bool {
+    window(app)
+        .drain_commands()
+        .any(|command| matches!(command, WindowCommand::Close))
+}
+
+#[test]
+fn typing_a_name_doesnt_set_off_the_hotkeys() {
+    let mut app = rendered_app(MemoryStorage::default(), WindowMode::Windowed);
+    edit_left_name(&mut app);
+
+    type_key(&mut app, KeyCode::M, 'm');
+    type_key(&mut app, KeyCode::A, 'a');
+    tap(&mut app, KeyCode::Escape);
+    assert!(!app.world.resource::<AudioSettings>().muted);
+    assert!(!asked_to_quit(&mut app));
+
+    tap(&mut app, KeyCode::Return);
+    assert_eq!(app.world.resource::<PlayerNames>().left, "Player 1ma");
+}
+
+#[test]
+fn hotkeys_are_back_once_the_name_is_in() {
+    let mut app = rendered_app(MemoryStorage::default(), WindowMode::Windowed);
+    edit_left_name(&mut app);
+    type_key(&mut app, KeyCode::M, 'm');
+    tap(&mut app, KeyCode::Return);
+
+    tap(&mut app, KeyCode::M);
+    assert!(app.world.resource::<AudioSettings>().muted);
+    tap(&mut app, KeyCode::Escape);
+    assert!(asked_to_quit(&mut app));
+}
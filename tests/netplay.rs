@@ -0,0 +1,106 @@
+#![cfg(feature = "netplay")]
+
+mod common;
+
+use bevy::prelude::*;
+use bevy_ggrs::Rollback;
+use bevy_pong::prelude::*;
+use common::*;
+use std::time::{Duration, Instant};
+
+// A match to two points, with the left paddle heading up and the right one down so both miss a lot
+fn short_match(app: &mut App) {
+    app.insert_resource(MatchSettings {
+        mode: MatchMode::Games(MatchFormat {
+            points_per_game: 2,
+            games_to_win: 1,
+        }),
+        ..default()
+    });
+}
+
+fn hold_paddles(app: &mut App) {
+    let mut paddle_inputs = app.world.resource_mut::<PaddleInputs>();
+    paddle_inputs.left = PlayerInput::new(true, false, false);
+    paddle_inputs.right = PlayerInput::new(false, true, false);
+}
+
+fn sync_test_app(check_distance: usize) -> App {
+    let mut app = headless_app();
+    short_match(&mut app);
+    app.add_plugin(NetplayPlugin { check_distance });
+    start(&mut app);
+    hold_paddles(&mut app);
+    app
+}
+
+// GGRS steps on real time rather than the app's clock, so this waits for it
+fn run_for(app: &mut App, seconds: f32, until: impl Fn(&App) -> bool) {
+    let deadline = Instant::now() + Duration::from_secs_f32(seconds);
+    while !until(app) && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(2));
+        app.update();
+    }
+}
+
+fn trajectory_hash(app: &App) -> TrajectoryHash {
+    *app.world.resource::<TrajectoryHash>()
+}
+
+#[test]
+fn rolled_back_match_ends_the_same_as_one_played_straight() {
+    let mut plain = headless_app();
+    short_match(&mut plain);
+    start(&mut plain);
+    hold_paddles(&mut plain);
+    for _ in 0..5000 {
+        if state(&plain) == GameState::GameOver {
+            break;
+        }
+        simulate_ticks(&mut plain, 1);
+    }
+    assert_eq!(state(&plain), GameState::GameOver);
+
+    // every step the last 7 are rolled back and played again
+    let mut rolled_back = sync_test_app(7);
+    run_for(&mut rolled_back, 60.0, |app| {
+        state(app) == GameState::GameOver
+    });
+    assert_eq!(state(&rolled_back), GameState::GameOver);
+
+    assert_eq!(scores(&rolled_back), scores(&plain));
+    assert_eq!(trajectory_hash(&rolled_back), trajectory_hash(&plain));
+    assert_eq!(
+        rolled_back.world.resource::<MatchScore>(),
+        plain.world.resource::<MatchScore>()
+    );
+}
+
+#[test]
+fn everything_on_the_field_is_rolled_back() {
+    let mut app = sync_test_app(2);
+    run_for(&mut app, 1.0, |_| false);
+
+    let mut untracked = app
+        .world
+        .query_filtered::<(), (Or<(With<Ball>, With<Paddle>)>, Without<Rollback>)>();
+    assert_eq!(untracked.iter(&app.world).count(), 0);
+    let mut tracked = app.world.query_filtered::<(), With<Rollback>>();
+    // both paddles and the ball
+    assert_eq!(tracked.iter(&app.world).count(), 3);
+    assert_ne!(paddle_y(&mut app, Side::Left), 0.0);
+}
+
+#[test]
+fn pausing_holds_the_rolled_back_match() {
+    let mut app = sync_test_app(2);
+    run_for(&mut app, 0.5, |_| false);
+    app.world
+        .resource_mut::<State<GameState>>()
+        .push(GameState::Paused)
+        .unwrap();
+    app.update();
+    let paused_at = paddle_y(&mut app, Side::Left);
+    run_for(&mut app, 0.3, |_| false);
+    assert_eq!(paddle_y(&mut app, Side::Left), paused_at);
+}
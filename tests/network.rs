@@ -0,0 +1,334 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_pong::prelude::*;
+use common::*;
+
+fn host_app(link: NetworkLink) -> App {
+    let mut app = headless_app();
+    app.insert_resource(NetworkRole::Host { port: 7878 })
+        .insert_resource(link);
+    start(&mut app);
+    app
+}
+
+fn client_app(link: NetworkLink) -> App {
+    let mut app = headless_app();
+    app.insert_resource(NetworkRole::Client {
+        address: "ws://localhost:7878".to_string(),
+    })
+    .insert_resource(link);
+    start(&mut app);
+    app
+}
+
+fn spectator_app(link: NetworkLink) -> App {
+    let mut app = headless_app();
+    app.insert_resource(NetworkRole::Spectator {
+        address: "ws://localhost:7878".to_string(),
+    })
+    .insert_resource(link);
+    start(&mut app);
+    app
+}
+
+// A spectator let in to watch the host's match, once it's been sent the match
+fn watch(host: &mut App) -> App {
+    let (host_end, spectator_end) = NetworkLink::pair();
+    host.world.resource_mut::<Spectators>().join(host_end);
+    let mut spectator = spectator_app(spectator_end);
+    simulate_ticks(host, 1);
+    simulate_ticks(&mut spectator, 1);
+    spectator
+}
+
+fn names(app: &App) -> PlayerNames {
+    app.world.resource::<PlayerNames>().clone()
+}
+
+// A host and a client joined in memory, once they've heard from each other
+fn online_apps() -> (App, App) {
+    let (host_link, client_link) = NetworkLink::pair();
+    let mut host = host_app(host_link);
+    let mut client = client_app(client_link);
+    tick_both(&mut host, &mut client, 2);
+    (host, client)
+}
+
+fn tick_both(host: &mut App, client: &mut App, ticks: u32) {
+    for _ in 0..ticks {
+        simulate_ticks(host, 1);
+        simulate_ticks(client, 1);
+    }
+}
+
+fn status(app: &App) -> NetworkStatus {
+    *app.world.resource::<NetworkStatus>()
+}
+
+#[test]
+fn messages_survive_the_trip_over_the_wire() {
+    let snapshot = NetMessage::Snapshot(MatchSnapshot {
+        paddles: [10.0, -20.0],
+        balls: vec![(Vec2::new(1.0, 2.0), Vec2::new(300.0, -40.0))],
+        score: [3, 7],
+        games: [1, 0],
+        winner: Some(Side::Right),
+        paused: true,
+        rally: 4,
+    });
+    let input = NetMessage::Input(PlayerInput::new(true, false, true));
+    let welcome = NetMessage::Welcome(MatchSync::default());
+    let join = NetMessage::Join { spectator: true };
+    for message in [snapshot, input, welcome, join, NetMessage::Full] {
+        assert_eq!(
+            NetMessage::from_bytes(&message.to_bytes()).unwrap(),
+            message
+        );
+    }
+    assert!(NetMessage::from_bytes(&[9, 9, 9]).is_err());
+}
+
+#[test]
+fn host_waits_for_the_client_before_serving() {
+    let (host_link, client_link) = NetworkLink::pair();
+    let mut host = host_app(host_link);
+    simulate_ticks(&mut host, 120);
+    assert_eq!(status(&host), NetworkStatus::Connecting);
+    let (position, velocity) = ball(&mut host);
+    assert_eq!(velocity, Vec2::ZERO, "the ball is at {position}");
+
+    client_link.send(NetMessage::Input(PlayerInput::default()));
+    simulate_ticks(&mut host, 1);
+    assert_eq!(status(&host), NetworkStatus::Connected);
+    assert_ne!(serve(&mut host), Vec2::ZERO);
+}
+
+#[test]
+fn client_plays_the_hosts_right_paddle() {
+    let (mut host, mut client) = online_apps();
+    assert_eq!(status(&host), NetworkStatus::Connected);
+    assert_eq!(status(&client), NetworkStatus::Connected);
+    place_ball(&mut host, Vec2::ZERO, Vec2::new(0.0, 1.0));
+    let (left, right) = (
+        paddle_y(&mut host, Side::Left),
+        paddle_y(&mut host, Side::Right),
+    );
+
+    client.world.resource_mut::<PaddleInputs>().right = PlayerInput::new(true, false, false);
+    tick_both(&mut host, &mut client, 10);
+    assert!(paddle_y(&mut host, Side::Right) > right);
+    assert_eq!(paddle_y(&mut host, Side::Left), left);
+
+    // and the client sees it move
+    let moved = paddle_y(&mut host, Side::Right);
+    tick_both(&mut host, &mut client, 20);
+    assert!(paddle_y(&mut client, Side::Right) > moved);
+}
+
+#[test]
+fn client_follows_the_hosts_ball_and_score() {
+    let (mut host, mut client) = online_apps();
+    place_ball(&mut host, Vec2::new(-200.0, 100.0), Vec2::new(300.0, 50.0));
+    host.insert_resource(Scoreboard {
+        left_score: 3,
+        right_score: 1,
+    });
+    tick_both(&mut host, &mut client, 20);
+
+    let (host_ball, host_velocity) = ball(&mut host);
+    let (client_ball, client_velocity) = ball(&mut client);
+    assert!(
+        host_ball.distance(client_ball) < 1.0,
+        "the host has the ball at {host_ball}, the client at {client_ball}"
+    );
+    assert_eq!(client_velocity, host_velocity);
+    assert_eq!(scores(&client), (3, 1));
+}
+
+#[test]
+fn client_never_runs_the_match_itself() {
+    let (_host_link, client_link) = NetworkLink::pair();
+    let mut client = client_app(client_link);
+    let arena = arena(&client);
+    place_ball(
+        &mut client,
+        Vec2::new(arena.left - 5.0, 0.0),
+        Vec2::new(-300.0, 0.0),
+    );
+    simulate_ticks(&mut client, 60);
+
+    assert_eq!(scores(&client), (0, 0));
+    assert_eq!(ball(&mut client).0, Vec2::new(arena.left - 5.0, 0.0));
+}
+
+#[test]
+fn hosts_game_over_reaches_the_client() {
+    let (mut host, mut client) = online_apps();
+    host.insert_resource(Scoreboard {
+        left_score: 11,
+        right_score: 4,
+    });
+    tick_both(&mut host, &mut client, 3);
+
+    assert_eq!(state(&host), GameState::GameOver);
+    assert_eq!(state(&client), GameState::GameOver);
+    assert_eq!(
+        client.world.resource::<MatchScore>().winner,
+        Some(Side::Left)
+    );
+}
+
+#[test]
+fn losing_the_other_player_pauses_the_match() {
+    let (mut host, client) = online_apps();
+    drop(client);
+    simulate_ticks(&mut host, 2);
+
+    assert_eq!(status(&host), NetworkStatus::Disconnected);
+    assert_eq!(state(&host), GameState::Paused);
+}
+
+#[test]
+fn spectator_joining_partway_is_sent_the_whole_match() {
+    let (mut host, mut client) = online_apps();
+    host.insert_resource(PlayerNames {
+        left: "Ana".to_string(),
+        right: "Ben".to_string(),
+    });
+    host.insert_resource(Scoreboard {
+        left_score: 3,
+        right_score: 1,
+    });
+    host.insert_resource(Rally { hits: 5 });
+    place_ball(&mut host, Vec2::new(-200.0, 100.0), Vec2::ZERO);
+    tick_both(&mut host, &mut client, 2);
+
+    let mut spectator = watch(&mut host);
+    assert_eq!(status(&spectator), NetworkStatus::Connected);
+    assert_eq!(host.world.resource::<Spectators>().watching(), 1);
+    assert_eq!(names(&spectator), names(&host));
+    assert_eq!(scores(&spectator), (3, 1));
+    assert_eq!(spectator.world.resource::<Rally>().hits, 5);
+    assert_eq!(ball(&mut spectator).0, Vec2::new(-200.0, 100.0));
+
+    // then it follows the match along with the client
+    host.insert_resource(Scoreboard {
+        left_score: 4,
+        right_score: 1,
+    });
+    tick_both(&mut host, &mut client, 2);
+    simulate_ticks(&mut spectator, 1);
+    assert_eq!(scores(&spectator), (4, 1));
+}
+
+#[test]
+fn spectator_has_no_say_in_the_match() {
+    let (mut host, mut client) = online_apps();
+    place_ball(&mut host, Vec2::ZERO, Vec2::new(0.0, 1.0));
+    let mut spectator = watch(&mut host);
+    let (left, right) = (
+        paddle_y(&mut host, Side::Left),
+        paddle_y(&mut host, Side::Right),
+    );
+
+    let mut paddle_inputs = spectator.world.resource_mut::<PaddleInputs>();
+    paddle_inputs.left = PlayerInput::new(true, false, false);
+    paddle_inputs.right = PlayerInput::new(true, false, false);
+    for _ in 0..10 {
+        tick_both(&mut host, &mut client, 1);
+        simulate_ticks(&mut spectator, 1);
+    }
+    assert_eq!(paddle_y(&mut host, Side::Left), left);
+    assert_eq!(paddle_y(&mut host, Side::Right), right);
+}
+
+#[test]
+fn spectators_past_the_limit_are_turned_away() {
+    let (host_link, client_link) = NetworkLink::pair();
+    let mut host = headless_app();
+    host.insert_resource(NetworkRole::Host { port: 7878 })
+        .insert_resource(host_link)
+        .insert_resource(Spectators::new(1));
+    start(&mut host);
+    let mut client = client_app(client_link);
+    tick_both(&mut host, &mut client, 2);
+
+    let first = watch(&mut host);
+    let mut second = watch(&mut host);
+    simulate_ticks(&mut second, 2);
+    assert_eq!(status(&first), NetworkStatus::Connected);
+    assert_eq!(status(&second), NetworkStatus::Full);
+    assert_eq!(host.world.resource::<Spectators>().watching(), 1);
+
+    // the one watching leaving makes room, without holding up the match
+    drop(first);
+    tick_both(&mut host, &mut client, 2);
+    assert_eq!(host.world.resource::<Spectators>().watching(), 0);
+    assert_eq!(state(&host), GameState::Playing);
+    let third = watch(&mut host);
+    assert_eq!(status(&third), NetworkStatus::Connected);
+}
+
+#[test]
+fn spectator_gets_its_own_names_back_on_leaving() {
+    let (mut host, _client) = online_apps();
+    host.insert_resource(PlayerNames {
+        left: "Ana".to_string(),
+        right: "Ben".to_string(),
+    });
+    let mut spectator = watch(&mut host);
+    assert_eq!(names(&spectator).left, "Ana");
+
+    spectator
+        .world
+        .resource_mut::<State<GameState>>()
+        .set(GameState::Menu)
+        .unwrap();
+    simulate_ticks(&mut spectator, 2);
+    assert_eq!(names(&spectator), PlayerNames::default());
+    assert!(!spectator.world.contains_resource::<NetworkRole>());
+}
+
+// A client with the keys and screens of the full game, joined to a host in memory
+fn rendered_online_apps() -> (App, App) {
+    let (host_link, client_link) = NetworkLink::pair();
+    let mut host = host_app(host_link);
+    let mut client = rendered_app(MemoryStorage::default(), bevy::window::WindowMode::Windowed);
+    client
+        .insert_resource(SkipMenu {
+            cpu_opponent: false,
+        })
+        .insert_resource(NetworkRole::Client {
+            address: "ws://localhost:7878".to_string(),
+        })
+        .insert_resource(client_link);
+    start(&mut client);
+    tick_both(&mut host, &mut client, 2);
+    (host, client)
+}
+
+#[test]
+fn only_the_host_pauses_or_restarts() {
+    let (mut host, mut client) = rendered_online_apps();
+    host.insert_resource(Scoreboard {
+        left_score: 3,
+        right_score: 2,
+    });
+    tick_both(&mut host, &mut client, 2);
+    assert_eq!(scores(&client), (3, 2));
+
+    let bindings = client.world.resource::<KeyBindings>().clone();
+    tap(&mut client, bindings.pause);
+    assert_eq!(state(&client), GameState::Playing);
+    tap(&mut client, bindings.restart);
+    assert_eq!(scores(&client), (3, 2));
+
+    // and the client follows the host when it does
+    host.world
+        .resource_mut::<State<GameState>>()
+        .push(GameState::Paused)
+        .unwrap();
+    tick_both(&mut host, &mut client, 2);
+    assert_eq!(state(&client), GameState::Paused);
+}
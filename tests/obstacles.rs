@@ -0,0 +1,169 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_pong::prelude::*;
+use common::*;
+
+fn with_obstacles(app: &mut App) {
+    app.insert_resource(MatchSettings {
+        obstacles: true,
+        ..Default::default()
+    });
+}
+
+fn obstacles(app: &mut App) -> Vec<Vec2> {
+    let mut query = app.world.query_filtered::<&Transform, With<Obstacle>>();
+    let mut positions: Vec<Vec2> = query
+        .iter(&app.world)
+        .map(|transform| transform.translation.truncate())
+        .collect();
+    positions.sort_by(|a, b| a.y.total_cmp(&b.y));
+    positions
+}
+
+#[test]
+fn obstacles_only_turn_up_in_obstacle_mode() {
+    let mut app = headless_app();
+    start(&mut app);
+    simulate_ticks(&mut app, 2);
+    assert!(obstacles(&mut app).is_empty());
+
+    let mut app = headless_app();
+    with_obstacles(&mut app);
+    start(&mut app);
+    simulate_ticks(&mut app, 2);
+    let positions = obstacles(&mut app);
+    assert_eq!(positions.len(), 3);
+    // near the middle, and nowhere near the serve
+    let (arena, config) = (arena(&app), config(&app));
+    for position in positions {
+        assert!(position.x.abs() <= arena.width() / 8.0);
+        let gap = (position - config.ball_starting_position).abs();
+        assert!(gap.x > 50.0 || gap.y > 90.0);
+    }
+}
+
+#[test]
+fn configured_obstacles_go_where_they_are_put() {
+    let mut app = headless_app();
+    with_obstacles(&mut app);
+    app.insert_resource(PongConfig {
+        obstacles: Some(vec![Vec2::new(0.0, 150.0), Vec2::new(0.0, -200.0)]),
+        ..Default::default()
+    });
+    start(&mut app);
+    simulate_ticks(&mut app, 2);
+    assert_eq!(
+        obstacles(&mut app),
+        [Vec2::new(0.0, -200.0), Vec2::new(0.0, 150.0)]
+    );
+}
+
+#[test]
+fn ball_bounces_straight_off_an_obstacle() {
+    let mut app = headless_app();
+    with_obstacles(&mut app);
+    app.insert_resource(PongConfig {
+        obstacles: Some(vec![Vec2::new(0.0, 150.0)]),
+        ..Default::default()
+    });
+    start(&mut app);
+    // a little above its middle, where a paddle would send it off at an angle
+    place_ball(&mut app, Vec2::new(-100.0, 170.0), Vec2::new(400.0, 0.0));
+    simulate_ticks(&mut app, 15);
+
+    let (_, velocity) = ball(&mut app);
+    assert!(velocity.x < 0.0);
+    assert_eq!(velocity.y, 0.0);
+    // and it wasn't taken for a paddle hit
+    assert_eq!(app.world.resource::<Rally>().hits, 0);
+}
+
+#[test]
+fn the_menu_clears_the_obstacles_and_the_next_match_gets_new_ones() {
+    let mut app = headless_app();
+    with_obstacles(&mut app);
+    start(&mut app);
+    simulate_ticks(&mut app, 2);
+    let first = obstacles(&mut app);
+
+    app.world
+        .resource_mut::<State<GameState>>()
+        .overwrite_set(GameState::Menu)
+        .unwrap();
+    simulate_ticks(&mut app, 2);
+    assert!(obstacles(&mut app).is_empty());
+
+    app.world
+        .resource_mut::<State<GameState>>()
+        .set(GameState::Playing)
+        .unwrap();
+    simulate_ticks(&mut app, 3);
+    let second = obstacles(&mut app);
+    assert_eq!(second.len(), 3);
+    assert_ne!(first, second);
+}
+
+fn with_moving_obstacle(app: &mut App) {
+    app.insert_resource(MatchSettings {
+        moving_obstacle: true,
+        ..Default::default()
+    });
+}
+
+fn moving_obstacle(app: &mut App) -> (Vec2, Vec2) {
+    let mut query = app
+        .world
+        .query_filtered::<(&Transform, &Velocity), With<Obstacle>>();
+    let (transform, velocity) = query.single(&app.world);
+    (transform.translation.truncate(), velocity.0)
+}
+
+#[test]
+fn moving_obstacle_patrols_between_the_walls() {
+    let mut app = headless_app();
+    with_moving_obstacle(&mut app);
+    start(&mut app);
+    remove_balls(&mut app);
+    simulate_ticks(&mut app, 2);
+    let (position, velocity) = moving_obstacle(&mut app);
+    assert_eq!(position.x, 0.0);
+    assert_eq!(velocity, Vec2::new(0.0, config(&app).moving_obstacle_speed));
+
+    let (arena, config) = (arena(&app), config(&app));
+    let reach = config.wall_thickness / 2.0 + config.obstacle_size.y / 2.0;
+    let mut turned = 0;
+    let mut last_direction = velocity.y;
+    for _ in 0..600 {
+        simulate_ticks(&mut app, 1);
+        let (position, velocity) = moving_obstacle(&mut app);
+        assert!(position.y <= arena.top - reach && position.y >= arena.bottom + reach);
+        if velocity.y != last_direction {
+            turned += 1;
+            last_direction = velocity.y;
+        }
+    }
+    // ten seconds is time enough to go up, down and back up again
+    assert!(turned >= 2, "it only turned {turned} times");
+}
+
+#[test]
+fn ball_picks_up_some_of_the_moving_obstacles_speed() {
+    let mut app = headless_app();
+    with_moving_obstacle(&mut app);
+    start(&mut app);
+    simulate_ticks(&mut app, 2);
+    let (position, velocity) = moving_obstacle(&mut app);
+    assert!(velocity.y > 0.0);
+    place_ball(
+        &mut app,
+        Vec2::new(-60.0, position.y),
+        Vec2::new(600.0, 0.0),
+    );
+    simulate_ticks(&mut app, 5);
+
+    let (_, ball_velocity) = ball(&mut app);
+    assert!(ball_velocity.x < 0.0);
+    assert!((ball_velocity.y - velocity.y * 0.5).abs() < 1e-3);
+    assert_eq!(app.world.resource::<Rally>().hits, 0);
+}
@@ -0,0 +1,232 @@
+use bevy::{prelude::*, sprite::collide_aabb::Collision};
+use bevy_pong::prelude::*;
+
+const BALL: Vec2 = Vec2::new(10.0, 10.0);
+const BOX: Vec2 = Vec2::new(20.0, 20.0);
+
+fn assert_close(a: f32, b: f32) {
+    assert!((a - b).abs() < 1e-3, "{a} is not close to {b}");
+}
+
+#[test]
+fn swept_ball_hits_the_side_facing_it() {
+    let (time, collision) = swept_collision(
+        Vec2::new(-100.0, 0.0),
+        Vec2::new(100.0, 0.0),
+        BALL,
+        Vec2::ZERO,
+        BOX,
+    )
+    .unwrap();
+    // the ball's edge meets the box's when its center is 15 short of the box's center
+    assert_close(time, 85.0 / 200.0);
+    assert_eq!(collision, Collision::Left);
+
+    let (time, collision) =
+        swept_collision(Vec2::new(0.0, 100.0), Vec2::ZERO, BALL, Vec2::ZERO, BOX).unwrap();
+    assert_close(time, 0.85);
+    assert_eq!(collision, Collision::Top);
+}
+
+#[test]
+fn swept_ball_cant_skip_over_a_thin_paddle() {
+    // the whole paddle is between where the ball starts and ends the step
+    let paddle = Vec2::new(20.0, 120.0);
+    let hit = swept_collision(
+        Vec2::new(-50.0, 0.0),
+        Vec2::new(50.0, 0.0),
+        Vec2::new(30.0, 30.0),
+        Vec2::ZERO,
+        paddle,
+    );
+    assert!(matches!(hit, Some((_, Collision::Left))));
+}
+
+#[test]
+fn swept_ball_that_misses_or_already_overlaps_doesnt_hit() {
+    assert!(swept_collision(
+        Vec2::new(-100.0, 100.0),
+        Vec2::new(100.0, 100.0),
+        BALL,
+        Vec2::ZERO,
+        BOX
+    )
+    .is_none());
+    assert!(swept_collision(
+        Vec2::new(-100.0, 0.0),
+        Vec2::new(-50.0, 0.0),
+        BALL,
+        Vec2::ZERO,
+        BOX
+    )
+    .is_none());
+    assert!(swept_collision(Vec2::ZERO, Vec2::new(100.0, 0.0), BALL, Vec2::ZERO, BOX).is_none());
+}
+
+#[test]
+fn overlapping_ball_is_pushed_out_of_the_shallowest_side() {
+    let (correction, collision) = resolve_overlap(
+        Vec2::new(14.0, 0.0),
+        BALL,
+        Vec2::new(-100.0, 0.0),
+        Vec2::ZERO,
+        BOX,
+    )
+    .unwrap();
+    assert_eq!(correction, Vec2::new(1.0, 0.0));
+    assert_eq!(collision, Collision::Right);
+
+    let (correction, collision) = resolve_overlap(
+        Vec2::new(3.0, -12.0),
+        BALL,
+        Vec2::new(100.0, 0.0),
+        Vec2::ZERO,
+        BOX,
+    )
+    .unwrap();
+    assert_eq!(correction, Vec2::new(0.0, -3.0));
+    assert_eq!(collision, Collision::Bottom);
+}
+
+#[test]
+fn ball_with_its_center_inside_goes_back_out_the_way_it_came() {
+    // it's nearer the right edge, but it came in moving right so it leaves on the left
+    let (correction, collision) = resolve_overlap(
+        Vec2::new(5.0, 0.0),
+        BALL,
+        Vec2::new(100.0, 0.0),
+        Vec2::ZERO,
+        BOX,
+    )
+    .unwrap();
+    assert_eq!(correction, Vec2::new(-20.0, 0.0));
+    assert_eq!(collision, Collision::Left);
+}
+
+#[test]
+fn ball_clear_of_the_box_isnt_overlapping() {
+    assert!(resolve_overlap(
+        Vec2::new(20.0, 0.0),
+        BALL,
+        Vec2::new(-100.0, 0.0),
+        Vec2::ZERO,
+        BOX
+    )
+    .is_none());
+    assert!(resolve_overlap(
+        Vec2::new(15.0, 15.0),
+        BALL,
+        Vec2::new(-100.0, 0.0),
+        Vec2::ZERO,
+        BOX
+    )
+    .is_none());
+}
+
+#[test]
+fn center_hit_bounces_straight_back() {
+    let velocity = paddle_bounce(Vec2::new(400.0, 50.0), 0.0, 120.0);
+    assert!(velocity.x < 0.0);
+    assert_close(velocity.y, 0.0);
+    assert_close(velocity.length(), Vec2::new(400.0, 50.0).length());
+}
+
+#[test]
+fn hits_towards_the_tips_deflect_further() {
+    let angle = |velocity: Vec2| velocity.y.atan2(velocity.x.abs());
+    let near_center = paddle_bounce(Vec2::new(-400.0, 0.0), 20.0, 120.0);
+    let near_tip = paddle_bounce(Vec2::new(-400.0, 0.0), 50.0, 120.0);
+    let below = paddle_bounce(Vec2::new(-400.0, 0.0), -50.0, 120.0);
+
+    assert!(near_center.x > 0.0 && near_tip.x > 0.0);
+    assert!(0.0 < angle(near_center) && angle(near_center) < angle(near_tip));
+    assert_close(angle(below), -angle(near_tip));
+    assert_close(near_tip.length(), 400.0);
+}
+
+#[test]
+fn hits_past_the_tip_deflect_no_further_than_the_tip() {
+    let tip = paddle_bounce(Vec2::new(400.0, 0.0), 60.0, 120.0);
+    let past_tip = paddle_bounce(Vec2::new(400.0, 0.0), 90.0, 120.0);
+    assert_eq!(tip, past_tip);
+    // 75 degrees at most, never straight up
+    assert!(tip.y.atan2(tip.x.abs()) <= 75f32.to_radians() + 1e-4);
+}
+
+#[test]
+fn speed_is_capped_without_changing_direction() {
+    let velocity = constrain_velocity(Vec2::new(1200.0, 1600.0), 900.0, 0.25);
+    assert_close(velocity.length(), 900.0);
+    assert_close(velocity.x / velocity.y, 0.75);
+}
+
+#[test]
+fn steep_velocity_gets_enough_horizontal_speed() {
+    for velocity in [
+        Vec2::new(10.0, 400.0),
+        Vec2::new(-10.0, -400.0),
+        Vec2::new(0.0, 400.0),
+    ] {
+        let constrained = constrain_velocity(velocity, 900.0, 0.25);
+        assert_close(constrained.length(), velocity.length());
+        assert_close(constrained.x.abs(), velocity.length() * 0.25);
+        assert_eq!(constrained.y.signum(), velocity.y.signum());
+        if velocity.x != 0.0 {
+            assert_eq!(constrained.x.signum(), velocity.x.signum());
+        }
+    }
+}
+
+#[test]
+fn velocity_within_the_limits_is_left_alone() {
+    let velocity = Vec2::new(-300.0, 400.0);
+    assert_eq!(constrain_velocity(velocity, 900.0, 0.25), velocity);
+}
+
+#[test]
+fn wall_turns_the_ball_around_and_a_moving_one_throws_it() {
+    assert_eq!(reflect(300.0, 0.0), -300.0);
+    assert_eq!(reflect(-300.0, 100.0), 500.0);
+}
+
+#[test]
+fn straight_path_crosses_where_it_was_heading() {
+    let crossing = predict_intercept(Vec2::ZERO, Vec2::new(400.0, 100.0), 400.0, -300.0, 300.0);
+    assert_close(crossing.unwrap(), 100.0);
+}
+
+#[test]
+fn prediction_follows_the_ball_off_the_walls() {
+    // off the top wall at x = 200, and back down to 100 by x = 400
+    let once = predict_intercept(Vec2::ZERO, Vec2::new(400.0, 300.0), 400.0, -150.0, 150.0);
+    assert_close(once.unwrap(), 0.0);
+    // off the top and then the bottom
+    let twice = predict_intercept(
+        Vec2::new(-300.0, 0.0),
+        Vec2::new(100.0, 200.0),
+        300.0,
+        -300.0,
+        300.0,
+    );
+    assert_close(twice.unwrap(), 0.0);
+    let leftwards = predict_intercept(
+        Vec2::new(100.0, 50.0),
+        Vec2::new(-100.0, -100.0),
+        -300.0,
+        -200.0,
+        200.0,
+    );
+    assert_close(leftwards.unwrap(), -50.0);
+}
+
+#[test]
+fn ball_heading_away_has_no_crossing() {
+    assert_eq!(
+        predict_intercept(Vec2::ZERO, Vec2::new(-400.0, 100.0), 400.0, -300.0, 300.0),
+        None
+    );
+    assert_eq!(
+        predict_intercept(Vec2::ZERO, Vec2::new(0.0, 100.0), 400.0, -300.0, 300.0),
+        None
+    );
+}
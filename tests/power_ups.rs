@@ -0,0 +1,216 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_pong::prelude::*;
+use common::*;
+
+fn add_power_up(app: &mut App, position: Vec2, kind: PowerUpKind) {
+    app.world.spawn((
+        SpatialBundle::from_transform(
+            Transform::from_translation(position.extend(0.5))
+                .with_scale(Vec3::new(30.0, 30.0, 1.0)),
+        ),
+        PowerUp::new(kind, 30.0),
+    ));
+}
+
+fn power_ups(app: &mut App) -> usize {
+    let mut query = app.world.query::<&PowerUp>();
+    query.iter(&app.world).count()
+}
+
+fn paddle_height(app: &mut App, side: Side) -> f32 {
+    let paddle = paddle(app, side);
+    app.world.get::<Transform>(paddle).unwrap().scale.y
+}
+
+fn effect(app: &mut App, side: Side) -> Option<PowerUpKind> {
+    let paddle = paddle(app, side);
+    app.world
+        .get::<ActiveEffect>(paddle)
+        .map(|effect| effect.kind)
+}
+
+#[test]
+fn grow_goes_to_the_last_hitter_and_wears_off() {
+    let mut app = headless_app();
+    start(&mut app);
+    // right up against the top wall, so growing would push it through
+    let (arena, config) = (arena(&app), config(&app));
+    let top = arena.top - config.wall_thickness / 2.0 - config.paddle_padding;
+    let left = paddle(&mut app, Side::Left);
+    app.world.get_mut::<Transform>(left).unwrap().translation.y = top - 60.0;
+
+    app.insert_resource(LastTouched(Side::Left));
+    add_power_up(&mut app, Vec2::ZERO, PowerUpKind::Grow);
+    place_ball(&mut app, Vec2::new(-40.0, 0.0), Vec2::new(600.0, 0.0));
+    simulate_ticks(&mut app, 3);
+    remove_balls(&mut app);
+
+    assert_eq!(power_ups(&mut app), 0);
+    assert_eq!(effect(&mut app, Side::Left), Some(PowerUpKind::Grow));
+    assert_eq!(paddle_height(&mut app, Side::Left), 180.0);
+    assert_eq!(paddle_y(&mut app, Side::Left), top - 90.0);
+    assert_eq!(paddle_height(&mut app, Side::Right), 120.0);
+
+    simulate_ticks(&mut app, 600);
+    assert_eq!(effect(&mut app, Side::Left), None);
+    assert_eq!(paddle_height(&mut app, Side::Left), 120.0);
+}
+
+#[test]
+fn shrink_goes_to_the_last_hitters_opponent() {
+    let mut app = headless_app();
+    start(&mut app);
+    app.insert_resource(LastTouched(Side::Left));
+    add_power_up(&mut app, Vec2::ZERO, PowerUpKind::Shrink);
+    place_ball(&mut app, Vec2::new(-40.0, 0.0), Vec2::new(600.0, 0.0));
+    simulate_ticks(&mut app, 3);
+
+    assert_eq!(effect(&mut app, Side::Right), Some(PowerUpKind::Shrink));
+    assert!((paddle_height(&mut app, Side::Right) - 80.0).abs() < 1e-3);
+    assert_eq!(paddle_height(&mut app, Side::Left), 120.0);
+}
+
+#[test]
+fn power_up_waits_for_the_first_hit_of_the_rally() {
+    let mut app = headless_app();
+    start(&mut app);
+    assert!(app.world.get_resource::<LastTouched>().is_none());
+    add_power_up(&mut app, Vec2::new(0.0, 200.0), PowerUpKind::Grow);
+    place_ball(&mut app, Vec2::new(-40.0, 200.0), Vec2::new(600.0, 0.0));
+    simulate_ticks(&mut app, 3);
+
+    assert_eq!(power_ups(&mut app), 1);
+    assert_eq!(effect(&mut app, Side::Left), None);
+    assert_eq!(effect(&mut app, Side::Right), None);
+}
+
+#[test]
+fn collecting_sends_the_kind_and_collector() {
+    let mut app = headless_app();
+    start(&mut app);
+    app.insert_resource(LastTouched(Side::Right));
+    add_power_up(&mut app, Vec2::ZERO, PowerUpKind::Grow);
+    place_ball(&mut app, Vec2::new(40.0, 0.0), Vec2::new(-600.0, 0.0));
+    simulate_ticks(&mut app, 3);
+
+    let events = app.world.resource::<Events<PowerUpCollected>>();
+    let collected: Vec<_> = events
+        .get_reader()
+        .iter(events)
+        .map(|event| (event.kind, event.collector_side))
+        .collect();
+    assert_eq!(collected, [(PowerUpKind::Grow, Side::Right)]);
+}
+
+#[test]
+fn uncollected_power_ups_disappear() {
+    let mut app = headless_app();
+    app.insert_resource(PongConfig {
+        power_up_interval: 1.0,
+        power_up_lifetime: 2.0,
+        ..Default::default()
+    })
+    .insert_resource(MatchSettings {
+        power_ups: true,
+        ..Default::default()
+    });
+    start(&mut app);
+    remove_balls(&mut app);
+
+    simulate_ticks(&mut app, 70);
+    assert_eq!(power_ups(&mut app), 1);
+    // gone after its two seconds, and the next one a second after that
+    simulate_ticks(&mut app, 120);
+    assert_eq!(power_ups(&mut app), 0);
+    simulate_ticks(&mut app, 60);
+    assert_eq!(power_ups(&mut app), 1);
+}
+
+#[test]
+fn power_ups_only_turn_up_when_turned_on() {
+    for power_ups_on in [false, true] {
+        let mut app = headless_app();
+        app.insert_resource(MatchSettings {
+            power_ups: power_ups_on,
+            ..Default::default()
+        });
+        start(&mut app);
+        remove_balls(&mut app);
+        simulate_ticks(&mut app, 600);
+
+        assert_eq!(power_ups(&mut app), usize::from(power_ups_on));
+    }
+}
+
+#[test]
+fn turbo_speeds_up_the_ball_within_the_cap() {
+    for (speed, boosted) in [(400.0, 600.0), (800.0, 900.0)] {
+        let mut app = headless_app();
+        start(&mut app);
+        app.insert_resource(LastTouched(Side::Left));
+        add_power_up(&mut app, Vec2::new(0.0, 200.0), PowerUpKind::Turbo);
+        let turbo_ball = place_ball(&mut app, Vec2::new(-40.0, 200.0), Vec2::new(speed, 0.0));
+        simulate_ticks(&mut app, 3);
+
+        assert_eq!(power_ups(&mut app), 0);
+        assert!((ball(&mut app).1.x - boosted).abs() < 1e-3);
+        assert_eq!(
+            app.world.get::<Turbo>(turbo_ball),
+            Some(&Turbo(boosted / speed))
+        );
+    }
+}
+
+#[test]
+fn goal_ends_the_turbo() {
+    let mut app = headless_app();
+    start(&mut app);
+    remove_balls(&mut app);
+    // one about to go in, the other with a turbo and a long way from either goal
+    add_ball(&mut app, Vec2::new(400.0, 200.0), Vec2::new(600.0, 0.0));
+    let turbo_ball = add_ball(&mut app, Vec2::new(0.0, 0.0), Vec2::new(0.0, 300.0));
+    app.world.entity_mut(turbo_ball).insert(Turbo(1.5));
+
+    simulate_ticks(&mut app, 10);
+    assert_eq!(scores(&app), (1, 0));
+    assert!(app.world.get::<Turbo>(turbo_ball).is_none());
+    assert!((ball(&mut app).1.length() - 200.0).abs() < 1e-3);
+}
+
+#[test]
+fn multi_ball_splits_the_ball_in_three() {
+    let mut app = headless_app();
+    start(&mut app);
+    app.insert_resource(LastTouched(Side::Left));
+    add_power_up(&mut app, Vec2::new(0.0, 50.0), PowerUpKind::MultiBall);
+    place_ball(&mut app, Vec2::new(-40.0, 50.0), Vec2::new(400.0, 0.0));
+    simulate_ticks(&mut app, 3);
+
+    let mut angles: Vec<f32> = balls(&mut app)
+        .iter()
+        .map(|(_, velocity)| {
+            assert!((velocity.length() - 400.0).abs() < 1e-3);
+            velocity.y.atan2(velocity.x).to_degrees().round()
+        })
+        .collect();
+    angles.sort_by(f32::total_cmp);
+    assert_eq!(angles, [-25.0, 0.0, 25.0]);
+}
+
+#[test]
+fn multi_ball_stops_at_the_most_balls_allowed() {
+    let mut app = headless_app();
+    app.insert_resource(PongConfig {
+        max_balls: 2,
+        ..Default::default()
+    });
+    start(&mut app);
+    app.insert_resource(LastTouched(Side::Left));
+    add_power_up(&mut app, Vec2::new(0.0, 50.0), PowerUpKind::MultiBall);
+    place_ball(&mut app, Vec2::new(-40.0, 50.0), Vec2::new(400.0, 0.0));
+    simulate_ticks(&mut app, 3);
+
+    assert_eq!(balls(&mut app).len(), 2);
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_pong::prelude::*;
+use common::*;
+
+// Short enough to record quickly. Only the recording is given these, the replay brings them to the playback
+fn short_match() -> MatchSettings {
+    MatchSettings {
+        mode: MatchMode::Games(MatchFormat {
+            games_to_win: 1,
+            points_per_game: 3,
+        }),
+        ..Default::default()
+    }
+}
+
+// A short match between a computer on the right and a left paddle that sweeps up and down
+fn record_match() -> Replay {
+    record_match_with(short_match())
+}
+
+fn record_match_with(settings: MatchSettings) -> Replay {
+    let mut app = headless_app();
+    app.insert_resource(SkipMenu { cpu_opponent: true })
+        .insert_resource(ReplayRecorder::enabled())
+        .insert_resource(settings);
+    start(&mut app);
+
+    let mut tick = 0;
+    while state(&app) == GameState::Playing {
+        assert!(tick < 100_000, "the recorded match never ended");
+        let up = tick / 40 % 2 == 0;
+        app.world.resource_mut::<PaddleInputs>().left = PlayerInput::new(up, !up, false);
+        simulate_ticks(&mut app, 1);
+        tick += 1;
+    }
+
+    let replay = app.world.resource::<ReplayRecorder>().replay().clone();
+    let (left, right) = scores(&app);
+    assert_eq!(replay.final_score, [left as u32, right as u32]);
+    assert_eq!(
+        replay.trajectory_hash,
+        app.world.resource::<TrajectoryHash>().0
+    );
+    replay
+}
+
+fn play_back(replay: Replay) -> App {
+    let mut app = headless_app();
+    // a different seed, the replay's own is what counts
+    app.insert_resource(GameRng::seeded(SEED + 1))
+        .insert_resource(replay.config.clone())
+        .insert_resource(ReplayPlayer::new(replay));
+    start(&mut app);
+    app
+}
+
+// Plays the replay through to the end, checking it finishes where the recording did
+fn assert_plays_back_exactly(replay: &Replay) {
+    let mut app = play_back(replay.clone());
+    let mut tick = 0;
+    while state(&app) == GameState::Playing {
+        assert!(
+            tick <= replay.ticks(),
+            "the replay ran past the end of its inputs"
+        );
+        simulate_ticks(&mut app, 1);
+        tick += 1;
+    }
+
+    let (left, right) = scores(&app);
+    assert_eq!([left as u32, right as u32], replay.final_score);
+    assert_eq!(
+        app.world.resource::<TrajectoryHash>().0,
+        replay.trajectory_hash
+    );
+    assert_eq!(app.world.resource::<ReplayPlayer>().tick(), replay.ticks());
+}
+
+#[test]
+fn replay_ends_the_same_way() {
+    let replay = record_match();
+    assert!(replay.cpu_controlled[1] && !replay.cpu_controlled[0]);
+    assert_plays_back_exactly(&replay);
+}
+
+#[test]
+fn replay_is_played_under_the_rules_it_was_recorded_with() {
+    let replay = record_match_with(MatchSettings {
+        power_ups: true,
+        gravity: Some(DEFAULT_GRAVITY),
+        ..short_match()
+    });
+    assert!(replay.settings.power_ups);
+    assert_eq!(replay.settings.gravity, Some(DEFAULT_GRAVITY));
+
+    // through the bytes, as a saved replay would be, and played back with no rules given
+    let replay = Replay::from_bytes(&replay.to_bytes()).unwrap();
+    assert_plays_back_exactly(&replay);
+}
+
+#[test]
+fn rubber_band_match_plays_back_exactly() {
+    let replay = record_match_with(MatchSettings {
+        rubber_band: true,
+        ..short_match()
+    });
+    assert!(replay.settings.rubber_band);
+    assert_plays_back_exactly(&replay);
+}
+
+#[test]
+fn matches_with_more_paddles_than_inputs_arent_recorded() {
+    let mut app = headless_app();
+    app.insert_resource(ReplayRecorder::enabled())
+        .insert_resource(MatchSettings {
+            doubles: true,
+            ..short_match()
+        });
+    start(&mut app);
+    simulate_ticks(&mut app, 10);
+    assert_eq!(app.world.resource::<ReplayRecorder>().replay().ticks(), 0);
+}
+
+#[test]
+fn replay_survives_the_round_trip_through_bytes() {
+    let replay = record_match();
+    let read = Replay::from_bytes(&replay.to_bytes()).unwrap();
+
+    assert_eq!(read.seed, replay.seed);
+    // colors are stored to 8 bits a channel, so compare the config as it's written
+    assert_eq!(read.to_bytes(), replay.to_bytes());
+    assert_eq!(read.settings.mode, replay.settings.mode);
+    assert_eq!(read.difficulty, replay.difficulty);
+    assert_eq!(read.cpu_controlled, replay.cpu_controlled);
+    assert_eq!(read.final_score, replay.final_score);
+    assert_eq!(read.trajectory_hash, replay.trajectory_hash);
+    assert_eq!(read.inputs, replay.inputs);
+}
+
+#[test]
+fn bytes_that_arent_a_replay_are_refused() {
+    assert!(matches!(
+        Replay::from_bytes(b"not a replay at all"),
+        Err(ReplayError::NotAReplay)
+    ));
+
+    let bytes = Replay::default().to_bytes();
+    assert!(matches!(
+        Replay::from_bytes(&bytes[..20]),
+        Err(ReplayError::Truncated)
+    ));
+}
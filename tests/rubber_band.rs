@@ -0,0 +1,156 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_pong::prelude::*;
+use common::*;
+
+// A match against the computer on the right, in rubber band mode, at `difficulty`
+fn rubber_band_app(difficulty: Difficulty) -> App {
+    let mut app = headless_app();
+    app.insert_resource(SkipMenu { cpu_opponent: true })
+        .insert_resource(difficulty)
+        .insert_resource(MatchSettings {
+            rubber_band: true,
+            ..default()
+        });
+    start(&mut app);
+    app
+}
+
+fn set_score(app: &mut App, left_score: usize, right_score: usize) {
+    app.insert_resource(Scoreboard {
+        left_score,
+        right_score,
+    });
+    simulate_ticks(app, 1);
+}
+
+fn shift(app: &App) -> f32 {
+    app.world.resource::<DifficultyShift>().0
+}
+
+#[test]
+fn shift_follows_the_players_lead_along_the_curve() {
+    let config = PongConfig::default();
+    assert_eq!(config.rubber_band_shift(0), 0.0);
+    assert_eq!(config.rubber_band_shift(2), 0.0);
+    assert_eq!(config.rubber_band_shift(3), 0.5);
+    assert_eq!(config.rubber_band_shift(-3), -0.5);
+    assert_eq!(config.rubber_band_shift(4), 1.0);
+    // never more than a notch
+    assert_eq!(config.rubber_band_shift(-9), -1.0);
+
+    let gentler = PongConfig {
+        rubber_band_gap: 2,
+        rubber_band_rate: 0.25,
+        ..default()
+    };
+    assert_eq!(gentler.rubber_band_shift(3), 0.5);
+}
+
+#[test]
+fn in_between_levels_blend_the_difficulties_either_side() {
+    for difficulty in [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard] {
+        let skill = Difficulty::skill_at(difficulty.level());
+        assert_eq!(skill.max_speed_fraction, difficulty.max_speed_fraction());
+        assert_eq!(skill.handicap, difficulty.handicap());
+        assert_eq!(skill.predicts_bounces, difficulty.predicts_bounces());
+    }
+
+    let (medium, hard) = (Difficulty::Medium, Difficulty::Hard);
+    let between = Difficulty::skill_at(1.25);
+    assert!(between.max_speed_fraction > medium.max_speed_fraction());
+    assert!(between.max_speed_fraction < hard.max_speed_fraction());
+    assert!(between.dead_zone < medium.dead_zone() && between.dead_zone > hard.dead_zone());
+    assert!(!between.predicts_bounces);
+    assert!(Difficulty::skill_at(1.75).predicts_bounces);
+
+    // nothing past Hard
+    assert_eq!(Difficulty::skill_at(3.0), Difficulty::skill_at(2.0));
+}
+
+#[test]
+fn computer_eases_off_while_the_player_is_behind() {
+    let mut app = rubber_band_app(Difficulty::Medium);
+    set_score(&mut app, 0, 2);
+    assert_eq!(shift(&app), 0.0);
+    set_score(&mut app, 0, 3);
+    assert_eq!(shift(&app), -0.5);
+    set_score(&mut app, 1, 8);
+    assert_eq!(shift(&app), -1.0);
+
+    // and sharpens up once the player is ahead
+    set_score(&mut app, 7, 3);
+    assert_eq!(shift(&app), 1.0);
+    set_score(&mut app, 0, 0);
+    assert_eq!(shift(&app), 0.0);
+}
+
+#[test]
+fn computer_ahead_by_a_notch_moves_at_the_next_difficultys_speed() {
+    let mut app = rubber_band_app(Difficulty::Medium);
+    // hanging high above the computer's paddle
+    place_ball(&mut app, Vec2::new(300.0, 200.0), Vec2::ZERO);
+    set_score(&mut app, 4, 0);
+    let start = paddle_y(&mut app, Side::Right);
+    simulate_ticks(&mut app, 5);
+
+    let expected =
+        config(&app).paddle_speed * Difficulty::Hard.max_speed_fraction() * TIME_STEP * 5.0;
+    let moved = paddle_y(&mut app, Side::Right) - start;
+    assert!((moved - expected).abs() < 1e-3, "the paddle moved {moved}");
+}
+
+#[test]
+fn difficulty_stays_put_without_rubber_banding_or_a_computer() {
+    let mut app = headless_app();
+    app.insert_resource(SkipMenu { cpu_opponent: true });
+    start(&mut app);
+    set_score(&mut app, 0, 5);
+    assert_eq!(shift(&app), 0.0);
+
+    // two players have no computer to move
+    let mut app = headless_app();
+    app.insert_resource(MatchSettings {
+        rubber_band: true,
+        ..default()
+    });
+    start(&mut app);
+    set_score(&mut app, 0, 5);
+    assert_eq!(shift(&app), 0.0);
+}
+
+#[test]
+fn shift_never_goes_against_the_lead() {
+    let backwards = PongConfig {
+        rubber_band_rate: -0.5,
+        ..default()
+    };
+    assert_eq!(backwards.rubber_band_shift(3), 0.0);
+    assert_eq!(backwards.rubber_band_shift(-9), 0.0);
+}
+
+#[test]
+fn rate_the_game_cant_use_is_put_back() {
+    for rate in [-0.5, f32::NAN, f32::INFINITY] {
+        let mut app = headless_app();
+        app.insert_resource(SkipMenu { cpu_opponent: true })
+            .insert_resource(PongConfig {
+                rubber_band_rate: rate,
+                ..default()
+            })
+            .insert_resource(MatchSettings {
+                rubber_band: true,
+                ..default()
+            });
+        start(&mut app);
+        assert_eq!(
+            config(&app).rubber_band_rate,
+            PongConfig::default().rubber_band_rate
+        );
+
+        // the computer eases off for a player who's behind, as usual
+        set_score(&mut app, 0, 3);
+        assert_eq!(shift(&app), -0.5);
+    }
+}
@@ -0,0 +1,202 @@
+mod common;
+
+use bevy_pong::prelude::*;
+use common::*;
+
+#[test]
+fn game_goes_to_the_first_to_the_points() {
+    let format = MatchFormat::default();
+    assert_eq!(format.game_winner(10, 9, false), None);
+    assert_eq!(format.game_winner(11, 9, false), Some(Side::Left));
+    assert_eq!(format.game_winner(3, 11, false), Some(Side::Right));
+    assert_eq!(format.game_winner(11, 10, false), Some(Side::Left));
+}
+
+#[test]
+fn win_by_two_needs_a_clear_lead() {
+    let format = MatchFormat::default();
+    assert_eq!(format.game_winner(11, 10, true), None);
+    assert_eq!(format.game_winner(12, 10, true), Some(Side::Left));
+    assert_eq!(format.game_winner(14, 15, true), None);
+    assert_eq!(format.game_winner(14, 16, true), Some(Side::Right));
+}
+
+#[test]
+fn deuce_only_once_both_are_a_point_short() {
+    let format = MatchFormat::default();
+    assert_eq!(format.deuce(10, 9, true), None);
+    assert_eq!(format.deuce(10, 10, true), Some(Deuce::Level));
+    assert_eq!(
+        format.deuce(11, 10, true),
+        Some(Deuce::Advantage(Side::Left))
+    );
+    assert_eq!(
+        format.deuce(12, 13, true),
+        Some(Deuce::Advantage(Side::Right))
+    );
+    assert_eq!(format.deuce(10, 10, false), None);
+}
+
+#[test]
+fn match_point_needs_the_next_point_to_win_the_last_game() {
+    let format = MatchFormat::default();
+    assert!(format.match_point(Side::Left, 10, 3, 0, false));
+    assert!(!format.match_point(Side::Right, 10, 3, 0, false));
+    assert!(!format.match_point(Side::Left, 10, 10, 0, true));
+    assert!(format.match_point(Side::Right, 10, 11, 0, true));
+
+    let best_of_three = MatchFormat::best_of(3, 11);
+    assert!(!best_of_three.match_point(Side::Left, 10, 3, 0, false));
+    assert!(best_of_three.match_point(Side::Left, 10, 3, 1, false));
+}
+
+#[test]
+fn best_of_needs_a_majority() {
+    assert_eq!(MatchFormat::best_of(3, 5).games_to_win, 2);
+    assert_eq!(MatchFormat::best_of(5, 5).games_to_win, 3);
+}
+
+#[test]
+fn serve_rule_picks_the_side() {
+    assert_eq!(
+        ServeRule::TowardLoser.serve_toward(Side::Left),
+        Some(Side::Right)
+    );
+    assert_eq!(
+        ServeRule::TowardWinner.serve_toward(Side::Left),
+        Some(Side::Left)
+    );
+    assert_eq!(ServeRule::Random.serve_toward(Side::Left), None);
+}
+
+#[test]
+fn harder_computer_is_faster_and_sharper() {
+    let levels = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+    for pair in levels.windows(2) {
+        let (easier, harder) = (pair[0], pair[1]);
+        assert!(easier.max_speed_fraction() < harder.max_speed_fraction());
+        assert!(easier.lookahead() < harder.lookahead());
+        assert!(easier.dead_zone() > harder.dead_zone());
+        let (easier, harder) = (easier.handicap(), harder.handicap());
+        assert!(easier.reaction_delay > harder.reaction_delay);
+        assert!(easier.aim_error > harder.aim_error);
+    }
+}
+
+#[test]
+fn match_ends_when_a_side_has_won() {
+    let mut app = headless_app();
+    start(&mut app);
+    app.insert_resource(Scoreboard {
+        left_score: 11,
+        right_score: 4,
+    });
+    simulate_ticks(&mut app, 2);
+
+    assert_eq!(app.world.resource::<MatchScore>().winner, Some(Side::Left));
+    assert_eq!(state(&app), GameState::GameOver);
+}
+
+#[test]
+fn match_plays_on_at_eleven_ten_when_it_has_to_be_won_by_two() {
+    let mut app = headless_app();
+    app.insert_resource(MatchSettings {
+        win_by_two: true,
+        ..Default::default()
+    });
+    start(&mut app);
+
+    app.insert_resource(Scoreboard {
+        left_score: 11,
+        right_score: 10,
+    });
+    simulate_ticks(&mut app, 2);
+    assert_eq!(app.world.resource::<MatchScore>().winner, None);
+    assert_eq!(state(&app), GameState::Playing);
+
+    app.insert_resource(Scoreboard {
+        left_score: 12,
+        right_score: 10,
+    });
+    simulate_ticks(&mut app, 2);
+    assert_eq!(app.world.resource::<MatchScore>().winner, Some(Side::Left));
+}
+
+#[test]
+fn level_one_short_goes_to_sudden_death_with_golden_goal() {
+    let mut app = headless_app();
+    app.insert_resource(MatchSettings {
+        golden_goal: true,
+        ..Default::default()
+    });
+    start(&mut app);
+
+    app.insert_resource(Scoreboard {
+        left_score: 10,
+        right_score: 10,
+    });
+    simulate_ticks(&mut app, 2);
+    let match_score = *app.world.resource::<MatchScore>();
+    assert!(match_score.sudden_death);
+    assert_eq!(match_score.winner, None);
+
+    // the next goal wins it, even without reaching the points
+    app.insert_resource(Scoreboard {
+        left_score: 10,
+        right_score: 11,
+    });
+    simulate_ticks(&mut app, 2);
+    assert_eq!(app.world.resource::<MatchScore>().winner, Some(Side::Right));
+}
+
+#[test]
+fn timed_match_tied_at_the_buzzer_goes_to_sudden_death() {
+    let mut app = headless_app();
+    let format = TimedFormat {
+        duration: 1.0,
+        clock_runs_during_serve: true,
+    };
+    app.insert_resource(MatchSettings {
+        mode: MatchMode::Timed(format),
+        ..Default::default()
+    });
+    start(&mut app);
+
+    // keep the ball out of the way while the clock runs down
+    remove_balls(&mut app);
+    app.insert_resource(Scoreboard {
+        left_score: 2,
+        right_score: 2,
+    });
+    simulate_ticks(&mut app, 90);
+    let match_score = *app.world.resource::<MatchScore>();
+    assert!(match_score.sudden_death);
+    assert_eq!(match_score.winner, None);
+    assert_eq!(state(&app), GameState::Playing);
+
+    app.insert_resource(Scoreboard {
+        left_score: 3,
+        right_score: 2,
+    });
+    simulate_ticks(&mut app, 2);
+    assert_eq!(app.world.resource::<MatchScore>().winner, Some(Side::Left));
+    assert_eq!(state(&app), GameState::GameOver);
+}
+
+#[test]
+fn practice_never_ends() {
+    let mut app = headless_app();
+    app.insert_resource(MatchSettings {
+        practice: true,
+        ..Default::default()
+    });
+    start(&mut app);
+    app.insert_resource(Scoreboard {
+        left_score: 30,
+        right_score: 0,
+    });
+    simulate_ticks(&mut app, 2);
+
+    assert_eq!(app.world.resource::<MatchScore>().winner, None);
+    assert_eq!(state(&app), GameState::Playing);
+}
@@ -0,0 +1,90 @@
+use bevy::{prelude::KeyCode, window::PresentMode};
+use bevy_pong::{prelude::*, save::FileStorage};
+
+#[test]
+fn settings_and_records_come_back_as_they_were_saved() {
+    let mut storage = SaveStorage(Box::new(MemoryStorage::default()));
+    let settings = Settings {
+        display: DisplaySettings {
+            theme: ThemePreset::Neon,
+            high_contrast: true,
+            fullscreen: true,
+            present_mode: Some(PresentMode::Immediate),
+        },
+        difficulty: Difficulty::Hard,
+        ..Default::default()
+    };
+    let stats = PersistentStats {
+        player_wins: 3,
+        longest_rally: 17,
+        ..Default::default()
+    };
+    storage.save("settings", &settings);
+    storage.save("stats", &stats);
+
+    assert_eq!(storage.load::<Settings>("settings"), Some(settings));
+    assert_eq!(storage.load::<PersistentStats>("stats"), Some(stats));
+}
+
+#[test]
+fn rebound_keys_are_saved_with_the_settings() {
+    let mut bindings = KeyBindings::default();
+    assert_eq!(bindings.rebind(Action::Serve, KeyCode::Return), None);
+    let settings = Settings {
+        bindings: Some(bindings),
+        ..Default::default()
+    };
+    let mut storage = SaveStorage(Box::new(MemoryStorage::default()));
+    storage.save("settings", &settings);
+
+    let loaded = storage.load::<Settings>("settings").unwrap();
+    assert_eq!(loaded.bindings.unwrap().serve, KeyCode::Return);
+    // settings saved before there were keys to change leave the game's own
+    let mut backend = MemoryStorage::default();
+    backend.save("settings", "(difficulty: Hard)").unwrap();
+    let storage = SaveStorage(Box::new(backend));
+    assert_eq!(storage.load::<Settings>("settings").unwrap().bindings, None);
+}
+
+#[test]
+fn rebinding_a_taken_key_swaps_the_two_actions() {
+    let mut bindings = KeyBindings::default();
+    assert_eq!(
+        bindings.rebind(Action::LeftUp, KeyCode::Up),
+        Some(Action::RightUp)
+    );
+    assert_eq!(bindings.left_up, KeyCode::Up);
+    assert_eq!(bindings.right_up, KeyCode::W);
+    // the key named is the one that counts now, wherever the old one was
+    assert_eq!(bindings.scan_code(Action::LeftUp), None);
+
+    // its own key again is no change at all
+    let rebound = bindings.clone();
+    assert_eq!(bindings.rebind(Action::LeftDown, KeyCode::S), None);
+    assert_eq!(bindings, rebound);
+}
+
+#[test]
+fn unreadable_or_missing_saves_give_nothing() {
+    let mut backend = MemoryStorage::default();
+    backend.save("stats", "not a record").unwrap();
+    let storage = SaveStorage(Box::new(backend));
+
+    assert_eq!(storage.load::<PersistentStats>("stats"), None);
+    assert_eq!(storage.load::<Settings>("settings"), None);
+}
+
+#[test]
+fn file_storage_keeps_a_file_for_each_key() {
+    let dir = std::env::temp_dir().join(format!("bevy_pong_save_{}", std::process::id()));
+    let mut storage = FileStorage { dir: dir.clone() };
+    assert_eq!(storage.load("stats"), Ok(None));
+
+    storage.save("stats", "(player_wins: 2)").unwrap();
+    assert_eq!(
+        storage.load("stats"),
+        Ok(Some("(player_wins: 2)".to_string()))
+    );
+    assert!(dir.join("stats.ron").exists());
+    std::fs::remove_dir_all(dir).unwrap();
+}
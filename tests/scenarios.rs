@@ -0,0 +1,991 @@
+mod common;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_pong::prelude::*;
+use common::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::time::Duration;
+
+#[test]
+fn ball_bounces_off_the_top_wall() {
+    let mut app = headless_app();
+    start(&mut app);
+    let top = arena(&app).top;
+    place_ball(
+        &mut app,
+        Vec2::new(0.0, top - 60.0),
+        Vec2::new(200.0, 400.0),
+    );
+
+    simulate_ticks(&mut app, 15);
+    let (position, velocity) = ball(&mut app);
+    assert!(velocity.y < 0.0, "the ball is still going up at {velocity}");
+    assert!(velocity.x > 0.0);
+    assert!(position.y < top);
+}
+
+#[test]
+fn ball_past_the_right_paddle_scores_for_the_left() {
+    let mut app = headless_app();
+    start(&mut app);
+    // well above the paddle, which stays where it is
+    place_ball(&mut app, Vec2::new(200.0, 200.0), Vec2::new(600.0, 0.0));
+
+    simulate_ticks(&mut app, 30);
+    assert_eq!(scores(&app), (1, 0));
+    // and a new ball is waiting to be served
+    assert_eq!(ball(&mut app).1, Vec2::ZERO);
+}
+
+#[test]
+fn ball_aimed_at_the_paddle_bounces_back() {
+    let mut app = headless_app();
+    start(&mut app);
+    let paddle_y = paddle_y(&mut app, Side::Right);
+    place_ball(&mut app, Vec2::new(200.0, paddle_y), Vec2::new(600.0, 0.0));
+
+    simulate_ticks(&mut app, 30);
+    assert_eq!(scores(&app), (0, 0));
+    assert!(ball(&mut app).1.x < 0.0);
+    assert_eq!(app.world.resource::<Rally>().hits, 1);
+}
+
+#[test]
+fn next_serve_goes_toward_the_side_that_conceded() {
+    let mut app = headless_app();
+    start(&mut app);
+    place_ball(&mut app, Vec2::new(200.0, 200.0), Vec2::new(600.0, 0.0));
+    simulate_ticks(&mut app, 30);
+    assert_eq!(scores(&app), (1, 0));
+    assert!(serve(&mut app).x > 0.0);
+
+    place_ball(&mut app, Vec2::new(-200.0, 200.0), Vec2::new(-600.0, 0.0));
+    simulate_ticks(&mut app, 30);
+    assert_eq!(scores(&app), (1, 1));
+    assert!(serve(&mut app).x < 0.0);
+}
+
+#[test]
+fn every_ball_scores_and_only_the_last_brings_a_new_serve() {
+    let mut app = headless_app();
+    start(&mut app);
+    remove_balls(&mut app);
+    add_ball(&mut app, Vec2::new(-300.0, 200.0), Vec2::new(-600.0, 0.0));
+    add_ball(&mut app, Vec2::new(100.0, -200.0), Vec2::new(-600.0, 0.0));
+
+    // the first is in, the second still on its way
+    simulate_ticks(&mut app, 20);
+    assert_eq!(scores(&app), (0, 1));
+    assert_eq!(balls(&mut app).len(), 1);
+    assert_ne!(ball(&mut app).1, Vec2::ZERO);
+
+    simulate_ticks(&mut app, 40);
+    assert_eq!(scores(&app), (0, 2));
+    assert_eq!(ball(&mut app).1, Vec2::ZERO);
+}
+
+#[test]
+fn paddles_stop_at_the_walls() {
+    let mut app = headless_app();
+    start(&mut app);
+    let (arena, config) = (arena(&app), config(&app));
+    let margin = config.wall_thickness / 2.0 + config.paddle_size.y / 2.0 + config.paddle_padding;
+
+    app.world.resource_mut::<PaddleInputs>().left = PlayerInput::new(true, false, false);
+    app.world.resource_mut::<PaddleInputs>().right = PlayerInput::new(false, true, false);
+    simulate_ticks(&mut app, 120);
+    assert_eq!(paddle_y(&mut app, Side::Left), arena.top - margin);
+    assert_eq!(paddle_y(&mut app, Side::Right), arena.bottom + margin);
+}
+
+#[test]
+fn paddles_move_at_the_configured_speed() {
+    let mut app = headless_app();
+    start(&mut app);
+    app.world.resource_mut::<PaddleInputs>().left = PlayerInput::new(true, false, false);
+    simulate_ticks(&mut app, 10);
+
+    let expected = config(&app).paddle_speed * TIME_STEP * 10.0;
+    assert!((paddle_y(&mut app, Side::Left) - expected).abs() < 1e-3);
+    assert_eq!(paddle_y(&mut app, Side::Right), 0.0);
+}
+
+#[test]
+fn halving_the_physics_step_keeps_the_ball_at_the_same_speed() {
+    let velocity = Vec2::new(300.0, 200.0);
+    let mut travelled = Vec::new();
+    for (step, ticks) in [(TIME_STEP, 30), (TIME_STEP / 2.0, 60)] {
+        let mut app = headless_app_with_step(step);
+        start(&mut app);
+        place_ball(&mut app, Vec2::ZERO, velocity);
+        simulate_ticks(&mut app, ticks);
+
+        // half a second either way
+        let (position, ball_velocity) = ball(&mut app);
+        assert_eq!(ball_velocity, velocity);
+        travelled.push(position);
+    }
+
+    let expected = velocity * 0.5;
+    for position in travelled {
+        assert!(
+            (position - expected).length() < 1e-2,
+            "the ball got to {position}, not {expected}"
+        );
+    }
+}
+
+#[test]
+fn each_paddle_keeps_its_own_speed_and_size() {
+    let mut app = headless_app();
+    start(&mut app);
+    set_paddle(&mut app, Side::Left, 900.0, 60.0);
+    set_paddle(&mut app, Side::Right, 250.0, 240.0);
+    app.world.resource_mut::<PaddleInputs>().left = PlayerInput::new(true, false, false);
+    app.world.resource_mut::<PaddleInputs>().right = PlayerInput::new(true, false, false);
+
+    simulate_ticks(&mut app, 10);
+    assert!((paddle_y(&mut app, Side::Left) - 900.0 * TIME_STEP * 10.0).abs() < 1e-3);
+    assert!((paddle_y(&mut app, Side::Right) - 250.0 * TIME_STEP * 10.0).abs() < 1e-3);
+
+    // each stops as soon as its own end reaches the wall
+    simulate_ticks(&mut app, 200);
+    let (arena, config) = (arena(&app), config(&app));
+    let top = arena.top - config.wall_thickness / 2.0 - config.paddle_padding;
+    assert_eq!(paddle_y(&mut app, Side::Left), top - 30.0);
+    assert_eq!(paddle_y(&mut app, Side::Right), top - 120.0);
+}
+
+#[test]
+fn ball_bounces_off_the_whole_of_a_large_paddle() {
+    let mut app = headless_app();
+    start(&mut app);
+    // past the end of a normal paddle, but well within this one
+    set_paddle(&mut app, Side::Right, 500.0, 240.0);
+    place_ball(&mut app, Vec2::new(200.0, 100.0), Vec2::new(600.0, 0.0));
+
+    simulate_ticks(&mut app, 30);
+    assert_eq!(scores(&app), (0, 0));
+    let (_, velocity) = ball(&mut app);
+    assert!(velocity.x < 0.0);
+    // deflected steeply, as it hit near the tip
+    assert!(velocity.y > velocity.x.abs());
+}
+
+#[test]
+fn computer_paddle_is_held_to_its_difficulty() {
+    for difficulty in [Difficulty::Easy, Difficulty::Hard] {
+        let mut app = headless_app();
+        app.insert_resource(SkipMenu { cpu_opponent: true })
+            .insert_resource(difficulty);
+        start(&mut app);
+        // hanging high above the computer's paddle
+        place_ball(&mut app, Vec2::new(300.0, 200.0), Vec2::ZERO);
+        simulate_ticks(&mut app, 10);
+
+        let expected =
+            config(&app).paddle_speed * difficulty.max_speed_fraction() * TIME_STEP * 10.0;
+        assert!((paddle_y(&mut app, Side::Right) - expected).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn hard_computer_heads_for_where_the_ball_will_come_down() {
+    for difficulty in [Difficulty::Medium, Difficulty::Hard] {
+        let mut app = headless_app();
+        app.insert_resource(SkipMenu { cpu_opponent: true })
+            .insert_resource(difficulty);
+        start(&mut app);
+        // climbing towards the top wall, and coming off it down low by the computer's paddle
+        place_ball(&mut app, Vec2::new(0.0, 200.0), Vec2::new(300.0, 500.0));
+        simulate_ticks(&mut app, 10);
+
+        let paddle = paddle_y(&mut app, Side::Right);
+        if difficulty == Difficulty::Hard {
+            assert!(paddle < 0.0, "the paddle went up to {paddle}");
+            simulate_ticks(&mut app, 70);
+            let (_, velocity) = ball(&mut app);
+            assert!(velocity.x < 0.0, "the ball got past going {velocity}");
+            assert_eq!(scores(&app), (0, 0));
+        } else {
+            // Medium just follows the ball up
+            assert!(paddle > 0.0, "the paddle went down to {paddle}");
+        }
+    }
+}
+
+// Fires 100 shots at the computer's paddle from all over the left half, the same ones every time,
+// and counts how many it sends back
+fn cpu_saves(handicap: CpuHandicap) -> usize {
+    let mut app = headless_app();
+    app.insert_resource(SkipMenu { cpu_opponent: true })
+        .insert_resource(MatchSettings {
+            practice: true,
+            ..default()
+        })
+        .insert_resource(handicap);
+    start(&mut app);
+
+    let mut shots = StdRng::seed_from_u64(7);
+    let mut saves = 0;
+    for _ in 0..100 {
+        let position = Vec2::new(-200.0, shots.gen_range(-250.0..250.0));
+        let velocity = Vec2::new(500.0, shots.gen_range(-500.0..500.0));
+        place_ball(&mut app, position, velocity);
+        let goals = scores(&app).0;
+        for _ in 0..300 {
+            simulate_ticks(&mut app, 1);
+            if scores(&app).0 > goals {
+                break;
+            }
+            if ball(&mut app).1.x < 0.0 {
+                saves += 1;
+                break;
+            }
+        }
+    }
+    saves
+}
+
+#[test]
+fn slower_or_less_accurate_computer_saves_fewer_shots() {
+    let sharp = cpu_saves(CpuHandicap::default());
+    let slow = cpu_saves(CpuHandicap {
+        reaction_delay: 0.4,
+        aim_error: 0.0,
+    });
+    let sloppy = cpu_saves(CpuHandicap {
+        reaction_delay: 0.0,
+        aim_error: 80.0,
+    });
+    assert!(slow + 10 <= sharp, "{slow} saves slow against {sharp}");
+    assert!(
+        sloppy + 10 <= sharp,
+        "{sloppy} saves sloppy against {sharp}"
+    );
+}
+
+#[test]
+fn pause_freezes_the_ball() {
+    let mut app = headless_app();
+    start(&mut app);
+    place_ball(&mut app, Vec2::ZERO, Vec2::new(300.0, 100.0));
+    simulate_ticks(&mut app, 5);
+
+    app.world
+        .resource_mut::<State<GameState>>()
+        .push(GameState::Paused)
+        .unwrap();
+    simulate_ticks(&mut app, 1);
+    let frozen = ball(&mut app);
+    simulate_ticks(&mut app, 60);
+    assert_eq!(ball(&mut app), frozen);
+
+    app.world.resource_mut::<State<GameState>>().pop().unwrap();
+    simulate_ticks(&mut app, 5);
+    let (position, velocity) = ball(&mut app);
+    assert_eq!(velocity, frozen.1);
+    assert!(position.x > frozen.0.x);
+}
+
+// Where the ball was, each time `see_ball` ran
+#[derive(Resource, Default)]
+struct SeenBall(Vec<f32>);
+
+fn see_ball(mut seen: ResMut<SeenBall>, ball_query: Query<&Transform, With<Ball>>) {
+    seen.0
+        .extend(ball_query.iter().map(|transform| transform.translation.x));
+}
+
+#[test]
+fn own_systems_run_in_the_physics_step_where_theyre_labelled() {
+    let mut app = headless_app();
+    app.init_resource::<SeenBall>().add_system_to_stage(
+        PhysicsStage,
+        see_ball
+            .after(PhysicsSet::Motion)
+            .before(PhysicsSet::Paddles),
+    );
+    start(&mut app);
+    place_ball(&mut app, Vec2::ZERO, Vec2::new(300.0, 0.0));
+    app.world.resource_mut::<SeenBall>().0.clear();
+    simulate_ticks(&mut app, 10);
+
+    // once a step, with the ball already moved each time
+    let seen = &app.world.resource::<SeenBall>().0;
+    assert_eq!(seen.len(), 10);
+    for (steps, x) in seen.iter().enumerate() {
+        assert!((x - 300.0 * TIME_STEP * (steps + 1) as f32).abs() < 1e-3);
+    }
+}
+
+// Steers the mouse paddles at half speed, standing in for a controller of its own
+fn half_speed_up(mut paddle_query: Query<&mut PaddleAction, With<MouseControlled>>) {
+    for mut action in &mut paddle_query {
+        action.axis = 0.5;
+    }
+}
+
+#[test]
+fn paddle_moves_by_its_action_whatever_set_it() {
+    let mut app = headless_app();
+    app.add_system_to_stage(
+        PhysicsStage,
+        half_speed_up
+            .after(PhysicsSet::Motion)
+            .before(PhysicsSet::Paddles),
+    );
+    start(&mut app);
+    let left = paddle(&mut app, Side::Left);
+    app.world.entity_mut(left).insert(MouseControlled);
+    // the keys only steer the paddles played from them
+    app.world.resource_mut::<PaddleInputs>().left = PlayerInput::new(false, true, false);
+    app.world.resource_mut::<PaddleInputs>().right = PlayerInput::new(false, true, false);
+    simulate_ticks(&mut app, 10);
+
+    let speed = config(&app).paddle_speed;
+    assert!((paddle_y(&mut app, Side::Left) - speed * 0.5 * TIME_STEP * 10.0).abs() < 1e-3);
+    assert!((paddle_y(&mut app, Side::Right) + speed * TIME_STEP * 10.0).abs() < 1e-3);
+    let right = paddle(&mut app, Side::Right);
+    let action = *app.world.get::<PaddleAction>(right).unwrap();
+    assert_eq!(
+        action,
+        PaddleAction {
+            axis: -1.0,
+            serve: false,
+            dash: false
+        }
+    );
+}
+
+#[test]
+#[cfg(not(target_arch = "wasm32"))]
+fn movement_keys_are_recognised_by_name_or_by_position() {
+    let mut app = headless_app();
+    app.add_plugin(bevy::input::InputPlugin);
+    start(&mut app);
+    let bindings = app.world.resource::<KeyBindings>().clone();
+    let up = bindings.scan_code(Action::LeftUp).unwrap();
+    let down = bindings.scan_code(Action::LeftDown).unwrap();
+
+    // on an AZERTY keyboard the key where QWERTY's W is comes up as Z
+    let mut travel = |movement_keys, key: KeyCode, scan_code: ScanCode| {
+        app.world.resource_mut::<KeyBindings>().movement_keys = movement_keys;
+        let start_y = paddle_y(&mut app, Side::Left);
+        app.world.resource_mut::<Input<KeyCode>>().press(key);
+        app.world.resource_mut::<Input<ScanCode>>().press(scan_code);
+        simulate_ticks(&mut app, 5);
+        app.world.resource_mut::<Input<KeyCode>>().release_all();
+        app.world.resource_mut::<Input<ScanCode>>().release_all();
+        paddle_y(&mut app, Side::Left) - start_y
+    };
+    assert!(travel(MovementKeys::Both, KeyCode::Z, up) > 0.0);
+    assert!(travel(MovementKeys::Physical, KeyCode::Z, up) > 0.0);
+    assert_eq!(travel(MovementKeys::Logical, KeyCode::Z, up), 0.0);
+    // and W is somewhere else, which only counts by name
+    let elsewhere = ScanCode(0);
+    assert!(travel(MovementKeys::Logical, KeyCode::W, elsewhere) > 0.0);
+    assert_eq!(travel(MovementKeys::Physical, KeyCode::W, elsewhere), 0.0);
+    // S is in the same place on every layout
+    assert!(travel(MovementKeys::Both, KeyCode::S, down) < 0.0);
+    assert!(travel(MovementKeys::Physical, KeyCode::Z, down) < 0.0);
+}
+
+#[test]
+fn long_gap_between_frames_only_catches_up_a_couple_of_steps() {
+    let mut app = headless_app();
+    start(&mut app);
+    place_ball(&mut app, Vec2::ZERO, Vec2::new(300.0, 0.0));
+
+    // a second with no frames, like a browser tab in the background
+    let last_update = app.world.resource::<Time>().last_update().unwrap();
+    app.insert_resource(TimeUpdateStrategy::ManualInstant(
+        last_update + Duration::from_secs(1),
+    ));
+    app.update();
+
+    let (position, _) = ball(&mut app);
+    assert!(
+        (position.x - 300.0 * TIME_STEP * 2.0).abs() < 1e-3,
+        "the ball jumped to {position}"
+    );
+}
+
+// Plays a second of the match at `fps` frames a second, returning the time the physics played
+// and where the ball got to
+fn play_a_second_at(fps: u32) -> (f32, Vec2) {
+    let mut app = headless_app();
+    start(&mut app);
+    place_ball(&mut app, Vec2::ZERO, Vec2::new(250.0, 100.0));
+    let played_before = app.world.resource::<MatchStats>().duration;
+
+    // starting half a step in, so rounding the frame times can't tip a step either way
+    let frame_times = std::iter::once(Duration::from_secs_f32(TIME_STEP / 2.0)).chain(
+        std::iter::repeat_n(Duration::from_secs(1) / fps, fps as usize),
+    );
+    for frame_time in frame_times {
+        let last_update = app.world.resource::<Time>().last_update().unwrap();
+        app.insert_resource(TimeUpdateStrategy::ManualInstant(last_update + frame_time));
+        app.update();
+    }
+
+    let played = app.world.resource::<MatchStats>().duration - played_before;
+    (played, ball(&mut app).0)
+}
+
+#[test]
+fn game_plays_at_the_same_speed_whatever_the_frame_rate() {
+    let (played, position) = play_a_second_at(60);
+    assert!(
+        (played - 1.0).abs() < TIME_STEP,
+        "{played} seconds were played"
+    );
+    for fps in [30, 144, 240] {
+        assert_eq!(play_a_second_at(fps), (played, position), "at {fps} fps");
+    }
+}
+
+#[test]
+fn serve_is_at_the_configured_speed_and_angle() {
+    let mut app = headless_app();
+    app.insert_resource(PongConfig {
+        ball_speed: 250.0,
+        max_serve_angle: 20.0,
+        ..Default::default()
+    })
+    .insert_resource(MatchSettings {
+        practice: true,
+        serve_rule: ServeRule::Random,
+        ..Default::default()
+    })
+    // no waiting around between serves
+    .insert_resource(ServeTimer(Timer::from_seconds(0.01, TimerMode::Once)));
+    start(&mut app);
+
+    let max_angle = 20f32.to_radians();
+    let (mut lowest, mut highest) = (0f32, 0f32);
+    let mut toward_right = 0;
+    for _ in 0..2000 {
+        let velocity = serve(&mut app);
+        assert!((velocity.length() - 250.0).abs() < 1e-3);
+        let angle = velocity.y.atan2(velocity.x.abs());
+        assert!(angle.abs() <= max_angle + 1e-4);
+        lowest = lowest.min(angle);
+        highest = highest.max(angle);
+        if velocity.x > 0.0 {
+            toward_right += 1;
+        }
+
+        // knock it straight into the goal to get another serve
+        let direction = velocity.x.signum();
+        place_ball(
+            &mut app,
+            Vec2::new(direction * 440.0, 200.0),
+            Vec2::new(direction * 600.0, 0.0),
+        );
+        simulate_ticks(&mut app, 5);
+    }
+
+    // right across the range, and to both sides
+    assert!(lowest < -0.95 * max_angle && highest > 0.95 * max_angle);
+    assert!(
+        (800..1200).contains(&toward_right),
+        "{toward_right} of 2000 serves went right"
+    );
+}
+
+#[test]
+fn long_rally_never_goes_over_the_speed_cap() {
+    let mut app = headless_app();
+    app.insert_resource(RallySettings {
+        speed_up_factor: 1.2,
+        max_speed_ups: 1000,
+    });
+    start(&mut app);
+    // straight at the middle of the paddles, so it comes straight back every time
+    place_ball(&mut app, Vec2::new(0.0, 0.0), Vec2::new(-400.0, 0.0));
+
+    let max_speed = PongConfig::default().max_ball_speed;
+    let mut heading = -1.0;
+    let mut hits = 0;
+    for _ in 0..10_000 {
+        simulate_ticks(&mut app, 1);
+        let (_, velocity) = ball(&mut app);
+        if velocity.x.signum() != heading {
+            heading = velocity.x.signum();
+            hits += 1;
+            assert!(
+                velocity.length() <= max_speed + 1e-3,
+                "hit {hits} sent the ball off at {}",
+                velocity.length()
+            );
+        }
+        if hits == 200 {
+            break;
+        }
+    }
+    assert_eq!(hits, 200);
+    assert_eq!(scores(&app), (0, 0));
+}
+
+#[test]
+fn first_serve_goes_either_way() {
+    let mut toward_right = 0;
+    for seed in 0..40 {
+        let mut app = headless_app();
+        app.insert_resource(GameRng::seeded(seed));
+        start(&mut app);
+        if serve(&mut app).x > 0.0 {
+            toward_right += 1;
+        }
+    }
+    assert!(
+        (5..35).contains(&toward_right),
+        "{toward_right} of 40 serves went right"
+    );
+}
+
+#[test]
+fn same_seed_gives_the_same_serves() {
+    let serves = |seed| {
+        let mut app = headless_app();
+        app.insert_resource(GameRng::seeded(seed))
+            .insert_resource(SkipMenu { cpu_opponent: true });
+        start(&mut app);
+        let mut serves = vec![];
+        while serves.len() < 3 {
+            serves.push(serve(&mut app));
+            // wait out the point
+            let goals = scores(&app);
+            while scores(&app) == goals {
+                simulate_ticks(&mut app, 1);
+            }
+        }
+        serves
+    };
+
+    assert_eq!(serves(7), serves(7));
+    assert_ne!(serves(7), serves(8));
+}
+
+#[test]
+fn long_headless_run_keeps_scoring() {
+    let mut app = headless_app();
+    app.insert_resource(SkipMenu { cpu_opponent: true })
+        .insert_resource(MatchSettings {
+            practice: true,
+            ..Default::default()
+        });
+    start(&mut app);
+    simulate_ticks(&mut app, 10_000);
+
+    // the left paddle never moves, so the computer should be well ahead
+    let scoreboard = app.world.resource::<Scoreboard>();
+    assert!(scoreboard.right_score > scoreboard.left_score);
+    assert!(
+        scoreboard.right_score >= 10,
+        "only {} goals in 10,000 steps",
+        scoreboard.right_score
+    );
+    assert_eq!(balls(&mut app).len(), 1);
+}
+
+#[test]
+fn gravity_pulls_the_ball_into_an_arc() {
+    let mut app = headless_app();
+    app.insert_resource(MatchSettings {
+        gravity: Some(DEFAULT_GRAVITY),
+        ..Default::default()
+    });
+    start(&mut app);
+    place_ball(&mut app, Vec2::new(-200.0, 0.0), Vec2::new(300.0, 300.0));
+    simulate_ticks(&mut app, 30);
+
+    // half a second of falling has taken 300 off its climb
+    let (position, velocity) = ball(&mut app);
+    assert!(velocity.y.abs() < 1.0, "{velocity}");
+    assert!(position.y > 0.0);
+
+    simulate_ticks(&mut app, 20);
+    let (_, velocity) = ball(&mut app);
+    assert!(velocity.y < 0.0);
+    // still making its way across, however steeply it falls
+    let config = config(&app);
+    assert!(velocity.x.abs() >= velocity.length() * config.min_horizontal_speed_fraction - 1e-3);
+    assert!(velocity.length() <= config.max_ball_speed + 1e-3);
+}
+
+#[test]
+fn serves_are_lobbed_upward_with_gravity() {
+    let mut app = headless_app();
+    app.insert_resource(MatchSettings {
+        gravity: Some(DEFAULT_GRAVITY),
+        practice: true,
+        ..Default::default()
+    });
+    start(&mut app);
+
+    for _ in 0..10 {
+        let velocity = serve(&mut app);
+        assert!(velocity.y > 0.0, "served at {velocity}");
+
+        let direction = velocity.x.signum();
+        place_ball(
+            &mut app,
+            Vec2::new(direction * 440.0, 200.0),
+            Vec2::new(direction * 600.0, 0.0),
+        );
+        simulate_ticks(&mut app, 5);
+    }
+}
+
+#[test]
+fn still_paddle_puts_no_spin_on_the_ball() {
+    let mut app = headless_app();
+    start(&mut app);
+    let paddle_y = paddle_y(&mut app, Side::Right);
+    place_ball(&mut app, Vec2::new(200.0, paddle_y), Vec2::new(600.0, 0.0));
+
+    simulate_ticks(&mut app, 30);
+    let (_, velocity) = ball(&mut app);
+    assert!(velocity.x < 0.0);
+    assert_eq!(velocity.y, 0.0);
+}
+
+#[test]
+fn moving_paddle_slices_the_ball() {
+    let bounce = |spin: f32| {
+        let mut app = headless_app();
+        app.insert_resource(PongConfig {
+            paddle_spin: spin,
+            ..Default::default()
+        });
+        start(&mut app);
+        app.world.resource_mut::<PaddleInputs>().right = PlayerInput::new(true, false, false);
+        simulate_ticks(&mut app, 5);
+        // aimed high, where the paddle will have got to by the time the ball reaches it
+        let paddle_y = paddle_y(&mut app, Side::Right);
+        place_ball(
+            &mut app,
+            Vec2::new(300.0, paddle_y + 60.0),
+            Vec2::new(600.0, 0.0),
+        );
+        simulate_ticks(&mut app, 15);
+        assert_eq!(app.world.resource::<Rally>().hits, 1);
+        ball(&mut app).1
+    };
+
+    let plain = bounce(0.0);
+    let sliced = bounce(0.5);
+    let paddle_speed = PongConfig::default().paddle_speed;
+    assert!((sliced.y - plain.y - 0.5 * paddle_speed).abs() < 1e-2);
+    assert_eq!(sliced.x, plain.x);
+}
+
+fn paddle_velocity(app: &mut App, side: Side) -> f32 {
+    let paddle = paddle(app, side);
+    app.world.get::<PaddleVelocity>(paddle).unwrap().0
+}
+
+#[test]
+fn paddle_with_inertia_speeds_up_and_slows_down() {
+    let mut app = headless_app();
+    app.insert_resource(MatchSettings {
+        paddle_inertia: true,
+        ..Default::default()
+    });
+    start(&mut app);
+    let config = config(&app);
+
+    app.world.resource_mut::<PaddleInputs>().left = PlayerInput::new(true, false, false);
+    simulate_ticks(&mut app, 1);
+    let velocity = paddle_velocity(&mut app, Side::Left);
+    assert!((velocity - config.paddle_acceleration * TIME_STEP).abs() < 1e-3);
+    assert!((paddle_y(&mut app, Side::Left) - velocity * TIME_STEP).abs() < 1e-3);
+    simulate_ticks(&mut app, 15);
+    assert_eq!(paddle_velocity(&mut app, Side::Left), config.paddle_speed);
+
+    // let go, it slides on a little way before stopping
+    app.world.resource_mut::<PaddleInputs>().left = PlayerInput::default();
+    simulate_ticks(&mut app, 1);
+    let velocity = paddle_velocity(&mut app, Side::Left);
+    assert!(
+        (velocity - (config.paddle_speed - config.paddle_deceleration * TIME_STEP)).abs() < 1e-3
+    );
+    simulate_ticks(&mut app, 10);
+    assert_eq!(paddle_velocity(&mut app, Side::Left), 0.0);
+
+    // without it the paddle's at full speed straight away
+    let mut app = headless_app();
+    start(&mut app);
+    app.world.resource_mut::<PaddleInputs>().left = PlayerInput::new(true, false, false);
+    simulate_ticks(&mut app, 2);
+    assert_eq!(paddle_velocity(&mut app, Side::Left), config.paddle_speed);
+}
+
+#[test]
+fn paddle_with_inertia_stops_dead_at_the_end_of_its_travel() {
+    let mut app = headless_app();
+    app.insert_resource(MatchSettings {
+        paddle_inertia: true,
+        ..Default::default()
+    });
+    start(&mut app);
+    app.world.resource_mut::<PaddleInputs>().left = PlayerInput::new(true, false, false);
+    simulate_ticks(&mut app, 120);
+    let top = paddle_y(&mut app, Side::Left);
+    assert_eq!(paddle_velocity(&mut app, Side::Left), 0.0);
+
+    // turned around it sets off straight away, with nothing to work off first
+    app.world.resource_mut::<PaddleInputs>().left = PlayerInput::new(false, true, false);
+    simulate_ticks(&mut app, 1);
+    let acceleration = config(&app).paddle_acceleration;
+    assert!((paddle_velocity(&mut app, Side::Left) + acceleration * TIME_STEP).abs() < 1e-3);
+    assert!(paddle_y(&mut app, Side::Left) < top);
+}
+
+#[test]
+fn paddle_with_inertia_slices_the_ball_at_the_speed_it_has() {
+    let bounce = |spin: f32| {
+        let mut app = headless_app();
+        app.insert_resource(PongConfig {
+            paddle_spin: spin,
+            ..Default::default()
+        })
+        .insert_resource(MatchSettings {
+            paddle_inertia: true,
+            ..Default::default()
+        });
+        start(&mut app);
+        app.world.resource_mut::<PaddleInputs>().right = PlayerInput::new(true, false, false);
+        simulate_ticks(&mut app, 2);
+        let paddle_y = paddle_y(&mut app, Side::Right);
+        place_ball(
+            &mut app,
+            Vec2::new(300.0, paddle_y + 20.0),
+            Vec2::new(600.0, 0.0),
+        );
+        simulate_ticks(&mut app, 15);
+        assert_eq!(app.world.resource::<Rally>().hits, 1);
+        ball(&mut app).1
+    };
+
+    let plain = bounce(0.0);
+    let sliced = bounce(0.5);
+    // still getting up to speed when it hit the ball, so it put less on it than a paddle at full speed would
+    let paddle_speed = PongConfig::default().paddle_speed;
+    assert!(sliced.y - plain.y > 0.0);
+    assert!(sliced.y - plain.y < 0.5 * paddle_speed - 1.0);
+}
+
+fn dash_app(settings: MatchSettings) -> App {
+    let mut app = headless_app();
+    app.insert_resource(settings);
+    start(&mut app);
+    app
+}
+
+fn set_left_input(app: &mut App, input: PlayerInput) {
+    app.world.resource_mut::<PaddleInputs>().left = input;
+}
+
+#[test]
+fn dash_covers_its_distance_then_waits_to_dash_again() {
+    let mut app = dash_app(MatchSettings {
+        paddle_dash: true,
+        ..default()
+    });
+    let config = config(&app);
+    let step_travel = config.paddle_speed * TIME_STEP;
+    let dash_steps = (config.dash_duration / TIME_STEP).round() as u32;
+
+    set_left_input(&mut app, PlayerInput::new(true, false, false).dashing());
+    simulate_ticks(&mut app, dash_steps);
+    assert!((paddle_y(&mut app, Side::Left) - config.dash_distance).abs() < 1e-3);
+
+    // holding the key down doesn't dash again, the paddle carries on at its own speed
+    simulate_ticks(&mut app, 1);
+    let y = paddle_y(&mut app, Side::Left);
+    assert!((y - config.dash_distance - step_travel).abs() < 1e-3);
+
+    // nor does pressing it again before the cooldown's up
+    set_left_input(&mut app, PlayerInput::new(false, true, false));
+    simulate_ticks(&mut app, 1);
+    set_left_input(&mut app, PlayerInput::new(false, true, false).dashing());
+    simulate_ticks(&mut app, 1);
+    assert!((paddle_y(&mut app, Side::Left) - (y - 2.0 * step_travel)).abs() < 1e-3);
+
+    set_left_input(&mut app, PlayerInput::default());
+    simulate_ticks(&mut app, (config.dash_cooldown / TIME_STEP).round() as u32);
+    let y = paddle_y(&mut app, Side::Left);
+    set_left_input(&mut app, PlayerInput::new(false, true, false).dashing());
+    simulate_ticks(&mut app, dash_steps);
+    assert!((paddle_y(&mut app, Side::Left) - (y - config.dash_distance)).abs() < 1e-3);
+}
+
+#[test]
+fn dash_stops_at_the_end_of_the_paddles_travel() {
+    let mut app = dash_app(MatchSettings {
+        paddle_dash: true,
+        ..default()
+    });
+    set_left_input(&mut app, PlayerInput::new(true, false, false));
+    simulate_ticks(&mut app, 120);
+    let top = paddle_y(&mut app, Side::Left);
+    set_left_input(&mut app, PlayerInput::new(false, true, false));
+    simulate_ticks(&mut app, 5);
+
+    set_left_input(&mut app, PlayerInput::new(true, false, false).dashing());
+    simulate_ticks(&mut app, 3);
+    assert_eq!(paddle_y(&mut app, Side::Left), top);
+    let left = paddle(&mut app, Side::Left);
+    let dash = *app.world.get::<PaddleDash>(left).unwrap();
+    assert_eq!(dash.remaining, 0.0);
+    assert!(dash.cooldown > 0.0);
+}
+
+#[test]
+fn paddles_dont_dash_unless_the_match_lets_them() {
+    let mut app = dash_app(MatchSettings::default());
+    set_left_input(&mut app, PlayerInput::new(true, false, false).dashing());
+    simulate_ticks(&mut app, 6);
+    let expected = config(&app).paddle_speed * TIME_STEP * 6.0;
+    assert!((paddle_y(&mut app, Side::Left) - expected).abs() < 1e-3);
+}
+
+#[test]
+fn hard_computer_dashes_for_a_ball_out_of_reach() {
+    let computer_travel = |cpu_dash: bool| {
+        let mut app = headless_app();
+        app.insert_resource(SkipMenu { cpu_opponent: true })
+            .insert_resource(Difficulty::Hard)
+            .insert_resource(MatchSettings {
+                paddle_dash: true,
+                cpu_dash,
+                ..default()
+            });
+        start(&mut app);
+        // hanging high above the computer's paddle, further than a dash goes
+        place_ball(&mut app, Vec2::new(300.0, 200.0), Vec2::ZERO);
+        simulate_ticks(&mut app, 10);
+        paddle_y(&mut app, Side::Right)
+    };
+
+    let config = PongConfig::default();
+    let steered = config.paddle_speed * Difficulty::Hard.max_speed_fraction() * TIME_STEP * 10.0;
+    assert!((computer_travel(false) - steered).abs() < 1e-3);
+    assert!(computer_travel(true) > config.dash_distance);
+}
+
+#[test]
+fn restart_ends_a_dash_and_its_cooldown() {
+    let mut app = rendered_app(MemoryStorage::default(), bevy::window::WindowMode::Windowed);
+    app.insert_resource(MatchSettings {
+        paddle_dash: true,
+        ..default()
+    });
+    simulate_ticks(&mut app, 1);
+    tap(&mut app, KeyCode::Return);
+    start(&mut app);
+
+    set_left_input(&mut app, PlayerInput::new(true, false, false).dashing());
+    simulate_ticks(&mut app, 2);
+    let left = paddle(&mut app, Side::Left);
+    assert!(app.world.get::<PaddleDash>(left).unwrap().remaining > 0.0);
+
+    set_left_input(&mut app, PlayerInput::default());
+    tap(&mut app, KeyCode::R);
+    assert_eq!(state(&app), GameState::Playing);
+    let dash = *app.world.get::<PaddleDash>(left).unwrap();
+    assert_eq!(dash.remaining, 0.0);
+    assert_eq!(dash.cooldown, 0.0);
+}
+
+// A match started from the menu entry `downs` below the first, in a `rendered_app` so the restart key works
+fn match_from_menu(downs: usize) -> App {
+    let mut app = rendered_app(MemoryStorage::default(), bevy::window::WindowMode::Windowed);
+    simulate_ticks(&mut app, 1);
+    for _ in 0..downs {
+        tap(&mut app, KeyCode::Down);
+    }
+    tap(&mut app, KeyCode::Return);
+    start(&mut app);
+    // the extra paddles join on the first step
+    simulate_ticks(&mut app, 1);
+    app
+}
+
+fn seat_position(app: &mut App, seat: Seat, half: Option<Half>) -> Vec2 {
+    let mut query = app
+        .world
+        .query_filtered::<(&Seat, Option<&Half>, &Transform), With<Paddle>>();
+    query
+        .iter(&app.world)
+        .find(|(paddle_seat, paddle_half, _)| **paddle_seat == seat && paddle_half.copied() == half)
+        .map(|(_, _, transform)| transform.translation.truncate())
+        .expect("the paddle is spawned")
+}
+
+#[test]
+fn restart_puts_doubles_partners_back_in_their_halves() {
+    // Doubles, fourth on the main menu
+    let mut app = match_from_menu(3);
+    let upper_start = seat_position(&mut app, Seat::Left, Some(Half::Upper));
+    assert!(upper_start.y > 0.0);
+
+    set_left_input(&mut app, PlayerInput::new(true, false, false));
+    simulate_ticks(&mut app, 10);
+    set_left_input(&mut app, PlayerInput::default());
+    assert!(seat_position(&mut app, Seat::Left, Some(Half::Upper)).y > upper_start.y);
+
+    // straight back to the middle of its half, not by way of the middle of the arena
+    tap(&mut app, KeyCode::R);
+    assert_eq!(
+        seat_position(&mut app, Seat::Left, Some(Half::Upper)),
+        upper_start
+    );
+}
+
+#[test]
+fn restart_puts_the_top_and_bottom_paddles_back_along_their_walls() {
+    // Four Players, third on the main menu
+    let mut app = match_from_menu(2);
+    let top_start = seat_position(&mut app, Seat::Top, None);
+    assert_eq!(top_start.x, 0.0);
+
+    app.world.resource_mut::<PaddleInputs>().top = PlayerInput::new(true, false, false);
+    simulate_ticks(&mut app, 10);
+    app.world.resource_mut::<PaddleInputs>().top = PlayerInput::default();
+    assert!(seat_position(&mut app, Seat::Top, None).x > 0.0);
+
+    // they're the match's own, and come back on the first step of the new one
+    tap(&mut app, KeyCode::R);
+    simulate_ticks(&mut app, 1);
+    assert_eq!(seat_position(&mut app, Seat::Top, None), top_start);
+}
+
+#[test]
+fn hit_squashes_a_top_paddle_across_its_thickness() {
+    let mut app = match_from_menu(2);
+    let top_start = seat_position(&mut app, Seat::Top, None);
+    place_ball(
+        &mut app,
+        top_start - Vec2::new(0.0, 60.0),
+        Vec2::new(0.0, 400.0),
+    );
+
+    let mut query = app.world.query_filtered::<(&Seat, &Sprite), With<Paddle>>();
+    for _ in 0..30 {
+        simulate_ticks(&mut app, 1);
+        let squash = query
+            .iter(&app.world)
+            .find(|(seat, _)| **seat == Seat::Top)
+            .and_then(|(_, sprite)| sprite.custom_size);
+        if let Some(squash) = squash.filter(|size| *size != Vec2::ONE) {
+            // shorter along the wall and thicker out from it, like the left and right paddles
+            assert!(squash.x < 1.0 && squash.y > 1.0, "squashed to {squash}");
+            return;
+        }
+    }
+    panic!("the ball never hit the top paddle");
+}
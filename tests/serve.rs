@@ -0,0 +1,85 @@
+use bevy_pong::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn serve_direction_is_always_a_unit_vector_heading_for_a_side() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let min_x = 45f32.to_radians().cos();
+    for _ in 0..10_000 {
+        let direction = serve_direction(&mut rng, None, 45f32.to_radians(), false);
+        assert!(direction.is_finite(), "served along {direction}");
+        assert!((direction.length() - 1.0).abs() < 1e-5);
+        assert!(
+            direction.x.abs() >= min_x - 1e-5,
+            "served along {direction}"
+        );
+    }
+}
+
+#[test]
+fn serve_direction_goes_either_way() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let toward_right = (0..1000)
+        .filter(|_| serve_direction(&mut rng, None, 45f32.to_radians(), false).x > 0.0)
+        .count();
+    assert!(
+        (400..600).contains(&toward_right),
+        "{toward_right} of 1000 serves went right"
+    );
+}
+
+#[test]
+fn serve_direction_heads_for_the_seat_asked_for() {
+    let mut rng = StdRng::seed_from_u64(3);
+    for _ in 0..100 {
+        assert!(serve_direction(&mut rng, Some(Seat::Left), 1.0, false).x < 0.0);
+        assert!(serve_direction(&mut rng, Some(Seat::Right), 1.0, false).x > 0.0);
+        assert!(serve_direction(&mut rng, Some(Seat::Top), 1.0, false).y > 0.0);
+        assert!(serve_direction(&mut rng, Some(Seat::Bottom), 1.0, false).y < 0.0);
+    }
+}
+
+#[test]
+fn serve_angle_stays_within_the_maximum_and_uses_all_of_it() {
+    let mut rng = StdRng::seed_from_u64(30);
+    for max_degrees in [20f32, 60.0, 85.0] {
+        let max_angle = max_degrees.to_radians();
+        let (mut lowest, mut highest) = (0f32, 0f32);
+        let mut toward_right = 0;
+        for _ in 0..5000 {
+            let direction = serve_direction(&mut rng, None, max_angle, false);
+            let angle = direction.y.atan2(direction.x.abs());
+            assert!(
+                angle.abs() <= max_angle + 1e-4,
+                "served at {} degrees",
+                angle.to_degrees()
+            );
+            lowest = lowest.min(angle);
+            highest = highest.max(angle);
+            if direction.x > 0.0 {
+                toward_right += 1;
+            }
+        }
+        // spread right across the range, and towards both sides
+        assert!(lowest < -0.95 * max_angle && highest > 0.95 * max_angle);
+        assert!(
+            (2000..3000).contains(&toward_right),
+            "{toward_right} of 5000 serves went right"
+        );
+    }
+}
+
+#[test]
+fn upward_serve_is_lobbed_at_least_a_quarter_of_the_maximum_up() {
+    let mut rng = StdRng::seed_from_u64(57);
+    let max_angle = 60f32.to_radians();
+    for _ in 0..5000 {
+        let direction = serve_direction(&mut rng, None, max_angle, true);
+        let angle = direction.y.atan2(direction.x.abs());
+        assert!(
+            (max_angle / 4.0 - 1e-4..=max_angle + 1e-4).contains(&angle),
+            "lobbed at {} degrees",
+            angle.to_degrees()
+        );
+    }
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_pong::prelude::*;
+use common::*;
+
+fn win_match(app: &mut App, left_score: usize, right_score: usize) {
+    app.insert_resource(Scoreboard {
+        left_score,
+        right_score,
+    });
+    simulate_ticks(app, 3);
+    assert_eq!(state(app), GameState::GameOver);
+}
+
+fn stats(app: &App) -> PersistentStats {
+    app.world.resource::<PersistentStats>().clone()
+}
+
+#[test]
+fn wins_against_the_computer_are_counted_for_whoever_won() {
+    let mut app = headless_app();
+    app.insert_resource(SkipMenu { cpu_opponent: true });
+    start(&mut app);
+    win_match(&mut app, 11, 4);
+    assert_eq!(stats(&app).player_wins, 1);
+    assert_eq!(stats(&app).cpu_wins, 0);
+
+    let mut app = headless_app();
+    app.insert_resource(SkipMenu { cpu_opponent: true });
+    start(&mut app);
+    win_match(&mut app, 9, 11);
+    assert_eq!(stats(&app).player_wins, 0);
+    assert_eq!(stats(&app).cpu_wins, 1);
+}
+
+#[test]
+fn two_player_matches_only_count_towards_the_margin() {
+    let mut app = headless_app();
+    app.insert_resource(PersistentStats {
+        best_winning_margin: 3,
+        ..Default::default()
+    });
+    start(&mut app);
+    win_match(&mut app, 2, 11);
+
+    let stats = stats(&app);
+    assert_eq!(stats.player_wins + stats.cpu_wins, 0);
+    assert_eq!(stats.best_winning_margin, 9);
+}
+
+#[test]
+fn longest_rally_outlasts_the_rally() {
+    let mut app = headless_app();
+    start(&mut app);
+    app.world.resource_mut::<Rally>().hits = 6;
+    simulate_ticks(&mut app, 1);
+    app.world.resource_mut::<Rally>().hits = 2;
+    simulate_ticks(&mut app, 1);
+
+    assert_eq!(stats(&app).longest_rally, 6);
+}
+
+fn match_stats(app: &App) -> MatchStats {
+    app.world.resource::<MatchStats>().clone()
+}
+
+// A ball heading flat at the left paddle's face from a little in front of it
+fn hit_left_paddle(app: &mut App) {
+    let paddle_x = arena(app).left + config(app).paddle_gap;
+    place_ball(app, Vec2::new(paddle_x + 40.0, 0.0), Vec2::new(-400.0, 0.0));
+    simulate_ticks(app, 20);
+}
+
+#[test]
+fn paddle_hits_are_counted_for_the_side_that_hit() {
+    let mut app = headless_app();
+    start(&mut app);
+    hit_left_paddle(&mut app);
+
+    let stats = match_stats(&app);
+    assert_eq!((stats.left_hits, stats.right_hits), (1, 0));
+    assert_eq!(stats.hits(Side::Left), 1);
+    assert_eq!(stats.current_rally, 1);
+    assert_eq!(stats.longest_rally, 1);
+    assert!(stats.max_ball_speed >= 400.0);
+}
+
+#[test]
+fn wall_bounces_dont_count_towards_the_rally() {
+    let mut app = headless_app();
+    start(&mut app);
+    let top = arena(&app).top;
+    place_ball(
+        &mut app,
+        Vec2::new(0.0, top - 40.0),
+        Vec2::new(100.0, 400.0),
+    );
+    simulate_ticks(&mut app, 20);
+
+    let stats = match_stats(&app);
+    assert_eq!(stats.wall_bounces, 1);
+    assert_eq!(stats.current_rally, 0);
+    assert_eq!(stats.longest_rally, 0);
+}
+
+#[test]
+fn a_goal_ends_the_rally() {
+    let mut app = headless_app();
+    start(&mut app);
+    hit_left_paddle(&mut app);
+    let left = arena(&app).left;
+    place_ball(&mut app, Vec2::new(left + 5.0, 0.0), Vec2::new(-400.0, 0.0));
+    simulate_ticks(&mut app, 5);
+    assert_eq!(scores(&app), (0, 1));
+
+    let stats = match_stats(&app);
+    assert_eq!(
+        (stats.rallies, stats.rally_hits, stats.current_rally),
+        (1, 1, 0)
+    );
+    assert_eq!(stats.average_rally(), 1.0);
+    assert_eq!(stats.longest_rally, 1);
+}
+
+#[test]
+fn match_time_only_counts_the_steps_played() {
+    let mut app = headless_app();
+    start(&mut app);
+    let before = match_stats(&app).duration;
+    simulate_ticks(&mut app, 10);
+
+    let played = match_stats(&app).duration - before;
+    let step = app.world.resource::<PhysicsStep>().0;
+    assert!((played - 10.0 * step).abs() < 1e-4, "{played}");
+}
+
+#[test]
+fn the_rally_goes_on_while_another_ball_is_in_play() {
+    let mut app = headless_app();
+    start(&mut app);
+    hit_left_paddle(&mut app);
+    let left = arena(&app).left;
+    add_ball(&mut app, Vec2::new(left + 5.0, 0.0), Vec2::new(-400.0, 0.0));
+    simulate_ticks(&mut app, 5);
+    assert_eq!(scores(&app), (0, 1));
+
+    let stats = match_stats(&app);
+    assert_eq!((stats.rallies, stats.current_rally), (0, 1));
+    assert_eq!(app.world.resource::<Rally>().hits, stats.current_rally);
+}
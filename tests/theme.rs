@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+use bevy_pong::prelude::*;
+
+#[test]
+fn classic_theme_is_the_configs_colors() {
+    let config = PongConfig {
+        paddle_color: Color::RED,
+        ..Default::default()
+    };
+    let theme = Theme::new(ThemePreset::Classic, &config);
+    assert_eq!(theme.paddle, Color::RED);
+    assert_eq!(theme.background, config.background_color);
+    assert_eq!(theme.text, config.text_color);
+}
+
+#[test]
+fn presets_cycle_back_round_to_classic() {
+    let mut preset = ThemePreset::Classic;
+    let mut seen = Vec::new();
+    for _ in 0..6 {
+        seen.push(preset);
+        preset = preset.next();
+    }
+    assert_eq!(preset, ThemePreset::Classic);
+    assert_eq!(
+        seen,
+        [
+            ThemePreset::Classic,
+            ThemePreset::Dark,
+            ThemePreset::Neon,
+            ThemePreset::Deuteranopia,
+            ThemePreset::Protanopia,
+            ThemePreset::Tritanopia,
+        ]
+    );
+    // each preset actually looks different
+    let config = PongConfig::default();
+    let themes: Vec<Theme> = seen
+        .iter()
+        .map(|preset| Theme::new(*preset, &config))
+        .collect();
+    for pair in themes.windows(2) {
+        assert_ne!(pair[0], pair[1]);
+    }
+}
+
+#[test]
+fn color_blind_themes_ring_the_ball() {
+    let config = PongConfig::default();
+    for preset in [
+        ThemePreset::Deuteranopia,
+        ThemePreset::Protanopia,
+        ThemePreset::Tritanopia,
+    ] {
+        let theme = Theme::new(preset, &config);
+        assert!(theme.ball_outline.is_some(), "{preset:?} has no outline");
+        assert_ne!(theme.ball_outline, Some(theme.background));
+    }
+    assert_eq!(Theme::new(ThemePreset::Classic, &config).ball_outline, None);
+}
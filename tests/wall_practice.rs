@@ -0,0 +1,110 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_pong::prelude::*;
+use common::*;
+
+fn wall_practice_app() -> App {
+    let mut app = headless_app();
+    app.insert_resource(MatchSettings {
+        wall_practice: true,
+        ..default()
+    });
+    start(&mut app);
+    // the right paddle is put away on the first step
+    simulate_ticks(&mut app, 1);
+    app
+}
+
+fn streak(app: &App) -> Streak {
+    *app.world.resource::<Streak>()
+}
+
+#[test]
+fn ball_bounces_off_the_right_wall() {
+    let mut app = wall_practice_app();
+    let arena = arena(&app);
+    let right_paddle = paddle(&mut app, Side::Right);
+    assert!(
+        !app.world
+            .get::<Visibility>(right_paddle)
+            .unwrap()
+            .is_visible
+    );
+
+    place_ball(
+        &mut app,
+        Vec2::new(arena.right - 40.0, 0.0),
+        Vec2::new(300.0, 0.0),
+    );
+    simulate_ticks(&mut app, 30);
+
+    let (position, velocity) = ball(&mut app);
+    assert!(
+        velocity.x < 0.0,
+        "the ball is at {position} going {velocity}"
+    );
+    assert_eq!(scores(&app), (0, 0));
+    assert_eq!(state(&app), GameState::Playing);
+}
+
+#[test]
+fn paddle_hits_build_a_streak() {
+    let mut app = wall_practice_app();
+    let paddle_x = arena(&app).left + config(&app).paddle_gap;
+    for hits in 1..=3 {
+        let y = paddle_y(&mut app, Side::Left);
+        place_ball(
+            &mut app,
+            Vec2::new(paddle_x + 60.0, y),
+            Vec2::new(-400.0, 0.0),
+        );
+        simulate_ticks(&mut app, 20);
+        assert_eq!(streak(&app), Streak { hits, best: hits });
+    }
+}
+
+#[test]
+fn missing_ends_the_streak_instead_of_scoring() {
+    let mut app = wall_practice_app();
+    let arena = arena(&app);
+    app.world.insert_resource(Streak { hits: 4, best: 6 });
+
+    place_ball(
+        &mut app,
+        Vec2::new(arena.left - 5.0, 200.0),
+        Vec2::new(-300.0, 0.0),
+    );
+    simulate_ticks(&mut app, 1);
+    let events = app.world.resource::<Events<BallMissed>>();
+    let misses: Vec<usize> = events
+        .get_reader()
+        .iter(events)
+        .map(|miss| miss.streak)
+        .collect();
+
+    assert_eq!(misses, [4]);
+    assert_eq!(streak(&app), Streak { hits: 0, best: 6 });
+    assert_eq!(scores(&app), (0, 0));
+    assert!(app.world.resource::<Events<GoalEvent>>().is_empty());
+
+    // served back at the player
+    let velocity = serve(&mut app);
+    assert!(velocity.x < 0.0, "served at {velocity}");
+}
+
+#[test]
+fn timed_wall_practice_never_ends() {
+    let mut app = headless_app();
+    app.insert_resource(MatchSettings {
+        wall_practice: true,
+        mode: MatchMode::Timed(TimedFormat {
+            duration: 1.0,
+            clock_runs_during_serve: true,
+        }),
+        ..default()
+    });
+    start(&mut app);
+    simulate_ticks(&mut app, 120);
+    assert_eq!(state(&app), GameState::Playing);
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use bevy::window::WindowMode;
+use bevy_pong::prelude::*;
+use common::*;
+
+#[test]
+fn fullscreen_from_the_command_line_lasts_past_the_first_frame() {
+    // as `--fullscreen` starts the game: the window opens fullscreen and the settings are told so
+    let mut app = rendered_app(MemoryStorage::default(), WindowMode::BorderlessFullscreen);
+    app.world.resource_mut::<DisplaySettings>().fullscreen = true;
+    simulate_ticks(&mut app, 3);
+
+    assert_eq!(window(&mut app).mode(), WindowMode::BorderlessFullscreen);
+}
+
+#[test]
+fn fullscreen_saved_last_time_comes_back() {
+    let mut saved = MemoryStorage::default();
+    saved
+        .save("settings", "(display: (fullscreen: true))")
+        .unwrap();
+    let mut app = rendered_app(saved, WindowMode::Windowed);
+    simulate_ticks(&mut app, 3);
+
+    assert_eq!(window(&mut app).mode(), WindowMode::BorderlessFullscreen);
+    assert!(app.world.resource::<DisplaySettings>().fullscreen);
+}
+
+#[test]
+fn windowed_stays_windowed() {
+    let mut app = rendered_app(MemoryStorage::default(), WindowMode::Windowed);
+    simulate_ticks(&mut app, 3);
+
+    assert_eq!(window(&mut app).mode(), WindowMode::Windowed);
+}